@@ -0,0 +1,159 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr, parse_macro_input};
+
+// #[derive(AmfObject)] maps a struct with named fields to an AMF 0 anonymous Object and
+// generates `ToAmf0`, `FromAmf0`, `Marshall`, `MarshallLength` and `Unmarshall` for it. Every
+// field type must implement `ToAmf0`/`FromAmf0`.
+//
+// Attributes:
+//
+//	#[amf(class_name = "com.example.Foo")]   on the struct: encode as a TypedObject with this
+//	                                         class name (top-level `marshall` only; nested
+//	                                         values are plain Objects)
+//	#[amf(rename = "hasVideo")]              on a field: use this property key
+//
+// `marshall_length` adds up the lengths of the fields (`ToAmf0::amf0_length`) without building
+// the value. If a field cannot be converted, `marshall` fails and `marshall_length` returns 0.
+#[proc_macro_derive(AmfObject, attributes(amf))]
+pub fn derive_amf_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut class_name: Option<LitStr> = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("amf")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("class_name") {
+                class_name = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported amf attribute, expected `class_name`"))
+            }
+        })?;
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "AmfObject can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "AmfObject can only be derived for structs",
+            ));
+        }
+    };
+
+    let mut idents = Vec::with_capacity(fields.len());
+    let mut keys = Vec::with_capacity(fields.len());
+    for field in fields {
+        let ident = field.ident.clone().unwrap(); // 已经确定是具名字段
+        let mut key = LitStr::new(&ident.to_string(), ident.span());
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("amf")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    key = meta.value()?.parse()?;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported amf attribute, expected `rename`"))
+                }
+            })?;
+        }
+        if key.value().len() > u16::MAX as usize {
+            return Err(syn::Error::new_spanned(
+                &key,
+                "property key longer than 65535 bytes",
+            ));
+        }
+        idents.push(ident);
+        keys.push(key);
+    }
+
+    let class_name = match class_name {
+        Some(class_name) => quote!(::core::option::Option::Some(#class_name)),
+        None => quote!(::core::option::Option::None),
+    };
+
+    let len = idents.len();
+    // 每个属性: 2 字节 key 长度 + key + 值
+    let key_lengths: Vec<usize> = keys.iter().map(|k| 2 + k.value().len()).collect();
+
+    Ok(quote! {
+        impl #impl_generics ::amf_rs::amf0::convert::ToAmf0 for #name #ty_generics #where_clause {
+            fn to_amf0(
+                &self,
+            ) -> ::core::result::Result<::amf_rs::amf0::nested::Amf0TypedValue, ::amf_rs::errors::AmfError> {
+                let properties: [(
+                    ::amf_rs::amf0::utf8::Utf8,
+                    ::amf_rs::amf0::nested::Amf0TypedValue,
+                ); #len] = [
+                    #((
+                        ::amf_rs::amf0::utf8::Utf8::new_from_str(#keys)?,
+                        ::amf_rs::amf0::convert::ToAmf0::to_amf0(&self.#idents)?,
+                    ),)*
+                ];
+                ::core::result::Result::Ok(::amf_rs::amf0::nested::Amf0TypedValue::Object(
+                    properties.into_iter().collect(),
+                ))
+            }
+
+            fn amf0_length(&self) -> ::core::result::Result<usize, ::amf_rs::errors::AmfError> {
+                // 类型标记 + 属性 + object end
+                ::core::result::Result::Ok(
+                    1 #(+ #key_lengths + ::amf_rs::amf0::convert::ToAmf0::amf0_length(&self.#idents)?)* + 3
+                )
+            }
+        }
+
+        impl #impl_generics ::amf_rs::amf0::convert::FromAmf0 for #name #ty_generics #where_clause {
+            #[allow(unused_mut)]
+            fn from_amf0(
+                value: ::amf_rs::amf0::nested::Amf0TypedValue,
+            ) -> ::core::result::Result<Self, ::amf_rs::errors::AmfError> {
+                let mut properties = ::amf_rs::amf0::convert::object_properties(value)?;
+                ::core::result::Result::Ok(Self {
+                    #(#idents: ::amf_rs::amf0::convert::take_property(&mut properties, #keys)?,)*
+                })
+            }
+        }
+
+        impl #impl_generics ::amf_rs::traits::Marshall for #name #ty_generics #where_clause {
+            fn marshall(&self) -> ::core::result::Result<::std::vec::Vec<u8>, ::amf_rs::errors::AmfError> {
+                let value = ::amf_rs::amf0::convert::ToAmf0::to_amf0(self)?;
+                ::amf_rs::amf0::convert::marshall_object(#class_name, &value)
+            }
+        }
+
+        impl #impl_generics ::amf_rs::traits::MarshallLength for #name #ty_generics #where_clause {
+            fn marshall_length(&self) -> usize {
+                ::amf_rs::amf0::convert::marshall_object_length(
+                    #class_name,
+                    ::amf_rs::amf0::convert::ToAmf0::amf0_length(self),
+                )
+            }
+        }
+
+        impl #impl_generics ::amf_rs::traits::Unmarshall for #name #ty_generics #where_clause {
+            fn unmarshall(
+                buf: &[u8],
+            ) -> ::core::result::Result<(Self, usize), ::amf_rs::errors::AmfError> {
+                let (value, n) = ::amf_rs::amf0::convert::unmarshall_object(buf, #class_name)?;
+                ::core::result::Result::Ok((::amf_rs::amf0::convert::FromAmf0::from_amf0(value)?, n))
+            }
+        }
+    })
+}
@@ -1,10 +1,10 @@
 use amf_rs::amf0::nested::Amf0TypedValue;
 use amf_rs::amf0::nested::{EcmaArrayType, ObjectType};
 use amf_rs::amf0::string::{LongStringType, StringType};
+use amf_rs::amf0::validate;
 use amf_rs::traits::{Marshall, Unmarshall};
 use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
 use indexmap::IndexMap;
-use std::iter;
 
 fn bench_string_types(c: &mut Criterion) {
     let mut group = c.benchmark_group("String Types");
@@ -29,7 +29,7 @@ fn bench_string_types(c: &mut Criterion) {
 
     // Prepare long string
     let n = u16::MAX as usize * 2;
-    let long_val = iter::repeat('a').take(n).collect::<String>();
+    let long_val = "a".repeat(n);
     let long = LongStringType::new_from_string(long_val).unwrap();
     group.bench_with_input(
         BenchmarkId::new("LongStringType_marshall", n),
@@ -76,6 +76,13 @@ fn bench_nested_types(c: &mut Criterion) {
             b.iter(|| ObjectType::unmarshall(data).unwrap());
         },
     );
+    group.bench_with_input(
+        BenchmarkId::new("ObjectType_validate", 100),
+        &obj_bytes,
+        |b, data| {
+            b.iter(|| validate(data).unwrap());
+        },
+    );
 
     // Prepare EcmaArrayType with 100 entries
     let ecma = EcmaArrayType::new(props);
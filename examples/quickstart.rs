@@ -7,17 +7,11 @@ use std::path::PathBuf;
 use std::{
     env,
     fs::File,
-    io::{self, BufReader, Read, Seek, SeekFrom},
+    io::{BufReader, Read},
 };
 
-use amf_rs::amf0::boolean::BooleanType;
-use amf_rs::amf0::marker::NullType;
-use amf_rs::amf0::marker::UndefinedType;
-use amf_rs::amf0::nested::{Amf0TypedValue, EcmaArrayType, ObjectType};
-use amf_rs::amf0::number::NumberType;
-use amf_rs::amf0::string::{LongStringType, StringType};
-use amf_rs::errors::AmfError;
-use amf_rs::traits::{Marshall, Unmarshall};
+use amf_rs::flv::{FlvReader, TagData};
+use amf_rs::prelude::*;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Single-type examples
@@ -32,6 +26,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Nested object/array example
     example_nested_types()?;
 
+    // Literal macro example
+    example_amf0_macro()?;
+
     // Production‑style FLV metadata extraction
     example_extract_and_parse_flv()?;
 
@@ -126,60 +123,43 @@ fn example_nested_types() -> Result<(), AmfError> {
     Ok(())
 }
 
-/// Example 7 (production style): Extract FLV metadata and parse AMF0 script data.
+/// Example 7: Build a value tree with the `amf0!` literal macro.
+fn example_amf0_macro() -> Result<(), AmfError> {
+    let metadata = amf0!({
+        "duration": 12.4,
+        "hasVideo": true,
+        "keyframes": {
+            "times": [0.0, 2.0, 4.0],
+            "filepositions": [13.0, 2048.0, 4096.0],
+        },
+    });
+    let bytes = metadata.marshall()?;
+    let (decoded, _) = Amf0TypedValue::unmarshall(&bytes)?;
+    println!("[amf0!] Unmarshalled: {}\n", decoded);
+    Ok(())
+}
+
+/// Example 8 (production style): Extract FLV metadata and parse AMF0 script data.
 fn example_extract_and_parse_flv() -> Result<(), Box<dyn std::error::Error>> {
     // Build path to examples/test.flv
     let mut flv_path = PathBuf::from(env::var("CARGO_MANIFEST_DIR")?);
     flv_path.push("examples/test.flv");
 
-    // Extract raw ScriptData tag
-    let data = extract_script_data(flv_path.to_str().unwrap())?;
-    // Parse AMF0 values, skip the "onMetaData" marker
-    let meta = parse_metadata(&data)?;
+    // Read tags until the onMetaData script tag
+    let reader = FlvReader::new(BufReader::new(File::open(flv_path)?))?;
+    let meta = find_metadata(reader)?;
     println!("[FLV Metadata] {}", meta);
     Ok(())
 }
 
-/// Reads an FLV file, locates the ScriptData tag, and returns its raw bytes.
-fn extract_script_data<P: AsRef<str>>(path: P) -> io::Result<Vec<u8>> {
-    let mut rdr = BufReader::new(File::open(path.as_ref())?);
-    let mut hdr = [0u8; 9];
-    rdr.read_exact(&mut hdr)?;
-    if &hdr[0..3] != b"FLV" {
-        return Err(io::Error::new(io::ErrorKind::InvalidData, "Not FLV"));
-    }
-    rdr.seek(SeekFrom::Start(13))?;
-    loop {
-        let mut th = [0u8; 11];
-        if rdr.read_exact(&mut th).is_err() {
-            break;
-        }
-        let len = u32::from_be_bytes([0, th[1], th[2], th[3]]);
-        if th[0] == 18 {
-            let mut buf = vec![0u8; len as usize];
-            rdr.read_exact(&mut buf)?;
-            return Ok(buf);
-        }
-        rdr.seek(SeekFrom::Current(len as i64 + 4))?;
-    }
-    Err(io::Error::new(
-        io::ErrorKind::NotFound,
-        "ScriptData not found",
-    ))
-}
-
-/// Parses AMF0 typed values from script data, skipping the "onMetaData" string marker.
-fn parse_metadata(data: &[u8]) -> Result<String, AmfError> {
-    let mut off = 0;
-    let mut out = String::new();
-    while off < data.len() {
-        let (v, n) = Amf0TypedValue::unmarshall(&data[off..])?;
-        let s = format!("{}", v);
-        if s != "\"onMetaData\"" {
-            out.push_str(&s);
-            out.push(' ');
+/// Returns the onMetaData value of the first script data tag that carries one.
+fn find_metadata<R: Read>(reader: FlvReader<R>) -> Result<Amf0TypedValue, AmfError> {
+    for tag in reader {
+        if let TagData::Script(body) = tag?.data
+            && body.is_on_metadata()
+        {
+            return Ok(body.value);
         }
-        off += n;
     }
-    Ok(out.trim().to_string())
+    Err(AmfError::Custom("onMetaData not found".to_string()))
 }
@@ -0,0 +1,15 @@
+#![no_main]
+
+use amf_rs::amf0::DecodeOptions;
+use amf_rs::amf0::nested::Amf0TypedValue;
+use amf_rs::traits::Marshall;
+use libfuzzer_sys::fuzz_target;
+
+// 任意输入都不能 panic，也不能耗尽内存
+fuzz_target!(|data: &[u8]| {
+    if let Ok((value, n)) = Amf0TypedValue::unmarshall_with(data, &DecodeOptions::default()) {
+        assert!(n <= data.len());
+        value.marshall().unwrap();
+    }
+    let _ = Amf0TypedValue::unmarshall_lossy(data);
+});
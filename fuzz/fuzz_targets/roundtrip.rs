@@ -0,0 +1,13 @@
+#![no_main]
+
+use amf_rs::amf0::nested::Amf0TypedValue;
+use amf_rs::traits::{Marshall, Unmarshall};
+use libfuzzer_sys::fuzz_target;
+
+// marshall -> unmarshall -> marshall 必须得到相同的字节(NaN 使得值本身无法直接比较)
+fuzz_target!(|value: Amf0TypedValue| {
+    let encoded = value.marshall().unwrap();
+    let (decoded, n) = Amf0TypedValue::unmarshall(&encoded).unwrap();
+    assert_eq!(n, encoded.len());
+    assert_eq!(decoded.marshall().unwrap(), encoded);
+});
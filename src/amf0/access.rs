@@ -0,0 +1,744 @@
+use crate::amf0::boolean::BooleanType;
+use crate::amf0::marker::{NullType, UndefinedType};
+use crate::amf0::nested::{Amf0TypedValue, EcmaArrayType, NestedType, ObjectType};
+use crate::amf0::number::NumberType;
+use crate::amf0::raw::RawValue;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use indexmap::IndexMap;
+use std::cmp::Ordering;
+use std::ops::Index;
+
+// 类似于 serde_json::Value 的访问方法，类型不匹配时返回 None
+impl Amf0TypedValue {
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Amf0TypedValue::Number(v) => Some(**v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Amf0TypedValue::Boolean(v) => Some(**v),
+            _ => None,
+        }
+    }
+
+    // Both String and LongString are returned.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Amf0TypedValue::String(v) => Some(v.as_ref().as_ref()),
+            Amf0TypedValue::LongString(v) => Some(v.as_ref().as_ref()),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&ObjectType> {
+        match self {
+            Amf0TypedValue::Object(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_ecma_array(&self) -> Option<&EcmaArrayType> {
+        match self {
+            Amf0TypedValue::EcmaArray(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_strict_array(&self) -> Option<&StrictArrayType> {
+        match self {
+            Amf0TypedValue::StrictArray(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_number_mut(&mut self) -> Option<&mut NumberType> {
+        match self {
+            Amf0TypedValue::Number(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool_mut(&mut self) -> Option<&mut BooleanType> {
+        match self {
+            Amf0TypedValue::Boolean(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut ObjectType> {
+        match self {
+            Amf0TypedValue::Object(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_ecma_array_mut(&mut self) -> Option<&mut EcmaArrayType> {
+        match self {
+            Amf0TypedValue::EcmaArray(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_strict_array_mut(&mut self) -> Option<&mut StrictArrayType> {
+        match self {
+            Amf0TypedValue::StrictArray(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, Amf0TypedValue::Number(_))
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Amf0TypedValue::Boolean(_))
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(
+            self,
+            Amf0TypedValue::String(_) | Amf0TypedValue::LongString(_)
+        )
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Amf0TypedValue::Null(_))
+    }
+
+    pub fn is_undefined(&self) -> bool {
+        matches!(self, Amf0TypedValue::Undefined(_))
+    }
+
+    pub fn as_raw(&self) -> Option<&RawValue> {
+        match self {
+            Amf0TypedValue::Raw(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // The type marker the value is encoded with. Together with `kind_name` and `as_raw` this
+    // lets the `_` arm a match needs (the enum is non-exhaustive) still tell values apart.
+    pub fn type_marker(&self) -> u8 {
+        let marker = match self {
+            Amf0TypedValue::Number(_) => TypeMarker::Number,
+            Amf0TypedValue::Boolean(_) => TypeMarker::Boolean,
+            Amf0TypedValue::String(_) => TypeMarker::String,
+            Amf0TypedValue::Object(_) => TypeMarker::Object,
+            Amf0TypedValue::MovieClip(_) => TypeMarker::MovieClip,
+            Amf0TypedValue::Null(_) => TypeMarker::Null,
+            Amf0TypedValue::Undefined(_) => TypeMarker::Undefined,
+            Amf0TypedValue::Reference(_) => TypeMarker::Reference,
+            Amf0TypedValue::EcmaArray(_) => TypeMarker::EcmaArray,
+            Amf0TypedValue::ObjectEnd(_) => TypeMarker::ObjectEnd,
+            Amf0TypedValue::StrictArray(_) => TypeMarker::StrictArray,
+            Amf0TypedValue::Date(_) => TypeMarker::Date,
+            Amf0TypedValue::LongString(_) => TypeMarker::LongString,
+            Amf0TypedValue::Unsupported(_) => TypeMarker::Unsupported,
+            Amf0TypedValue::Recordset(_) => TypeMarker::Recordset,
+            Amf0TypedValue::XmlDocument(_) => TypeMarker::XmlDocument,
+            Amf0TypedValue::TypedObject(_) => TypeMarker::TypedObject,
+            Amf0TypedValue::Raw(v) => return v.marker,
+        };
+        marker as u8
+    }
+}
+
+// 与 serde_json 一致，索引不存在(或者类型不匹配)时返回 Null 而不是 panic，方便链式访问:
+// value["keyframes"]["times"][3]
+static NULL: Amf0TypedValue = Amf0TypedValue::Null(NullType);
+
+impl Index<&str> for Amf0TypedValue {
+    type Output = Amf0TypedValue;
+
+    fn index(&self, key: &str) -> &Self::Output {
+        self.get_key(key).unwrap_or(&NULL)
+    }
+}
+
+impl Index<usize> for Amf0TypedValue {
+    type Output = Amf0TypedValue;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.get_index(index).unwrap_or(&NULL)
+    }
+}
+
+impl Amf0TypedValue {
+    // Looks up a nested value with a dotted path such as `keyframes.times[3]`: `.name` walks
+    // into an Object or ECMA array, `[n]` into a strict array. Returns None if any step is
+    // missing or the path is malformed. Keys containing `.` or `[` cannot be expressed; use
+    // `pointer` for those.
+    pub fn get_path(&self, path: &str) -> Option<&Amf0TypedValue> {
+        parse_path(path)?
+            .into_iter()
+            .try_fold(self, |current, segment| match segment {
+                Segment::Key(key) => current.get_key(key),
+                Segment::Index(index) => current.get_index(index),
+            })
+    }
+
+    // Sets the value at a `get_path` path and returns the one it replaced, e.g.
+    // `message.set_path("info.code", "NetStream.Play.Start")`. Missing steps are created on
+    // demand: an Object before `.name`, a strict array before `[n]` (Null and Undefined on
+    // the way are replaced likewise), and an index one past the end of an array appends.
+    // Fails on a malformed path, an index further out of bounds, a step into a scalar, or a new
+    // key longer than 65535 bytes; steps created before the failing one are kept.
+    pub fn set_path(
+        &mut self,
+        path: &str,
+        value: impl Into<Amf0TypedValue>,
+    ) -> Result<Option<Amf0TypedValue>, AmfError> {
+        let segments = parse_path(path).ok_or_else(|| AmfError::InvalidPath(path.to_string()))?;
+        let mut current = self;
+        let mut created = false;
+        for segment in segments {
+            (current, created) = current.child_or_insert(segment, path)?;
+        }
+        let previous = std::mem::replace(current, value.into());
+        Ok((!created).then_some(previous))
+    }
+
+    // Removes the value at a `get_path` path and returns it; None if the path is malformed,
+    // empty or leads nowhere. Removing a property keeps the order of the others; removing an
+    // array element shifts the following ones down.
+    pub fn remove_path(&mut self, path: &str) -> Option<Amf0TypedValue> {
+        let mut segments = parse_path(path)?;
+        let last = segments.pop()?;
+        let parent =
+            segments
+                .into_iter()
+                .try_fold(self, |current, segment| match (segment, current) {
+                    (Segment::Key(key), Amf0TypedValue::Object(v)) => v.get_mut(key),
+                    (Segment::Key(key), Amf0TypedValue::EcmaArray(v)) => v.get_mut(key),
+                    (Segment::Index(index), Amf0TypedValue::StrictArray(v)) => v.get_mut(index),
+                    _ => None,
+                })?;
+        match (last, parent) {
+            (Segment::Key(key), Amf0TypedValue::Object(v)) => v.remove(key),
+            (Segment::Key(key), Amf0TypedValue::EcmaArray(v)) => v.remove(key),
+            (Segment::Index(index), Amf0TypedValue::StrictArray(v)) if index < v.len() => {
+                Some(v.remove(index))
+            }
+            _ => None,
+        }
+    }
+
+    // 返回子节点，以及它是否是刚刚创建的(此时值为 Undefined)
+    fn child_or_insert(
+        &mut self,
+        segment: Segment<'_>,
+        path: &str,
+    ) -> Result<(&mut Amf0TypedValue, bool), AmfError> {
+        let placeholder = Amf0TypedValue::Undefined(UndefinedType);
+        match segment {
+            Segment::Key(key) => {
+                if self.is_null() || self.is_undefined() {
+                    *self = Amf0TypedValue::Object(ObjectType::default());
+                }
+                let kind = self.kind_name();
+                let properties = match self {
+                    Amf0TypedValue::Object(v) => &mut **v,
+                    Amf0TypedValue::EcmaArray(v) => &mut **v,
+                    _ => {
+                        return Err(AmfError::TypeMismatch {
+                            want: "object",
+                            got: kind,
+                        }
+                        .at_path(path));
+                    }
+                };
+                if properties.contains_key(key) {
+                    return Ok((properties.get_mut(key).unwrap(), false));
+                }
+                let (index, _) = properties.insert_full(Utf8::new_from_str(key)?, placeholder);
+                Ok((&mut properties[index], true))
+            }
+            Segment::Index(index) => {
+                if self.is_null() || self.is_undefined() {
+                    *self = Amf0TypedValue::StrictArray(StrictArrayType::default());
+                }
+                let kind = self.kind_name();
+                let Amf0TypedValue::StrictArray(values) = self else {
+                    return Err(AmfError::TypeMismatch {
+                        want: "strict array",
+                        got: kind,
+                    }
+                    .at_path(path));
+                };
+                match index.cmp(&values.len()) {
+                    Ordering::Less => Ok((&mut values[index], false)),
+                    Ordering::Equal => {
+                        values.push(placeholder);
+                        Ok((values.last_mut().unwrap(), true))
+                    }
+                    Ordering::Greater => Err(AmfError::IndexOutOfBounds {
+                        index,
+                        len: values.len(),
+                    }
+                    .at_path(path)),
+                }
+            }
+        }
+    }
+
+    // Looks up a nested value with a JSON Pointer (RFC 6901) such as `/keyframes/times/3`,
+    // mirroring `serde_json::Value::pointer`. `~1` and `~0` escape `/` and `~` in keys.
+    pub fn pointer(&self, pointer: &str) -> Option<&Amf0TypedValue> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        if !pointer.starts_with('/') {
+            return None;
+        }
+        pointer[1..].split('/').try_fold(self, |current, token| {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            match current {
+                Amf0TypedValue::StrictArray(_) => current.get_index(parse_index(&token)?),
+                _ => current.get_key(&token),
+            }
+        })
+    }
+
+    // Flattens nested values into one map keyed by `get_path` paths, in document order:
+    // `{"keyframes": {"times": [0.0]}}` gives `keyframes.times[0] -> 0.0`. Empty Objects,
+    // ECMA arrays and strict arrays are kept as leaves so nothing is lost; a scalar root is
+    // stored under the empty path. Handy for feeding metadata into flat key-value stores.
+    pub fn flatten(&self) -> IndexMap<String, Amf0TypedValue> {
+        let mut flat = IndexMap::new();
+        let mut stack = vec![(String::new(), self)];
+        while let Some((path, value)) = stack.pop() {
+            let children: Vec<(String, &Amf0TypedValue)> = match value {
+                Amf0TypedValue::Object(v) if !v.is_empty() => child_paths(&path, v),
+                Amf0TypedValue::EcmaArray(v) if !v.is_empty() => child_paths(&path, v),
+                Amf0TypedValue::StrictArray(v) if !v.is_empty() => v
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| (format!("{}[{}]", path, i), v))
+                    .collect(),
+                _ => {
+                    flat.insert(path, value.clone());
+                    continue;
+                }
+            };
+            // 逆序压栈，保证按文档顺序弹出
+            stack.extend(children.into_iter().rev());
+        }
+        flat
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Amf0TypedValue> {
+        match self {
+            Amf0TypedValue::Object(v) => v.get(key),
+            Amf0TypedValue::EcmaArray(v) => v.get(key),
+            _ => None,
+        }
+    }
+
+    fn get_index(&self, index: usize) -> Option<&Amf0TypedValue> {
+        match self {
+            Amf0TypedValue::StrictArray(v) => v.get(index),
+            _ => None,
+        }
+    }
+}
+
+// Typed lookups for the common case of reading known fields, e.g. `metadata.get_f64("duration")`.
+// On `Amf0TypedValue` the argument is a `get_path` path (`keyframes.times[0]`), on `ObjectType`
+// and `EcmaArrayType` a property name. The `_or_err` variants fail with an `AmfError::Context`
+// holding the path around `MissingValue` or `TypeMismatch`, e.g. `Type mismatch: want number,
+// got strict array (at path keyframes.times)`.
+macro_rules! typed_getters {
+    ($($get:ident, $or_err:ident, $as:ident, $t:ty, $want:literal;)*) => {
+        $(
+            pub fn $get(&self, path: &str) -> Option<$t> {
+                self.lookup(path)?.$as()
+            }
+
+            pub fn $or_err(&self, path: &str) -> Result<$t, AmfError> {
+                let value = self
+                    .lookup(path)
+                    .ok_or_else(|| AmfError::MissingValue.at_path(path))?;
+                value.$as().ok_or_else(|| {
+                    AmfError::TypeMismatch {
+                        want: $want,
+                        got: value.kind_name(),
+                    }
+                    .at_path(path)
+                })
+            }
+        )*
+    };
+}
+
+macro_rules! impl_typed_getters {
+    () => {
+        typed_getters! {
+            get_f64, get_f64_or_err, as_number, f64, "number";
+            get_bool, get_bool_or_err, as_bool, bool, "boolean";
+            get_str, get_str_or_err, as_str, &str, "string";
+            get_object, get_object_or_err, as_object, &ObjectType, "object";
+        }
+    };
+}
+
+impl Amf0TypedValue {
+    impl_typed_getters!();
+
+    fn lookup(&self, path: &str) -> Option<&Amf0TypedValue> {
+        self.get_path(path)
+    }
+
+    // A short human-readable name of the value's type, as used in error messages: "number",
+    // "string", "ECMA array", ...
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Amf0TypedValue::Number(_) => "number",
+            Amf0TypedValue::Boolean(_) => "boolean",
+            Amf0TypedValue::String(_) | Amf0TypedValue::LongString(_) => "string",
+            Amf0TypedValue::Object(_) => "object",
+            Amf0TypedValue::EcmaArray(_) => "ECMA array",
+            Amf0TypedValue::StrictArray(_) => "strict array",
+            Amf0TypedValue::Null(_) => "null",
+            Amf0TypedValue::Undefined(_) => "undefined",
+            _ => "unsupported value",
+        }
+    }
+}
+
+impl<const LBW: usize, const TM: u8> NestedType<LBW, TM> {
+    impl_typed_getters!();
+
+    fn lookup(&self, key: &str) -> Option<&Amf0TypedValue> {
+        self.get(key)
+    }
+}
+
+enum Segment<'p> {
+    Key(&'p str),
+    Index(usize),
+}
+
+// 解析 get_path 的路径语法；空路径得到空的 Vec(指向根值)
+fn parse_path(path: &str) -> Option<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    if path.is_empty() {
+        return Some(segments);
+    }
+    for (i, part) in path.split('.').enumerate() {
+        let (key, mut indices) = match part.find('[') {
+            Some(pos) => part.split_at(pos),
+            None => (part, ""),
+        };
+        // 只有第一段可以省略 key(比如 "[0].name" 直接索引根数组)
+        if !key.is_empty() {
+            segments.push(Segment::Key(key));
+        } else if i > 0 || indices.is_empty() {
+            return None;
+        }
+        while !indices.is_empty() {
+            let end = indices.find(']')?;
+            segments.push(Segment::Index(parse_index(&indices[1..end])?));
+            indices = &indices[end + 1..];
+            if !indices.is_empty() && !indices.starts_with('[') {
+                return None;
+            }
+        }
+    }
+    Some(segments)
+}
+
+fn child_paths<'a>(
+    path: &str,
+    properties: &'a IndexMap<Utf8, Amf0TypedValue>,
+) -> Vec<(String, &'a Amf0TypedValue)> {
+    properties
+        .iter()
+        .map(|(k, v)| match path {
+            "" => (k.to_string(), v),
+            _ => (format!("{}.{}", path, k), v),
+        })
+        .collect()
+}
+
+// 数组下标只接受规范的十进制形式(不允许前导 0 和符号)
+fn parse_index(s: &str) -> Option<usize> {
+    if s.is_empty() || (s.len() > 1 && s.starts_with('0')) || !s.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    s.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::errors::ErrorKind;
+
+    #[test]
+    fn test_catch_all_accessors() {
+        let date = Amf0TypedValue::raw(0x0b, vec![0; 10]);
+        for (value, marker, kind) in [
+            (amf0!(1.0), 0x00, "number"),
+            (amf0!("a"), 0x02, "string"),
+            (amf0!([]), 0x0a, "strict array"),
+            (date.clone(), 0x0b, "unsupported value"),
+        ] {
+            assert_eq!(value.type_marker(), marker);
+            assert_eq!(value.kind_name(), kind);
+        }
+        assert_eq!(date.as_raw().map(|raw| raw.bytes.len()), Some(10));
+        assert_eq!(amf0!(null).as_raw(), None);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let value = amf0!({
+            "duration": 12.5,
+            "stereo": true,
+            "encoder": "Lavf60",
+            "times": [0.0],
+            "cover": null,
+            "tag": undefined,
+        });
+        let object = value.as_object().unwrap();
+        assert!(value.as_ecma_array().is_none());
+        assert_eq!(object["duration"].as_number(), Some(12.5));
+        assert_eq!(object["stereo"].as_bool(), Some(true));
+        assert_eq!(object["encoder"].as_str(), Some("Lavf60"));
+        assert_eq!(object["times"].as_strict_array().map(|v| v.len()), Some(1));
+        assert!(object["cover"].is_null());
+        assert!(object["tag"].is_undefined());
+        assert!(!object["tag"].is_null());
+
+        // 类型不匹配
+        assert_eq!(object["encoder"].as_number(), None);
+        assert_eq!(object["duration"].as_str(), None);
+        assert!(object["duration"].as_object().is_none());
+    }
+
+    #[test]
+    fn test_long_string_as_str() {
+        let long = "a".repeat(u16::MAX as usize + 1);
+        let value = Amf0TypedValue::try_from(long.clone()).unwrap();
+        assert!(value.is_string());
+        assert_eq!(value.as_str(), Some(long.as_str()));
+    }
+
+    #[test]
+    fn test_mutable_accessors() {
+        let mut value = amf0!(1.0);
+        *value.as_number_mut().unwrap() = NumberType::new(2.0);
+        assert_eq!(value.as_number(), Some(2.0));
+        assert!(value.as_bool_mut().is_none());
+
+        let mut value = amf0!([1.0, 2.0]);
+        let array = value.as_strict_array_mut().unwrap();
+        *array = StrictArrayType::new(vec![Amf0TypedValue::Null(NullType)]);
+        assert!(value.as_strict_array().unwrap()[0].is_null());
+    }
+
+    #[test]
+    fn test_index() {
+        let value = amf0!({
+            "width": 1280,
+            "keyframes": {"times": [0.0, 2.0]},
+        });
+        assert_eq!(value["width"].as_number(), Some(1280.0));
+        assert_eq!(value["keyframes"]["times"][1].as_number(), Some(2.0));
+
+        // 缺失的 key、越界的下标以及类型不匹配都得到 Null
+        assert!(value["height"].is_null());
+        assert!(value["keyframes"]["times"][2].is_null());
+        assert!(value["width"]["x"].is_null());
+        assert!(value[0].is_null());
+        assert!(value["missing"]["deeper"][3].is_null());
+    }
+
+    #[test]
+    fn test_index_ecma_array() {
+        let properties = vec![(
+            crate::amf0::utf8::Utf8::new_from_str("duration").unwrap(),
+            Amf0TypedValue::from(3.0),
+        )];
+        let value = Amf0TypedValue::EcmaArray(properties.into_iter().collect());
+        assert_eq!(value["duration"].as_number(), Some(3.0));
+    }
+
+    fn sample_metadata() -> Amf0TypedValue {
+        amf0!({
+            "duration": 12.5,
+            "keyframes": {
+                "times": [0.0, 2.0, 4.0, 6.0],
+                "filepositions": [13.0, 2048.0],
+            },
+            "a/b": {"~c": true},
+            "tracks": [{"id": 1}, {"id": 2}],
+        })
+    }
+
+    #[test]
+    fn test_get_path() {
+        let value = sample_metadata();
+        assert_eq!(value.get_path(""), Some(&value));
+        assert_eq!(
+            value.get_path("duration").and_then(|v| v.as_number()),
+            Some(12.5)
+        );
+        assert_eq!(
+            value
+                .get_path("keyframes.times[3]")
+                .and_then(|v| v.as_number()),
+            Some(6.0)
+        );
+        assert_eq!(
+            value.get_path("tracks[1].id").and_then(|v| v.as_number()),
+            Some(2.0)
+        );
+        let tracks = &value["tracks"];
+        assert_eq!(
+            tracks.get_path("[0].id").and_then(|v| v.as_number()),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn test_get_path_missing_or_malformed() {
+        let value = sample_metadata();
+        for path in [
+            "height",
+            "keyframes.times[4]",
+            "keyframes.times.0",
+            "duration[0]",
+            "keyframes..times",
+            "keyframes.[0]",
+            "keyframes.times[01]",
+            "keyframes.times[-1]",
+            "keyframes.times[1",
+            "keyframes.times[1]x",
+            "tracks[0]id",
+        ] {
+            assert_eq!(value.get_path(path), None, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_flatten() {
+        let value = amf0!({
+            "duration": 12.5,
+            "keyframes": {"times": [0.0, 2.0], "filepositions": []},
+            "tracks": [{"id": 1}],
+            "empty": {},
+        });
+        let flat = value.flatten();
+        assert_eq!(
+            flat.keys().collect::<Vec<_>>(),
+            [
+                "duration",
+                "keyframes.times[0]",
+                "keyframes.times[1]",
+                "keyframes.filepositions",
+                "tracks[0].id",
+                "empty",
+            ]
+        );
+        for (path, v) in &flat {
+            assert_eq!(value.get_path(path), Some(v), "{}", path);
+        }
+        assert_eq!(amf0!(true).flatten()[""], amf0!(true));
+    }
+
+    #[test]
+    fn test_typed_getters() {
+        let value = sample_metadata();
+        assert_eq!(value.get_f64("duration"), Some(12.5));
+        assert_eq!(value.get_f64("keyframes.times[1]"), Some(2.0));
+        assert_eq!(value.get_bool("a/b.~c"), Some(true));
+        assert_eq!(value.get_str("duration"), None);
+        let keyframes = value.get_object("keyframes").unwrap();
+        assert_eq!(keyframes.get_f64("times"), None);
+
+        let err = value.get_f64_or_err("keyframes.times").unwrap_err();
+        assert_eq!(err.path(), Some("keyframes.times"));
+        assert!(matches!(
+            err.without_context(),
+            AmfError::TypeMismatch {
+                want: "number",
+                got: "strict array"
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Type mismatch: want number, got strict array (at path keyframes.times)"
+        );
+        let err = keyframes.get_str_or_err("encoder").unwrap_err();
+        assert_eq!(err.path(), Some("encoder"));
+        assert_eq!(err.kind(), ErrorKind::MissingValue);
+        assert_eq!(value.get_object_or_err("keyframes").unwrap(), keyframes);
+    }
+
+    #[test]
+    fn test_set_and_remove_path() {
+        let mut value = amf0!({"info": {"level": "status"}, "args": [1.0]});
+        assert_eq!(
+            value.set_path("info.level", amf0!("error")).unwrap(),
+            Some(amf0!("status"))
+        );
+        assert_eq!(value.set_path("info.code", amf0!("Failed")).unwrap(), None);
+        assert_eq!(value.set_path("args[1]", 2.0).unwrap(), None);
+        assert_eq!(value.set_path("extra.tracks[0].id", 1.0).unwrap(), None);
+        assert_eq!(
+            value,
+            amf0!({
+                "info": {"level": "error", "code": "Failed"},
+                "args": [1.0, 2.0],
+                "extra": {"tracks": [{"id": 1.0}]},
+            })
+        );
+
+        for (path, kind) in [
+            ("args[3]", ErrorKind::OutOfRange),
+            ("args[0].x", ErrorKind::TypeMismatch),
+            ("info.level.x", ErrorKind::TypeMismatch),
+            ("info..code", ErrorKind::InvalidPath),
+        ] {
+            let err = value.set_path(path, 0.0).unwrap_err();
+            assert_eq!(err.kind(), kind, "{}", path);
+        }
+
+        assert_eq!(value.remove_path("info.level"), Some(amf0!("error")));
+        assert_eq!(value.remove_path("args[0]"), Some(amf0!(1.0)));
+        assert_eq!(value.remove_path("args[1]"), None);
+        assert_eq!(value.remove_path("missing.key"), None);
+        assert_eq!(value.remove_path(""), None);
+        assert_eq!(
+            value,
+            amf0!({"info": {"code": "Failed"}, "args": [2.0], "extra": {"tracks": [{"id": 1.0}]}})
+        );
+    }
+
+    #[test]
+    fn test_pointer() {
+        let value = sample_metadata();
+        assert_eq!(value.pointer(""), Some(&value));
+        assert_eq!(
+            value
+                .pointer("/keyframes/filepositions/1")
+                .and_then(|v| v.as_number()),
+            Some(2048.0)
+        );
+        assert_eq!(
+            value.pointer("/a~1b/~0c").and_then(|v| v.as_bool()),
+            Some(true)
+        );
+        assert_eq!(value.pointer("duration"), None);
+        assert_eq!(value.pointer("/tracks/2"), None);
+        assert_eq!(value.pointer("/tracks/01"), None);
+    }
+}
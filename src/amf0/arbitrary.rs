@@ -0,0 +1,125 @@
+use crate::amf0::boolean::BooleanType;
+use crate::amf0::marker::{NullType, UndefinedType};
+use crate::amf0::nested::{Amf0TypedValue, EcmaArrayType, ObjectType};
+use crate::amf0::number::NumberType;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::string::{LongStringType, StringType};
+use crate::amf0::utf8::Utf8;
+use arbitrary::{Arbitrary, Result, Unstructured};
+use indexmap::IndexMap;
+
+// Containers stop being generated below this depth, which is also the default
+// `DecodeOptions::max_depth`, so every generated tree decodes with the default options.
+const MAX_DEPTH: usize = 64;
+
+// Generates every value `unmarshall` can produce: the unsupported markers, `ObjectEnd` and
+// `Raw` are left out since they do not round-trip. Numbers include NaN with arbitrary payloads,
+// so fuzz targets should compare the re-encoded bytes rather than the values.
+impl<'a> Arbitrary<'a> for Amf0TypedValue {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_value(u, 0)
+    }
+}
+
+fn arbitrary_value(u: &mut Unstructured<'_>, depth: usize) -> Result<Amf0TypedValue> {
+    // 超过深度限制后只生成标量
+    let choices = if depth < MAX_DEPTH { 9 } else { 6 };
+    let value = match u.choose_index(choices)? {
+        0 => Amf0TypedValue::Number(NumberType::new(f64::arbitrary(u)?)),
+        1 => Amf0TypedValue::Boolean(BooleanType::new(bool::arbitrary(u)?)),
+        2 => Amf0TypedValue::String(StringType::new(arbitrary_utf8(u)?)),
+        3 => {
+            let s = <&str>::arbitrary(u)?;
+            Amf0TypedValue::LongString(LongStringType::new_from_str(s).unwrap()) // u32 长度足够
+        }
+        4 => Amf0TypedValue::Null(NullType),
+        5 => Amf0TypedValue::Undefined(UndefinedType),
+        6 => Amf0TypedValue::Object(ObjectType::new(arbitrary_properties(u, depth)?)),
+        7 => Amf0TypedValue::EcmaArray(EcmaArrayType::new(arbitrary_properties(u, depth)?)),
+        _ => {
+            let mut values = Vec::new();
+            for _ in 0..u.arbitrary_len::<u8>()? {
+                values.push(arbitrary_value(u, depth + 1)?);
+            }
+            Amf0TypedValue::StrictArray(StrictArrayType::new(values))
+        }
+    };
+    Ok(value)
+}
+
+fn arbitrary_properties(
+    u: &mut Unstructured<'_>,
+    depth: usize,
+) -> Result<IndexMap<Utf8, Amf0TypedValue>> {
+    let mut properties = IndexMap::new();
+    for _ in 0..u.arbitrary_len::<(u8, u8)>()? {
+        let key = arbitrary_utf8(u)?;
+        properties.insert(key, arbitrary_value(u, depth + 1)?);
+    }
+    Ok(properties)
+}
+
+// 截断到 u16 长度以内(在字符边界上)
+fn arbitrary_utf8(u: &mut Unstructured<'_>) -> Result<Utf8> {
+    let s = <&str>::arbitrary(u)?;
+    let mut end = s.len().min(u16::MAX as usize);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    Ok(Utf8::new_from_str(&s[..end]).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0::decode::DecodeOptions;
+    use crate::traits::{Marshall, Unmarshall};
+
+    // 用确定性的伪随机输入代替 fuzzer
+    fn pseudo_random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_generated_values_round_trip() {
+        for seed in 0..128 {
+            let data = pseudo_random_bytes(seed, 1024);
+            let mut u = Unstructured::new(&data);
+            let value = Amf0TypedValue::arbitrary(&mut u).unwrap();
+            let encoded = value.marshall().unwrap();
+            let (decoded, n) = Amf0TypedValue::unmarshall(&encoded).unwrap();
+            assert_eq!(n, encoded.len());
+            assert_eq!(decoded.marshall().unwrap(), encoded);
+            let (decoded, _) =
+                Amf0TypedValue::unmarshall_with(&encoded, &DecodeOptions::default()).unwrap();
+            assert_eq!(decoded.marshall().unwrap(), encoded);
+        }
+    }
+
+    #[test]
+    fn test_depth_is_bounded() {
+        fn depth(value: &Amf0TypedValue) -> usize {
+            match value {
+                Amf0TypedValue::StrictArray(v) => 1 + v.iter().map(depth).max().unwrap_or(0),
+                Amf0TypedValue::Object(v) => 1 + v.values().map(depth).max().unwrap_or(0),
+                Amf0TypedValue::EcmaArray(v) => 1 + v.values().map(depth).max().unwrap_or(0),
+                _ => 0,
+            }
+        }
+
+        // 0x08 总是选择 StrictArray 分支，并且给出非零的长度
+        let data = vec![0x08; 1 << 12];
+        let value = Amf0TypedValue::arbitrary(&mut Unstructured::new(&data)).unwrap();
+        assert_eq!(depth(&value), MAX_DEPTH);
+        let encoded = value.marshall().unwrap();
+        assert!(Amf0TypedValue::unmarshall_with(&encoded, &DecodeOptions::default()).is_ok());
+    }
+}
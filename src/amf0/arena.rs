@@ -0,0 +1,370 @@
+use crate::amf0::borrowed::Amf0ValueRef;
+use crate::amf0::decode::{DEFAULT_MAX_DEPTH, check_limit};
+use crate::amf0::nested::{Amf0TypedValue, MAX_PREALLOCATED_VALUES};
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::{CowUtf8, CowUtf8Long, unmarshall_str};
+use crate::errors::AmfError;
+use crate::traits::{Marshall, MarshallLength};
+use bumpalo::Bump;
+use bumpalo::collections::Vec as BumpVec;
+
+// A decoded AMF 0 value whose strings, keys and containers all live in a `bumpalo::Bump`
+// arena, produced by `ArenaValue::unmarshall_in`. Nothing is allocated on the global heap and
+// nothing is freed per node: resetting or dropping the arena releases the whole document at
+// once, which suits services decoding large numbers of small command messages (reuse one
+// `Bump` and call `reset` between messages). Unlike `Amf0ValueRef`, the value does not borrow
+// the input buffer. Properties are kept in wire order, duplicates included.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum ArenaValue<'bump> {
+    Number(f64),
+    Boolean(bool),
+    String(&'bump str),
+    LongString(&'bump str),
+    Object(&'bump [(&'bump str, ArenaValue<'bump>)]),
+    EcmaArray(&'bump [(&'bump str, ArenaValue<'bump>)]),
+    StrictArray(&'bump [ArenaValue<'bump>]),
+    Null,
+    Undefined,
+}
+
+impl<'bump> ArenaValue<'bump> {
+    // Decodes one value like `Amf0TypedValue::unmarshall` into `bump`, returning it with the
+    // number of bytes consumed. On error, whatever was allocated so far stays in the arena
+    // until it is reset. As with `Amf0ValueRef::unmarshall_borrowed`, nesting is limited to the
+    // default `DecodeOptions::max_depth`.
+    pub fn unmarshall_in(buf: &[u8], bump: &'bump Bump) -> Result<(Self, usize), AmfError> {
+        Self::unmarshall_at(buf, bump, 0)
+    }
+
+    // depth 是包含这个值的容器层数
+    fn unmarshall_at(
+        buf: &[u8],
+        bump: &'bump Bump,
+        depth: usize,
+    ) -> Result<(Self, usize), AmfError> {
+        if buf.is_empty() {
+            return Err(AmfError::BufferTooSmall { want: 1, got: 0 });
+        }
+        let rest = &buf[1..];
+        let marker = TypeMarker::try_from(buf[0])?;
+        if matches!(
+            marker,
+            TypeMarker::Object | TypeMarker::EcmaArray | TypeMarker::StrictArray
+        ) {
+            check_limit("max_depth", DEFAULT_MAX_DEPTH, depth + 1)?;
+        }
+        let (value, n) = match marker {
+            TypeMarker::Number => {
+                let bytes = rest.get(..8).ok_or(AmfError::BufferTooSmall {
+                    want: 9,
+                    got: buf.len(),
+                })?;
+                (
+                    ArenaValue::Number(f64::from_be_bytes(bytes.try_into().unwrap())),
+                    8,
+                )
+            }
+            TypeMarker::Boolean => {
+                let byte = rest.first().ok_or(AmfError::BufferTooSmall {
+                    want: 2,
+                    got: buf.len(),
+                })?;
+                (ArenaValue::Boolean(*byte != 0), 1)
+            }
+            TypeMarker::String => {
+                let (s, n) = unmarshall_str::<2>(rest)?;
+                (ArenaValue::String(bump.alloc_str(s)), n)
+            }
+            TypeMarker::LongString => {
+                let (s, n) = unmarshall_str::<4>(rest)?;
+                (ArenaValue::LongString(bump.alloc_str(s)), n)
+            }
+            TypeMarker::Null => (ArenaValue::Null, 0),
+            TypeMarker::Undefined => (ArenaValue::Undefined, 0),
+            TypeMarker::Object => {
+                let (properties, n) = unmarshall_properties(rest, bump, depth + 1)?;
+                (ArenaValue::Object(properties), n)
+            }
+            TypeMarker::EcmaArray => {
+                let count = rest.get(..4).ok_or(AmfError::BufferTooSmall {
+                    want: 5,
+                    got: buf.len(),
+                })?;
+                let declared = u32::from_be_bytes(count.try_into().unwrap());
+                let (properties, n) = unmarshall_properties(&rest[4..], bump, depth + 1)?;
+                if properties.len() != declared as usize {
+                    return Err(AmfError::EcmaArrayCountMismatch {
+                        declared,
+                        actual: properties.len(),
+                    });
+                }
+                (ArenaValue::EcmaArray(properties), 4 + n)
+            }
+            TypeMarker::StrictArray => {
+                let count = rest.get(..4).ok_or(AmfError::BufferTooSmall {
+                    want: 5,
+                    got: buf.len(),
+                })?;
+                let count = u32::from_be_bytes(count.try_into().unwrap()) as usize;
+                let mut values =
+                    BumpVec::with_capacity_in(count.min(MAX_PREALLOCATED_VALUES), bump);
+                let mut offset = 4;
+                for _ in 0..count {
+                    let (value, n) = Self::unmarshall_at(&rest[offset..], bump, depth + 1)?;
+                    values.push(value);
+                    offset += n;
+                }
+                (ArenaValue::StrictArray(values.into_bump_slice()), offset)
+            }
+            TypeMarker::ObjectEnd => return Err(AmfError::InvalidObjectEnd),
+            _ => return Err(AmfError::UnsupportedTypeMarker(buf[0])),
+        };
+        Ok((value, 1 + n))
+    }
+
+    // The value of property `key` of an Object or ECMA array; with duplicate keys the last one
+    // wins, as in `Amf0TypedValue::unmarshall`.
+    pub fn get(&self, key: &str) -> Option<&ArenaValue<'bump>> {
+        match self {
+            ArenaValue::Object(properties) | ArenaValue::EcmaArray(properties) => properties
+                .iter()
+                .rev()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            ArenaValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'bump str> {
+        match self {
+            ArenaValue::String(s) | ArenaValue::LongString(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // Copies the value out of the arena, with the same semantics as `Amf0ValueRef::into_owned`.
+    pub fn to_owned_value(&self) -> Amf0TypedValue {
+        self.as_value_ref().into_owned()
+    }
+
+    // A view of the same value as `Amf0ValueRef`; strings are borrowed from the arena.
+    pub fn as_value_ref(&self) -> Amf0ValueRef<'bump> {
+        // 长度在解码时已经检查过
+        let properties = |properties: &[(&'bump str, ArenaValue<'bump>)]| {
+            properties
+                .iter()
+                .map(|(k, v)| (CowUtf8::new(*k).unwrap(), v.as_value_ref()))
+                .collect()
+        };
+        match *self {
+            ArenaValue::Number(n) => Amf0ValueRef::Number(n),
+            ArenaValue::Boolean(b) => Amf0ValueRef::Boolean(b),
+            ArenaValue::String(s) => Amf0ValueRef::String(CowUtf8::new(s).unwrap()),
+            ArenaValue::LongString(s) => Amf0ValueRef::LongString(CowUtf8Long::new(s).unwrap()),
+            ArenaValue::Object(p) => Amf0ValueRef::Object(properties(p)),
+            ArenaValue::EcmaArray(p) => Amf0ValueRef::EcmaArray(properties(p)),
+            ArenaValue::StrictArray(values) => {
+                Amf0ValueRef::StrictArray(values.iter().map(Self::as_value_ref).collect())
+            }
+            ArenaValue::Null => Amf0ValueRef::Null,
+            ArenaValue::Undefined => Amf0ValueRef::Undefined,
+        }
+    }
+}
+
+// 解析属性直到 object end，返回消费的字节数(包括 object end)
+#[allow(clippy::type_complexity)]
+fn unmarshall_properties<'bump>(
+    buf: &[u8],
+    bump: &'bump Bump,
+    depth: usize,
+) -> Result<(&'bump [(&'bump str, ArenaValue<'bump>)], usize), AmfError> {
+    let mut properties = BumpVec::new_in(bump);
+    let mut offset = 0;
+    loop {
+        if buf[offset..].starts_with(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]) {
+            return Ok((properties.into_bump_slice(), offset + 3));
+        }
+        let (key, n) = unmarshall_str::<2>(&buf[offset..])?;
+        offset += n;
+        let key = &*bump.alloc_str(key);
+        let (value, n) = ArenaValue::unmarshall_at(&buf[offset..], bump, depth)?;
+        offset += n;
+        properties.push((key, value));
+    }
+}
+
+impl Marshall for ArenaValue<'_> {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        self.write_to(buf);
+        Ok(())
+    }
+}
+
+impl ArenaValue<'_> {
+    // 解码得到的值总是可以编码，不会失败
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            ArenaValue::Number(n) => {
+                buf.push(TypeMarker::Number as u8);
+                buf.extend_from_slice(&n.to_be_bytes());
+            }
+            ArenaValue::Boolean(b) => buf.extend_from_slice(&[TypeMarker::Boolean as u8, *b as u8]),
+            ArenaValue::String(s) => {
+                buf.push(TypeMarker::String as u8);
+                buf.extend_from_slice(&(s.len() as u16).to_be_bytes());
+                buf.extend_from_slice(s.as_bytes());
+            }
+            ArenaValue::LongString(s) => {
+                buf.push(TypeMarker::LongString as u8);
+                buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+                buf.extend_from_slice(s.as_bytes());
+            }
+            ArenaValue::Object(properties) => {
+                buf.push(TypeMarker::Object as u8);
+                marshall_properties_to(properties, buf);
+            }
+            ArenaValue::EcmaArray(properties) => {
+                buf.push(TypeMarker::EcmaArray as u8);
+                buf.extend_from_slice(&(properties.len() as u32).to_be_bytes());
+                marshall_properties_to(properties, buf);
+            }
+            ArenaValue::StrictArray(values) => {
+                buf.push(TypeMarker::StrictArray as u8);
+                buf.extend_from_slice(&(values.len() as u32).to_be_bytes());
+                for value in values.iter() {
+                    value.write_to(buf);
+                }
+            }
+            ArenaValue::Null => buf.push(TypeMarker::Null as u8),
+            ArenaValue::Undefined => buf.push(TypeMarker::Undefined as u8),
+        }
+    }
+}
+
+fn marshall_properties_to(properties: &[(&str, ArenaValue<'_>)], buf: &mut Vec<u8>) {
+    for (k, v) in properties {
+        buf.extend_from_slice(&(k.len() as u16).to_be_bytes());
+        buf.extend_from_slice(k.as_bytes());
+        v.write_to(buf);
+    }
+    buf.extend_from_slice(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]);
+}
+
+impl MarshallLength for ArenaValue<'_> {
+    fn marshall_length(&self) -> usize {
+        let properties_length = |properties: &[(&str, ArenaValue<'_>)]| -> usize {
+            properties
+                .iter()
+                .map(|(k, v)| 2 + k.len() + v.marshall_length())
+                .sum::<usize>()
+                + 3 // object end
+        };
+        match self {
+            ArenaValue::Number(_) => 9,
+            ArenaValue::Boolean(_) => 2,
+            ArenaValue::String(s) => 1 + 2 + s.len(),
+            ArenaValue::LongString(s) => 1 + 4 + s.len(),
+            ArenaValue::Object(properties) => 1 + properties_length(properties),
+            ArenaValue::EcmaArray(properties) => 1 + 4 + properties_length(properties),
+            ArenaValue::StrictArray(values) => {
+                1 + 4 + values.iter().map(|v| v.marshall_length()).sum::<usize>()
+            }
+            ArenaValue::Null | ArenaValue::Undefined => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::traits::Unmarshall;
+
+    #[test]
+    fn test_unmarshall_in_matches_unmarshall() {
+        let value = amf0!({
+            "duration": 12.5,
+            "encoder": "Lavf60",
+            "hasAudio": true,
+            "keyframes": {"times": [0.0, 2.0], "tag": undefined},
+            "cover": null,
+        });
+        let ecma = Amf0TypedValue::EcmaArray(value.as_object().unwrap().clone().into_ecma_array());
+        let mut bump = Bump::new();
+        for v in [value, ecma] {
+            let data = v.marshall().unwrap();
+            let (decoded, n) = ArenaValue::unmarshall_in(&data, &bump).unwrap();
+            assert_eq!(n, data.len());
+            assert_eq!(decoded.marshall_length(), data.len());
+            assert_eq!(decoded.marshall().unwrap(), data);
+            assert_eq!(
+                decoded.to_owned_value(),
+                Amf0TypedValue::unmarshall(&data).unwrap().0
+            );
+            assert_eq!(
+                decoded.as_value_ref(),
+                Amf0ValueRef::unmarshall_borrowed(&data).unwrap().0
+            );
+
+            // 字符串在 arena 中，而不是指向输入
+            let encoder = decoded.get("encoder").and_then(|v| v.as_str()).unwrap();
+            assert_eq!(encoder, "Lavf60");
+            assert!(!data.as_ptr_range().contains(&encoder.as_ptr()));
+            assert_eq!(
+                decoded
+                    .get("keyframes")
+                    .and_then(|v| v.get("times"))
+                    .map(|v| v.marshall_length()),
+                Some(1 + 4 + 9 * 2)
+            );
+        }
+        assert!(bump.allocated_bytes() > 0);
+        bump.reset();
+    }
+
+    #[test]
+    fn test_deep_nesting_is_limited() {
+        // 二十万层嵌套的 strict array
+        let mut data = [0x0a, 0x00, 0x00, 0x00, 0x01].repeat(200_000);
+        data.push(0x05);
+        assert!(matches!(
+            ArenaValue::unmarshall_in(&data, &Bump::new()),
+            Err(AmfError::LimitExceeded {
+                limit: "max_depth",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        let bump = Bump::new();
+        let data = amf0!({"a": [1.0, "b"]}).marshall().unwrap();
+        for end in 0..data.len() {
+            assert!(ArenaValue::unmarshall_in(&data[..end], &bump).is_err());
+        }
+        assert!(matches!(
+            ArenaValue::unmarshall_in(&[0x02, 0x00, 0x02, 0xc3, 0x28], &bump),
+            Err(AmfError::InvalidUtf8(_))
+        ));
+        assert!(matches!(
+            ArenaValue::unmarshall_in(&[0x0a, 0xff, 0xff, 0xff, 0xff, 0x05], &bump),
+            Err(AmfError::BufferTooSmall { .. })
+        ));
+    }
+}
@@ -0,0 +1,279 @@
+use crate::amf0::decode::DecodeOptions;
+use crate::amf0::nested::Amf0TypedValue;
+use crate::errors::AmfError;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+// Decodes a sequence of AMF 0 values from an `AsyncRead` (e.g. a `TcpStream`).
+//
+// Bytes read from the underlying reader are kept in an internal buffer until a complete value
+// can be decoded, so `next_value` is cancellation-safe: if the future is dropped (for example
+// inside `tokio::select!`) no data is lost and the next call resumes where the previous one
+// stopped.
+//
+// Values are decoded with `unmarshall_with` and the `DecodeOptions` given at construction, so
+// a peer cannot make the decoder buffer more than `max_total_bytes` for one value (e.g. by
+// announcing a 4 GB LongString and then sending it a byte at a time). An incomplete value is
+// only decoded again once the bytes it was missing have arrived.
+#[derive(Debug)]
+pub struct AsyncDecoder<R> {
+    reader: R,
+    buf: Vec<u8>,
+    options: DecodeOptions,
+    // 上一次解码时缺少数据，至少要缓冲这么多字节才值得重新解码
+    wanted: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, DecodeOptions::default())
+    }
+
+    pub fn with_capacity(reader: R, capacity: usize) -> Self {
+        let mut decoder = Self::new(reader);
+        decoder.buf.reserve(capacity);
+        decoder
+    }
+
+    pub fn with_options(reader: R, options: DecodeOptions) -> Self {
+        Self {
+            reader,
+            buf: Vec::with_capacity(READ_CHUNK_SIZE),
+            options,
+            wanted: 0,
+        }
+    }
+
+    // Returns `Ok(None)` once the reader reaches EOF on a value boundary. EOF in the middle of a
+    // value is reported as an `UnexpectedEof` io error. Decode errors are those of
+    // `unmarshall_with`, wrapped in `AmfError::Context`.
+    pub async fn next_value(&mut self) -> Result<Option<Amf0TypedValue>, AmfError> {
+        loop {
+            if !self.buf.is_empty() && self.buf.len() >= self.wanted {
+                match Amf0TypedValue::unmarshall_with(&self.buf, &self.options) {
+                    Ok((value, consumed)) => {
+                        self.buf.drain(..consumed);
+                        self.wanted = 0;
+                        return Ok(Some(value));
+                    }
+                    // 数据还不完整，继续从 reader 读取
+                    Err(e) => match e.without_context() {
+                        AmfError::BufferTooSmall { want, .. } => self.wanted = *want,
+                        _ => return Err(e),
+                    },
+                }
+            }
+
+            // 缺少的字节数已知时一次读够，但单个值不会超过 max_total_bytes(否则解码已经失败)
+            let limit = self
+                .wanted
+                .saturating_sub(self.buf.len())
+                .max(READ_CHUNK_SIZE)
+                .min(self.options.max_total_bytes.saturating_sub(self.buf.len()))
+                .max(1);
+            self.buf.reserve(limit.min(READ_CHUNK_SIZE));
+            // read_buf 是 cancellation-safe 的：读到的数据只会追加到 self.buf 中
+            let n = (&mut self.reader)
+                .take(limit as u64)
+                .read_buf(&mut self.buf)
+                .await?;
+            if n == 0 {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                return Err(AmfError::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "EOF with {} bytes of incomplete AMF 0 value",
+                        self.buf.len()
+                    ),
+                )));
+            }
+        }
+    }
+
+    // Bytes that have been read from the underlying reader but not yet decoded.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    // Returns the reader together with any bytes already buffered but not yet decoded.
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        (self.reader, self.buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0::nested::ObjectType;
+    use crate::amf0::number::NumberType;
+    use crate::amf0::string::StringType;
+    use crate::amf0::utf8::Utf8;
+    use crate::traits::Marshall;
+    use indexmap::IndexMap;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::io::ReadBuf;
+
+    // 每次只返回一个字节的 reader，用来模拟网络上被拆分的数据
+    struct OneByteReader {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    impl AsyncRead for OneByteReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            if self.pos < self.data.len() && buf.remaining() > 0 {
+                let b = self.data[self.pos];
+                buf.put_slice(&[b]);
+                self.pos += 1;
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn command_values() -> Vec<Amf0TypedValue> {
+        let mut props = IndexMap::new();
+        props.insert(
+            Utf8::new_from_str("app").unwrap(),
+            Amf0TypedValue::String(StringType::new_from_str("live").unwrap()),
+        );
+        props.insert(
+            Utf8::new_from_str("objectEncoding").unwrap(),
+            Amf0TypedValue::Number(NumberType::new(0.0)),
+        );
+        vec![
+            Amf0TypedValue::String(StringType::new_from_str("connect").unwrap()),
+            Amf0TypedValue::Number(NumberType::new(1.0)),
+            Amf0TypedValue::Object(ObjectType::new(props)),
+        ]
+    }
+
+    fn encode(values: &[Amf0TypedValue]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.marshall().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_next_value_sequence() {
+        let values = command_values();
+        let data = encode(&values);
+        let mut decoder = AsyncDecoder::new(data.as_slice());
+        for expected in &values {
+            assert_eq!(decoder.next_value().await.unwrap().as_ref(), Some(expected));
+        }
+        assert!(decoder.next_value().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_next_value_split_reads() {
+        let values = command_values();
+        let reader = OneByteReader {
+            data: encode(&values),
+            pos: 0,
+        };
+        let mut decoder = AsyncDecoder::new(reader);
+        for expected in &values {
+            assert_eq!(decoder.next_value().await.unwrap().as_ref(), Some(expected));
+        }
+        assert!(decoder.next_value().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_next_value_unexpected_eof() {
+        let data = encode(&command_values());
+        let truncated = &data[..data.len() - 1];
+        let mut decoder = AsyncDecoder::new(truncated);
+        decoder.next_value().await.unwrap();
+        decoder.next_value().await.unwrap();
+        let err = decoder.next_value().await.unwrap_err();
+        assert!(matches!(err, AmfError::Io(ref e) if e.kind() == io::ErrorKind::UnexpectedEof));
+    }
+
+    #[tokio::test]
+    async fn test_next_value_invalid_marker() {
+        let data = [0xffu8, 0x00];
+        let mut decoder = AsyncDecoder::new(&data[..]);
+        let err = decoder.next_value().await.unwrap_err();
+        assert!(matches!(
+            err.without_context(),
+            AmfError::InvalidTypeMarker(0xff)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dripped_long_string_is_capped() {
+        // LongString 声明了 4 GB，之后每次只发送一个字节
+        let mut data = vec![0x0c, 0xff, 0xff, 0xff, 0xff];
+        data.resize(4096, b'a');
+        let reader = OneByteReader { data, pos: 0 };
+        let options = DecodeOptions {
+            max_total_bytes: 1024,
+            ..Default::default()
+        };
+        let mut decoder = AsyncDecoder::with_options(reader, options);
+        let err = decoder.next_value().await.unwrap_err();
+        assert_eq!(err.kind(), crate::errors::ErrorKind::LimitExceeded);
+        assert!(decoder.buffer().len() <= 1024);
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_wanted_bytes() {
+        let data = encode(&command_values());
+        let (mut tx, rx) = tokio::io::duplex(64);
+        let mut decoder = AsyncDecoder::new(rx);
+
+        // 字符串 "connect" 的长度前缀到了之后，剩下的字节到齐之前不会再解码
+        tokio::io::AsyncWriteExt::write_all(&mut tx, &data[..4])
+            .await
+            .unwrap();
+        tokio::select! {
+            biased;
+            _ = decoder.next_value() => panic!("value must not be complete yet"),
+            _ = tokio::task::yield_now() => {}
+        }
+        assert_eq!(decoder.wanted, 10);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_read_keeps_buffered_bytes() {
+        let values = command_values();
+        let data = encode(&values);
+        let (mut tx, rx) = tokio::io::duplex(64);
+        let mut decoder = AsyncDecoder::new(rx);
+
+        // 只写入第一个值的一部分，next_value 会阻塞在读取上，然后被取消
+        tokio::io::AsyncWriteExt::write_all(&mut tx, &data[..4])
+            .await
+            .unwrap();
+        tokio::select! {
+            biased;
+            _ = decoder.next_value() => panic!("value must not be complete yet"),
+            _ = tokio::task::yield_now() => {}
+        }
+        assert_eq!(decoder.buffer(), &data[..4]);
+
+        tokio::io::AsyncWriteExt::write_all(&mut tx, &data[4..])
+            .await
+            .unwrap();
+        drop(tx);
+        for expected in &values {
+            assert_eq!(decoder.next_value().await.unwrap().as_ref(), Some(expected));
+        }
+        assert!(decoder.next_value().await.unwrap().is_none());
+    }
+}
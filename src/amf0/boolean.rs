@@ -26,11 +26,16 @@ impl BooleanType {
 
 impl Marshall for BooleanType {
     fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
         debug_assert!(self.type_marker == TypeMarker::Boolean);
-        let mut buf = [0u8; 2];
-        buf[0] = self.type_marker as u8; // 单字节情况下不用考虑字节序
-        buf[1] = self.value as u8;
-        Ok(buf.to_vec())
+        buf.push(self.type_marker as u8); // 单字节情况下不用考虑字节序
+        buf.push(self.value as u8);
+        Ok(())
     }
 }
 
@@ -0,0 +1,383 @@
+use crate::amf0::boolean::BooleanType;
+use crate::amf0::decode::{DEFAULT_MAX_DEPTH, check_limit};
+use crate::amf0::marker::{NullType, UndefinedType};
+use crate::amf0::nested::{Amf0TypedValue, EcmaArrayType, MAX_PREALLOCATED_VALUES, ObjectType};
+use crate::amf0::number::NumberType;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::string::{LongStringType, StringType};
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::{CowUtf8, CowUtf8Long};
+use crate::errors::AmfError;
+use crate::traits::{Marshall, MarshallLength};
+use indexmap::IndexMap;
+
+// A decoded AMF 0 value whose strings and property keys borrow from the input buffer, produced
+// by `Amf0ValueRef::unmarshall_borrowed`. Meant for inspecting large documents (e.g. FLV
+// metadata with tens of thousands of keyframes) without copying every string; `into_owned`
+// converts to an `Amf0TypedValue` when the value has to outlive the buffer. Properties are kept
+// in wire order, duplicates included, so re-encoding reproduces the input.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Amf0ValueRef<'a> {
+    Number(f64),
+    Boolean(bool),
+    String(CowUtf8<'a>),
+    LongString(CowUtf8Long<'a>),
+    Object(Vec<(CowUtf8<'a>, Amf0ValueRef<'a>)>),
+    EcmaArray(Vec<(CowUtf8<'a>, Amf0ValueRef<'a>)>),
+    StrictArray(Vec<Amf0ValueRef<'a>>),
+    Null,
+    Undefined,
+}
+
+impl<'a> Amf0ValueRef<'a> {
+    // Decodes one value like `Amf0TypedValue::unmarshall`, returning it with the number of bytes
+    // consumed. Containers are decoded recursively, so nesting is limited to the default
+    // `DecodeOptions::max_depth`; deeper input fails with `AmfError::LimitExceeded`.
+    pub fn unmarshall_borrowed(buf: &'a [u8]) -> Result<(Self, usize), AmfError> {
+        Self::unmarshall_at(buf, 0)
+    }
+
+    // depth 是包含这个值的容器层数
+    fn unmarshall_at(buf: &'a [u8], depth: usize) -> Result<(Self, usize), AmfError> {
+        if buf.is_empty() {
+            return Err(AmfError::BufferTooSmall { want: 1, got: 0 });
+        }
+        let rest = &buf[1..];
+        let marker = TypeMarker::try_from(buf[0])?;
+        if matches!(
+            marker,
+            TypeMarker::Object | TypeMarker::EcmaArray | TypeMarker::StrictArray
+        ) {
+            check_limit("max_depth", DEFAULT_MAX_DEPTH, depth + 1)?;
+        }
+        let (value, n) = match marker {
+            TypeMarker::Number => {
+                let bytes = rest.get(..8).ok_or(AmfError::BufferTooSmall {
+                    want: 9,
+                    got: buf.len(),
+                })?;
+                let value = f64::from_be_bytes(bytes.try_into().unwrap());
+                (Amf0ValueRef::Number(value), 8)
+            }
+            TypeMarker::Boolean => {
+                let byte = rest.first().ok_or(AmfError::BufferTooSmall {
+                    want: 2,
+                    got: buf.len(),
+                })?;
+                (Amf0ValueRef::Boolean(*byte != 0), 1)
+            }
+            TypeMarker::String => {
+                let (s, n) = CowUtf8::unmarshall_borrowed(rest)?;
+                (Amf0ValueRef::String(s), n)
+            }
+            TypeMarker::LongString => {
+                let (s, n) = CowUtf8Long::unmarshall_borrowed(rest)?;
+                (Amf0ValueRef::LongString(s), n)
+            }
+            TypeMarker::Null => (Amf0ValueRef::Null, 0),
+            TypeMarker::Undefined => (Amf0ValueRef::Undefined, 0),
+            TypeMarker::Object => {
+                let (properties, n) = unmarshall_properties(rest, depth + 1)?;
+                (Amf0ValueRef::Object(properties), n)
+            }
+            TypeMarker::EcmaArray => {
+                let count = rest.get(..4).ok_or(AmfError::BufferTooSmall {
+                    want: 5,
+                    got: buf.len(),
+                })?;
+                let declared = u32::from_be_bytes(count.try_into().unwrap());
+                let (properties, n) = unmarshall_properties(&rest[4..], depth + 1)?;
+                if properties.len() != declared as usize {
+                    return Err(AmfError::EcmaArrayCountMismatch {
+                        declared,
+                        actual: properties.len(),
+                    });
+                }
+                (Amf0ValueRef::EcmaArray(properties), 4 + n)
+            }
+            TypeMarker::StrictArray => {
+                let count = rest.get(..4).ok_or(AmfError::BufferTooSmall {
+                    want: 5,
+                    got: buf.len(),
+                })?;
+                let count = u32::from_be_bytes(count.try_into().unwrap()) as usize;
+                let mut values = Vec::with_capacity(count.min(MAX_PREALLOCATED_VALUES));
+                let mut offset = 4;
+                for _ in 0..count {
+                    let (value, n) = Self::unmarshall_at(&rest[offset..], depth + 1)?;
+                    values.push(value);
+                    offset += n;
+                }
+                (Amf0ValueRef::StrictArray(values), offset)
+            }
+            TypeMarker::ObjectEnd => return Err(AmfError::InvalidObjectEnd),
+            _ => return Err(AmfError::UnsupportedTypeMarker(buf[0])),
+        };
+        Ok((value, 1 + n))
+    }
+
+    // The value of property `key` of an Object or ECMA array; with duplicate keys the last one
+    // wins, as in `Amf0TypedValue::unmarshall`.
+    pub fn get(&self, key: &str) -> Option<&Amf0ValueRef<'a>> {
+        match self {
+            Amf0ValueRef::Object(properties) | Amf0ValueRef::EcmaArray(properties) => properties
+                .iter()
+                .rev()
+                .find(|(k, _)| &**k == key)
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Amf0ValueRef::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Amf0ValueRef::String(s) => Some(s),
+            Amf0ValueRef::LongString(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // Copies every borrowed string. Duplicate keys collapse into one property, the last value
+    // winning, exactly as `Amf0TypedValue::unmarshall` would have decoded the same bytes.
+    pub fn into_owned(self) -> Amf0TypedValue {
+        match self {
+            Amf0ValueRef::Number(n) => Amf0TypedValue::Number(NumberType::new(n)),
+            Amf0ValueRef::Boolean(b) => Amf0TypedValue::Boolean(BooleanType::new(b)),
+            Amf0ValueRef::String(s) => Amf0TypedValue::String(StringType::new(s.into_owned())),
+            Amf0ValueRef::LongString(s) => {
+                Amf0TypedValue::LongString(LongStringType::new(s.into_owned()))
+            }
+            Amf0ValueRef::Object(properties) => {
+                Amf0TypedValue::Object(ObjectType::new(into_owned_properties(properties)))
+            }
+            Amf0ValueRef::EcmaArray(properties) => {
+                Amf0TypedValue::EcmaArray(EcmaArrayType::new(into_owned_properties(properties)))
+            }
+            Amf0ValueRef::StrictArray(values) => Amf0TypedValue::StrictArray(StrictArrayType::new(
+                values.into_iter().map(Self::into_owned).collect(),
+            )),
+            Amf0ValueRef::Null => Amf0TypedValue::Null(NullType),
+            Amf0ValueRef::Undefined => Amf0TypedValue::Undefined(UndefinedType),
+        }
+    }
+}
+
+fn into_owned_properties(
+    properties: Vec<(CowUtf8<'_>, Amf0ValueRef<'_>)>,
+) -> IndexMap<crate::amf0::utf8::Utf8, Amf0TypedValue> {
+    properties
+        .into_iter()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect()
+}
+
+// 解析属性直到 object end，返回消费的字节数(包括 object end)
+#[allow(clippy::type_complexity)]
+fn unmarshall_properties(
+    buf: &[u8],
+    depth: usize,
+) -> Result<(Vec<(CowUtf8<'_>, Amf0ValueRef<'_>)>, usize), AmfError> {
+    let mut properties = Vec::new();
+    let mut offset = 0;
+    loop {
+        if buf[offset..].starts_with(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]) {
+            return Ok((properties, offset + 3));
+        }
+        let (key, n) = CowUtf8::unmarshall_borrowed(&buf[offset..])?;
+        offset += n;
+        let (value, n) = Amf0ValueRef::unmarshall_at(&buf[offset..], depth)?;
+        offset += n;
+        properties.push((key, value));
+    }
+}
+
+impl Marshall for Amf0ValueRef<'_> {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        match self {
+            Amf0ValueRef::Number(n) => {
+                buf.push(TypeMarker::Number as u8);
+                buf.extend_from_slice(&n.to_be_bytes());
+            }
+            Amf0ValueRef::Boolean(b) => {
+                buf.extend_from_slice(&[TypeMarker::Boolean as u8, *b as u8])
+            }
+            Amf0ValueRef::String(s) => {
+                buf.push(TypeMarker::String as u8);
+                s.marshall_to(buf)?;
+            }
+            Amf0ValueRef::LongString(s) => {
+                buf.push(TypeMarker::LongString as u8);
+                s.marshall_to(buf)?;
+            }
+            Amf0ValueRef::Object(properties) => {
+                buf.push(TypeMarker::Object as u8);
+                marshall_properties_to(properties, buf)?;
+            }
+            Amf0ValueRef::EcmaArray(properties) => {
+                buf.push(TypeMarker::EcmaArray as u8);
+                buf.extend_from_slice(&(properties.len() as u32).to_be_bytes());
+                marshall_properties_to(properties, buf)?;
+            }
+            Amf0ValueRef::StrictArray(values) => {
+                buf.push(TypeMarker::StrictArray as u8);
+                buf.extend_from_slice(&(values.len() as u32).to_be_bytes());
+                for value in values {
+                    value.marshall_to(buf)?;
+                }
+            }
+            Amf0ValueRef::Null => buf.push(TypeMarker::Null as u8),
+            Amf0ValueRef::Undefined => buf.push(TypeMarker::Undefined as u8),
+        }
+        Ok(())
+    }
+}
+
+fn marshall_properties_to(
+    properties: &[(CowUtf8<'_>, Amf0ValueRef<'_>)],
+    buf: &mut Vec<u8>,
+) -> Result<(), AmfError> {
+    for (k, v) in properties {
+        k.marshall_to(buf)?;
+        v.marshall_to(buf)?;
+    }
+    buf.extend_from_slice(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]);
+    Ok(())
+}
+
+impl MarshallLength for Amf0ValueRef<'_> {
+    fn marshall_length(&self) -> usize {
+        let properties_length = |properties: &[(CowUtf8<'_>, Amf0ValueRef<'_>)]| -> usize {
+            properties
+                .iter()
+                .map(|(k, v)| k.marshall_length() + v.marshall_length())
+                .sum::<usize>()
+                + 3 // object end
+        };
+        match self {
+            Amf0ValueRef::Number(_) => 9,
+            Amf0ValueRef::Boolean(_) => 2,
+            Amf0ValueRef::String(s) => 1 + s.marshall_length(),
+            Amf0ValueRef::LongString(s) => 1 + s.marshall_length(),
+            Amf0ValueRef::Object(properties) => 1 + properties_length(properties),
+            Amf0ValueRef::EcmaArray(properties) => 1 + 4 + properties_length(properties),
+            Amf0ValueRef::StrictArray(values) => {
+                1 + 4 + values.iter().map(|v| v.marshall_length()).sum::<usize>()
+            }
+            Amf0ValueRef::Null | Amf0ValueRef::Undefined => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::traits::Unmarshall;
+
+    fn sample_bytes() -> Vec<u8> {
+        let value = amf0!({
+            "duration": 12.5,
+            "encoder": "Lavf60",
+            "hasAudio": true,
+            "keyframes": {"times": [0.0, 2.0], "tag": undefined},
+            "cover": null,
+        });
+        let ecma = value.as_object().unwrap().clone().into_ecma_array();
+        Amf0TypedValue::EcmaArray(ecma).marshall().unwrap()
+    }
+
+    #[test]
+    fn test_unmarshall_borrowed_matches_unmarshall() {
+        let data = sample_bytes();
+        let (value, n) = Amf0ValueRef::unmarshall_borrowed(&data).unwrap();
+        assert_eq!(n, data.len());
+        assert_eq!(value.marshall_length(), data.len());
+        assert_eq!(value.marshall().unwrap(), data);
+
+        let (expected, _) = Amf0TypedValue::unmarshall(&data).unwrap();
+        assert_eq!(value.clone().into_owned(), expected);
+
+        // 字符串和 key 都直接指向输入
+        let Amf0ValueRef::EcmaArray(properties) = &value else {
+            panic!("expected an ECMA array");
+        };
+        assert!(properties.iter().all(|(k, _)| k.is_borrowed()));
+        let Some(Amf0ValueRef::String(encoder)) = value.get("encoder") else {
+            panic!("expected a string");
+        };
+        assert!(encoder.is_borrowed());
+        assert!(data.as_ptr_range().contains(&encoder.as_ptr()));
+
+        assert_eq!(
+            value.get("duration").and_then(|v| v.as_number()),
+            Some(12.5)
+        );
+        assert_eq!(
+            value
+                .get("keyframes")
+                .and_then(|v| v.get("times"))
+                .map(|v| v.marshall_length()),
+            Some(1 + 4 + 9 * 2)
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys_are_kept() {
+        // {"a": 1, "a": 2}
+        let mut data = vec![TypeMarker::Object as u8];
+        for n in [1.0, 2.0] {
+            data.extend_from_slice(&[0x00, 0x01, b'a']);
+            data.extend_from_slice(&Amf0TypedValue::from(n).marshall().unwrap());
+        }
+        data.extend_from_slice(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]);
+
+        let (value, _) = Amf0ValueRef::unmarshall_borrowed(&data).unwrap();
+        assert_eq!(value.marshall().unwrap(), data);
+        assert_eq!(value.get("a").and_then(|v| v.as_number()), Some(2.0));
+        assert_eq!(value.into_owned(), amf0!({"a": 2.0}));
+    }
+
+    #[test]
+    fn test_deep_nesting_is_limited() {
+        // 二十万层嵌套的 strict array
+        let mut data = [0x0a, 0x00, 0x00, 0x00, 0x01].repeat(200_000);
+        data.push(0x05);
+        assert!(matches!(
+            Amf0ValueRef::unmarshall_borrowed(&data),
+            Err(AmfError::LimitExceeded {
+                limit: "max_depth",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        let data = sample_bytes();
+        for end in 0..data.len() {
+            assert!(Amf0ValueRef::unmarshall_borrowed(&data[..end]).is_err());
+        }
+        assert!(matches!(
+            Amf0ValueRef::unmarshall_borrowed(&[0x02, 0x00, 0x02, 0xc3, 0x28]),
+            Err(AmfError::InvalidUtf8(_))
+        ));
+        assert!(matches!(
+            Amf0ValueRef::unmarshall_borrowed(&[TypeMarker::Date as u8]),
+            Err(AmfError::UnsupportedTypeMarker(0x0b))
+        ));
+    }
+}
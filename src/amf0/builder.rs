@@ -0,0 +1,113 @@
+use crate::amf0::marker::{NullType, UndefinedType};
+use crate::amf0::nested::{Amf0TypedValue, EcmaArrayType, NestedType, ObjectType};
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::Utf8;
+use indexmap::IndexMap;
+
+// Fluent construction of an Object or ECMA array:
+//
+//	ObjectType::builder()
+//	    .number("width", 1280.0)
+//	    .string("encoder", "obs")
+//	    .bool("stereo", true)
+//	    .build()
+//
+// Properties keep the order they were added in; adding a key again replaces its value in
+// place. Like `amf0!`, the builder panics on a key longer than 65535 bytes (and on a string
+// beyond the 4 GB LongString limit).
+#[derive(Debug, Clone, Default)]
+pub struct NestedBuilder<const LBW: usize, const TM: u8> {
+    properties: IndexMap<Utf8, Amf0TypedValue>,
+}
+
+pub type ObjectBuilder = NestedBuilder<0, { TypeMarker::Object as u8 }>;
+pub type EcmaArrayBuilder = NestedBuilder<4, { TypeMarker::EcmaArray as u8 }>;
+
+impl<const LBW: usize, const TM: u8> NestedType<LBW, TM> {
+    pub fn builder() -> NestedBuilder<LBW, TM> {
+        NestedBuilder::default()
+    }
+}
+
+impl<const LBW: usize, const TM: u8> NestedBuilder<LBW, TM> {
+    pub fn value(mut self, key: &str, value: impl Into<Amf0TypedValue>) -> Self {
+        let key = Utf8::new_from_str(key).expect("property key is longer than 65535 bytes");
+        self.properties.insert(key, value.into());
+        self
+    }
+
+    pub fn number(self, key: &str, value: f64) -> Self {
+        self.value(key, value)
+    }
+
+    // 超过 65535 字节的字符串自动编码为 LongString
+    pub fn string(self, key: &str, value: impl Into<String>) -> Self {
+        let value = Amf0TypedValue::string(value).expect("string is longer than 4 GB");
+        self.value(key, value)
+    }
+
+    pub fn bool(self, key: &str, value: bool) -> Self {
+        self.value(key, value)
+    }
+
+    pub fn null(self, key: &str) -> Self {
+        self.value(key, Amf0TypedValue::Null(NullType))
+    }
+
+    pub fn undefined(self, key: &str) -> Self {
+        self.value(key, Amf0TypedValue::Undefined(UndefinedType))
+    }
+
+    // 嵌套的对象可以用另一个 builder 构造
+    pub fn object(self, key: &str, value: ObjectType) -> Self {
+        self.value(key, value)
+    }
+
+    pub fn ecma_array(self, key: &str, value: EcmaArrayType) -> Self {
+        self.value(key, value)
+    }
+
+    pub fn array(self, key: &str, values: Vec<Amf0TypedValue>) -> Self {
+        self.value(key, values)
+    }
+
+    pub fn build(self) -> NestedType<LBW, TM> {
+        NestedType::new(self.properties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+
+    #[test]
+    fn test_builder() {
+        let object = ObjectType::builder()
+            .number("width", 1280.0)
+            .string("encoder", "obs")
+            .bool("stereo", true)
+            .null("cover")
+            .object("video", ObjectType::builder().number("fps", 30.0).build())
+            .array("tags", vec![amf0!("live")])
+            .build();
+        assert_eq!(
+            Amf0TypedValue::Object(object.clone()),
+            amf0!({
+                "width": 1280.0,
+                "encoder": "obs",
+                "stereo": true,
+                "cover": null,
+                "video": {"fps": 30.0},
+                "tags": ["live"],
+            })
+        );
+
+        let array = EcmaArrayType::builder()
+            .number("duration", 0.0)
+            .number("duration", 12.5)
+            .build();
+        assert_eq!(array.len(), 1);
+        assert_eq!(array["duration"], Amf0TypedValue::from(12.5));
+    }
+}
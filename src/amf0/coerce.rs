@@ -0,0 +1,177 @@
+use crate::amf0::nested::Amf0TypedValue;
+use crate::amf0::number::{NonFinite, write_ecma_number};
+
+// ActionScript-style conversions, for peers that are sloppy about types (a `duration` sent as
+// the String "12.5", a flag sent as 0 or 1). Unlike the `as_*` accessors these never fail:
+//  - `coerce_f64` follows `Number(x)`: numeric strings parse (surrounding whitespace, `0x` hex
+//    and `Infinity` included, the empty string is 0), true is 1, null is 0, undefined and
+//    anything unparsable are NaN. Arrays go through their string form, so `[5]` is 5.
+//  - `coerce_bool` follows `Boolean(x)`: 0, NaN, "", null and undefined are false, every other
+//    value (including the String "false" and empty objects) is true.
+//  - `coerce_string` follows `String(x)`: numbers print like ActionScript (`1e+21`, `NaN`, -0 as
+//    `0`), arrays join their elements with commas, objects become `[object Object]`.
+impl Amf0TypedValue {
+    pub fn coerce_f64(&self) -> f64 {
+        match self {
+            Amf0TypedValue::Number(v) => **v,
+            Amf0TypedValue::Boolean(v) => {
+                if **v {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Amf0TypedValue::Null(_) => 0.0,
+            Amf0TypedValue::Undefined(_) => f64::NAN,
+            Amf0TypedValue::String(_) | Amf0TypedValue::LongString(_) => {
+                string_to_number(self.as_str().unwrap())
+            }
+            Amf0TypedValue::StrictArray(_) => string_to_number(&self.coerce_string()),
+            _ => f64::NAN,
+        }
+    }
+
+    pub fn coerce_bool(&self) -> bool {
+        match self {
+            Amf0TypedValue::Number(v) => **v != 0.0 && !v.is_nan(),
+            Amf0TypedValue::Boolean(v) => **v,
+            Amf0TypedValue::String(_) | Amf0TypedValue::LongString(_) => {
+                !self.as_str().unwrap().is_empty()
+            }
+            Amf0TypedValue::Null(_) | Amf0TypedValue::Undefined(_) => false,
+            _ => true,
+        }
+    }
+
+    pub fn coerce_string(&self) -> String {
+        let mut out = String::new();
+        self.write_coerced(&mut out);
+        out
+    }
+
+    fn write_coerced(&self, out: &mut String) {
+        match self {
+            Amf0TypedValue::Number(v) => write_number(out, **v),
+            Amf0TypedValue::Boolean(v) => out.push_str(if **v { "true" } else { "false" }),
+            Amf0TypedValue::String(_) | Amf0TypedValue::LongString(_) => {
+                out.push_str(self.as_str().unwrap())
+            }
+            Amf0TypedValue::Null(_) => out.push_str("null"),
+            Amf0TypedValue::Undefined(_) => out.push_str("undefined"),
+            // Array.join: null 和 undefined 元素输出为空字符串
+            Amf0TypedValue::StrictArray(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    if !value.is_null() && !value.is_undefined() {
+                        value.write_coerced(out);
+                    }
+                }
+            }
+            _ => out.push_str("[object Object]"),
+        }
+    }
+}
+
+fn write_number(out: &mut String, n: f64) {
+    if n.is_nan() {
+        out.push_str("NaN");
+    } else if n.is_infinite() {
+        out.push_str(if n > 0.0 { "Infinity" } else { "-Infinity" });
+    } else {
+        write_ecma_number(out, n, NonFinite::Null).unwrap(); // 写入 String 不会失败
+    }
+}
+
+// ECMAScript 的 StringToNumber
+fn string_to_number(s: &str) -> f64 {
+    let s = s.trim();
+    if s.is_empty() {
+        return 0.0;
+    }
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        if hex.is_empty() || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return f64::NAN;
+        }
+        return hex.bytes().fold(0.0, |n, b| {
+            n * 16.0 + (b as char).to_digit(16).unwrap() as f64
+        });
+    }
+    let (sign, unsigned) = match s.as_bytes()[0] {
+        b'-' => (-1.0, &s[1..]),
+        b'+' => (1.0, &s[1..]),
+        _ => (1.0, s),
+    };
+    if unsigned == "Infinity" {
+        return sign * f64::INFINITY;
+    }
+    // Rust 的 parse 还接受 "inf"、"NaN" 之类，这里只允许十进制数字
+    let decimal = unsigned
+        .bytes()
+        .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-'));
+    match unsigned.parse::<f64>() {
+        Ok(n) if decimal && unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.') => {
+            sign * n
+        }
+        _ => f64::NAN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::amf0;
+
+    #[test]
+    fn test_coerce_f64() {
+        let cases = [
+            (amf0!(12.5), 12.5),
+            (amf0!(" 12.5\n"), 12.5),
+            (amf0!(""), 0.0),
+            (amf0!("1e3"), 1000.0),
+            (amf0!(".5"), 0.5),
+            (amf0!("-0x10"), f64::NAN),
+            (amf0!("0x1F"), 31.0),
+            (amf0!("-Infinity"), f64::NEG_INFINITY),
+            (amf0!("inf"), f64::NAN),
+            (amf0!("12px"), f64::NAN),
+            (amf0!(true), 1.0),
+            (amf0!(null), 0.0),
+            (amf0!(undefined), f64::NAN),
+            (amf0!([]), 0.0),
+            (amf0!([" 5 "]), 5.0),
+            (amf0!([1.0, 2.0]), f64::NAN),
+            (amf0!({"a": 1.0}), f64::NAN),
+        ];
+        for (value, expected) in cases {
+            let n = value.coerce_f64();
+            assert!(
+                n == expected || n.is_nan() && expected.is_nan(),
+                "{}: {}",
+                value,
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn test_coerce_bool_and_string() {
+        assert!(!amf0!(0.0).coerce_bool());
+        assert!(!amf0!(f64::NAN).coerce_bool());
+        assert!(!amf0!("").coerce_bool());
+        assert!(!amf0!(null).coerce_bool());
+        assert!(amf0!("false").coerce_bool());
+        assert!(amf0!({}).coerce_bool());
+
+        assert_eq!(amf0!(1e21).coerce_string(), "1e+21");
+        assert_eq!(amf0!(-0.0).coerce_string(), "0");
+        assert_eq!(amf0!(f64::NEG_INFINITY).coerce_string(), "-Infinity");
+        assert_eq!(amf0!(false).coerce_string(), "false");
+        assert_eq!(amf0!(undefined).coerce_string(), "undefined");
+        assert_eq!(
+            amf0!([1.5, null, "a", [true]]).coerce_string(),
+            "1.5,,a,true"
+        );
+        assert_eq!(amf0!({"a": 1.0}).coerce_string(), "[object Object]");
+    }
+}
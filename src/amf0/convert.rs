@@ -0,0 +1,557 @@
+use crate::amf0::boolean::BooleanType;
+use crate::amf0::marker::{NullType, UndefinedType};
+use crate::amf0::nested::{Amf0TypedValue, ObjectType, unmarshall_properties};
+use crate::amf0::number::NumberType;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use crate::traits::{Marshall, MarshallLength, Unmarshall};
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, HashMap};
+
+// Conversion of a Rust value into an `Amf0TypedValue`. Implemented for the primitive types,
+// `String`, `Option` and `Vec`, and generated by `#[derive(AmfObject)]` for structs.
+pub trait ToAmf0 {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError>;
+
+    // The encoded length of `to_amf0()`, i.e. `self.to_amf0()?.marshall_length()`. The
+    // implementations in this crate and the derived ones compute it without building the value.
+    fn amf0_length(&self) -> Result<usize, AmfError> {
+        Ok(self.to_amf0()?.marshall_length())
+    }
+}
+
+// Conversion of an `Amf0TypedValue` back into a Rust value.
+pub trait FromAmf0: Sized {
+    fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError>;
+
+    // Called when an object property is absent. Only `Option` accepts this (as `None`).
+    fn from_missing(key: &str) -> Result<Self, AmfError> {
+        Err(AmfError::MissingValue.at_path(key))
+    }
+}
+
+fn type_mismatch(want: &'static str, got: &Amf0TypedValue) -> AmfError {
+    AmfError::TypeMismatch {
+        want,
+        got: got.kind_name(),
+    }
+}
+
+impl ToAmf0 for Amf0TypedValue {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        Ok(self.clone())
+    }
+
+    fn amf0_length(&self) -> Result<usize, AmfError> {
+        Ok(self.marshall_length())
+    }
+}
+
+impl FromAmf0 for Amf0TypedValue {
+    fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+        Ok(value)
+    }
+}
+
+impl ToAmf0 for f64 {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        Ok(Amf0TypedValue::Number(NumberType::new(*self)))
+    }
+
+    fn amf0_length(&self) -> Result<usize, AmfError> {
+        Ok(9)
+    }
+}
+
+impl FromAmf0 for f64 {
+    fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+        match value {
+            Amf0TypedValue::Number(v) => Ok(*v),
+            other => Err(type_mismatch("number", &other)),
+        }
+    }
+}
+
+impl ToAmf0 for f32 {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        (*self as f64).to_amf0()
+    }
+
+    fn amf0_length(&self) -> Result<usize, AmfError> {
+        Ok(9)
+    }
+}
+
+impl FromAmf0 for f32 {
+    fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+        f64::from_amf0(value).map(|v| v as f32)
+    }
+}
+
+// AMF 0 只有 f64 一种数字类型，整数只有在没有小数部分且不越界时才能被读出
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl ToAmf0 for $t {
+                fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+                    (*self as f64).to_amf0()
+                }
+
+                fn amf0_length(&self) -> Result<usize, AmfError> {
+                    Ok(9)
+                }
+            }
+
+            impl FromAmf0 for $t {
+                fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+                    let n = f64::from_amf0(value)?;
+                    if n.fract() != 0.0 || n < <$t>::MIN as f64 || n > <$t>::MAX as f64 {
+                        return Err(AmfError::NumberOutOfRange {
+                            value: n,
+                            target: stringify!($t),
+                        });
+                    }
+                    Ok(n as $t)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl ToAmf0 for bool {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        Ok(Amf0TypedValue::Boolean(BooleanType::new(*self)))
+    }
+
+    fn amf0_length(&self) -> Result<usize, AmfError> {
+        Ok(2)
+    }
+}
+
+impl FromAmf0 for bool {
+    fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+        match value {
+            Amf0TypedValue::Boolean(v) => Ok(*v),
+            other => Err(type_mismatch("boolean", &other)),
+        }
+    }
+}
+
+impl ToAmf0 for str {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        Amf0TypedValue::string(self)
+    }
+
+    // 和 Amf0TypedValue::string 一样按长度选择 String 或 LongString
+    fn amf0_length(&self) -> Result<usize, AmfError> {
+        if self.len() <= u16::MAX as usize {
+            Ok(3 + self.len())
+        } else if self.len() <= u32::MAX as usize {
+            Ok(5 + self.len())
+        } else {
+            Err(AmfError::StringTooLong {
+                max: u32::MAX as usize,
+                got: self.len(),
+            })
+        }
+    }
+}
+
+impl ToAmf0 for String {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        self.as_str().to_amf0()
+    }
+
+    fn amf0_length(&self) -> Result<usize, AmfError> {
+        self.as_str().amf0_length()
+    }
+}
+
+impl FromAmf0 for String {
+    fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+        match value {
+            Amf0TypedValue::String(v) => String::try_from(v),
+            Amf0TypedValue::LongString(v) => String::try_from(v),
+            other => Err(type_mismatch("string", &other)),
+        }
+    }
+}
+
+// None 编码为 Null，解码时 Null、Undefined 以及缺失的属性都得到 None
+impl<T: ToAmf0> ToAmf0 for Option<T> {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        match self {
+            Some(v) => v.to_amf0(),
+            None => Ok(Amf0TypedValue::Null(NullType)),
+        }
+    }
+
+    fn amf0_length(&self) -> Result<usize, AmfError> {
+        match self {
+            Some(v) => v.amf0_length(),
+            None => Ok(1),
+        }
+    }
+}
+
+impl<T: FromAmf0> FromAmf0 for Option<T> {
+    fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+        match value {
+            Amf0TypedValue::Null(_) | Amf0TypedValue::Undefined(_) => Ok(None),
+            other => T::from_amf0(other).map(Some),
+        }
+    }
+
+    fn from_missing(_key: &str) -> Result<Self, AmfError> {
+        Ok(None)
+    }
+}
+
+impl<T: ToAmf0> ToAmf0 for Vec<T> {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        Ok(Amf0TypedValue::StrictArray(StrictArrayType::new(
+            self.iter().map(ToAmf0::to_amf0).collect::<Result<_, _>>()?,
+        )))
+    }
+
+    fn amf0_length(&self) -> Result<usize, AmfError> {
+        self.iter().try_fold(5, |n, v| Ok(n + v.amf0_length()?))
+    }
+}
+
+impl<T: FromAmf0> FromAmf0 for Vec<T> {
+    fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+        match value {
+            Amf0TypedValue::StrictArray(v) => v.into_iter().map(T::from_amf0).collect(),
+            other => Err(type_mismatch("strict array", &other)),
+        }
+    }
+}
+
+impl<T: ToAmf0 + ?Sized> ToAmf0 for &T {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        (**self).to_amf0()
+    }
+
+    fn amf0_length(&self) -> Result<usize, AmfError> {
+        (**self).amf0_length()
+    }
+}
+
+// 字符串为 key 的 map 编码为 Object(HashMap 的属性顺序不确定)，解码时也接受 ECMA array
+macro_rules! impl_map {
+    ($($map:ident),*) => {
+        $(
+            impl<T: ToAmf0> ToAmf0 for $map<String, T> {
+                fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+                    let properties = self
+                        .iter()
+                        .map(|(k, v)| Ok((Utf8::new_from_str(k)?, v.to_amf0()?)))
+                        .collect::<Result<IndexMap<_, _>, AmfError>>()?;
+                    Ok(Amf0TypedValue::Object(ObjectType::new(properties)))
+                }
+            }
+
+            impl<T: FromAmf0> FromAmf0 for $map<String, T> {
+                fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+                    object_properties(value)?
+                        .into_iter()
+                        .map(|(k, v)| match T::from_amf0(v) {
+                            Ok(v) => Ok((k.to_string(), v)),
+                            Err(e) => Err(e.in_property(&k)),
+                        })
+                        .collect()
+                }
+            }
+        )*
+    };
+}
+
+impl_map!(IndexMap, HashMap, BTreeMap);
+
+// 以下是 #[derive(AmfObject)] 生成的代码所使用的辅助函数，不属于公开 API
+
+#[doc(hidden)]
+pub fn object_properties(
+    value: Amf0TypedValue,
+) -> Result<IndexMap<Utf8, Amf0TypedValue>, AmfError> {
+    match value {
+        Amf0TypedValue::Object(v) => Ok(v.into_inner()),
+        Amf0TypedValue::EcmaArray(v) => Ok(v.into_inner()),
+        other => Err(type_mismatch("object", &other)),
+    }
+}
+
+#[doc(hidden)]
+pub fn take_property<T: FromAmf0>(
+    properties: &mut IndexMap<Utf8, Amf0TypedValue>,
+    key: &str,
+) -> Result<T, AmfError> {
+    match properties.swap_remove(key) {
+        Some(value) => T::from_amf0(value).map_err(|e| e.in_property(key)),
+        None => T::from_missing(key),
+    }
+}
+
+pub(crate) fn key(name: &str) -> Utf8 {
+    Utf8::new_from_str(name).unwrap() // 属性名都很短
+}
+
+// 类型化结构里的字符串属性。只有超过 LongString 的 4 GB 上限才会失败，内存中放不下这样的结构
+pub(crate) fn string(value: impl Into<String>) -> Amf0TypedValue {
+    Amf0TypedValue::string(value).unwrap()
+}
+
+// 供 flv、rtmp 中的类型化结构使用：按名字取出属性(不改变其余属性的顺序)。缺失的属性得到
+// Undefined，解码为 None；类型不对时错误的 path 指向这个属性
+pub(crate) fn field<T: FromAmf0>(
+    properties: &mut IndexMap<Utf8, Amf0TypedValue>,
+    name: &str,
+) -> Result<T, AmfError> {
+    let value = properties
+        .shift_remove(name)
+        .unwrap_or(Amf0TypedValue::Undefined(UndefinedType));
+    T::from_amf0(value).map_err(|e| e.in_property(name))
+}
+
+//	If a strongly typed object has an alias registered for its class then the type name
+//	will also be serialized. Typed objects are considered complex types and reoccurring
+//	instances can be sent by reference.
+//	class-name = UTF-8
+//	object-type = object-property
+//	typed-object-type = class-name *(object-property) object-end-marker
+#[doc(hidden)]
+pub fn marshall_object(
+    class_name: Option<&str>,
+    value: &Amf0TypedValue,
+) -> Result<Vec<u8>, AmfError> {
+    let (Some(class_name), Amf0TypedValue::Object(object)) = (class_name, value) else {
+        return value.marshall();
+    };
+    let class_name = Utf8::new_from_str(class_name)?;
+    let mut vec = Vec::with_capacity(1 + class_name.marshall_length() + object.marshall_length());
+    vec.push(TypeMarker::TypedObject as u8);
+    class_name.marshall_to(&mut vec)?;
+    object.marshall_properties_to(&mut vec)?; // 不写 Object 的类型标记
+    Ok(vec)
+}
+
+// `length` 是 Object 编码后的长度(`ToAmf0::amf0_length`)；转换失败时 marshall 也会失败，
+// MarshallLength 无法返回错误，长度记为 0
+#[doc(hidden)]
+pub fn marshall_object_length(class_name: Option<&str>, length: Result<usize, AmfError>) -> usize {
+    match (class_name, length) {
+        (_, Err(_)) => 0,
+        (None, Ok(length)) => length,
+        (Some(class_name), Ok(length)) => length + 2 + class_name.len(), // 2 bytes for class name length
+    }
+}
+
+// Object 和 EcmaArray 总是可以被接受；TypedObject 只有在类名一致时才被接受
+#[doc(hidden)]
+pub fn unmarshall_object(
+    buf: &[u8],
+    class_name: Option<&str>,
+) -> Result<(Amf0TypedValue, usize), AmfError> {
+    if buf.first() != Some(&(TypeMarker::TypedObject as u8)) {
+        return Amf0TypedValue::unmarshall(buf);
+    }
+    let (got, name_len) = Utf8::unmarshall(&buf[1..])?;
+    if class_name != Some(got.as_ref()) {
+        return Err(AmfError::ClassNameMismatch {
+            want: class_name.map(str::to_string),
+            got: got.to_string(),
+        });
+    }
+    let (properties, offset) = unmarshall_properties(buf, 1 + name_len, 0)?;
+    Ok((Amf0TypedValue::Object(ObjectType::new(properties)), offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorKind;
+    use amf_rs_derive::AmfObject;
+
+    #[derive(Debug, PartialEq, AmfObject)]
+    struct Metadata {
+        duration: f64,
+        width: u32,
+        #[amf(rename = "hasAudio")]
+        has_audio: bool,
+        encoder: String,
+        cover: Option<String>,
+        times: Vec<f64>,
+    }
+
+    #[derive(Debug, PartialEq, AmfObject)]
+    #[amf(class_name = "com.example.Point")]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    #[derive(Debug, PartialEq, AmfObject)]
+    struct Shape {
+        name: String,
+        origin: Point,
+    }
+
+    fn sample_metadata() -> Metadata {
+        Metadata {
+            duration: 12.5,
+            width: 1280,
+            has_audio: true,
+            encoder: "Lavf60".to_string(),
+            cover: None,
+            times: vec![0.0, 2.0],
+        }
+    }
+
+    #[test]
+    fn test_derive_round_trip() {
+        let metadata = sample_metadata();
+        let bytes = metadata.marshall().unwrap();
+        assert_eq!(bytes[0], TypeMarker::Object as u8);
+        assert_eq!(metadata.marshall_length(), bytes.len());
+        let (decoded, n) = Metadata::unmarshall(&bytes).unwrap();
+        assert_eq!(decoded, metadata);
+        assert_eq!(n, bytes.len());
+    }
+
+    #[test]
+    fn test_derive_rename_and_order() {
+        let value = sample_metadata().to_amf0().unwrap();
+        let properties = object_properties(value).unwrap();
+        let keys: Vec<&str> = properties.keys().map(|k| k.as_ref()).collect();
+        assert_eq!(
+            keys,
+            vec!["duration", "width", "hasAudio", "encoder", "cover", "times"]
+        );
+    }
+
+    #[test]
+    fn test_derive_reads_ecma_array_and_missing_option() {
+        let mut properties = IndexMap::new();
+        properties.insert(
+            Utf8::new_from_str("name").unwrap(),
+            "box".to_amf0().unwrap(),
+        );
+        properties.insert(
+            Utf8::new_from_str("origin").unwrap(),
+            Point { x: 1.0, y: 2.0 }.to_amf0().unwrap(),
+        );
+        let bytes = crate::amf0::nested::EcmaArrayType::new(properties)
+            .marshall()
+            .unwrap();
+        let (shape, _) = Shape::unmarshall(&bytes).unwrap();
+        assert_eq!(shape.origin, Point { x: 1.0, y: 2.0 });
+
+        // cover 缺失时为 None，其它字段缺失时报错
+        let mut properties = object_properties(sample_metadata().to_amf0().unwrap()).unwrap();
+        properties.swap_remove("cover");
+        let value = Amf0TypedValue::Object(ObjectType::new(properties.clone()));
+        assert_eq!(Metadata::from_amf0(value).unwrap(), sample_metadata());
+        properties.swap_remove("width");
+        let value = Amf0TypedValue::Object(ObjectType::new(properties));
+        assert!(Metadata::from_amf0(value).is_err());
+    }
+
+    #[test]
+    fn test_derive_typed_object() {
+        let point = Point { x: 1.0, y: -1.0 };
+        let bytes = point.marshall().unwrap();
+        assert_eq!(bytes[0], TypeMarker::TypedObject as u8);
+        assert_eq!(&bytes[1..3], &[0x00, 17]);
+        assert_eq!(&bytes[3..20], b"com.example.Point");
+        assert_eq!(&bytes[bytes.len() - 3..], &[0x00, 0x00, 0x09]);
+        assert_eq!(point.marshall_length(), bytes.len());
+
+        let (decoded, n) = Point::unmarshall(&bytes).unwrap();
+        assert_eq!(decoded, point);
+        assert_eq!(n, bytes.len());
+
+        // 类名不一致
+        let mut wrong = bytes.clone();
+        wrong[3] = b'C';
+        assert!(Point::unmarshall(&wrong).is_err());
+    }
+
+    // 无法转换的字段
+    #[derive(Debug, PartialEq)]
+    struct Unconvertible;
+
+    impl ToAmf0 for Unconvertible {
+        fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+            Err(AmfError::Custom("unconvertible".into()))
+        }
+    }
+
+    impl FromAmf0 for Unconvertible {
+        fn from_amf0(_: Amf0TypedValue) -> Result<Self, AmfError> {
+            Err(AmfError::Custom("unconvertible".into()))
+        }
+    }
+
+    #[derive(AmfObject)]
+    struct WithUnconvertible {
+        name: String,
+        broken: Unconvertible,
+    }
+
+    #[test]
+    fn test_derive_marshall_length() {
+        let shape = Shape {
+            name: "box".into(),
+            origin: Point { x: 1.0, y: 2.0 },
+        };
+        assert_eq!(shape.marshall_length(), shape.marshall().unwrap().len());
+        assert_eq!(
+            shape.amf0_length().unwrap(),
+            shape.to_amf0().unwrap().marshall_length()
+        );
+
+        let long = "x".repeat(u16::MAX as usize + 1);
+        assert_eq!(
+            long.amf0_length().unwrap(),
+            long.to_amf0().unwrap().marshall_length()
+        );
+
+        // marshall 失败时 marshall_length 为 0
+        let value = WithUnconvertible {
+            name: "a".into(),
+            broken: Unconvertible,
+        };
+        assert!(value.marshall().is_err());
+        assert_eq!(value.marshall_length(), 0);
+    }
+
+    #[test]
+    fn test_map_conversion() {
+        let mut bitrates = BTreeMap::new();
+        bitrates.insert("audio".to_string(), 128u32);
+        bitrates.insert("video".to_string(), 2500u32);
+        let value = bitrates.to_amf0().unwrap();
+        assert_eq!(value["video"], Amf0TypedValue::from(2500.0));
+        assert_eq!(BTreeMap::<String, u32>::from_amf0(value).unwrap(), bitrates);
+
+        let ecma = Amf0TypedValue::EcmaArray(ObjectType::new(IndexMap::new()).into_ecma_array());
+        assert!(HashMap::<String, f64>::from_amf0(ecma).unwrap().is_empty());
+
+        let err = IndexMap::<String, u8>::from_amf0(crate::amf0!({"a": 1, "b": "x"})).unwrap_err();
+        assert_eq!(err.path(), Some("b"));
+        assert_eq!(err.kind(), ErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn test_integer_conversion() {
+        assert_eq!(u8::from_amf0(255u8.to_amf0().unwrap()).unwrap(), 255);
+        assert!(u8::from_amf0(256u32.to_amf0().unwrap()).is_err());
+        assert!(u32::from_amf0((-1i32).to_amf0().unwrap()).is_err());
+        assert!(i32::from_amf0(1.5f64.to_amf0().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,432 @@
+use crate::amf0::decode::DecodeOptions;
+use crate::amf0::nested::Amf0TypedValue;
+use crate::errors::AmfError;
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+// Decodes one AMF 0 value from `bytes` and deserializes it into `T`. The buffer must contain
+// exactly one value; trailing bytes are reported as an error. The bytes are decoded with the
+// limits of `DecodeOptions::default()`, which also keeps the recursive deserialization of the
+// result within `max_depth` levels.
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, AmfError> {
+    let (value, consumed) = Amf0TypedValue::unmarshall_with(bytes, &DecodeOptions::default())?;
+    if consumed != bytes.len() {
+        return Err(AmfError::TrailingBytes {
+            consumed,
+            remaining: bytes.len() - consumed,
+        });
+    }
+    from_value(value)
+}
+
+// Deserializes `T` from an already decoded `Amf0TypedValue`. This is the inverse of `to_value`:
+// Objects and ECMA arrays feed structs and maps, strict arrays feed sequences and tuples, and
+// integral Numbers can be read into any integer type that can hold them.
+pub fn from_value<T: DeserializeOwned>(value: Amf0TypedValue) -> Result<T, AmfError> {
+    T::deserialize(value)
+}
+
+impl Amf0TypedValue {
+    fn invalid_type(&self, exp: &dyn de::Expected) -> AmfError {
+        let unexpected = match self {
+            Amf0TypedValue::Number(v) => de::Unexpected::Float(**v),
+            Amf0TypedValue::Boolean(v) => de::Unexpected::Bool(**v),
+            Amf0TypedValue::String(v) => de::Unexpected::Str(v),
+            Amf0TypedValue::LongString(v) => de::Unexpected::Str(v),
+            Amf0TypedValue::Object(_) | Amf0TypedValue::EcmaArray(_) => de::Unexpected::Map,
+            Amf0TypedValue::StrictArray(_) => de::Unexpected::Seq,
+            Amf0TypedValue::Null(_) | Amf0TypedValue::Undefined(_) => de::Unexpected::Unit,
+            _ => de::Unexpected::Other("unsupported AMF 0 value"),
+        };
+        de::Error::invalid_type(unexpected, exp)
+    }
+
+    fn deserialize_integer<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        match self {
+            // AMF 0 只有 f64 一种数字类型，整数值只有在没有小数部分时才能被读出
+            Amf0TypedValue::Number(ref v) if v.fract() == 0.0 => {
+                let n = **v;
+                // i64::MIN 是 -2^63，可以精确表示；u64::MAX as f64 舍入成了 2^64，已经超出范围
+                if n < 0.0 && n >= i64::MIN as f64 {
+                    visitor.visit_i64(n as i64)
+                } else if n >= 0.0 && n < u64::MAX as f64 {
+                    visitor.visit_u64(n as u64)
+                } else {
+                    Err(de::Error::invalid_value(de::Unexpected::Float(n), &visitor))
+                }
+            }
+            other => Err(other.invalid_type(&visitor)),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Amf0TypedValue {
+    type Error = AmfError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        match self {
+            Amf0TypedValue::Number(v) => visitor.visit_f64(v.into()),
+            Amf0TypedValue::Boolean(v) => visitor.visit_bool(v.into()),
+            Amf0TypedValue::String(v) => visitor.visit_string(String::try_from(v)?),
+            Amf0TypedValue::LongString(v) => visitor.visit_string(String::try_from(v)?),
+            Amf0TypedValue::Object(v) => visit_map(v, visitor),
+            Amf0TypedValue::EcmaArray(v) => visit_map(v, visitor),
+            Amf0TypedValue::StrictArray(v) => {
+                let mut seq = SeqDeserializer::new(v.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Amf0TypedValue::Null(_) | Amf0TypedValue::Undefined(_) => visitor.visit_unit(),
+            other => Err(other.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, AmfError> {
+        match self {
+            Amf0TypedValue::Null(_) | Amf0TypedValue::Undefined(_) => visitor.visit_none(),
+            other => visitor.visit_some(other),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, AmfError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, AmfError> {
+        match self {
+            Amf0TypedValue::String(v) => visitor.visit_enum(EnumDeserializer {
+                variant: String::try_from(v)?,
+                value: None,
+            }),
+            Amf0TypedValue::Object(v) if v.len() == 1 => {
+                let (variant, value) = v.into_iter().next().unwrap(); // 前边已经校验了长度
+                visitor.visit_enum(EnumDeserializer {
+                    variant: String::try_from(variant)?,
+                    value: Some(value),
+                })
+            }
+            other => Err(other.invalid_type(&"a string or an object with a single key")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool f32 f64 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+fn visit_map<'de, T, V>(properties: T, visitor: V) -> Result<V::Value, AmfError>
+where
+    T: IntoIterator<Item = (crate::amf0::utf8::Utf8, Amf0TypedValue)>,
+    V: Visitor<'de>,
+{
+    let entries = properties
+        .into_iter()
+        .map(|(k, v)| String::try_from(k).map(|k| (k, v)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut map = MapDeserializer::new(entries.into_iter());
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+impl IntoDeserializer<'_, AmfError> for Amf0TypedValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Option<Amf0TypedValue>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = AmfError;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), AmfError> {
+        let variant = seed.deserialize(IntoDeserializer::<AmfError>::into_deserializer(
+            self.variant,
+        ))?;
+        Ok((variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+struct VariantDeserializer {
+    value: Option<Amf0TypedValue>,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = AmfError;
+
+    fn unit_variant(self) -> Result<(), AmfError> {
+        match self.value {
+            None => Ok(()),
+            Some(value) => de::Deserialize::deserialize(value),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, AmfError> {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, AmfError> {
+        match self.value {
+            Some(value) => de::Deserializer::deserialize_seq(value, visitor),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, AmfError> {
+        match self.value {
+            Some(value) => de::Deserializer::deserialize_map(value, visitor),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0::nested::{EcmaArrayType, ObjectType};
+    use crate::amf0::number::NumberType;
+    use crate::amf0::string::StringType;
+    use crate::amf0::utf8::Utf8;
+    use crate::amf0::{to_value, to_vec};
+    use crate::traits::Marshall;
+    use indexmap::IndexMap;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Metadata {
+        duration: f64,
+        width: u32,
+        height: u32,
+        has_audio: bool,
+        encoder: String,
+        cover: Option<String>,
+        times: Vec<f64>,
+        codec: Codec,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Codec {
+        Avc,
+        Custom(u8),
+        Pair(u8, u8),
+        Named { id: u8 },
+    }
+
+    fn sample_metadata() -> Metadata {
+        Metadata {
+            duration: 12.5,
+            width: 1280,
+            height: 720,
+            has_audio: true,
+            encoder: "Lavf60".to_string(),
+            cover: None,
+            times: vec![0.0, 2.0, 4.0],
+            codec: Codec::Avc,
+        }
+    }
+
+    #[test]
+    fn test_struct_to_object() {
+        let value = to_value(&sample_metadata()).unwrap();
+        let Amf0TypedValue::Object(object) = &value else {
+            panic!("expected object, got {:?}", value)
+        };
+        let keys: Vec<&str> = object.keys().map(|k| k.as_ref()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "duration", "width", "height", "hasAudio", "encoder", "cover", "times", "codec"
+            ]
+        );
+        assert_eq!(
+            object.get("width"),
+            Some(&Amf0TypedValue::Number(NumberType::new(1280.0)))
+        );
+        assert_eq!(
+            object.get("codec"),
+            Some(&Amf0TypedValue::String(
+                StringType::new_from_str("Avc").unwrap()
+            ))
+        );
+        assert_eq!(
+            to_vec(&sample_metadata()).unwrap(),
+            value.marshall().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = sample_metadata();
+        let bytes = to_vec(&original).unwrap();
+        let decoded: Metadata = from_slice(&bytes).unwrap();
+        assert_eq!(decoded, original);
+    }
+
+    #[test]
+    fn test_enum_variants_round_trip() {
+        for codec in [
+            Codec::Avc,
+            Codec::Custom(7),
+            Codec::Pair(1, 2),
+            Codec::Named { id: 9 },
+        ] {
+            let bytes = to_vec(&codec).unwrap();
+            let decoded: Codec = from_slice(&bytes).unwrap();
+            assert_eq!(decoded, codec);
+        }
+    }
+
+    #[test]
+    fn test_map_to_ecma_array() {
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), 1u8);
+        map.insert("b".to_string(), 2u8);
+        let value = to_value(&map).unwrap();
+        assert!(matches!(value, Amf0TypedValue::EcmaArray(ref v) if v.len() == 2));
+        let decoded: BTreeMap<String, u8> = from_value(value).unwrap();
+        assert_eq!(decoded, map);
+    }
+
+    #[test]
+    fn test_from_hand_built_ecma_array() {
+        // FLV onMetaData 通常是 EcmaArray，也可以直接读到结构体中
+        let mut props = IndexMap::new();
+        props.insert(
+            Utf8::new_from_str("id").unwrap(),
+            Amf0TypedValue::Number(NumberType::new(3.0)),
+        );
+        let bytes = EcmaArrayType::new(props).marshall().unwrap();
+
+        #[derive(Deserialize)]
+        struct Named {
+            id: u8,
+        }
+        let decoded: Named = from_slice(&bytes).unwrap();
+        assert_eq!(decoded.id, 3);
+    }
+
+    #[test]
+    fn test_integer_from_fractional_number_fails() {
+        let value = Amf0TypedValue::Number(NumberType::new(1.5));
+        assert!(from_value::<u32>(value).is_err());
+        let value = Amf0TypedValue::Number(NumberType::new(-1.0));
+        assert!(from_value::<u32>(value).is_err());
+        let value = Amf0TypedValue::Number(NumberType::new(-1.0));
+        assert_eq!(from_value::<i8>(value).unwrap(), -1);
+    }
+
+    #[test]
+    fn test_integer_out_of_range() {
+        let two_pow_64 = Amf0TypedValue::Number(NumberType::new(18446744073709551616.0));
+        assert!(from_value::<u64>(two_pow_64).is_err());
+        let largest = Amf0TypedValue::Number(NumberType::new(18446744073709549568.0));
+        assert_eq!(from_value::<u64>(largest).unwrap(), 18446744073709549568);
+        let below_min = Amf0TypedValue::Number(NumberType::new(-9223372036854777856.0));
+        assert!(from_value::<i64>(below_min).is_err());
+        let min = Amf0TypedValue::Number(NumberType::new(i64::MIN as f64));
+        assert_eq!(from_value::<i64>(min).unwrap(), i64::MIN);
+    }
+
+    #[test]
+    fn test_from_slice_deep_nesting() {
+        // 二十万层嵌套的 strict array 在解码时就被拒绝，不会进入递归的反序列化
+        let mut bytes = Vec::new();
+        for _ in 0..200_000 {
+            bytes.extend_from_slice(&[0x0a, 0, 0, 0, 1]);
+        }
+        bytes.push(0x05);
+        let err = from_slice::<de::IgnoredAny>(&bytes).unwrap_err();
+        assert_eq!(err.kind(), crate::errors::ErrorKind::LimitExceeded);
+    }
+
+    #[test]
+    fn test_from_slice_trailing_bytes() {
+        let mut bytes = to_vec(&1u8).unwrap();
+        bytes.push(0x05);
+        assert!(matches!(
+            from_slice::<u8>(&bytes),
+            Err(AmfError::TrailingBytes { remaining: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_missing_field() {
+        let bytes = ObjectType::default().marshall().unwrap();
+        assert!(from_slice::<Metadata>(&bytes).is_err());
+    }
+}
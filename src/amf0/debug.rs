@@ -0,0 +1,284 @@
+use crate::amf0::decode::{DecodeOptions, check_limit};
+use crate::amf0::to_json::write_json_string;
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::str_from_utf8;
+use crate::errors::{AmfError, checked_end};
+use std::fmt::Write;
+
+// Renders `buf` as an annotated dump, one line per element: byte offset, the raw bytes in hex
+// (the first 16, for long strings), the marker or "key", and the decoded value. Property keys
+// and children are indented under their container. Every value in `buf` is dumped, so a whole
+// script tag body (name followed by value) can be passed at once. Decoding stops at the first
+// error, which is reported on the last line instead of failing, since a dump is most useful
+// on exactly the input that does not decode:
+//
+//	000000  02 00 0a 6f 6e 4d 65 74 61 44 61 74 61            String "onMetaData"
+//	00000d  08 00 00 00 01                                    EcmaArray count=1
+//	000012  00 08 64 75 72 61 74 69 6f 6e                       key "duration"
+//	00001c  00 40 28 00 00 00 00 00 00                          Number 12
+//	000025  00 00 09                                          ObjectEnd
+//
+// Containers nested deeper than `DecodeOptions::default().max_depth` are reported as an error;
+// use `annotate_with` to choose the limit. Indentation stops growing after 64 levels, deeper
+// lines are marked with `…`.
+pub fn annotate(buf: &[u8]) -> String {
+    annotate_with(buf, &DecodeOptions::default())
+}
+
+// `annotate` with the nesting limit taken from `options.max_depth`.
+pub fn annotate_with(buf: &[u8], options: &DecodeOptions) -> String {
+    let mut annotator = Annotator {
+        buf,
+        offset: 0,
+        max_depth: options.max_depth,
+        out: String::new(),
+    };
+    while annotator.offset < buf.len() {
+        let start = annotator.offset;
+        if let Err(e) = annotator.scan() {
+            // 出错时 offset 停在出错的元素内部，从元素开头报告
+            let _ = writeln!(
+                annotator.out,
+                "{:06x}  error after {} bytes: {}",
+                start,
+                annotator.offset - start,
+                e
+            );
+            break;
+        }
+    }
+    annotator.out
+}
+
+const HEX_BYTES: usize = 16;
+// 超过这个层数不再缩进，避免输出随嵌套深度平方增长
+const MAX_INDENT_DEPTH: usize = 64;
+const OBJECT_END: [u8; 3] = [0x00, 0x00, TypeMarker::ObjectEnd as u8];
+
+enum Frame {
+    StrictArray(u32),
+    Properties,
+}
+
+struct Annotator<'b> {
+    buf: &'b [u8],
+    offset: usize,
+    max_depth: usize,
+    out: String,
+}
+
+impl Annotator<'_> {
+    // 输出一个完整的值(包括容器的所有子元素)，嵌套关系保存在堆上的栈里
+    fn scan(&mut self) -> Result<(), AmfError> {
+        let mut stack = Vec::new();
+        if let Some(frame) = self.value(0)? {
+            check_limit("max_depth", self.max_depth, 1)?;
+            stack.push(frame);
+        }
+        loop {
+            let depth = stack.len();
+            let Some(frame) = stack.last_mut() else {
+                break;
+            };
+            match frame {
+                Frame::StrictArray(0) => {
+                    stack.pop();
+                    continue;
+                }
+                Frame::StrictArray(remaining) => *remaining -= 1,
+                Frame::Properties => {
+                    let start = self.offset;
+                    if self.buf[start..].starts_with(&OBJECT_END) {
+                        self.offset += 3;
+                        self.line(start, depth - 1, "ObjectEnd", "");
+                        stack.pop();
+                        continue;
+                    }
+                    let key = self.utf8::<2>()?;
+                    self.line(start, depth, "key", &key);
+                }
+            }
+            if let Some(frame) = self.value(depth)? {
+                check_limit("max_depth", self.max_depth, depth + 1)?;
+                stack.push(frame);
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Option<Frame>, AmfError> {
+        let start = self.offset;
+        let marker = self.take(1)?[0];
+        let marker = TypeMarker::try_from(marker)?;
+        let (description, frame) = match marker {
+            TypeMarker::Number => (f64::from_be_bytes(self.read_array()?).to_string(), None),
+            TypeMarker::Boolean => ((self.take(1)?[0] != 0).to_string(), None),
+            TypeMarker::String => (self.utf8::<2>()?, None),
+            TypeMarker::LongString | TypeMarker::XmlDocument => (self.utf8::<4>()?, None),
+            TypeMarker::Null | TypeMarker::Undefined | TypeMarker::Unsupported => {
+                (String::new(), None)
+            }
+            TypeMarker::Reference => {
+                let index = u16::from_be_bytes(self.read_array()?);
+                (format!("index={}", index), None)
+            }
+            TypeMarker::Date => {
+                let millis = f64::from_be_bytes(self.read_array()?);
+                let time_zone = i16::from_be_bytes(self.read_array()?);
+                (format!("{} ms, tz={}", millis, time_zone), None)
+            }
+            TypeMarker::Object => (String::new(), Some(Frame::Properties)),
+            TypeMarker::TypedObject => (
+                format!("class={}", self.utf8::<2>()?),
+                Some(Frame::Properties),
+            ),
+            TypeMarker::EcmaArray => {
+                let count = u32::from_be_bytes(self.read_array()?);
+                (format!("count={}", count), Some(Frame::Properties))
+            }
+            TypeMarker::StrictArray => {
+                let count = u32::from_be_bytes(self.read_array()?);
+                (format!("count={}", count), Some(Frame::StrictArray(count)))
+            }
+            TypeMarker::ObjectEnd => return Err(AmfError::InvalidObjectEnd),
+            TypeMarker::MovieClip | TypeMarker::Recordset => {
+                return Err(AmfError::UnsupportedTypeMarker(marker as u8));
+            }
+        };
+        self.line(start, depth, &format!("{:?}", marker), &description);
+        Ok(frame)
+    }
+
+    fn line(&mut self, start: usize, depth: usize, name: &str, description: &str) {
+        let bytes = &self.buf[start..self.offset];
+        let mut hex = String::with_capacity(HEX_BYTES * 3 + 2);
+        for b in bytes.iter().take(HEX_BYTES) {
+            let _ = write!(hex, "{:02x} ", b);
+        }
+        if bytes.len() > HEX_BYTES {
+            hex.push_str("..");
+        }
+        let _ = write!(
+            self.out,
+            "{:06x}  {:<50}{:indent$}{}{}",
+            start,
+            hex,
+            "",
+            if depth > MAX_INDENT_DEPTH { "… " } else { "" },
+            name,
+            indent = depth.min(MAX_INDENT_DEPTH) * 2
+        );
+        if !description.is_empty() {
+            self.out.push(' ');
+            self.out.push_str(description);
+        }
+        self.out.push('\n');
+    }
+
+    // 读取字符串并转成带引号、转义后的形式
+    fn utf8<const LBW: usize>(&mut self) -> Result<String, AmfError> {
+        let mut length = [0u8; 8];
+        length[8 - LBW..].copy_from_slice(self.take(LBW)?);
+        let length = u64::from_be_bytes(length) as usize;
+        let s = str_from_utf8(self.take(length)?).map_err(AmfError::InvalidUtf8)?;
+        let mut quoted = String::with_capacity(s.len() + 2);
+        write_json_string(&mut quoted, s).unwrap(); // 写入 String 不会失败
+        Ok(quoted)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&[u8], AmfError> {
+        let end = checked_end(self.offset, n)?;
+        if self.buf.len() < end {
+            return Err(AmfError::BufferTooSmall {
+                want: end,
+                got: self.buf.len(),
+            });
+        }
+        let bytes = &self.buf[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], AmfError> {
+        Ok(self.take(N)?.try_into().unwrap()) // take 保证了长度
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::amf0::nested::Amf0TypedValue;
+    use crate::traits::Marshall;
+
+    #[test]
+    fn test_annotate() {
+        let mut data = amf0!("onMetaData").marshall().unwrap();
+        let metadata = amf0!({"duration": 12.0}).as_object().unwrap().clone();
+        data.extend(
+            Amf0TypedValue::EcmaArray(metadata.into_ecma_array())
+                .marshall()
+                .unwrap(),
+        );
+        data.extend(amf0!([true, null]).marshall().unwrap());
+        let dump = annotate(&data);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!(
+                "000000  {:<50}String \"onMetaData\"",
+                "02 00 0a 6f 6e 4d 65 74 61 44 61 74 61 "
+            )
+        );
+        assert!(lines[1].ends_with("EcmaArray count=1"), "{}", lines[1]);
+        assert!(lines[2].starts_with("000012  00 08 64 75 "), "{}", lines[2]);
+        assert!(lines[2].ends_with("  key \"duration\""), "{}", lines[2]);
+        assert!(lines[3].ends_with("  Number 12"), "{}", lines[3]);
+        assert!(lines[4].ends_with(" ObjectEnd"), "{}", lines[4]);
+        assert!(lines[5].ends_with("StrictArray count=2"), "{}", lines[5]);
+        assert!(lines[6].ends_with("  Boolean true"), "{}", lines[6]);
+        assert!(lines[7].ends_with("  Null"), "{}", lines[7]);
+        assert_eq!(lines.len(), 8);
+    }
+
+    #[test]
+    fn test_annotate_deep_nesting() {
+        // 十万层嵌套的 strict array
+        let depth = 100_000;
+        let mut data = Vec::with_capacity(depth * 5 + 1);
+        for _ in 0..depth {
+            data.extend_from_slice(&[0x0a, 0x00, 0x00, 0x00, 0x01]);
+        }
+        data.push(0x05);
+
+        // 默认的 max_depth 之后停止
+        let dump = annotate(&data);
+        let lines: Vec<&str> = dump.lines().collect();
+        let max_depth = DecodeOptions::default().max_depth;
+        assert_eq!(lines.len(), max_depth + 2);
+        assert!(lines.last().unwrap().contains("max_depth"), "{}", dump);
+
+        // 不限制深度时缩进在 64 层之后不再增长
+        let dump = annotate_with(&data, &DecodeOptions::unlimited());
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), depth + 1);
+        let last = lines.last().unwrap();
+        assert!(last.ends_with("… Null"), "{}", last);
+        assert!(!lines[MAX_INDENT_DEPTH].contains('…'));
+        assert_eq!(last.find('…'), lines[MAX_INDENT_DEPTH + 1].find('…'));
+    }
+
+    #[test]
+    fn test_annotate_reports_error() {
+        // 第二个值被截断
+        let data = [0x05, 0x00, 0x40, 0x28];
+        let dump = annotate(&data);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert!(lines[0].ends_with("Null"));
+        assert!(
+            lines[1].starts_with("000001  error after 1 bytes: "),
+            "{}",
+            lines[1]
+        );
+    }
+}
@@ -0,0 +1,1555 @@
+use crate::amf0::boolean::BooleanType;
+use crate::amf0::extension::ExtensionRegistry;
+use crate::amf0::marker::{NullType, UndefinedType};
+use crate::amf0::nested::{
+    Amf0TypedValue, MAX_PREALLOCATED_VALUES, NestedType, properties_capacity,
+};
+use crate::amf0::number::NumberType;
+use crate::amf0::raw::RawValue;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::string::{LongStringType, StringType};
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::{AmfUtf8, Utf8, str_from_utf8};
+use crate::amf0::visit::{Path, PathSegment};
+use crate::errors::{AmfError, AmfWarning, ErrorKind, checked_end};
+use indexmap::IndexMap;
+use indexmap::map::Entry;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// How `Amf0TypedValue::unmarshall_with` treats input that deviates from the specification
+// without being ambiguous: a Boolean byte other than 0/1, an ECMA array count that does not
+// match its properties (FFmpeg sometimes writes 0) and a LongString that fits in a String.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    // Reject every deviation.
+    Strict,
+    // Accept the deviations and record an `AmfWarning` for each of them.
+    #[default]
+    Lenient,
+}
+
+// What to do when an Object or ECMA array contains the same key more than once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeyPolicy {
+    // Keep the first value and drop the later ones.
+    FirstWins,
+    // Keep the last value, at the position of the first occurrence (what `unmarshall` does).
+    #[default]
+    LastWins,
+    // Fail the decode.
+    Error,
+    // Keep the first value as the property and the later ones in `NestedType::duplicates`;
+    // `NestedType::get_all` returns all of them.
+    KeepAll,
+}
+
+// How strings and property keys that are not valid UTF-8 are decoded. Older Flash encoders
+// write Latin-1 into fields the specification defines as UTF-8.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Utf8Fallback {
+    // Fail with `AmfError::InvalidUtf8`.
+    #[default]
+    Error,
+    // Replace invalid sequences with U+FFFD, like `String::from_utf8_lossy`.
+    Lossy,
+    // Decode the whole string as Latin-1 (ISO 8859-1), mapping every byte to the code point of
+    // the same value.
+    Latin1,
+}
+
+// Limits for `Amf0TypedValue::unmarshall_with`, meant for decoding untrusted network input.
+// Exceeding any of them fails the decode with `AmfError::LimitExceeded` before the offending
+// value is allocated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeOptions {
+    pub mode: ParseMode,
+    pub duplicate_keys: DuplicateKeyPolicy,
+    // How many Objects, ECMA arrays and strict arrays may be nested inside each other. A scalar
+    // root has depth 0, the properties of a root Object depth 1. Decoding, encoding, cloning,
+    // comparing and dropping a value never recurse, but `Debug`, serde and the schema functions
+    // do, so values much deeper than the default are only safe to use with the former.
+    pub max_depth: usize,
+    // Upper bound for the UTF-8 length of any String, LongString or property key.
+    pub max_string_bytes: usize,
+    // Upper bound for the number of properties of an Object or ECMA array, and for the number
+    // of elements of a strict array.
+    pub max_properties: usize,
+    // Upper bound for the number of bytes consumed by the whole value.
+    pub max_total_bytes: usize,
+    // Accept input that ends in the middle of the value, as in an FLV file whose recording
+    // was cut off mid-write: every open container is closed with the entries decoded so far,
+    // Objects and ECMA arrays are flagged with `NestedType::is_truncated`, and each is reported
+    // as `AmfWarning::Truncated`. Off by default, because a streaming caller usually wants to
+    // wait for more data instead.
+    pub allow_truncated: bool,
+    // Applies in both parse modes, since it has to be opted into; every string decoded this
+    // way is reported as `AmfWarning::NonUtf8String`.
+    pub invalid_utf8: Utf8Fallback,
+    // Instead of failing on a type marker this crate does not decode, return it as
+    // `Amf0TypedValue::Raw` holding the exact bytes of the value, so that a proxy can forward
+    // it unchanged. Works wherever the length of the value can be determined (Reference, Date,
+    // XmlDocument, TypedObject, Unsupported); the root value may also be a reserved or unknown
+    // marker, in which case the rest of the input is taken as its bytes.
+    pub raw_unsupported: bool,
+    // Handlers for type markers the specification does not define, consulted before such a
+    // marker is reported as `AmfError::InvalidTypeMarker`.
+    pub extensions: Option<Arc<ExtensionRegistry>>,
+    // Share one allocation between all property keys with the same name decoded by one call,
+    // instead of allocating every key separately. Saves memory on arrays of objects (e.g.
+    // thousands of `{"time": .., "offset": ..}` entries), and comparing two interned keys
+    // short-circuits on pointer equality.
+    pub intern_keys: bool,
+}
+
+// 默认的嵌套层数上限，也用于 unmarshall，以及 unmarshall_borrowed 和 unmarshall_in 这些没有 DecodeOptions 的递归解码
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 64;
+
+impl Default for DecodeOptions {
+    // 足够容纳常见的 FLV metadata(比如上万个关键帧的 keyframes 数组)
+    fn default() -> Self {
+        Self {
+            mode: ParseMode::default(),
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_string_bytes: 16 * 1024 * 1024,
+            max_properties: 1024 * 1024,
+            max_total_bytes: 64 * 1024 * 1024,
+            allow_truncated: false,
+            invalid_utf8: Utf8Fallback::default(),
+            raw_unsupported: false,
+            extensions: None,
+            intern_keys: false,
+        }
+    }
+}
+
+impl DecodeOptions {
+    // No limits at all. Unlike `unmarshall`, unsupported type markers are reported as errors.
+    pub fn unlimited() -> Self {
+        Self {
+            mode: ParseMode::default(),
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            max_depth: usize::MAX,
+            max_string_bytes: usize::MAX,
+            max_properties: usize::MAX,
+            max_total_bytes: usize::MAX,
+            allow_truncated: false,
+            invalid_utf8: Utf8Fallback::default(),
+            raw_unsupported: false,
+            extensions: None,
+            intern_keys: false,
+        }
+    }
+}
+
+impl Amf0TypedValue {
+    // Decodes one value like `unmarshall`, enforcing the limits in `options`. Returns the value
+    // and the number of bytes consumed. Errors are wrapped in `AmfError::Context`, which tells
+    // where in `buf` decoding failed.
+    pub fn unmarshall_with(buf: &[u8], options: &DecodeOptions) -> Result<(Self, usize), AmfError> {
+        Self::unmarshall_with_warnings(buf, options).map(|(value, n, _)| (value, n))
+    }
+
+    // Same as `unmarshall_with`, also returning the deviations tolerated in
+    // `ParseMode::Lenient`, in the order they were encountered.
+    pub fn unmarshall_with_warnings(
+        buf: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize, Vec<AmfWarning>), AmfError> {
+        let mut decoder = Decoder::new(buf, options, false);
+        match decoder.read_value() {
+            Ok(value) => Ok((value, decoder.offset, decoder.warnings)),
+            // 出错时通过 ? 提前返回，没有弹出的 path 正好指向出错的值
+            Err(e) => Err(AmfError::Context {
+                offset: Some(decoder.failed_at),
+                path: decoder.path.to_string(),
+                source: Box::new(e),
+            }),
+        }
+    }
+
+    // Best-effort decoding for forensic tooling inspecting corrupted files: instead of failing,
+    // every sub-value that cannot be decoded is replaced by `Undefined` and reported as
+    // `AmfWarning::UndecodableValue`. When the position of the next value cannot be determined
+    // (e.g. after an unknown type marker or at the end of truncated input) the remaining input
+    // is abandoned and every enclosing container is closed with the entries decoded so far,
+    // each reported as `AmfWarning::Truncated`. Returns None only if not even the root value
+    // could be decoded. Uses the default `DecodeOptions`.
+    pub fn unmarshall_lossy(buf: &[u8]) -> (Option<Self>, Vec<AmfWarning>) {
+        let options = DecodeOptions::default();
+        let mut decoder = Decoder::new(buf, &options, true);
+        match decoder.read_value() {
+            Ok(value) => (Some(value), decoder.warnings),
+            Err(e) => {
+                decoder.warnings.push(AmfWarning::UndecodableValue {
+                    offset: decoder.failed_at,
+                    kind: e.kind(),
+                });
+                (None, decoder.warnings)
+            }
+        }
+    }
+}
+
+struct Decoder<'b, 'o> {
+    options: &'o DecodeOptions,
+    buf: &'b [u8],
+    offset: usize,
+    depth: usize,
+    warnings: Vec<AmfWarning>,
+    // 当前正在解码的值的路径，以及正在解码的值(或属性名)的起始位置
+    path: Path<'b>,
+    failed_at: usize,
+    // unmarshall_lossy 模式: 出错的值用 Undefined 代替。resumable 表示出错的值已经被完整跳过，
+    // 可以继续解码后面的值；abandoned 表示无法确定下一个值的位置，剩余的输入全部放弃
+    lossy: bool,
+    resumable: bool,
+    abandoned: bool,
+    // DecodeOptions::intern_keys 开启时，本次解码中出现过的所有 key
+    interned: Option<HashSet<Arc<str>>>,
+}
+
+impl<'b, 'o> Decoder<'b, 'o> {
+    fn new(buf: &'b [u8], options: &'o DecodeOptions, lossy: bool) -> Self {
+        Self {
+            options,
+            buf,
+            offset: 0,
+            depth: 0,
+            warnings: Vec::new(),
+            path: Path::default(),
+            failed_at: 0,
+            lossy,
+            resumable: false,
+            abandoned: false,
+            interned: options.intern_keys.then(HashSet::new),
+        }
+    }
+
+    // Called with the error of a child value (or key). Returns the value to store in its place,
+    // if any; errors that cannot be tolerated are propagated.
+    fn recover(&mut self, err: AmfError) -> Result<Option<Amf0TypedValue>, AmfError> {
+        if self.lossy {
+            self.warnings.push(AmfWarning::UndecodableValue {
+                offset: self.failed_at,
+                kind: err.kind(),
+            });
+            if !self.resumable {
+                self.abandoned = true;
+            }
+            return Ok(Some(Amf0TypedValue::Undefined(UndefinedType)));
+        }
+        if self.options.allow_truncated && err.kind() == ErrorKind::Truncated {
+            // 输入在这个值的中间结束了: 丢弃这个不完整的值，整个输入视为已经消费
+            self.abandoned = true;
+            self.offset = self.buf.len();
+            return Ok(None);
+        }
+        Err(err)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'b [u8], AmfError> {
+        let end = checked_end(self.offset, n)?;
+        check_limit("max_total_bytes", self.options.max_total_bytes, end)?;
+        if self.buf.len() < end {
+            return Err(AmfError::BufferTooSmall {
+                want: end,
+                got: self.buf.len(),
+            });
+        }
+        let bytes = &self.buf[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], AmfError> {
+        Ok(self.take(N)?.try_into().unwrap()) // take 保证了长度
+    }
+
+    fn read_u8(&mut self) -> Result<u8, AmfError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_utf8<const LBW: usize>(&mut self, at: usize) -> Result<AmfUtf8<LBW>, AmfError> {
+        AmfUtf8::new(self.read_str::<LBW>(at)?.into_owned())
+    }
+
+    // `at` is the offset reported in a `NonUtf8String` warning.
+    fn read_str<const LBW: usize>(&mut self, at: usize) -> Result<Cow<'b, str>, AmfError> {
+        let length = if LBW == 2 {
+            u16::from_be_bytes(self.read_array()?) as usize
+        } else {
+            u32::from_be_bytes(self.read_array()?) as usize
+        };
+        // 先检查长度前缀，再去读取(和分配)字符串本身
+        check_limit("max_string_bytes", self.options.max_string_bytes, length)?;
+        let bytes = self.take(length)?;
+        let err = match str_from_utf8(bytes) {
+            Ok(s) => return Ok(Cow::Borrowed(s)),
+            Err(e) => e,
+        };
+        let s = match self.options.invalid_utf8 {
+            Utf8Fallback::Error => {
+                self.resumable = true; // 字符串的长度是已知的
+                return Err(AmfError::InvalidUtf8(err));
+            }
+            Utf8Fallback::Lossy => String::from_utf8_lossy(bytes).into_owned(),
+            Utf8Fallback::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+        };
+        self.warnings.push(AmfWarning::NonUtf8String { offset: at });
+        Ok(Cow::Owned(s))
+    }
+
+    fn key<const LBW: usize>(&mut self, key: Cow<'b, str>) -> Result<AmfUtf8<LBW>, AmfError> {
+        let Some(interned) = &mut self.interned else {
+            return AmfUtf8::new(key.into_owned());
+        };
+        if let Some(shared) = interned.get(&*key) {
+            return AmfUtf8::new_shared(shared.clone());
+        }
+        let shared: Arc<str> = Arc::from(key);
+        interned.insert(shared.clone());
+        AmfUtf8::new_shared(shared)
+    }
+
+    fn deviation(&mut self, warning: AmfWarning) -> Result<(), AmfError> {
+        match self.options.mode {
+            ParseMode::Strict => Err(AmfError::Deviation(warning)),
+            ParseMode::Lenient => {
+                self.warnings.push(warning);
+                Ok(())
+            }
+        }
+    }
+
+    // Decodes one value. Containers are kept on an explicit stack of frames rather than decoded
+    // recursively, so input nested deeper than the call stack allows (up to `max_depth`) is
+    // handled like any other. A child's result is handed to the frame below it exactly where
+    // the recursive decoder returned to its caller, including errors propagated with `?`.
+    fn read_value(&mut self) -> Result<Amf0TypedValue, AmfError> {
+        let mut stack: Vec<Frame<'b>> = Vec::new();
+        let mut result = None;
+        let mut step = Step::Child;
+        loop {
+            match step {
+                Step::Child => match self.begin_value() {
+                    Ok(Begin::Value(value)) => result = Some(Ok(value)),
+                    Ok(Begin::Container(frame)) => stack.push(frame),
+                    Err(e) => result = Some(Err(e)),
+                },
+                Step::Next => {}
+                Step::Finish => {
+                    let frame = stack.pop().unwrap(); // Finish 只由栈顶的 frame 产生
+                    result = Some(self.finish(frame));
+                }
+                Step::Fail(e) => {
+                    stack.pop();
+                    result = Some(Err(e));
+                }
+            }
+            let Some(frame) = stack.last_mut() else {
+                return result.unwrap(); // 栈为空时一定已经得到了根值
+            };
+            // 子值已经完成时交给父容器，否则继续读取栈顶容器的下一个子值
+            let next = match result.take() {
+                Some(result) => self.accept(frame, result),
+                None => self.advance(frame),
+            };
+            step = next.unwrap_or_else(Step::Fail);
+        }
+    }
+
+    // Decodes a scalar, or reads the header of a container and returns its frame.
+    fn begin_value(&mut self) -> Result<Begin<'b>, AmfError> {
+        let start = self.offset;
+        self.failed_at = start;
+        self.resumable = false;
+        let byte = self.read_u8()?;
+        let marker = match TypeMarker::try_from(byte) {
+            Ok(marker) => marker,
+            Err(e) => {
+                if let Some(result) = self.read_extension(byte) {
+                    return result.map(Begin::Value);
+                }
+                // 未知的标记无法确定长度，只有根值可以把剩余的输入全部当作它的内容
+                if self.options.raw_unsupported && self.depth == 0 {
+                    self.take(self.buf.len() - self.offset)?;
+                    return Ok(Begin::Value(self.raw_value(start)));
+                }
+                return Err(e);
+            }
+        };
+        let value = match marker {
+            TypeMarker::Number => {
+                let value = f64::from_be_bytes(self.read_array()?);
+                Amf0TypedValue::Number(NumberType::new(value))
+            }
+            TypeMarker::Boolean => {
+                let byte = self.read_u8()?;
+                if byte > 1 {
+                    self.deviation(AmfWarning::NonCanonicalBoolean {
+                        offset: start,
+                        byte,
+                    })?;
+                }
+                Amf0TypedValue::Boolean(BooleanType::new(byte != 0))
+            }
+            TypeMarker::String => {
+                let inner = self.read_utf8::<2>(start)?;
+                Amf0TypedValue::String(StringType::new(inner))
+            }
+            TypeMarker::LongString => {
+                let inner = self.read_utf8::<4>(start)?;
+                if inner.len() <= u16::MAX as usize {
+                    self.deviation(AmfWarning::NonMinimalString {
+                        offset: start,
+                        length: inner.len(),
+                    })?;
+                }
+                Amf0TypedValue::LongString(LongStringType::new(inner))
+            }
+            TypeMarker::Null => Amf0TypedValue::Null(NullType),
+            TypeMarker::Undefined => Amf0TypedValue::Undefined(UndefinedType),
+            TypeMarker::Object => {
+                let frame = self.properties_frame(start, PropertiesKind::Object)?;
+                return Ok(Begin::Container(frame));
+            }
+            TypeMarker::EcmaArray => {
+                let frame = self.properties_frame(start, PropertiesKind::EcmaArray)?;
+                return Ok(Begin::Container(frame));
+            }
+            TypeMarker::StrictArray => return self.strict_array_frame(start).map(Begin::Container),
+            TypeMarker::ObjectEnd => return Err(AmfError::InvalidObjectEnd),
+            // class name 之后的内容和 Object 相同，读取(并丢弃)这些属性才能跳过它
+            TypeMarker::TypedObject if self.options.raw_unsupported || self.lossy => {
+                self.read_str::<2>(self.offset)?;
+                let kind = PropertiesKind::SkippedTypedObject { start };
+                let frame = self.properties_frame(self.offset, kind)?;
+                return Ok(Begin::Container(frame));
+            }
+            marker => {
+                if self.options.raw_unsupported {
+                    if self.skip_unsupported(marker)? {
+                        return Ok(Begin::Value(self.raw_value(start)));
+                    }
+                    if self.depth == 0 {
+                        self.take(self.buf.len() - self.offset)?;
+                        return Ok(Begin::Value(self.raw_value(start)));
+                    }
+                } else if self.lossy {
+                    self.skip_unsupported(marker)?;
+                }
+                return Err(AmfError::UnsupportedTypeMarker(self.buf[start]));
+            }
+        };
+        Ok(Begin::Value(value))
+    }
+
+    // 跳过长度可以确定的不支持类型(用于 lossy 模式和 raw_unsupported)，返回是否跳过。
+    // TypedObject 需要读取嵌套的属性，在 begin_value 中处理
+    fn skip_unsupported(&mut self, marker: TypeMarker) -> Result<bool, AmfError> {
+        let length = match marker {
+            TypeMarker::Unsupported => 0,
+            TypeMarker::Reference => 2,
+            TypeMarker::Date => 8 + 2, // f64 毫秒数 + s16 时区
+            TypeMarker::XmlDocument => u32::from_be_bytes(self.read_array()?) as usize,
+            _ => return Ok(false),
+        };
+        self.take(length)?;
+        self.resumable = true;
+        Ok(true)
+    }
+
+    fn read_extension(&mut self, marker: u8) -> Option<Result<Amf0TypedValue, AmfError>> {
+        let registry = self.options.extensions.as_ref()?;
+        let result = registry.decode(marker, &self.buf[self.offset..])?;
+        Some(result.and_then(|(value, n)| self.take(n).map(|_| value)))
+    }
+
+    fn raw_value(&self, start: usize) -> Amf0TypedValue {
+        let bytes = self.buf[start + 1..self.offset].to_vec();
+        Amf0TypedValue::Raw(RawValue::new(self.buf[start], bytes))
+    }
+
+    fn enter(&mut self) -> Result<(), AmfError> {
+        self.depth += 1;
+        check_limit("max_depth", self.options.max_depth, self.depth)
+    }
+
+    fn strict_array_frame(&mut self, start: usize) -> Result<Frame<'b>, AmfError> {
+        self.enter()?;
+        let count = u32::from_be_bytes(self.read_array()?) as usize;
+        check_limit("max_properties", self.options.max_properties, count)?;
+        let values = Vec::with_capacity(count.min(MAX_PREALLOCATED_VALUES));
+        Ok(Frame::StrictArray {
+            start,
+            count,
+            index: 0,
+            values,
+        })
+    }
+
+    fn properties_frame(
+        &mut self,
+        start: usize,
+        kind: PropertiesKind,
+    ) -> Result<Frame<'b>, AmfError> {
+        self.enter()?;
+        let mut length = 0u32;
+        if kind == PropertiesKind::EcmaArray {
+            length = u32::from_be_bytes(self.read_array()?);
+        }
+        // 在 Lenient 模式下声明的个数只是一个提示
+        let remaining = self.buf.len() - self.offset;
+        let capacity = properties_capacity(length, remaining).min(self.options.max_properties);
+        Ok(Frame::Properties(PropertiesFrame {
+            kind,
+            start,
+            length,
+            properties: IndexMap::with_capacity(capacity),
+            duplicates: Vec::new(),
+            count: 0,
+            pending: Pending::None,
+        }))
+    }
+
+    // Moves the frame on top of the stack forward: starts its next child or reports that it is
+    // complete.
+    fn advance(&mut self, frame: &mut Frame<'b>) -> Result<Step, AmfError> {
+        match frame {
+            Frame::StrictArray { count, index, .. } => {
+                if *index == *count {
+                    return Ok(Step::Finish);
+                }
+                self.path.push(PathSegment::Index(*index));
+                Ok(Step::Child)
+            }
+            Frame::Properties(frame) => {
+                if self.buf[self.offset..].starts_with(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]) {
+                    self.take(3)?;
+                    return Ok(Step::Finish);
+                }
+                let key_offset = self.offset;
+                self.failed_at = key_offset;
+                self.resumable = false;
+                match self.read_str::<2>(key_offset) {
+                    Ok(key) => {
+                        // 路径只用于错误信息，经过转码的 key 使用其中合法的 UTF-8 前缀
+                        let label = match key {
+                            Cow::Borrowed(key) => key,
+                            Cow::Owned(_) => utf8_prefix(&self.buf[key_offset + 2..self.offset]),
+                        };
+                        self.path.push(PathSegment::Key(label));
+                        frame.pending = Pending::Key(key, key_offset);
+                    }
+                    Err(e) => {
+                        // 属性名无法解码时丢弃整个属性(值仍然要读取，才能继续解码后面的属性)
+                        self.recover(e)?;
+                        if self.abandoned {
+                            return Ok(Step::Finish);
+                        }
+                        frame.pending = Pending::Discard;
+                    }
+                }
+                Ok(Step::Child)
+            }
+        }
+    }
+
+    // Hands the result of the frame's current child to it.
+    fn accept(
+        &mut self,
+        frame: &mut Frame<'b>,
+        result: Result<Amf0TypedValue, AmfError>,
+    ) -> Result<Step, AmfError> {
+        match frame {
+            Frame::StrictArray {
+                start,
+                index,
+                values,
+                ..
+            } => {
+                match result {
+                    Ok(value) => values.push(value),
+                    Err(e) => values.extend(self.recover(e)?),
+                }
+                self.path.pop();
+                *index += 1;
+                if self.abandoned {
+                    self.warnings.push(AmfWarning::Truncated { offset: *start });
+                    return Ok(Step::Finish);
+                }
+                Ok(Step::Next)
+            }
+            Frame::Properties(frame) => {
+                match std::mem::replace(&mut frame.pending, Pending::None) {
+                    Pending::Discard => {
+                        if let Err(e) = result {
+                            self.recover(e)?;
+                        }
+                        if self.abandoned {
+                            return Ok(Step::Finish);
+                        }
+                        Ok(Step::Next)
+                    }
+                    Pending::Key(key, key_offset) => {
+                        let value = match result {
+                            Ok(value) => Some(value),
+                            Err(e) => self.recover(e)?,
+                        };
+                        self.path.pop();
+                        let Some(value) = value else {
+                            return Ok(Step::Finish); // 只有在输入被截断时才会没有值
+                        };
+                        self.failed_at = key_offset;
+                        let key = self.key(key)?;
+                        frame.count += 1;
+                        check_limit("max_properties", self.options.max_properties, frame.count)?;
+                        match frame.properties.entry(key) {
+                            Entry::Vacant(entry) => {
+                                entry.insert(value);
+                            }
+                            Entry::Occupied(mut entry) => match self.options.duplicate_keys {
+                                DuplicateKeyPolicy::FirstWins => {}
+                                DuplicateKeyPolicy::LastWins => {
+                                    entry.insert(value);
+                                }
+                                DuplicateKeyPolicy::Error => {
+                                    return Err(AmfError::DuplicateKey(entry.key().to_string()));
+                                }
+                                DuplicateKeyPolicy::KeepAll => {
+                                    frame.duplicates.push((entry.key().clone(), value))
+                                }
+                            },
+                        }
+                        if self.abandoned {
+                            return Ok(Step::Finish);
+                        }
+                        Ok(Step::Next)
+                    }
+                    Pending::None => unreachable!("a child value without a pending property"),
+                }
+            }
+        }
+    }
+
+    // Builds the value of a complete frame.
+    fn finish(&mut self, frame: Frame<'b>) -> Result<Amf0TypedValue, AmfError> {
+        let frame = match frame {
+            Frame::StrictArray { values, .. } => {
+                self.depth -= 1;
+                return Ok(Amf0TypedValue::StrictArray(StrictArrayType::new(values)));
+            }
+            Frame::Properties(frame) => frame,
+        };
+        let truncated = self.abandoned;
+        if truncated {
+            self.warnings.push(AmfWarning::Truncated {
+                offset: frame.start,
+            });
+        } else if frame.kind == PropertiesKind::EcmaArray && frame.count != frame.length as usize {
+            // 仅在 EcmaArray 情况下校验长度
+            self.failed_at = frame.start;
+            self.deviation(AmfWarning::EcmaArrayCountMismatch {
+                offset: frame.start,
+                declared: frame.length,
+                actual: frame.count,
+            })?;
+        }
+        self.depth -= 1;
+        match frame.kind {
+            PropertiesKind::Object => Ok(Amf0TypedValue::Object(
+                NestedType::new(frame.properties)
+                    .with_duplicates(frame.duplicates)
+                    .with_truncated(truncated),
+            )),
+            PropertiesKind::EcmaArray => Ok(Amf0TypedValue::EcmaArray(
+                NestedType::new(frame.properties)
+                    .with_duplicates(frame.duplicates)
+                    .with_truncated(truncated),
+            )),
+            PropertiesKind::SkippedTypedObject { start } => {
+                self.resumable = true;
+                if self.options.raw_unsupported {
+                    return Ok(self.raw_value(start));
+                }
+                Err(AmfError::UnsupportedTypeMarker(self.buf[start]))
+            }
+        }
+    }
+}
+
+enum Begin<'b> {
+    Value(Amf0TypedValue),
+    Container(Frame<'b>),
+}
+
+// What `read_value` does next with the frame on top of the stack.
+enum Step {
+    // 开始解码栈顶容器的下一个子值
+    Child,
+    // 栈顶容器继续前进
+    Next,
+    // 栈顶容器已经完整，出栈并生成它的值
+    Finish,
+    // 栈顶容器出错，出栈并把错误交给下一层
+    Fail(AmfError),
+}
+
+// 一个正在解码的容器
+enum Frame<'b> {
+    StrictArray {
+        start: usize,
+        count: usize,
+        // 下一个元素的下标(出错被丢弃的元素也计算在内)
+        index: usize,
+        values: Vec<Amf0TypedValue>,
+    },
+    Properties(PropertiesFrame<'b>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PropertiesKind {
+    Object,
+    EcmaArray,
+    // 属性只是为了跳过这个值而读取，完成后得到 Raw 值(raw_unsupported)或者错误(lossy)
+    SkippedTypedObject { start: usize },
+}
+
+struct PropertiesFrame<'b> {
+    kind: PropertiesKind,
+    start: usize,
+    length: u32,
+    properties: IndexMap<Utf8, Amf0TypedValue>,
+    duplicates: Vec<(Utf8, Amf0TypedValue)>,
+    count: usize, // 包括重复的 key
+    pending: Pending<'b>,
+}
+
+// 正在解码的子值属于哪个属性
+enum Pending<'b> {
+    None,
+    // 属性名，以及它的起始位置
+    Key(Cow<'b, str>, usize),
+    // 属性名无法解码，值读取后丢弃
+    Discard,
+}
+
+fn utf8_prefix(bytes: &[u8]) -> &str {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap(), // 前缀一定是合法的
+    }
+}
+
+pub(crate) fn check_limit(limit: &'static str, max: usize, got: usize) -> Result<(), AmfError> {
+    if got > max {
+        return Err(AmfError::LimitExceeded { limit, max, got });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::amf0::nested::EcmaArrayType;
+    use crate::amf0::utf8::Utf8;
+    use crate::traits::{Marshall, MarshallLength, Unmarshall};
+
+    fn sample_value() -> Amf0TypedValue {
+        amf0!({
+            "duration": 12.5,
+            "encoder": "Lavf60",
+            "stereo": true,
+            "keyframes": {"times": [0.0, 2.0], "tag": undefined},
+            "cover": null,
+        })
+    }
+
+    #[test]
+    fn test_default_options_match_unmarshall() {
+        let value = sample_value();
+        let ecma = Amf0TypedValue::EcmaArray(value.as_object().unwrap().clone().into_ecma_array());
+        for v in [value, ecma] {
+            let mut data = v.marshall().unwrap();
+            data.push(TypeMarker::Null as u8); // 后面还有其它值
+            let decoded = Amf0TypedValue::unmarshall_with(&data, &DecodeOptions::default());
+            assert_eq!(decoded.unwrap(), Amf0TypedValue::unmarshall(&data).unwrap());
+        }
+
+        // 嵌套层数的上限也相同
+        let max_depth = DecodeOptions::default().max_depth;
+        for depth in [max_depth, max_depth + 1] {
+            let mut data = [TypeMarker::StrictArray as u8, 0, 0, 0, 1].repeat(depth);
+            data.push(TypeMarker::Null as u8);
+            let decoded = Amf0TypedValue::unmarshall_with(&data, &DecodeOptions::default());
+            let plain = Amf0TypedValue::unmarshall(&data);
+            if depth == max_depth {
+                assert_eq!(decoded.unwrap(), plain.unwrap());
+            } else {
+                assert_eq!(limit_of(decoded), "max_depth");
+                assert_eq!(limit_of(plain), "max_depth");
+            }
+        }
+    }
+
+    fn limit_of(result: Result<(Amf0TypedValue, usize), AmfError>) -> &'static str {
+        match result.as_ref().map_err(|e| e.without_context()) {
+            Err(AmfError::LimitExceeded { limit, .. }) => limit,
+            other => panic!("expected LimitExceeded, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let data = amf0!({"a": [[{"b": 1.0}]]}).marshall().unwrap();
+        let mut options = DecodeOptions {
+            max_depth: 4,
+            ..DecodeOptions::default()
+        };
+        assert!(Amf0TypedValue::unmarshall_with(&data, &options).is_ok());
+        options.max_depth = 3;
+        assert_eq!(
+            limit_of(Amf0TypedValue::unmarshall_with(&data, &options)),
+            "max_depth"
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting_does_not_overflow_stack() {
+        // 十万层嵌套的 strict array，每层只有 5 个字节
+        let mut data = Vec::new();
+        for _ in 0..100_000 {
+            data.extend_from_slice(&[TypeMarker::StrictArray as u8, 0, 0, 0, 1]);
+        }
+        data.push(TypeMarker::Null as u8);
+        assert_eq!(
+            limit_of(Amf0TypedValue::unmarshall_with(
+                &data,
+                &DecodeOptions::default()
+            )),
+            "max_depth"
+        );
+    }
+
+    #[test]
+    fn test_unlimited_depth_decodes_iteratively() {
+        // 十万层 strict array 和 Object 交替嵌套，最内层是 null
+        let depth = 100_000;
+        let mut data = Vec::new();
+        for i in 0..depth {
+            if i % 2 == 0 {
+                data.extend_from_slice(&[TypeMarker::StrictArray as u8, 0, 0, 0, 1]);
+            } else {
+                data.extend_from_slice(&[TypeMarker::Object as u8, 0x00, 0x01, b'a']);
+            }
+        }
+        data.push(TypeMarker::Null as u8);
+        for i in (0..depth).rev() {
+            if i % 2 == 1 {
+                data.extend_from_slice(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]);
+            }
+        }
+
+        let (value, n) =
+            Amf0TypedValue::unmarshall_with(&data, &DecodeOptions::unlimited()).unwrap();
+        assert_eq!(n, data.len());
+        // 逐层向下走到最内层的 null
+        let mut current = &value;
+        let mut levels = 0;
+        loop {
+            current = match current {
+                Amf0TypedValue::StrictArray(v) => &v[0],
+                Amf0TypedValue::Object(v) => &v["a"],
+                _ => break,
+            };
+            levels += 1;
+        }
+        assert_eq!(levels, depth);
+        assert!(matches!(current, Amf0TypedValue::Null(_)));
+        drop(value); // drop 同样不能递归
+
+        // 没有 DecodeOptions 的 unmarshall 使用默认的嵌套层数上限
+        assert_eq!(limit_of(Amf0TypedValue::unmarshall(&data)), "max_depth");
+
+        // 错误的路径指向最内层
+        let null_offset = data.len() - 1 - (depth / 2) * 3;
+        data[null_offset] = 0xff;
+        let err = Amf0TypedValue::unmarshall_with(&data, &DecodeOptions::unlimited()).unwrap_err();
+        let AmfError::Context { offset, path, .. } = &err else {
+            panic!("expected a context error, got {:?}", err);
+        };
+        assert_eq!(*offset, Some(null_offset));
+        assert!(path.ends_with("[0].a"), "{}", &path[path.len() - 20..]);
+    }
+
+    #[test]
+    fn test_max_string_bytes() {
+        // 长度前缀声称有 4 GB，在读取内容之前就应该被拒绝
+        let data = [TypeMarker::LongString as u8, 0xff, 0xff, 0xff, 0xff, b'a'];
+        assert_eq!(
+            limit_of(Amf0TypedValue::unmarshall_with(
+                &data,
+                &DecodeOptions::default()
+            )),
+            "max_string_bytes"
+        );
+
+        // 属性名同样受限制
+        let data = amf0!({"encoder": "x"}).marshall().unwrap();
+        let options = DecodeOptions {
+            max_string_bytes: 6,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(
+            limit_of(Amf0TypedValue::unmarshall_with(&data, &options)),
+            "max_string_bytes"
+        );
+    }
+
+    #[test]
+    fn test_max_properties() {
+        let options = DecodeOptions {
+            max_properties: 2,
+            ..DecodeOptions::default()
+        };
+        let data = amf0!([1.0, 2.0]).marshall().unwrap();
+        assert!(Amf0TypedValue::unmarshall_with(&data, &options).is_ok());
+
+        // strict array 的元素个数在读取元素之前检查
+        let data = [TypeMarker::StrictArray as u8, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!(
+            limit_of(Amf0TypedValue::unmarshall_with(&data, &options)),
+            "max_properties"
+        );
+
+        let data = amf0!({"a": 1.0, "b": 2.0, "c": 3.0}).marshall().unwrap();
+        assert_eq!(
+            limit_of(Amf0TypedValue::unmarshall_with(&data, &options)),
+            "max_properties"
+        );
+    }
+
+    #[test]
+    fn test_max_total_bytes() {
+        let data = sample_value().marshall().unwrap();
+        let mut options = DecodeOptions {
+            max_total_bytes: data.len(),
+            ..DecodeOptions::default()
+        };
+        assert_eq!(
+            Amf0TypedValue::unmarshall_with(&data, &options).unwrap().1,
+            data.len()
+        );
+        options.max_total_bytes = data.len() - 1;
+        assert_eq!(
+            limit_of(Amf0TypedValue::unmarshall_with(&data, &options)),
+            "max_total_bytes"
+        );
+    }
+
+    fn strict() -> DecodeOptions {
+        DecodeOptions {
+            mode: ParseMode::Strict,
+            ..DecodeOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_boolean_byte_above_one() {
+        let data = [TypeMarker::Boolean as u8, 0x02];
+        let (value, n, warnings) =
+            Amf0TypedValue::unmarshall_with_warnings(&data, &DecodeOptions::default()).unwrap();
+        assert_eq!(value, Amf0TypedValue::from(true));
+        assert_eq!(n, 2);
+        assert_eq!(
+            warnings,
+            vec![AmfWarning::NonCanonicalBoolean { offset: 0, byte: 2 }]
+        );
+        assert!(Amf0TypedValue::unmarshall_with(&data, &strict()).is_err());
+    }
+
+    #[test]
+    fn test_ecma_array_count_mismatch() {
+        // FFmpeg 有时把 ECMA array 的个数写成 0
+        let mut data = Amf0TypedValue::EcmaArray(
+            sample_value()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into_ecma_array(),
+        )
+        .marshall()
+        .unwrap();
+        data[1..5].copy_from_slice(&[0, 0, 0, 0]);
+
+        let (value, _, warnings) =
+            Amf0TypedValue::unmarshall_with_warnings(&data, &DecodeOptions::default()).unwrap();
+        assert_eq!(value.as_ecma_array().unwrap().len(), 5);
+        assert_eq!(
+            warnings,
+            vec![AmfWarning::EcmaArrayCountMismatch {
+                offset: 0,
+                declared: 0,
+                actual: 5
+            }]
+        );
+        let err = Amf0TypedValue::unmarshall_with(&data, &strict()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::CountMismatch);
+        // 不带选项的 unmarshall 同样报告个数不一致
+        let err = Amf0TypedValue::unmarshall(&data).unwrap_err();
+        assert!(matches!(
+            err,
+            AmfError::EcmaArrayCountMismatch {
+                declared: 0,
+                actual: 5
+            }
+        ));
+    }
+
+    #[test]
+    fn test_ecma_array_huge_count_is_not_trusted() {
+        let mut data = amf0!({"a": 1.0, "b": null})
+            .as_object()
+            .unwrap()
+            .clone()
+            .into_ecma_array()
+            .marshall()
+            .unwrap();
+        data[1..5].copy_from_slice(&u32::MAX.to_be_bytes());
+
+        let (value, _, warnings) =
+            Amf0TypedValue::unmarshall_with_warnings(&data, &DecodeOptions::unlimited()).unwrap();
+        let array = value.as_ecma_array().unwrap();
+        assert_eq!(array.len(), 2);
+        // 预分配的容量受剩余 buffer 的长度限制，而不是 0xFFFFFFFF
+        assert!(array.capacity() <= data.len() / 3);
+        assert_eq!(
+            warnings,
+            vec![AmfWarning::EcmaArrayCountMismatch {
+                offset: 0,
+                declared: u32::MAX,
+                actual: 2
+            }]
+        );
+
+        let err = EcmaArrayType::unmarshall(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::CountMismatch);
+    }
+
+    #[test]
+    fn test_nested_huge_counts_are_not_preallocated() {
+        // 每一层 strict array 都声明了 max_properties 个元素，后面跟着 1 MB 的 null。
+        // 按剩余 buffer 的长度预分配的话，每一层都要分配上百 MB
+        let depth = DecodeOptions::default().max_depth;
+        let count = DecodeOptions::default().max_properties as u32;
+        let mut data = Vec::new();
+        for _ in 0..depth {
+            data.push(TypeMarker::StrictArray as u8);
+            data.extend_from_slice(&count.to_be_bytes());
+        }
+        data.resize(data.len() + 1024 * 1024, TypeMarker::Null as u8);
+
+        let err = Amf0TypedValue::unmarshall_with(&data, &DecodeOptions::default()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+
+        // 截断时保留下来的外层数组只有一个元素，可以看到预分配的容量
+        let options = DecodeOptions {
+            allow_truncated: true,
+            ..DecodeOptions::default()
+        };
+        let (value, _) = Amf0TypedValue::unmarshall_with(&data, &options).unwrap();
+        let mut current = &value;
+        let mut levels = 0;
+        while let Amf0TypedValue::StrictArray(array) = current {
+            levels += 1;
+            current = &array[0];
+            if current.as_strict_array().is_some() {
+                assert_eq!(array.len(), 1);
+                assert!(array.capacity() <= MAX_PREALLOCATED_VALUES);
+            }
+        }
+        assert_eq!(levels, depth);
+    }
+
+    #[test]
+    fn test_non_minimal_long_string() {
+        let value = amf0!({"a": (Amf0TypedValue::LongString(
+            LongStringType::new_from_str("hi").unwrap()
+        ))});
+        let data = value.marshall().unwrap();
+        let (decoded, _, warnings) =
+            Amf0TypedValue::unmarshall_with_warnings(&data, &DecodeOptions::default()).unwrap();
+        assert_eq!(decoded, value);
+        assert_eq!(
+            warnings,
+            vec![AmfWarning::NonMinimalString {
+                offset: 4,
+                length: 2
+            }]
+        );
+        assert!(Amf0TypedValue::unmarshall_with(&data, &strict()).is_err());
+
+        // 规范的输入在严格模式下没有问题
+        let data = sample_value().marshall().unwrap();
+        let (_, _, warnings) = Amf0TypedValue::unmarshall_with_warnings(&data, &strict()).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    // {"a": 1, "b": 2, "a": 3}，手工构造(amf0! 宏会合并重复的 key)
+    fn duplicated_keys(marker: TypeMarker) -> Vec<u8> {
+        let mut data = vec![marker as u8];
+        if marker == TypeMarker::EcmaArray {
+            data.extend_from_slice(&3u32.to_be_bytes());
+        }
+        for (key, n) in [("a", 1.0), ("b", 2.0), ("a", 3.0)] {
+            data.extend_from_slice(&Utf8::new_from_str(key).unwrap().marshall().unwrap());
+            data.extend_from_slice(&Amf0TypedValue::from(n).marshall().unwrap());
+        }
+        data.extend_from_slice(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]);
+        data
+    }
+
+    fn decode_with_policy(
+        data: &[u8],
+        duplicate_keys: DuplicateKeyPolicy,
+    ) -> Result<Amf0TypedValue, AmfError> {
+        let options = DecodeOptions {
+            duplicate_keys,
+            ..strict()
+        };
+        Amf0TypedValue::unmarshall_with(data, &options).map(|(v, _)| v)
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let data = duplicated_keys(TypeMarker::Object);
+
+        let value = decode_with_policy(&data, DuplicateKeyPolicy::LastWins).unwrap();
+        assert_eq!(value, Amf0TypedValue::unmarshall(&data).unwrap().0);
+        assert_eq!(value, amf0!({"a": 3.0, "b": 2.0}));
+
+        let value = decode_with_policy(&data, DuplicateKeyPolicy::FirstWins).unwrap();
+        assert_eq!(value, amf0!({"a": 1.0, "b": 2.0}));
+
+        let result = decode_with_policy(&data, DuplicateKeyPolicy::Error);
+        let err = result.unwrap_err();
+        assert!(matches!(err.without_context(), AmfError::DuplicateKey(key) if key == "a"));
+        assert_eq!(err.kind(), ErrorKind::DuplicateKey);
+    }
+
+    #[test]
+    fn test_duplicate_keys_keep_all() {
+        for marker in [TypeMarker::Object, TypeMarker::EcmaArray] {
+            let data = duplicated_keys(marker);
+            let value = decode_with_policy(&data, DuplicateKeyPolicy::KeepAll).unwrap();
+            let properties = match &value {
+                Amf0TypedValue::Object(v) => v.clone().into_ecma_array(),
+                Amf0TypedValue::EcmaArray(v) => v.clone(),
+                _ => unreachable!(),
+            };
+            assert_eq!(properties.len(), 2);
+            assert_eq!(properties.duplicates().len(), 1);
+            let all: Vec<_> = properties
+                .get_all("a")
+                .filter_map(|v| v.as_number())
+                .collect();
+            assert_eq!(all, vec![1.0, 3.0]);
+            assert_eq!(properties.get_all("b").count(), 1);
+            assert_eq!(properties.get_all("c").count(), 0);
+
+            // 重新编码时保留全部三个属性(重复的排在最后)
+            let encoded = value.marshall().unwrap();
+            assert_eq!(encoded.len(), data.len());
+            assert_eq!(value.marshall_with(&Default::default()).unwrap(), encoded);
+            let reencoded = decode_with_policy(&encoded, DuplicateKeyPolicy::KeepAll).unwrap();
+            assert_eq!(reencoded, value);
+        }
+    }
+
+    #[test]
+    fn test_error_context() {
+        let value = amf0!({
+            "duration": 12.5,
+            "keyframes": {"times": [0.0, 2.0, 4.0], "filepositions": [13.0]},
+        });
+        let mut data = value.marshall().unwrap();
+        // 把 times[2](也就是 4.0)的类型标记改成一个不存在的值
+        let number = Amf0TypedValue::from(4.0).marshall().unwrap();
+        let pos = data.windows(9).position(|w| w == number).unwrap();
+        data[pos] = 0x42;
+
+        let err = Amf0TypedValue::unmarshall_with(&data, &DecodeOptions::default()).unwrap_err();
+        assert_eq!(err.offset(), Some(pos));
+        assert_eq!(err.path(), Some("keyframes.times[2]"));
+        assert!(
+            err.to_string()
+                .ends_with(&format!("(at offset {}, path keyframes.times[2])", pos))
+        );
+        assert!(matches!(
+            err.without_context(),
+            AmfError::InvalidTypeMarker(0x42)
+        ));
+        assert_eq!(err.kind(), ErrorKind::InvalidTypeMarker);
+        assert!(std::error::Error::source(&err).is_some());
+
+        // 根上的错误路径为空
+        let err = Amf0TypedValue::unmarshall_with(&[0x42], &DecodeOptions::default()).unwrap_err();
+        assert_eq!(err.offset(), Some(0));
+        assert_eq!(err.path(), Some(""));
+    }
+
+    #[test]
+    fn test_lossy_unknown_marker_abandons_rest() {
+        let value = amf0!({
+            "duration": 12.5,
+            "keyframes": {"times": [0.0, 2.0, 4.0], "filepositions": [13.0]},
+        });
+        let mut data = value.marshall().unwrap();
+        let number = Amf0TypedValue::from(4.0).marshall().unwrap();
+        let pos = data.windows(9).position(|w| w == number).unwrap();
+        data[pos] = 0x42;
+
+        let (decoded, warnings) = Amf0TypedValue::unmarshall_lossy(&data);
+        assert_eq!(
+            decoded.unwrap(),
+            amf0!({
+                "duration": 12.5,
+                "keyframes": {"times": [0.0, 2.0, undefined]},
+            })
+        );
+        let keyframes = 1 + 2 + 8 + 9 + 2 + 9;
+        let times = keyframes + 1 + 2 + 5;
+        assert_eq!(
+            warnings,
+            vec![
+                AmfWarning::UndecodableValue {
+                    offset: pos,
+                    kind: ErrorKind::InvalidTypeMarker
+                },
+                AmfWarning::Truncated { offset: times },
+                AmfWarning::Truncated { offset: keyframes },
+                AmfWarning::Truncated { offset: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lossy_skips_values_of_known_length() {
+        // {"a": <非法 UTF-8 字符串>, "d": <Date>, "b": 1.0}
+        let mut data = vec![TypeMarker::Object as u8];
+        data.extend_from_slice(&Utf8::new_from_str("a").unwrap().marshall().unwrap());
+        data.extend_from_slice(&[TypeMarker::String as u8, 0x00, 0x02, 0xc3, 0x28]);
+        data.extend_from_slice(&Utf8::new_from_str("d").unwrap().marshall().unwrap());
+        data.push(TypeMarker::Date as u8);
+        data.extend_from_slice(&[0u8; 10]);
+        data.extend_from_slice(&Utf8::new_from_str("b").unwrap().marshall().unwrap());
+        data.extend_from_slice(&Amf0TypedValue::from(1.0).marshall().unwrap());
+        data.extend_from_slice(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]);
+
+        let (decoded, warnings) = Amf0TypedValue::unmarshall_lossy(&data);
+        assert_eq!(
+            decoded.unwrap(),
+            amf0!({"a": undefined, "d": undefined, "b": 1.0})
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                AmfWarning::UndecodableValue {
+                    offset: 4,
+                    kind: ErrorKind::InvalidUtf8
+                },
+                AmfWarning::UndecodableValue {
+                    offset: 12,
+                    kind: ErrorKind::UnsupportedType
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lossy_truncated_input() {
+        let data = sample_value().marshall().unwrap();
+        for end in 1..data.len() {
+            let (decoded, warnings) = Amf0TypedValue::unmarshall_lossy(&data[..end]);
+            assert!(
+                decoded.unwrap().as_object().unwrap().is_truncated(),
+                "truncated at {}",
+                end
+            );
+            assert_eq!(warnings.last(), Some(&AmfWarning::Truncated { offset: 0 }));
+        }
+        let (decoded, warnings) = Amf0TypedValue::unmarshall_lossy(&data);
+        assert_eq!(decoded.unwrap(), sample_value());
+        assert!(warnings.is_empty());
+
+        let (decoded, warnings) = Amf0TypedValue::unmarshall_lossy(&[]);
+        assert!(decoded.is_none());
+        assert_eq!(
+            warnings,
+            vec![AmfWarning::UndecodableValue {
+                offset: 0,
+                kind: ErrorKind::Truncated
+            }]
+        );
+    }
+
+    #[test]
+    fn test_allow_truncated() {
+        let value = amf0!({
+            "duration": 12.5,
+            "keyframes": {"times": [0.0, 2.0]},
+            "encoder": "Lavf60",
+        });
+        let data = value.marshall().unwrap();
+        let options = DecodeOptions {
+            allow_truncated: true,
+            ..DecodeOptions::default()
+        };
+        for end in 1..data.len() {
+            let (decoded, n, warnings) =
+                Amf0TypedValue::unmarshall_with_warnings(&data[..end], &options).unwrap();
+            let object = decoded.as_object().unwrap();
+            assert!(object.is_truncated(), "truncated at {}", end);
+            assert_eq!(n, end);
+            assert_eq!(warnings.last(), Some(&AmfWarning::Truncated { offset: 0 }));
+            // 截断前已经完整的属性都保留下来(最后一个属性可能只解码了一部分)
+            assert!(
+                object
+                    .iter()
+                    .rev()
+                    .skip(1)
+                    .all(|(k, v)| &value[k.as_ref()] == v)
+            );
+        }
+
+        // 在 keyframes 之后截断，只丢失 encoder
+        let cut = data.len() - (2 + 7 + 1 + 2 + 6 + 3);
+        let (decoded, _) = Amf0TypedValue::unmarshall_with(&data[..cut], &options).unwrap();
+        assert_eq!(
+            decoded.as_object().unwrap().clone().into_inner(),
+            amf0!({"duration": 12.5, "keyframes": {"times": [0.0, 2.0]}})
+                .as_object()
+                .unwrap()
+                .clone()
+                .into_inner()
+        );
+
+        // 完整的输入不受影响，默认选项下截断仍然是错误
+        let (decoded, _) = Amf0TypedValue::unmarshall_with(&data, &options).unwrap();
+        assert!(!decoded.as_object().unwrap().is_truncated());
+        let err = Amf0TypedValue::unmarshall_with(&data[..cut], &DecodeOptions::default());
+        assert_eq!(err.unwrap_err().kind(), ErrorKind::Truncated);
+    }
+
+    #[test]
+    fn test_invalid_utf8_fallback() {
+        // {"caf\xe9": "na\xefve"}，Latin-1 编码的 key 和值
+        let mut data = vec![TypeMarker::Object as u8, 0x00, 0x04];
+        data.extend_from_slice(b"caf\xe9");
+        data.extend_from_slice(&[TypeMarker::String as u8, 0x00, 0x05]);
+        data.extend_from_slice(b"na\xefve");
+        data.extend_from_slice(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]);
+
+        let err = Amf0TypedValue::unmarshall_with(&data, &DecodeOptions::default()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidUtf8);
+
+        let options = DecodeOptions {
+            invalid_utf8: Utf8Fallback::Latin1,
+            ..strict()
+        };
+        let (value, n, warnings) =
+            Amf0TypedValue::unmarshall_with_warnings(&data, &options).unwrap();
+        assert_eq!(n, data.len());
+        assert_eq!(value, amf0!({"café": "naïve"}));
+        assert_eq!(
+            warnings,
+            vec![
+                AmfWarning::NonUtf8String { offset: 1 },
+                AmfWarning::NonUtf8String { offset: 7 },
+            ]
+        );
+
+        let options = DecodeOptions {
+            invalid_utf8: Utf8Fallback::Lossy,
+            ..DecodeOptions::default()
+        };
+        let (value, _) = Amf0TypedValue::unmarshall_with(&data, &options).unwrap();
+        assert_eq!(value, amf0!({"caf\u{fffd}": "na\u{fffd}ve"}));
+    }
+
+    #[test]
+    fn test_truncated_and_unsupported() {
+        let data = sample_value().marshall().unwrap();
+        let result =
+            Amf0TypedValue::unmarshall_with(&data[..data.len() - 1], &DecodeOptions::default());
+        assert!(matches!(
+            result.unwrap_err().without_context(),
+            AmfError::BufferTooSmall { .. }
+        ));
+
+        // 不支持的类型返回错误，而不是 panic
+        let data = [TypeMarker::MovieClip as u8];
+        let err = Amf0TypedValue::unmarshall_with(&data, &DecodeOptions::unlimited()).unwrap_err();
+        assert!(matches!(
+            err.without_context(),
+            AmfError::UnsupportedTypeMarker(0x04)
+        ));
+        assert_eq!(err.kind(), ErrorKind::UnsupportedType);
+
+        // 单独出现的 object end 标记
+        let err = Amf0TypedValue::unmarshall_with(&[0x09], &DecodeOptions::default()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidObjectEnd);
+    }
+    #[test]
+    fn test_raw_unsupported_round_trip() {
+        let key = |k: &str| Utf8::new_from_str(k).unwrap().marshall().unwrap();
+        let date = [
+            &[TypeMarker::Date as u8][..],
+            &[0x42, 0x77, 0, 0, 0, 0, 0, 0, 0, 0],
+        ]
+        .concat();
+        let reference = vec![TypeMarker::Reference as u8, 0x00, 0x01];
+        let xml = [&[TypeMarker::XmlDocument as u8, 0, 0, 0, 3][..], b"<a>"].concat();
+        // TypedObject: class name + 属性(嵌套一个 Date) + object end
+        let typed = [
+            &[TypeMarker::TypedObject as u8][..],
+            &key("Foo"),
+            &key("at"),
+            &date,
+            &[0x00, 0x00, TypeMarker::ObjectEnd as u8],
+        ]
+        .concat();
+        let mut data = vec![TypeMarker::Object as u8];
+        for (k, v) in [("d", &date), ("r", &reference), ("x", &xml), ("t", &typed)] {
+            data.extend_from_slice(&key(k));
+            data.extend_from_slice(v);
+        }
+        data.extend_from_slice(&key("u"));
+        data.push(TypeMarker::Unsupported as u8);
+        data.extend_from_slice(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]);
+
+        assert!(Amf0TypedValue::unmarshall_with(&data, &DecodeOptions::default()).is_err());
+        let options = DecodeOptions {
+            raw_unsupported: true,
+            ..Default::default()
+        };
+        let (value, n) = Amf0TypedValue::unmarshall_with(&data, &options).unwrap();
+        assert_eq!(n, data.len());
+        assert_eq!(
+            value.get_path("d"),
+            Some(&Amf0TypedValue::Raw(RawValue::new(
+                0x0b,
+                date[1..].to_vec()
+            )))
+        );
+        assert_eq!(
+            value.get_path("t"),
+            Some(&Amf0TypedValue::Raw(RawValue::new(
+                0x10,
+                typed[1..].to_vec()
+            )))
+        );
+        assert_eq!(
+            value.get_path("u"),
+            Some(&Amf0TypedValue::Raw(RawValue::new(0x0d, vec![])))
+        );
+        assert_eq!(value.marshall().unwrap(), data);
+        assert_eq!(value.marshall_length(), data.len());
+    }
+
+    #[test]
+    fn test_raw_unsupported_unknown_marker() {
+        let options = DecodeOptions {
+            raw_unsupported: true,
+            ..Default::default()
+        };
+        // 根值: 剩余的输入都属于这个值(比如 0x11 之后的 AMF 3 数据)
+        let data = [0x11, 0x06, 0x05, b'h', b'i'];
+        let (value, n) = Amf0TypedValue::unmarshall_with(&data, &options).unwrap();
+        assert_eq!(n, data.len());
+        assert_eq!(
+            value,
+            Amf0TypedValue::Raw(RawValue::new(0x11, data[1..].to_vec()))
+        );
+        assert_eq!(value.marshall().unwrap(), data);
+
+        // 嵌套在容器中时无法确定长度，仍然报错
+        let data = [TypeMarker::StrictArray as u8, 0, 0, 0, 1, 0x11, 0x01];
+        let err = Amf0TypedValue::unmarshall_with(&data, &options).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidTypeMarker);
+        let data = [
+            TypeMarker::StrictArray as u8,
+            0,
+            0,
+            0,
+            1,
+            TypeMarker::MovieClip as u8,
+        ];
+        let err = Amf0TypedValue::unmarshall_with(&data, &options).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedType);
+    }
+    #[test]
+    fn test_length_overflow() {
+        // 64 位平台上 u32 长度无法溢出，直接模拟 32 位平台上 offset + length 回绕的情况
+        let options = DecodeOptions::unlimited();
+        let mut decoder = Decoder::new(&[0x05, 0x05], &options, false);
+        decoder.take(1).unwrap();
+        let err = decoder.take(usize::MAX).unwrap_err();
+        assert!(matches!(
+            err,
+            AmfError::LengthOverflow {
+                offset: 1,
+                length: usize::MAX
+            }
+        ));
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        assert_eq!(decoder.offset, 1);
+    }
+
+    #[test]
+    fn test_intern_keys() {
+        let value = amf0!([
+            {"time": 0.0, "offset": 13.0},
+            {"time": 2.0, "offset": 4096.0},
+            {"time": 4.0, "offset": 8192.0, "nested": {"time": 1.0}},
+        ]);
+        let data = value.marshall().unwrap();
+        let options = DecodeOptions {
+            intern_keys: true,
+            ..Default::default()
+        };
+        let (decoded, n) = Amf0TypedValue::unmarshall_with(&data, &options).unwrap();
+        assert_eq!(n, data.len());
+        assert_eq!(decoded, value);
+        assert_eq!(decoded.marshall().unwrap(), data);
+
+        // 所有的 "time" 共享同一份内存
+        let time_keys: Vec<_> = decoded
+            .as_strict_array()
+            .unwrap()
+            .iter()
+            .chain([decoded.get_path("[2].nested").unwrap()])
+            .map(|v| v.as_object().unwrap().get_key_value("time").unwrap().0)
+            .collect();
+        let first = time_keys[0].as_shared().unwrap();
+        assert_eq!(time_keys.len(), 4);
+        for key in &time_keys {
+            assert!(Arc::ptr_eq(first, key.as_shared().unwrap()));
+        }
+        // 默认不共享
+        let (decoded, _) = Amf0TypedValue::unmarshall_with(&data, &Default::default()).unwrap();
+        let object = decoded.get_path("[0]").unwrap().as_object().unwrap();
+        assert!(
+            object
+                .get_key_value("time")
+                .unwrap()
+                .0
+                .as_shared()
+                .is_none()
+        );
+    }
+}
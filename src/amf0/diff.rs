@@ -0,0 +1,189 @@
+use crate::amf0::nested::Amf0TypedValue;
+use crate::amf0::utf8::Utf8;
+use indexmap::IndexMap;
+use std::fmt::{Display, Formatter};
+
+// One difference between two value trees, located by a path in the syntax accepted by
+// `Amf0TypedValue::get_path` (e.g. `keyframes.times[3]`; "" is the root).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffEntry {
+    // Present in the second tree only.
+    Added {
+        path: String,
+        value: Amf0TypedValue,
+    },
+    // Present in the first tree only.
+    Removed {
+        path: String,
+        value: Amf0TypedValue,
+    },
+    // Present in both with different values. Containers of the same kind are compared
+    // property by property (or element by element) instead, so `old` and `new` are only
+    // containers when their kinds differ, e.g. an Object replaced by an ECMA array.
+    Changed {
+        path: String,
+        old: Amf0TypedValue,
+        new: Amf0TypedValue,
+    },
+}
+
+impl DiffEntry {
+    pub fn path(&self) -> &str {
+        match self {
+            DiffEntry::Added { path, .. }
+            | DiffEntry::Removed { path, .. }
+            | DiffEntry::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl Display for DiffEntry {
+    // `+ path: value`, `- path: value` or `~ path: old -> new`, values in compact JSON
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let path = match self.path() {
+            "" => "<root>",
+            path => path,
+        };
+        match self {
+            DiffEntry::Added { value, .. } => write!(f, "+ {}: {}", path, value),
+            DiffEntry::Removed { value, .. } => write!(f, "- {}: {}", path, value),
+            DiffEntry::Changed { old, new, .. } => write!(f, "~ {}: {} -> {}", path, old, new),
+        }
+    }
+}
+
+// Compares two value trees and lists every path that was added, removed or changed going
+// from `a` to `b`, in document order (properties of `a` first, then those only in `b`).
+// Objects and ECMA arrays are matched by key, strict arrays by index; property order is
+// ignored. NaN equals NaN, but String and LongString with the same text differ, since they
+// encode differently. Nesting is tracked on the heap, so deep trees cannot overflow the stack.
+pub fn diff(a: &Amf0TypedValue, b: &Amf0TypedValue) -> Vec<DiffEntry> {
+    let mut entries = Vec::new();
+    let mut stack = vec![(String::new(), Some(a), Some(b))];
+    while let Some((path, a, b)) = stack.pop() {
+        let (a, b) = match (a, b) {
+            (Some(a), Some(b)) => (a, b),
+            (Some(value), None) => {
+                entries.push(DiffEntry::Removed {
+                    path,
+                    value: value.clone(),
+                });
+                continue;
+            }
+            (None, Some(value)) => {
+                entries.push(DiffEntry::Added {
+                    path,
+                    value: value.clone(),
+                });
+                continue;
+            }
+            (None, None) => continue,
+        };
+        let children = match (a, b) {
+            (Amf0TypedValue::Object(a), Amf0TypedValue::Object(b)) => property_pairs(&path, a, b),
+            (Amf0TypedValue::EcmaArray(a), Amf0TypedValue::EcmaArray(b)) => {
+                property_pairs(&path, a, b)
+            }
+            (Amf0TypedValue::StrictArray(a), Amf0TypedValue::StrictArray(b)) => {
+                (0..a.len().max(b.len()))
+                    .map(|i| (format!("{}[{}]", path, i), a.get(i), b.get(i)))
+                    .collect()
+            }
+            _ => {
+                if !same_scalar(a, b) {
+                    entries.push(DiffEntry::Changed {
+                        path,
+                        old: a.clone(),
+                        new: b.clone(),
+                    });
+                }
+                continue;
+            }
+        };
+        // 逆序压栈，保证按文档顺序弹出
+        stack.extend(children.into_iter().rev());
+    }
+    entries
+}
+
+type Pair<'a> = (
+    String,
+    Option<&'a Amf0TypedValue>,
+    Option<&'a Amf0TypedValue>,
+);
+
+fn property_pairs<'a>(
+    path: &str,
+    a: &'a IndexMap<Utf8, Amf0TypedValue>,
+    b: &'a IndexMap<Utf8, Amf0TypedValue>,
+) -> Vec<Pair<'a>> {
+    let child = |k: &Utf8| match path {
+        "" => k.to_string(),
+        _ => format!("{}.{}", path, k),
+    };
+    let mut pairs: Vec<_> = a
+        .iter()
+        .map(|(k, v)| (child(k), Some(v), b.get(k)))
+        .collect();
+    pairs.extend(
+        b.iter()
+            .filter(|(k, _)| !a.contains_key(*k))
+            .map(|(k, v)| (child(k), None, Some(v))),
+    );
+    pairs
+}
+
+fn same_scalar(a: &Amf0TypedValue, b: &Amf0TypedValue) -> bool {
+    match (a, b) {
+        (Amf0TypedValue::Number(x), Amf0TypedValue::Number(y)) => {
+            **x == **y || (x.is_nan() && y.is_nan())
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+
+    #[test]
+    fn test_diff() {
+        let a = amf0!({
+            "duration": 12.5,
+            "encoder": "Lavf60",
+            "keyframes": {"times": [0.0, 2.0, 4.0]},
+            "nan": f64::NAN,
+            "stereo": true,
+        });
+        let b = amf0!({
+            "stereo": true,
+            "duration": 13.0,
+            "keyframes": {"times": [0.0, 2.5]},
+            "nan": f64::NAN,
+            "width": 1280.0,
+        });
+        let lines: Vec<_> = diff(&a, &b).iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            lines,
+            [
+                "~ duration: 12.5 -> 13",
+                "- encoder: \"Lavf60\"",
+                "~ keyframes.times[1]: 2 -> 2.5",
+                "- keyframes.times[2]: 4",
+                "+ width: 1280",
+            ]
+        );
+        assert!(diff(&a, &a).is_empty());
+
+        // 容器类型不同时整体报告为 Changed
+        let entries = diff(&amf0!({"a": []}), &amf0!({"a": {}}));
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path(), "a");
+        assert!(matches!(entries[0], DiffEntry::Changed { .. }));
+        assert_eq!(
+            diff(&amf0!(1.0), &amf0!(null))[0].to_string(),
+            "~ <root>: 1 -> null"
+        );
+    }
+}
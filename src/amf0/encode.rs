@@ -0,0 +1,565 @@
+use crate::amf0::extension::ExtensionRegistry;
+use crate::amf0::nested::{Amf0TypedValue, NestedType};
+use crate::amf0::pool::{BufferPool, PooledBuffer};
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use crate::traits::{Marshall, MarshallLength};
+use std::mem::MaybeUninit;
+use std::sync::Arc;
+
+// quiet NaN，没有 payload(与 f64::NAN 相同)
+const CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+// Which type `EncodeOptions::map_representation` writes Objects and ECMA arrays as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapRepresentation {
+    // Each as its own type (what `marshall` does).
+    #[default]
+    Preserve,
+    // Every map as an anonymous Object.
+    Object,
+    // Every map as an ECMA array.
+    EcmaArray,
+}
+
+// Which properties `EncodeOptions::omit_properties` leaves out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OmitProperties {
+    // Write every property.
+    #[default]
+    None,
+    // Skip properties whose value is `undefined`, like Flash Player does.
+    Undefined,
+    // Skip properties whose value is `undefined` or `null`.
+    NullAndUndefined,
+}
+
+impl OmitProperties {
+    fn omits(self, value: &Amf0TypedValue) -> bool {
+        match self {
+            OmitProperties::None => false,
+            OmitProperties::Undefined => value.is_undefined(),
+            OmitProperties::NullAndUndefined => value.is_undefined() || value.is_null(),
+        }
+    }
+}
+
+// Knobs for `Amf0TypedValue::marshall_with`. The default options produce exactly the same
+// bytes as `marshall`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    // Re-select String vs LongString from the actual UTF-8 length of every string in the tree:
+    // a LongString of at most 65535 bytes is written as a String, anything longer as a
+    // LongString.
+    pub normalize_strings: bool,
+    // Byte-stable output for content hashing, caching and snapshot tests: properties are
+    // written sorted by key (duplicates included) instead of in insertion order, every NaN is
+    // written as the same quiet NaN, strings are normalized as with `normalize_strings`, and
+    // `Raw` values are rejected since their bytes cannot be canonicalized. The encoder never
+    // writes references, so equal subtrees always produce equal bytes.
+    pub canonical: bool,
+    // Writes every Object and ECMA array in the tree as the selected type, for servers that
+    // only accept ECMA arrays for metadata (or only anonymous objects), without converting the
+    // tree first. Properties are unchanged.
+    pub map_representation: MapRepresentation,
+    // Leaves out Object and ECMA array properties holding `undefined` (or also `null`), which
+    // shrinks payloads built from structs whose unset optional fields decode to `undefined`.
+    // The ECMA array count covers only the properties written. Strict array elements are
+    // always written, since skipping them would shift the indices.
+    pub omit_properties: OmitProperties,
+    // Encoders for vendor-specific type markers, offered every value before it is written.
+    pub extensions: Option<Arc<ExtensionRegistry>>,
+}
+
+impl Amf0TypedValue {
+    // Encodes the value like `marshall`, applying `options` to every value in the tree.
+    pub fn marshall_with(&self, options: &EncodeOptions) -> Result<Vec<u8>, AmfError> {
+        let mut encoder = Encoder {
+            options,
+            out: Vec::with_capacity(self.marshall_length()), // 选项只会小幅改变长度
+        };
+        encoder.write_value(self)?;
+        Ok(encoder.out)
+    }
+
+    // Encodes the value like `marshall` into a buffer checked out of `pool`, which returns to
+    // the pool when the result is dropped.
+    pub fn marshall_pooled(&self, pool: &BufferPool) -> Result<PooledBuffer, AmfError> {
+        self.marshall_pooled_with(pool, &EncodeOptions::default())
+    }
+
+    // `marshall_pooled` with the options of `marshall_with`.
+    pub fn marshall_pooled_with(
+        &self,
+        pool: &BufferPool,
+        options: &EncodeOptions,
+    ) -> Result<PooledBuffer, AmfError> {
+        let mut buf = pool.get();
+        buf.reserve(self.marshall_length());
+        let mut encoder = Encoder {
+            options,
+            out: &mut *buf,
+        };
+        encoder.write_value(self)?;
+        Ok(buf)
+    }
+
+    // Encodes the value like `marshall` into a caller-provided buffer that does not have to be
+    // initialized (e.g. the spare capacity of a pooled `Vec`, or a multi-megabyte output
+    // buffer that would otherwise be zeroed first). Returns the number of bytes written; only
+    // that prefix of `buf` is initialized. Fails with `AmfError::BufferTooSmall` if the
+    // encoding does not fit, `want` being the full encoded length.
+    pub fn marshall_into_uninit(&self, buf: &mut [MaybeUninit<u8>]) -> Result<usize, AmfError> {
+        self.marshall_into_uninit_with(buf, &EncodeOptions::default())
+    }
+
+    // `marshall_into_uninit` with the options of `marshall_with`.
+    pub fn marshall_into_uninit_with(
+        &self,
+        buf: &mut [MaybeUninit<u8>],
+        options: &EncodeOptions,
+    ) -> Result<usize, AmfError> {
+        let mut encoder = Encoder {
+            options,
+            out: UninitOutput { buf, len: 0 },
+        };
+        encoder.write_value(self)?;
+        let UninitOutput { buf, len } = encoder.out;
+        if len > buf.len() {
+            return Err(AmfError::BufferTooSmall {
+                want: len,
+                got: buf.len(),
+            });
+        }
+        Ok(len)
+    }
+}
+
+// `Marshall::marshall_to` for Objects, ECMA arrays and strict arrays: the default options write
+// the same bytes, and the encoder keeps the nesting on the heap.
+pub(crate) fn marshall_nested_to(
+    value: &Amf0TypedValue,
+    buf: &mut Vec<u8>,
+) -> Result<(), AmfError> {
+    let mut encoder = Encoder {
+        options: &EncodeOptions::default(),
+        out: buf,
+    };
+    encoder.write_value(value)
+}
+
+// Where the encoder writes to.
+trait Output {
+    fn push(&mut self, byte: u8);
+    fn extend_from_slice(&mut self, bytes: &[u8]);
+}
+
+impl Output for Vec<u8> {
+    fn push(&mut self, byte: u8) {
+        Vec::push(self, byte)
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        Vec::extend_from_slice(self, bytes)
+    }
+}
+
+impl Output for &mut Vec<u8> {
+    fn push(&mut self, byte: u8) {
+        Vec::push(self, byte)
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        Vec::extend_from_slice(self, bytes)
+    }
+}
+
+// 写入未初始化的 buffer。放不下时继续累计长度但不再写入，最后由调用者报告需要的长度
+struct UninitOutput<'a> {
+    buf: &'a mut [MaybeUninit<u8>],
+    len: usize,
+}
+
+impl Output for UninitOutput<'_> {
+    fn push(&mut self, byte: u8) {
+        self.extend_from_slice(&[byte])
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        let end = self.len.saturating_add(bytes.len());
+        if end <= self.buf.len() {
+            self.buf[self.len..end].write_copy_of_slice(bytes);
+        }
+        self.len = end;
+    }
+}
+
+struct Encoder<'o, O> {
+    options: &'o EncodeOptions,
+    out: O,
+}
+
+// 容器中还没有写入的子值
+enum Pending<'a> {
+    // 写完之后还要写 object end
+    Properties(std::vec::IntoIter<(&'a Utf8, &'a Amf0TypedValue)>),
+    Values(std::slice::Iter<'a, Amf0TypedValue>),
+}
+
+impl<O: Output> Encoder<'_, O> {
+    // 嵌套关系保存在堆上的栈里，深层嵌套的值不会撑爆调用栈
+    fn write_value(&mut self, value: &Amf0TypedValue) -> Result<(), AmfError> {
+        let mut stack = Vec::new();
+        self.start_value(value, &mut stack)?;
+        while let Some(pending) = stack.last_mut() {
+            match pending {
+                Pending::Properties(properties) => match properties.next() {
+                    Some((k, v)) => {
+                        self.out.extend_from_slice(&(k.len() as u16).to_be_bytes());
+                        self.out.extend_from_slice(k.as_bytes());
+                        self.start_value(v, &mut stack)?;
+                    }
+                    None => {
+                        self.out
+                            .extend_from_slice(&[0x00, 0x00, TypeMarker::ObjectEnd as u8]);
+                        stack.pop();
+                    }
+                },
+                Pending::Values(values) => match values.next() {
+                    Some(v) => self.start_value(v, &mut stack)?,
+                    None => {
+                        stack.pop();
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    // 标量直接写入；容器只写头部，子值压栈
+    fn start_value<'a>(
+        &mut self,
+        value: &'a Amf0TypedValue,
+        stack: &mut Vec<Pending<'a>>,
+    ) -> Result<(), AmfError> {
+        if let Some(registry) = &self.options.extensions
+            && let Some((marker, payload)) = registry.encode(value)?
+        {
+            self.out.push(marker);
+            self.out.extend_from_slice(&payload);
+            return Ok(());
+        }
+        match value {
+            Amf0TypedValue::Number(v) if self.options.canonical && v.is_nan() => {
+                self.out.push(TypeMarker::Number as u8);
+                self.out.extend_from_slice(&CANONICAL_NAN.to_be_bytes());
+                Ok(())
+            }
+            Amf0TypedValue::String(v) if self.normalize_strings() => self.write_string(v),
+            Amf0TypedValue::LongString(v) if self.normalize_strings() => self.write_string(v),
+            // Raw 值的字节无法规范化
+            Amf0TypedValue::Raw(v) if self.options.canonical => {
+                Err(AmfError::UnsupportedTypeMarker(v.marker))
+            }
+            Amf0TypedValue::Object(v) => self.start_map(TypeMarker::Object, v, stack),
+            Amf0TypedValue::EcmaArray(v) => self.start_map(TypeMarker::EcmaArray, v, stack),
+            Amf0TypedValue::StrictArray(v) => {
+                if v.len() > u32::MAX as usize {
+                    return Err(AmfError::TooLong {
+                        what: "Strict array",
+                        max: u32::MAX as usize,
+                        got: v.len(),
+                    });
+                }
+                self.out.push(TypeMarker::StrictArray as u8);
+                self.out.extend_from_slice(&(v.len() as u32).to_be_bytes());
+                stack.push(Pending::Values(v.iter()));
+                Ok(())
+            }
+            Amf0TypedValue::Number(v) => {
+                self.out.push(TypeMarker::Number as u8);
+                self.out.extend_from_slice(&v.to_be_bytes());
+                Ok(())
+            }
+            Amf0TypedValue::Boolean(v) => {
+                self.out.push(TypeMarker::Boolean as u8);
+                self.out.push(**v as u8);
+                Ok(())
+            }
+            Amf0TypedValue::String(v) => {
+                self.out.push(TypeMarker::String as u8);
+                self.out.extend_from_slice(&(v.len() as u16).to_be_bytes());
+                self.out.extend_from_slice(v.as_bytes());
+                Ok(())
+            }
+            Amf0TypedValue::LongString(v) => {
+                self.out.push(TypeMarker::LongString as u8);
+                self.out.extend_from_slice(&(v.len() as u32).to_be_bytes());
+                self.out.extend_from_slice(v.as_bytes());
+                Ok(())
+            }
+            Amf0TypedValue::Null(_) => {
+                self.out.push(TypeMarker::Null as u8);
+                Ok(())
+            }
+            Amf0TypedValue::Undefined(_) => {
+                self.out.push(TypeMarker::Undefined as u8);
+                Ok(())
+            }
+            Amf0TypedValue::Raw(v) => {
+                self.out.push(v.marker);
+                self.out.extend_from_slice(&v.bytes);
+                Ok(())
+            }
+            // 其余的类型很少出现，先编码到临时的 Vec 再复制
+            other => {
+                self.out.extend_from_slice(&other.marshall()?);
+                Ok(())
+            }
+        }
+    }
+
+    fn normalize_strings(&self) -> bool {
+        self.options.normalize_strings || self.options.canonical
+    }
+
+    fn write_string(&mut self, s: &str) -> Result<(), AmfError> {
+        if s.len() <= u16::MAX as usize {
+            self.out.push(TypeMarker::String as u8);
+            self.out.extend_from_slice(&(s.len() as u16).to_be_bytes());
+        } else {
+            let length = u32::try_from(s.len()).map_err(|_| AmfError::StringTooLong {
+                max: u32::MAX as usize,
+                got: s.len(),
+            })?;
+            self.out.push(TypeMarker::LongString as u8);
+            self.out.extend_from_slice(&length.to_be_bytes());
+        }
+        self.out.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+
+    fn start_map<'a, const LBW: usize, const TM: u8>(
+        &mut self,
+        marker: TypeMarker,
+        map: &'a NestedType<LBW, TM>,
+        stack: &mut Vec<Pending<'a>>,
+    ) -> Result<(), AmfError> {
+        let marker = match self.options.map_representation {
+            MapRepresentation::Preserve => marker,
+            MapRepresentation::Object => TypeMarker::Object,
+            MapRepresentation::EcmaArray => TypeMarker::EcmaArray,
+        };
+        let omit = self.options.omit_properties;
+        let mut properties: Vec<_> = map
+            .iter()
+            .chain(map.duplicates().iter().map(|(k, v)| (k, v)))
+            .filter(|(_, v)| !omit.omits(v))
+            .collect();
+        if self.options.canonical {
+            // 稳定排序: 重复的 key 保持原来的相对顺序
+            properties.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+        }
+        self.out.push(marker as u8);
+        if marker == TypeMarker::EcmaArray {
+            self.out
+                .extend_from_slice(&(properties.len() as u32).to_be_bytes());
+        }
+        stack.push(Pending::Properties(properties.into_iter()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::amf0::raw::RawValue;
+    use crate::amf0::string::LongStringType;
+
+    #[test]
+    fn test_string_selection() {
+        assert!(matches!(
+            Amf0TypedValue::string("short").unwrap(),
+            Amf0TypedValue::String(_)
+        ));
+        assert!(matches!(
+            Amf0TypedValue::string("a".repeat(u16::MAX as usize)).unwrap(),
+            Amf0TypedValue::String(_)
+        ));
+        assert!(matches!(
+            Amf0TypedValue::string("a".repeat(u16::MAX as usize + 1)).unwrap(),
+            Amf0TypedValue::LongString(_)
+        ));
+    }
+
+    #[test]
+    fn test_default_options_match_marshall() {
+        let value = amf0!({
+            "duration": 12.5,
+            "encoder": "Lavf60",
+            "keyframes": {"times": [0.0, 2.0], "tag": undefined},
+            "cover": null,
+        });
+        let ecma = Amf0TypedValue::EcmaArray(value.as_object().unwrap().clone().into_ecma_array());
+        for v in [value, ecma] {
+            assert_eq!(
+                v.marshall_with(&EncodeOptions::default()).unwrap(),
+                v.marshall().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize_strings() {
+        let short_long = Amf0TypedValue::LongString(LongStringType::new_from_str("hi").unwrap());
+        let value = amf0!({"a": [(short_long.clone())]});
+        let options = EncodeOptions {
+            normalize_strings: true,
+            ..Default::default()
+        };
+
+        let encoded = value.marshall_with(&options).unwrap();
+        let expected = amf0!({"a": ["hi"]}).marshall().unwrap();
+        assert_eq!(encoded, expected);
+
+        // 关闭选项时保持原样
+        let encoded = value.marshall_with(&EncodeOptions::default()).unwrap();
+        assert_eq!(encoded, value.marshall().unwrap());
+        assert_ne!(encoded, expected);
+    }
+    #[test]
+    fn test_canonical() {
+        let options = EncodeOptions {
+            canonical: true,
+            ..Default::default()
+        };
+        let a = amf0!({"b": 1.0, "a": {"y": null, "x": [(f64::NAN)]}});
+        let payload_nan = f64::from_bits(0x7ff8_0000_0000_0001);
+        let b = amf0!({"a": {"x": [(payload_nan)], "y": null}, "b": 1.0});
+        assert_ne!(a.marshall().unwrap(), b.marshall().unwrap());
+
+        let encoded = a.marshall_with(&options).unwrap();
+        assert_eq!(encoded, b.marshall_with(&options).unwrap());
+        let expected = amf0!({"a": {"x": [(f64::NAN)], "y": null}, "b": 1.0});
+        assert_eq!(encoded, expected.marshall().unwrap());
+
+        let short_long = Amf0TypedValue::LongString(LongStringType::new_from_str("hi").unwrap());
+        assert_eq!(
+            short_long.marshall_with(&options).unwrap(),
+            amf0!("hi").marshall().unwrap()
+        );
+
+        let raw = Amf0TypedValue::Raw(RawValue::new(TypeMarker::Reference as u8, vec![0, 1]));
+        assert!(raw.marshall_with(&EncodeOptions::default()).is_ok());
+        assert!(raw.marshall_with(&options).is_err());
+    }
+
+    #[test]
+    fn test_map_representation() {
+        let object = amf0!({"duration": 12.5, "keyframes": {"times": [0.0]}});
+        let keyframes = amf0!({"times": [0.0]}).as_object().unwrap().clone();
+        let mut ecma = object.as_object().unwrap().clone().into_ecma_array();
+        ecma.insert(
+            "keyframes",
+            Amf0TypedValue::EcmaArray(keyframes.into_ecma_array()),
+        )
+        .unwrap();
+        let ecma = Amf0TypedValue::EcmaArray(ecma);
+        assert_ne!(object.marshall().unwrap(), ecma.marshall().unwrap());
+
+        for (representation, expected) in [
+            (MapRepresentation::Object, &object),
+            (MapRepresentation::EcmaArray, &ecma),
+        ] {
+            let options = EncodeOptions {
+                map_representation: representation,
+                ..Default::default()
+            };
+            for v in [&object, &ecma] {
+                assert_eq!(
+                    v.marshall_with(&options).unwrap(),
+                    expected.marshall().unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_omit_properties() {
+        let value = amf0!({
+            "app": "live",
+            "tcUrl": undefined,
+            "pageUrl": null,
+            "args": [undefined, null],
+        });
+        let ecma = Amf0TypedValue::EcmaArray(value.as_object().unwrap().clone().into_ecma_array());
+        for (omit, expected) in [
+            (OmitProperties::None, value.clone()),
+            (
+                OmitProperties::Undefined,
+                amf0!({"app": "live", "pageUrl": null, "args": [undefined, null]}),
+            ),
+            (
+                OmitProperties::NullAndUndefined,
+                amf0!({"app": "live", "args": [undefined, null]}),
+            ),
+        ] {
+            let options = EncodeOptions {
+                omit_properties: omit,
+                ..Default::default()
+            };
+            assert_eq!(
+                value.marshall_with(&options).unwrap(),
+                expected.marshall().unwrap()
+            );
+            // ECMA array 的个数只计算写出的属性
+            let expected = expected.as_object().unwrap().clone().into_ecma_array();
+            let encoded = ecma.marshall_with(&options).unwrap();
+            assert_eq!(encoded, expected.marshall().unwrap());
+            assert_eq!(
+                Amf0TypedValue::unmarshall_exact(&encoded).unwrap(),
+                Amf0TypedValue::EcmaArray(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn test_marshall_into_uninit() {
+        let value = amf0!({
+            "duration": 12.5,
+            "flags": [true, false, null, undefined],
+            "long": (Amf0TypedValue::LongString(LongStringType::new_from_str("x").unwrap())),
+            "raw": (Amf0TypedValue::Raw(RawValue::new(0x0b, vec![0; 10]))),
+            "nested": {"a": "b"},
+        });
+        let expected = value.marshall().unwrap();
+
+        let mut buf = vec![MaybeUninit::uninit(); expected.len() + 8];
+        let n = value.marshall_into_uninit(&mut buf).unwrap();
+        assert_eq!(n, expected.len());
+        assert_eq!(written(&buf[..n]), expected);
+
+        // 空间不足时报告完整的长度
+        let mut buf = vec![MaybeUninit::uninit(); expected.len() - 1];
+        assert!(matches!(
+            value.marshall_into_uninit(&mut buf),
+            Err(AmfError::BufferTooSmall { want, got }) if want == expected.len() && got == expected.len() - 1
+        ));
+
+        let options = EncodeOptions {
+            canonical: true,
+            ..Default::default()
+        };
+        let value = amf0!({"b": 1.0, "a": "x"});
+        let mut buf = [MaybeUninit::uninit(); 64];
+        let n = value.marshall_into_uninit_with(&mut buf, &options).unwrap();
+        assert_eq!(written(&buf[..n]), value.marshall_with(&options).unwrap());
+    }
+
+    fn written(buf: &[MaybeUninit<u8>]) -> Vec<u8> {
+        // SAFETY: marshall_into_uninit 初始化了返回长度之内的所有字节
+        buf.iter().map(|b| unsafe { b.assume_init() }).collect()
+    }
+}
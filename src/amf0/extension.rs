@@ -0,0 +1,273 @@
+use crate::amf0::nested::Amf0TypedValue;
+use crate::amf0::type_marker::TypeMarker;
+use crate::errors::AmfError;
+use indexmap::IndexMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+type DecodeHandler = dyn Fn(&[u8]) -> Result<(Amf0TypedValue, usize), AmfError> + Send + Sync;
+type EncodeHandler = dyn Fn(&Amf0TypedValue) -> Result<Option<Vec<u8>>, AmfError> + Send + Sync;
+
+// Handlers for vendor-specific type markers (some game servers use bytes above the 0x10
+// TypedObject marker for their own types). Attach a registry to `DecodeOptions::extensions`
+// and `EncodeOptions::extensions`; it is consulted by `unmarshall_with` and `marshall_with`
+// only, plain `unmarshall`/`marshall` keep following the specification.
+#[derive(Clone, Default)]
+pub struct ExtensionRegistry {
+    decoders: IndexMap<u8, Arc<DecodeHandler>>,
+    encoders: IndexMap<u8, Arc<EncodeHandler>>,
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Registers the decoder for `marker`, replacing any previous one. The handler receives the
+    // input following the marker byte and returns the decoded value together with the number
+    // of bytes it consumed. Markers defined by the AMF 0 specification cannot be overridden.
+    pub fn register_decoder<F>(&mut self, marker: u8, handler: F) -> Result<&mut Self, AmfError>
+    where
+        F: Fn(&[u8]) -> Result<(Amf0TypedValue, usize), AmfError> + Send + Sync + 'static,
+    {
+        check_marker(marker)?;
+        self.decoders.insert(marker, Arc::new(handler));
+        Ok(self)
+    }
+
+    // Registers the encoder for `marker`, replacing any previous one. Before writing a value,
+    // `marshall_with` offers it to every encoder in registration order; the first one that
+    // returns `Some(payload)` claims it, and `marker` followed by `payload` is written instead.
+    pub fn register_encoder<F>(&mut self, marker: u8, handler: F) -> Result<&mut Self, AmfError>
+    where
+        F: Fn(&Amf0TypedValue) -> Result<Option<Vec<u8>>, AmfError> + Send + Sync + 'static,
+    {
+        check_marker(marker)?;
+        self.encoders.insert(marker, Arc::new(handler));
+        Ok(self)
+    }
+
+    // The process-wide registry, for servers that register their types once at startup and
+    // then decode on any worker thread: `DecodeOptions { extensions: ExtensionRegistry::global(),
+    // .. }`. `None` until something is registered with `update_global`. It is a snapshot;
+    // later updates do not affect registries already handed out.
+    pub fn global() -> Option<Arc<ExtensionRegistry>> {
+        read_global().clone()
+    }
+
+    // Modifies the process-wide registry: `f` works on a copy, which replaces the global one
+    // only if `f` succeeds, so a failed registration leaves it unchanged and threads decoding
+    // concurrently never see a half-updated registry.
+    pub fn update_global<F, R>(f: F) -> Result<R, AmfError>
+    where
+        F: FnOnce(&mut ExtensionRegistry) -> Result<R, AmfError>,
+    {
+        let mut global = write_global();
+        let mut registry = global.as_deref().cloned().unwrap_or_default();
+        let result = f(&mut registry)?;
+        *global = Some(Arc::new(registry));
+        Ok(result)
+    }
+
+    pub(crate) fn decode(
+        &self,
+        marker: u8,
+        buf: &[u8],
+    ) -> Option<Result<(Amf0TypedValue, usize), AmfError>> {
+        self.decoders.get(&marker).map(|handler| handler(buf))
+    }
+
+    // 返回认领了这个值的 marker 和 payload
+    pub(crate) fn encode(&self, value: &Amf0TypedValue) -> Result<Option<(u8, Vec<u8>)>, AmfError> {
+        for (marker, handler) in &self.encoders {
+            if let Some(payload) = handler(value)? {
+                return Ok(Some((*marker, payload)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+// 进程内全局的 registry。只在启动时更新，读多写少，用 RwLock
+static GLOBAL: RwLock<Option<Arc<ExtensionRegistry>>> = RwLock::new(None);
+
+// 锁内只有一次赋值，持锁线程 panic 不会留下不一致的状态
+fn read_global() -> RwLockReadGuard<'static, Option<Arc<ExtensionRegistry>>> {
+    GLOBAL
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn write_global() -> RwLockWriteGuard<'static, Option<Arc<ExtensionRegistry>>> {
+    GLOBAL
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn check_marker(marker: u8) -> Result<(), AmfError> {
+    if TypeMarker::try_from(marker).is_ok() {
+        return Err(AmfError::Custom(format!(
+            "Type marker 0x{:02x} is defined by AMF 0 and cannot be registered",
+            marker
+        )));
+    }
+    Ok(())
+}
+
+impl Debug for ExtensionRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExtensionRegistry")
+            .field("decoders", &self.decoders.keys().collect::<Vec<_>>())
+            .field("encoders", &self.encoders.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+// 闭包无法比较，两个 registry 相等当且仅当它们为相同的 marker 注册了同一批 handler
+impl PartialEq for ExtensionRegistry {
+    fn eq(&self, other: &Self) -> bool {
+        fn same<H: ?Sized>(a: &IndexMap<u8, Arc<H>>, b: &IndexMap<u8, Arc<H>>) -> bool {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b.iter())
+                    .all(|((ka, ha), (kb, hb))| ka == kb && Arc::ptr_eq(ha, hb))
+        }
+        same(&self.decoders, &other.decoders) && same(&self.encoders, &other.encoders)
+    }
+}
+
+impl Eq for ExtensionRegistry {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::amf0::decode::DecodeOptions;
+    use crate::amf0::encode::EncodeOptions;
+    use crate::errors::ErrorKind;
+    use crate::traits::Marshall;
+
+    const INT32: u8 = 0x20;
+
+    // 某些游戏服务器使用的扩展: 0x20 后跟一个大端 i32
+    fn int32_registry() -> Arc<ExtensionRegistry> {
+        let mut registry = ExtensionRegistry::new();
+        registry
+            .register_decoder(INT32, |buf| {
+                let bytes: [u8; 4] = buf.get(..4).and_then(|b| b.try_into().ok()).ok_or(
+                    AmfError::BufferTooSmall {
+                        want: 4,
+                        got: buf.len(),
+                    },
+                )?;
+                Ok((Amf0TypedValue::from(i32::from_be_bytes(bytes)), 4))
+            })
+            .unwrap()
+            .register_encoder(INT32, |value| {
+                Ok(value
+                    .as_number()
+                    .filter(|n| n.fract() == 0.0 && *n >= i32::MIN as f64 && *n <= i32::MAX as f64)
+                    .map(|n| (n as i32).to_be_bytes().to_vec()))
+            })
+            .unwrap();
+        Arc::new(registry)
+    }
+
+    #[test]
+    fn test_decode_with_extension() {
+        let data = [
+            TypeMarker::StrictArray as u8,
+            0,
+            0,
+            0,
+            2,
+            INT32,
+            0xff,
+            0xff,
+            0xff,
+            0xfe,
+            TypeMarker::Null as u8,
+        ];
+        let err = Amf0TypedValue::unmarshall_with(&data, &DecodeOptions::default()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidTypeMarker);
+
+        let options = DecodeOptions {
+            extensions: Some(int32_registry()),
+            ..Default::default()
+        };
+        let (value, n) = Amf0TypedValue::unmarshall_with(&data, &options).unwrap();
+        assert_eq!(n, data.len());
+        assert_eq!(value, amf0!([-2.0, null]));
+
+        // handler 的错误照常返回，并带有出错位置
+        let err = Amf0TypedValue::unmarshall_with(&data[..7], &options).unwrap_err();
+        assert_eq!(err.offset(), Some(5));
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+    }
+
+    #[test]
+    fn test_encode_with_extension() {
+        let value = amf0!({"id": 7.0, "ratio": 0.5});
+        let options = EncodeOptions {
+            extensions: Some(int32_registry()),
+            ..Default::default()
+        };
+        let encoded = value.marshall_with(&options).unwrap();
+        assert_ne!(encoded, value.marshall().unwrap());
+        let id = [INT32, 0, 0, 0, 7];
+        assert!(encoded.windows(id.len()).any(|w| w == id));
+
+        let decode_options = DecodeOptions {
+            extensions: options.extensions.clone(),
+            ..Default::default()
+        };
+        let (decoded, _) = Amf0TypedValue::unmarshall_with(&encoded, &decode_options).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn test_core_markers_cannot_be_registered() {
+        let mut registry = ExtensionRegistry::new();
+        assert!(
+            registry
+                .register_decoder(TypeMarker::Date as u8, |_| Err(AmfError::InvalidObjectEnd))
+                .is_err()
+        );
+        assert!(registry.register_encoder(0x11, |_| Ok(None)).is_ok());
+        assert_eq!(registry, registry.clone());
+        assert_ne!(registry, ExtensionRegistry::new());
+    }
+
+    #[test]
+    fn test_global_registry() {
+        // 其他测试不使用全局 registry，这里只注册一个测试专用的 marker
+        const MARKER: u8 = 0x7e;
+        let data = [MARKER, TypeMarker::Null as u8];
+        let err = ExtensionRegistry::update_global(|registry| {
+            registry.register_decoder(MARKER, |_| Ok((Amf0TypedValue::from(true), 0)))?;
+            registry
+                .register_decoder(TypeMarker::Date as u8, |_| Err(AmfError::InvalidObjectEnd))?;
+            Ok(())
+        });
+        assert!(err.is_err());
+        // 失败的更新不会生效
+        assert!(
+            ExtensionRegistry::global()
+                .is_none_or(|registry| registry.decode(MARKER, &data[1..]).is_none())
+        );
+
+        ExtensionRegistry::update_global(|registry| {
+            registry.register_decoder(MARKER, |_| Ok((Amf0TypedValue::from(true), 0)))?;
+            Ok(())
+        })
+        .unwrap();
+        let handle = std::thread::spawn(move || {
+            let options = DecodeOptions {
+                extensions: ExtensionRegistry::global(),
+                ..Default::default()
+            };
+            Amf0TypedValue::unmarshall_with(&data, &options).unwrap()
+        });
+        assert_eq!(handle.join().unwrap(), (amf0!(true), 1));
+    }
+}
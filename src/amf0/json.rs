@@ -0,0 +1,198 @@
+use crate::amf0::boolean::BooleanType;
+use crate::amf0::marker::NullType;
+use crate::amf0::nested::{Amf0TypedValue, ObjectType};
+use crate::amf0::number::MAX_SAFE_INTEGER;
+use crate::amf0::number::NumberType;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use indexmap::IndexMap;
+use serde_json::{Map, Number, Value};
+
+// serde_json::Value -> Amf0TypedValue:
+//
+//	null     -> Null
+//	bool     -> Boolean
+//	number   -> Number (64-bit integers beyond 2^53 are rounded to the nearest f64)
+//	string   -> String, or LongString above 65535 bytes (UTF-8 length)
+//	array    -> StrictArray
+//	object   -> Object (JSON has no way to tell an ECMA array apart, so it is never produced)
+impl TryFrom<Value> for Amf0TypedValue {
+    type Error = AmfError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Null => Ok(Amf0TypedValue::Null(NullType)),
+            Value::Bool(v) => Ok(Amf0TypedValue::Boolean(BooleanType::new(v))),
+            Value::Number(v) => {
+                // 不开启 arbitrary_precision 时 as_f64 总是有值
+                let n = v.as_f64().ok_or(AmfError::TypeMismatch {
+                    want: "number",
+                    got: "arbitrary precision JSON number",
+                })?;
+                Ok(Amf0TypedValue::Number(NumberType::new(n)))
+            }
+            Value::String(v) => Amf0TypedValue::string(v),
+            Value::Array(v) => Ok(Amf0TypedValue::StrictArray(StrictArrayType::new(
+                v.into_iter()
+                    .map(Amf0TypedValue::try_from)
+                    .collect::<Result<_, _>>()?,
+            ))),
+            Value::Object(v) => {
+                let mut properties = IndexMap::with_capacity(v.len());
+                for (key, value) in v {
+                    properties.insert(Utf8::new(key)?, Amf0TypedValue::try_from(value)?);
+                }
+                Ok(Amf0TypedValue::Object(ObjectType::new(properties)))
+            }
+        }
+    }
+}
+
+// Amf0TypedValue -> serde_json::Value:
+//
+//	Null / Undefined      -> null
+//	Boolean               -> bool
+//	Number                -> integer if it is integral and within ±(2^53 - 1), float otherwise;
+//	                         NaN and ±Infinity have no JSON form and are rejected
+//	String / LongString   -> string
+//	StrictArray           -> array
+//	Object / EcmaArray    -> object (property order is kept only with serde_json's
+//	                         preserve_order feature)
+//
+// The remaining (unsupported) variants are rejected.
+impl TryFrom<&Amf0TypedValue> for Value {
+    type Error = AmfError;
+
+    fn try_from(value: &Amf0TypedValue) -> Result<Self, Self::Error> {
+        match value {
+            Amf0TypedValue::Null(_) | Amf0TypedValue::Undefined(_) => Ok(Value::Null),
+            Amf0TypedValue::Boolean(v) => Ok(Value::Bool(**v)),
+            Amf0TypedValue::Number(v) => number_to_json(**v),
+            Amf0TypedValue::String(v) => Ok(Value::String((**v).to_string())),
+            Amf0TypedValue::LongString(v) => Ok(Value::String((**v).to_string())),
+            Amf0TypedValue::StrictArray(v) => Ok(Value::Array(
+                v.iter().map(Value::try_from).collect::<Result<_, _>>()?,
+            )),
+            Amf0TypedValue::Object(v) => properties_to_json(v.iter()),
+            Amf0TypedValue::EcmaArray(v) => properties_to_json(v.iter()),
+            other => Err(AmfError::TypeMismatch {
+                want: "JSON value",
+                got: other.kind_name(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Amf0TypedValue> for Value {
+    type Error = AmfError;
+
+    fn try_from(value: Amf0TypedValue) -> Result<Self, Self::Error> {
+        Value::try_from(&value)
+    }
+}
+
+fn number_to_json(n: f64) -> Result<Value, AmfError> {
+    if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER as f64 {
+        return Ok(Value::Number(Number::from(n as i64)));
+    }
+    Number::from_f64(n)
+        .map(Value::Number)
+        .ok_or(AmfError::NumberOutOfRange {
+            value: n,
+            target: "JSON",
+        })
+}
+
+fn properties_to_json<'a, I>(properties: I) -> Result<Value, AmfError>
+where
+    I: IntoIterator<Item = (&'a Utf8, &'a Amf0TypedValue)>,
+{
+    let mut map = Map::new();
+    for (key, value) in properties {
+        map.insert(key.to_string(), Value::try_from(value)?);
+    }
+    Ok(Value::Object(map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0::marker::UndefinedType;
+    use crate::amf0::nested::EcmaArrayType;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_json() {
+        let value = Amf0TypedValue::try_from(json!({
+            "duration": 12.5,
+            "width": 1280,
+            "stereo": true,
+            "encoder": "Lavf60",
+            "times": [0, 2.5],
+            "cover": null
+        }))
+        .unwrap();
+        let Amf0TypedValue::Object(object) = value else {
+            panic!("expected object")
+        };
+        assert_eq!(
+            object.get("width"),
+            Some(&Amf0TypedValue::Number(NumberType::new(1280.0)))
+        );
+        assert_eq!(
+            object.get("times"),
+            Some(&Amf0TypedValue::StrictArray(StrictArrayType::new(vec![
+                Amf0TypedValue::Number(NumberType::new(0.0)),
+                Amf0TypedValue::Number(NumberType::new(2.5)),
+            ])))
+        );
+        assert_eq!(object.get("cover"), Some(&Amf0TypedValue::Null(NullType)));
+    }
+
+    #[test]
+    fn test_from_json_long_string() {
+        let long = "a".repeat(u16::MAX as usize + 1);
+        let value = Amf0TypedValue::try_from(Value::String(long)).unwrap();
+        assert!(matches!(value, Amf0TypedValue::LongString(_)));
+        let value = Amf0TypedValue::try_from(json!("a".repeat(u16::MAX as usize))).unwrap();
+        assert!(matches!(value, Amf0TypedValue::String(_)));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut props = IndexMap::new();
+        props.insert(
+            Utf8::new_from_str("width").unwrap(),
+            Amf0TypedValue::Number(NumberType::new(1280.0)),
+        );
+        props.insert(
+            Utf8::new_from_str("framerate").unwrap(),
+            Amf0TypedValue::Number(NumberType::new(29.97)),
+        );
+        props.insert(
+            Utf8::new_from_str("tag").unwrap(),
+            Amf0TypedValue::Undefined(UndefinedType),
+        );
+        let value = Amf0TypedValue::EcmaArray(EcmaArrayType::new(props));
+        assert_eq!(
+            Value::try_from(&value).unwrap(),
+            json!({"width": 1280, "framerate": 29.97, "tag": null})
+        );
+    }
+
+    #[test]
+    fn test_to_json_numbers() {
+        assert_eq!(number_to_json(-3.0).unwrap(), json!(-3));
+        assert_eq!(number_to_json(1e300).unwrap(), json!(1e300));
+        assert!(number_to_json(f64::NAN).is_err());
+        assert!(number_to_json(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = json!({"a": [1, "x", false, null, {"b": 0.5}]});
+        let value = Amf0TypedValue::try_from(original.clone()).unwrap();
+        assert_eq!(Value::try_from(value).unwrap(), original);
+    }
+}
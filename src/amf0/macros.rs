@@ -0,0 +1,176 @@
+// Builds an `Amf0TypedValue` from a JSON-like literal, in the spirit of `serde_json::json!`:
+//
+//	amf0!(null)                     -> Null
+//	amf0!(undefined)                -> Undefined
+//	amf0!([a, b, ...])              -> StrictArray
+//	amf0!({"key": value, ...})      -> Object (keys are string literals or parenthesized
+//	                                   expressions evaluating to something that derefs to str)
+//	amf0!(expr)                     -> `ToAmf0::to_amf0(&expr)`, so numbers, booleans, strings,
+//	                                   Options, Vecs and `#[derive(AmfObject)]` structs all work
+//
+// Values nest freely. Like `json!`, the macro panics if a key or value cannot be represented
+// (for example a key longer than 65535 bytes).
+#[macro_export]
+macro_rules! amf0 {
+    (null) => {
+        $crate::amf0::nested::Amf0TypedValue::Null($crate::amf0::marker::NullType)
+    };
+
+    (undefined) => {
+        $crate::amf0::nested::Amf0TypedValue::Undefined($crate::amf0::marker::UndefinedType)
+    };
+
+    ([ $($tt:tt)* ]) => {
+        $crate::amf0::nested::Amf0TypedValue::StrictArray(
+            $crate::amf0::strict_array::StrictArrayType::new($crate::amf0!(@array [] [] $($tt)*)),
+        )
+    };
+
+    ({ $($tt:tt)* }) => {{
+        let properties: ::std::vec::Vec<(
+            $crate::amf0::utf8::Utf8,
+            $crate::amf0::nested::Amf0TypedValue,
+        )> = $crate::amf0!(@object [] $($tt)*);
+        $crate::amf0::nested::Amf0TypedValue::Object(properties.into_iter().collect())
+    }};
+
+    ($other:expr) => {
+        $crate::amf0::convert::ToAmf0::to_amf0(&$other).expect("value is not representable in AMF 0")
+    };
+
+    // 以下为内部规则: 逐个 token 收集元素，遇到顶层的逗号就结束当前元素
+
+    (@array [$($done:expr,)*] []) => {
+        ::std::vec![$($done,)*]
+    };
+
+    (@array [$($done:expr,)*] [$($cur:tt)+]) => {
+        ::std::vec![$($done,)* $crate::amf0!($($cur)+),]
+    };
+
+    (@array [$($done:expr,)*] [$($cur:tt)+] , $($rest:tt)*) => {
+        $crate::amf0!(@array [$($done,)* $crate::amf0!($($cur)+),] [] $($rest)*)
+    };
+
+    (@array [$($done:expr,)*] [$($cur:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::amf0!(@array [$($done,)*] [$($cur)* $next] $($rest)*)
+    };
+
+    (@object [$($done:expr,)*]) => {
+        ::std::vec![$($done,)*]
+    };
+
+    (@object [$($done:expr,)*] $key:tt : $($rest:tt)*) => {
+        $crate::amf0!(@value [$($done,)*] ($key) [] $($rest)*)
+    };
+
+    (@value [$($done:expr,)*] ($key:expr) [$($cur:tt)+]) => {
+        $crate::amf0!(@object [$($done,)* $crate::amf0!(@entry $key, $($cur)+),])
+    };
+
+    (@value [$($done:expr,)*] ($key:expr) [$($cur:tt)+] , $($rest:tt)*) => {
+        $crate::amf0!(@object [$($done,)* $crate::amf0!(@entry $key, $($cur)+),] $($rest)*)
+    };
+
+    (@value [$($done:expr,)*] ($key:expr) [$($cur:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::amf0!(@value [$($done,)*] ($key) [$($cur)* $next] $($rest)*)
+    };
+
+    (@entry $key:expr, $($value:tt)+) => {
+        (
+            $crate::amf0::utf8::Utf8::new_from_str(&$key).expect("key is not representable in AMF 0"),
+            $crate::amf0!($($value)+),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::amf0::boolean::BooleanType;
+    use crate::amf0::marker::{NullType, UndefinedType};
+    use crate::amf0::nested::{Amf0TypedValue, ObjectType};
+    use crate::amf0::number::NumberType;
+    use crate::amf0::strict_array::StrictArrayType;
+    use crate::amf0::string::StringType;
+    use crate::amf0::utf8::Utf8;
+    use indexmap::IndexMap;
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(amf0!(null), Amf0TypedValue::Null(NullType));
+        assert_eq!(amf0!(undefined), Amf0TypedValue::Undefined(UndefinedType));
+        assert_eq!(amf0!(true), Amf0TypedValue::Boolean(BooleanType::new(true)));
+        assert_eq!(amf0!(-1.5), Amf0TypedValue::Number(NumberType::new(-1.5)));
+        assert_eq!(
+            amf0!("hi"),
+            Amf0TypedValue::String(StringType::new_from_str("hi").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_object_and_array() {
+        let width = 1280;
+        let value = amf0!({
+            "duration": 12.4,
+            "width": width,
+            "hasVideo": true,
+            "keyframes": {
+                "times": [0.0, 2.0 + 0.5, null],
+                "filepositions": [],
+            },
+            "tags": [{}, [undefined]],
+        });
+
+        let mut keyframes = IndexMap::new();
+        keyframes.insert(
+            Utf8::new_from_str("times").unwrap(),
+            Amf0TypedValue::StrictArray(StrictArrayType::new(vec![
+                Amf0TypedValue::Number(NumberType::new(0.0)),
+                Amf0TypedValue::Number(NumberType::new(2.5)),
+                Amf0TypedValue::Null(NullType),
+            ])),
+        );
+        keyframes.insert(
+            Utf8::new_from_str("filepositions").unwrap(),
+            Amf0TypedValue::StrictArray(StrictArrayType::default()),
+        );
+        let mut props = IndexMap::new();
+        props.insert(
+            Utf8::new_from_str("duration").unwrap(),
+            Amf0TypedValue::Number(NumberType::new(12.4)),
+        );
+        props.insert(
+            Utf8::new_from_str("width").unwrap(),
+            Amf0TypedValue::Number(NumberType::new(1280.0)),
+        );
+        props.insert(
+            Utf8::new_from_str("hasVideo").unwrap(),
+            Amf0TypedValue::Boolean(BooleanType::new(true)),
+        );
+        props.insert(
+            Utf8::new_from_str("keyframes").unwrap(),
+            Amf0TypedValue::Object(ObjectType::new(keyframes)),
+        );
+        props.insert(
+            Utf8::new_from_str("tags").unwrap(),
+            Amf0TypedValue::StrictArray(StrictArrayType::new(vec![
+                Amf0TypedValue::Object(ObjectType::default()),
+                Amf0TypedValue::StrictArray(StrictArrayType::new(vec![Amf0TypedValue::Undefined(
+                    UndefinedType,
+                )])),
+            ])),
+        );
+        assert_eq!(value, Amf0TypedValue::Object(ObjectType::new(props)));
+    }
+
+    #[test]
+    fn test_expression_keys() {
+        let key = String::from("dynamic");
+        let value = amf0!({ (key): "v", ("a".to_string() + "b"): 1 });
+        let Amf0TypedValue::Object(object) = value else {
+            panic!("expected object")
+        };
+        let keys: Vec<&str> = object.keys().map(|k| k.as_ref()).collect();
+        assert_eq!(keys, vec!["dynamic", "ab"]);
+    }
+}
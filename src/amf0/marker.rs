@@ -14,6 +14,11 @@ impl<M: MarkerType> Marshall for M {
         buf[0] = M::TM as u8; // 单字节情况下，不需考虑字节序问题
         Ok(buf.to_vec())
     }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        buf.push(M::TM as u8);
+        Ok(())
+    }
 }
 
 impl<M: MarkerType> MarshallLength for M {
@@ -24,7 +29,7 @@ impl<M: MarkerType> MarshallLength for M {
 
 impl<M: MarkerType + Default> Unmarshall for M {
     fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
-        if buf.len() < 1 {
+        if buf.is_empty() {
             return Err(AmfError::BufferTooSmall {
                 want: 1,
                 got: buf.len(),
@@ -0,0 +1,137 @@
+use crate::amf0::nested::Amf0TypedValue;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::utf8::Utf8;
+use indexmap::IndexMap;
+
+// What `Amf0TypedValue::merge` does where both sides hold a value that is not an Object or
+// ECMA array pair (those are always merged property by property).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MergeStrategy {
+    // The incoming value replaces the existing one.
+    #[default]
+    Overwrite,
+    // The existing value is kept; only missing properties are added.
+    KeepExisting,
+    // Two strict arrays are concatenated (existing elements first); anything else is
+    // overwritten.
+    CombineArrays,
+}
+
+impl Amf0TypedValue {
+    // Recursively merges `other` into `self`, e.g. per-stream overrides onto default metadata.
+    // When both are Objects or ECMA arrays (in any combination) every property of `other` is
+    // merged into the property of the same name, or appended if `self` lacks it; `self` keeps
+    // its own type. Otherwise `strategy` decides. Nesting is walked on the heap, so deeply
+    // nested values cannot overflow the stack.
+    pub fn merge(&mut self, other: Amf0TypedValue, strategy: MergeStrategy) {
+        let mut stack = vec![(self, other)];
+        while let Some((target, source)) = stack.pop() {
+            let source = match (target.is_map(), source) {
+                (true, Amf0TypedValue::Object(source)) => {
+                    merge_properties(target.map_mut(), source.into_inner(), &mut stack);
+                    continue;
+                }
+                (true, Amf0TypedValue::EcmaArray(source)) => {
+                    merge_properties(target.map_mut(), source.into_inner(), &mut stack);
+                    continue;
+                }
+                (_, source) => source,
+            };
+            match (strategy, &mut *target, source) {
+                (MergeStrategy::KeepExisting, _, _) => {}
+                (
+                    MergeStrategy::CombineArrays,
+                    Amf0TypedValue::StrictArray(existing),
+                    Amf0TypedValue::StrictArray(source),
+                ) => {
+                    let mut values = existing.take_values();
+                    values.extend(Vec::from(source));
+                    *existing = StrictArrayType::new(values);
+                }
+                (_, _, source) => *target = source,
+            }
+        }
+    }
+}
+
+impl Amf0TypedValue {
+    fn is_map(&self) -> bool {
+        matches!(
+            self,
+            Amf0TypedValue::Object(_) | Amf0TypedValue::EcmaArray(_)
+        )
+    }
+
+    fn map_mut(&mut self) -> &mut IndexMap<Utf8, Amf0TypedValue> {
+        match self {
+            Amf0TypedValue::Object(v) => v,
+            Amf0TypedValue::EcmaArray(v) => v,
+            _ => unreachable!("checked by is_map"),
+        }
+    }
+}
+
+// 先追加新的 key，再把两边都有的 key 压栈；压栈的可变引用来自同一次 iter_mut，互不重叠
+fn merge_properties<'a>(
+    target: &'a mut IndexMap<Utf8, Amf0TypedValue>,
+    source: IndexMap<Utf8, Amf0TypedValue>,
+    stack: &mut Vec<(&'a mut Amf0TypedValue, Amf0TypedValue)>,
+) {
+    let (mut shared, new): (IndexMap<_, _>, IndexMap<_, _>) = source
+        .into_iter()
+        .partition(|(k, _)| target.contains_key(k.as_ref()));
+    target.extend(new);
+    for (k, v) in target.iter_mut() {
+        if let Some(source) = shared.swap_remove(k.as_ref()) {
+            stack.push((v, source));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+
+    #[test]
+    fn test_merge_strategies() {
+        let defaults = amf0!({
+            "encoder": "amf-rs",
+            "video": {"width": 1280.0, "height": 720.0},
+            "tags": ["live"],
+        });
+        let overrides = amf0!({
+            "video": {"width": 1920.0, "codec": "avc"},
+            "tags": ["hd"],
+            "title": "stream",
+        });
+
+        let mut merged = defaults.clone();
+        merged.merge(overrides.clone(), MergeStrategy::Overwrite);
+        assert_eq!(
+            merged,
+            amf0!({
+                "encoder": "amf-rs",
+                "video": {"width": 1920.0, "height": 720.0, "codec": "avc"},
+                "tags": ["hd"],
+                "title": "stream",
+            })
+        );
+
+        let mut merged = defaults.clone();
+        merged.merge(overrides.clone(), MergeStrategy::KeepExisting);
+        assert_eq!(merged["video"]["width"], Amf0TypedValue::from(1280.0));
+        assert_eq!(merged["video"]["codec"], amf0!("avc"));
+        assert_eq!(merged["tags"], amf0!(["live"]));
+
+        let mut merged = defaults;
+        merged.merge(overrides, MergeStrategy::CombineArrays);
+        assert_eq!(merged["tags"], amf0!(["live", "hd"]));
+        assert_eq!(merged["video"]["width"], Amf0TypedValue::from(1920.0));
+
+        // 非对象的根值按策略处理
+        let mut scalar = amf0!(1.0);
+        scalar.merge(amf0!({"a": 1.0}), MergeStrategy::Overwrite);
+        assert_eq!(scalar, amf0!({"a": 1.0}));
+    }
+}
@@ -1,9 +1,66 @@
+mod access;
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+#[cfg(feature = "bumpalo")]
+pub mod arena;
+#[cfg(feature = "tokio")]
+mod async_decoder;
 pub mod boolean;
+pub mod borrowed;
+mod builder;
+mod coerce;
+pub mod convert;
+#[cfg(feature = "serde")]
+mod de;
+pub mod debug;
+mod decode;
+mod diff;
+mod encode;
+mod extension;
+#[cfg(feature = "json")]
+mod json;
+mod macros;
 pub mod marker;
+mod merge;
 pub mod nested;
 pub mod number;
 pub mod object_end;
+mod pool;
+pub mod raw;
+mod read;
+#[cfg(feature = "serde")]
+mod ser;
+#[cfg(feature = "serde")]
+mod serde_value;
+mod shared;
+mod stats;
+pub mod strict_array;
 pub mod string;
+mod to_json;
 pub mod type_marker;
 pub mod unsupported;
 pub mod utf8;
+mod validate;
+pub mod visit;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "derive")]
+pub use amf_rs_derive::AmfObject;
+#[cfg(feature = "tokio")]
+pub use async_decoder::AsyncDecoder;
+pub use builder::{EcmaArrayBuilder, NestedBuilder, ObjectBuilder};
+#[cfg(feature = "serde")]
+pub use de::{from_slice, from_value};
+pub use decode::{DecodeOptions, DuplicateKeyPolicy, ParseMode, Utf8Fallback};
+pub use diff::{DiffEntry, diff};
+pub use encode::{EncodeOptions, MapRepresentation, OmitProperties};
+pub use extension::ExtensionRegistry;
+pub use merge::MergeStrategy;
+pub use pool::{BufferPool, PooledBuffer};
+pub use read::ChunkReader;
+#[cfg(feature = "serde")]
+pub use ser::{to_value, to_vec};
+pub use shared::SharedValue;
+pub use stats::ValueStats;
+pub use validate::validate;
@@ -1,12 +1,17 @@
 use crate::amf0::boolean::BooleanType;
+use crate::amf0::decode::{DEFAULT_MAX_DEPTH, check_limit};
+use crate::amf0::encode::marshall_nested_to;
 use crate::amf0::marker::{NullType, UndefinedType};
 use crate::amf0::number::NumberType;
 use crate::amf0::object_end::ObjectEndType;
+use crate::amf0::raw::RawValue;
+use crate::amf0::strict_array::StrictArrayType;
 use crate::amf0::string::{LongStringType, StringType};
+use crate::amf0::to_json::write_json_object;
 use crate::amf0::type_marker::TypeMarker;
 use crate::amf0::unsupported::{
-    DateType, MovieClipType, RecordsetType, ReferenceType, StrictArrayType, TypedObjectType,
-    UnsupportedType, XmlDocumentType,
+    DateType, MovieClipType, RecordsetType, ReferenceType, TypedObjectType, UnsupportedType,
+    XmlDocumentType,
 };
 use crate::amf0::utf8::Utf8;
 use crate::errors::AmfError;
@@ -15,9 +20,13 @@ use indexmap::IndexMap;
 use std::borrow::Borrow;
 use std::fmt::Display;
 use std::io;
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 
-#[derive(Debug, Clone, PartialEq)]
+// Non-exhaustive so that wire types this crate does not decode yet (Date, Reference, AMF 3
+// switches, ...) can get their own variant in a minor release. Matches need a `_` arm; use
+// `type_marker()`, `kind_name()` and `as_raw()` to handle the rest generically.
+#[derive(Debug)]
+#[non_exhaustive]
 pub enum Amf0TypedValue {
     Number(NumberType),
     Boolean(BooleanType),
@@ -36,116 +45,402 @@ pub enum Amf0TypedValue {
     Recordset(RecordsetType),
     XmlDocument(XmlDocumentType),
     TypedObject(TypedObjectType),
+    // Opt-in, see `DecodeOptions::raw_unsupported`; never produced by `unmarshall`.
+    Raw(RawValue),
 }
 
 impl Marshall for Amf0TypedValue {
     fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    // Containers go through the encoder, which keeps the nesting on the heap, so deeply nested
+    // values (which `unmarshall` accepts) cannot overflow the call stack.
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
         match self {
-            Amf0TypedValue::Number(v) => v.marshall(),
-            Amf0TypedValue::Boolean(v) => v.marshall(),
-            Amf0TypedValue::String(v) => v.marshall(),
-            Amf0TypedValue::Object(v) => v.marshall(),
-            Amf0TypedValue::MovieClip(v) => v.marshall(),
-            Amf0TypedValue::Null(v) => v.marshall(),
-            Amf0TypedValue::Undefined(v) => v.marshall(),
-            Amf0TypedValue::Reference(v) => v.marshall(),
-            Amf0TypedValue::EcmaArray(v) => v.marshall(),
-            Amf0TypedValue::ObjectEnd(v) => v.marshall(),
-            Amf0TypedValue::StrictArray(v) => v.marshall(),
-            Amf0TypedValue::Date(v) => v.marshall(),
-            Amf0TypedValue::LongString(v) => v.marshall(),
-            Amf0TypedValue::Unsupported(v) => v.marshall(),
-            Amf0TypedValue::Recordset(v) => v.marshall(),
-            Amf0TypedValue::XmlDocument(v) => v.marshall(),
-            Amf0TypedValue::TypedObject(v) => v.marshall(),
+            Amf0TypedValue::Object(_)
+            | Amf0TypedValue::EcmaArray(_)
+            | Amf0TypedValue::StrictArray(_) => marshall_nested_to(self, buf),
+            Amf0TypedValue::Number(v) => v.marshall_to(buf),
+            Amf0TypedValue::Boolean(v) => v.marshall_to(buf),
+            Amf0TypedValue::String(v) => v.marshall_to(buf),
+            Amf0TypedValue::MovieClip(v) => v.marshall_to(buf),
+            Amf0TypedValue::Null(v) => v.marshall_to(buf),
+            Amf0TypedValue::Undefined(v) => v.marshall_to(buf),
+            Amf0TypedValue::Reference(v) => v.marshall_to(buf),
+            Amf0TypedValue::ObjectEnd(v) => v.marshall_to(buf),
+            Amf0TypedValue::Date(v) => v.marshall_to(buf),
+            Amf0TypedValue::LongString(v) => v.marshall_to(buf),
+            Amf0TypedValue::Unsupported(v) => v.marshall_to(buf),
+            Amf0TypedValue::Recordset(v) => v.marshall_to(buf),
+            Amf0TypedValue::XmlDocument(v) => v.marshall_to(buf),
+            Amf0TypedValue::TypedObject(v) => v.marshall_to(buf),
+            Amf0TypedValue::Raw(v) => v.marshall_to(buf),
         }
     }
 }
 
 impl MarshallLength for Amf0TypedValue {
+    // 子值保存在堆上的栈里逐个累加，深层嵌套的值不会撑爆调用栈
     fn marshall_length(&self) -> usize {
-        match self {
-            Amf0TypedValue::Number(v) => v.marshall_length(),
-            Amf0TypedValue::Boolean(v) => v.marshall_length(),
-            Amf0TypedValue::String(v) => v.marshall_length(),
-            Amf0TypedValue::Object(v) => v.marshall_length(),
-            Amf0TypedValue::MovieClip(v) => v.marshall_length(),
-            Amf0TypedValue::Null(v) => v.marshall_length(),
-            Amf0TypedValue::Undefined(v) => v.marshall_length(),
-            Amf0TypedValue::Reference(v) => v.marshall_length(),
-            Amf0TypedValue::EcmaArray(v) => v.marshall_length(),
-            Amf0TypedValue::ObjectEnd(v) => v.marshall_length(),
-            Amf0TypedValue::StrictArray(v) => v.marshall_length(),
-            Amf0TypedValue::Date(v) => v.marshall_length(),
-            Amf0TypedValue::LongString(v) => v.marshall_length(),
-            Amf0TypedValue::Unsupported(v) => v.marshall_length(),
-            Amf0TypedValue::Recordset(v) => v.marshall_length(),
-            Amf0TypedValue::XmlDocument(v) => v.marshall_length(),
-            Amf0TypedValue::TypedObject(v) => v.marshall_length(),
+        let mut length = 0;
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            length += match value {
+                Amf0TypedValue::Object(v) => v.shallow_length(&mut stack),
+                Amf0TypedValue::EcmaArray(v) => v.shallow_length(&mut stack),
+                Amf0TypedValue::StrictArray(v) => {
+                    stack.extend(v.iter());
+                    1 + 4 // 1 byte for type marker, 4 bytes for array count
+                }
+                Amf0TypedValue::Number(v) => v.marshall_length(),
+                Amf0TypedValue::Boolean(v) => v.marshall_length(),
+                Amf0TypedValue::String(v) => v.marshall_length(),
+                Amf0TypedValue::MovieClip(v) => v.marshall_length(),
+                Amf0TypedValue::Null(v) => v.marshall_length(),
+                Amf0TypedValue::Undefined(v) => v.marshall_length(),
+                Amf0TypedValue::Reference(v) => v.marshall_length(),
+                Amf0TypedValue::ObjectEnd(v) => v.marshall_length(),
+                Amf0TypedValue::Date(v) => v.marshall_length(),
+                Amf0TypedValue::LongString(v) => v.marshall_length(),
+                Amf0TypedValue::Unsupported(v) => v.marshall_length(),
+                Amf0TypedValue::Recordset(v) => v.marshall_length(),
+                Amf0TypedValue::XmlDocument(v) => v.marshall_length(),
+                Amf0TypedValue::TypedObject(v) => v.marshall_length(),
+                Amf0TypedValue::Raw(v) => v.marshall_length(),
+            };
         }
+        length
     }
 }
 
-impl Unmarshall for Amf0TypedValue {
-    fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
-        if buf.is_empty() {
-            return Err(AmfError::Custom("Buffer is empty".to_string()));
-        }
-        if buf.len() >= 3 && buf[0] == 0x00 && buf[1] == 0x00 && buf[2] == 0x09 {
-            return Ok((Amf0TypedValue::ObjectEnd(ObjectEndType::default()), 3));
+// 和 marshall_length 一样，容器逐层复制，嵌套关系保存在堆上的栈里
+impl Clone for Amf0TypedValue {
+    fn clone(&self) -> Self {
+        match self {
+            Amf0TypedValue::Object(_)
+            | Amf0TypedValue::EcmaArray(_)
+            | Amf0TypedValue::StrictArray(_) => clone_nested(self),
+            Amf0TypedValue::Number(v) => Amf0TypedValue::Number(v.clone()),
+            Amf0TypedValue::Boolean(v) => Amf0TypedValue::Boolean(v.clone()),
+            Amf0TypedValue::String(v) => Amf0TypedValue::String(v.clone()),
+            Amf0TypedValue::MovieClip(v) => Amf0TypedValue::MovieClip(v.clone()),
+            Amf0TypedValue::Null(v) => Amf0TypedValue::Null(v.clone()),
+            Amf0TypedValue::Undefined(v) => Amf0TypedValue::Undefined(v.clone()),
+            Amf0TypedValue::Reference(v) => Amf0TypedValue::Reference(v.clone()),
+            Amf0TypedValue::ObjectEnd(v) => Amf0TypedValue::ObjectEnd(v.clone()),
+            Amf0TypedValue::Date(v) => Amf0TypedValue::Date(v.clone()),
+            Amf0TypedValue::LongString(v) => Amf0TypedValue::LongString(v.clone()),
+            Amf0TypedValue::Unsupported(v) => Amf0TypedValue::Unsupported(v.clone()),
+            Amf0TypedValue::Recordset(v) => Amf0TypedValue::Recordset(v.clone()),
+            Amf0TypedValue::XmlDocument(v) => Amf0TypedValue::XmlDocument(v.clone()),
+            Amf0TypedValue::TypedObject(v) => Amf0TypedValue::TypedObject(v.clone()),
+            Amf0TypedValue::Raw(v) => Amf0TypedValue::Raw(v.clone()),
         }
+    }
+}
 
-        let type_marker = TypeMarker::try_from(buf[0])?;
-        match type_marker {
-            TypeMarker::Number => {
-                NumberType::unmarshall(buf).map(|v| (Amf0TypedValue::Number(v.0), v.1))
-            }
-            TypeMarker::Boolean => {
-                BooleanType::unmarshall(buf).map(|v| (Amf0TypedValue::Boolean(v.0), v.1))
-            }
-            TypeMarker::String => {
-                StringType::unmarshall(buf).map(|v| (Amf0TypedValue::String(v.0), v.1))
-            }
-            TypeMarker::Object => {
-                ObjectType::unmarshall(buf).map(|v| (Amf0TypedValue::Object(v.0), v.1))
-            }
-            TypeMarker::MovieClip => {
-                MovieClipType::unmarshall(buf).map(|v| (Amf0TypedValue::MovieClip(v.0), v.1))
+fn clone_nested(root: &Amf0TypedValue) -> Amf0TypedValue {
+    // (正在复制的容器, 已经复制好的子值)
+    let mut stack = vec![(root, Vec::new())];
+    loop {
+        let (source, values) = stack.last_mut().unwrap(); // 栈在返回之前不会为空
+        let source: &Amf0TypedValue = source;
+        match source.child(values.len()) {
+            Some(child) if is_container(child) => stack.push((child, Vec::new())),
+            Some(child) => values.push(child.clone()),
+            None => {
+                let (source, values) = stack.pop().unwrap();
+                let value = match source {
+                    Amf0TypedValue::Object(v) => Amf0TypedValue::Object(v.with_values(values)),
+                    Amf0TypedValue::EcmaArray(v) => {
+                        Amf0TypedValue::EcmaArray(v.with_values(values))
+                    }
+                    _ => Amf0TypedValue::StrictArray(StrictArrayType::new(values)),
+                };
+                match stack.last_mut() {
+                    Some((_, parent)) => parent.push(value),
+                    None => return value,
+                }
             }
-            TypeMarker::Null => NullType::unmarshall(buf).map(|v| (Amf0TypedValue::Null(v.0), v.1)),
-            TypeMarker::Undefined => {
-                UndefinedType::unmarshall(buf).map(|v| (Amf0TypedValue::Undefined(v.0), v.1))
+        }
+    }
+}
+
+// 逐对比较，子容器的值对压入堆上的栈
+impl PartialEq for Amf0TypedValue {
+    fn eq(&self, other: &Self) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some(pair) = stack.pop() {
+            let equal = match pair {
+                (Amf0TypedValue::Object(a), Amf0TypedValue::Object(b)) => {
+                    a.shallow_eq(b, &mut stack)
+                }
+                (Amf0TypedValue::EcmaArray(a), Amf0TypedValue::EcmaArray(b)) => {
+                    a.shallow_eq(b, &mut stack)
+                }
+                (Amf0TypedValue::StrictArray(a), Amf0TypedValue::StrictArray(b)) => {
+                    stack.extend(a.iter().zip(b.iter()));
+                    a.len() == b.len()
+                }
+                (Amf0TypedValue::Number(a), Amf0TypedValue::Number(b)) => a == b,
+                (Amf0TypedValue::Boolean(a), Amf0TypedValue::Boolean(b)) => a == b,
+                (Amf0TypedValue::String(a), Amf0TypedValue::String(b)) => a == b,
+                (Amf0TypedValue::MovieClip(a), Amf0TypedValue::MovieClip(b)) => a == b,
+                (Amf0TypedValue::Null(a), Amf0TypedValue::Null(b)) => a == b,
+                (Amf0TypedValue::Undefined(a), Amf0TypedValue::Undefined(b)) => a == b,
+                (Amf0TypedValue::Reference(a), Amf0TypedValue::Reference(b)) => a == b,
+                (Amf0TypedValue::ObjectEnd(a), Amf0TypedValue::ObjectEnd(b)) => a == b,
+                (Amf0TypedValue::Date(a), Amf0TypedValue::Date(b)) => a == b,
+                (Amf0TypedValue::LongString(a), Amf0TypedValue::LongString(b)) => a == b,
+                (Amf0TypedValue::Unsupported(a), Amf0TypedValue::Unsupported(b)) => a == b,
+                (Amf0TypedValue::Recordset(a), Amf0TypedValue::Recordset(b)) => a == b,
+                (Amf0TypedValue::XmlDocument(a), Amf0TypedValue::XmlDocument(b)) => a == b,
+                (Amf0TypedValue::TypedObject(a), Amf0TypedValue::TypedObject(b)) => a == b,
+                (Amf0TypedValue::Raw(a), Amf0TypedValue::Raw(b)) => a == b,
+                _ => false,
+            };
+            if !equal {
+                return false;
             }
-            TypeMarker::Reference => {
-                ReferenceType::unmarshall(buf).map(|v| (Amf0TypedValue::Reference(v.0), v.1))
+        }
+        true
+    }
+}
+
+impl Unmarshall for Amf0TypedValue {
+    // Containers are decoded with an explicit stack instead of recursion, so deeply nested input
+    // cannot overflow the call stack. Nesting is still limited to the default
+    // `DecodeOptions::max_depth`, because `Debug`, serde and the schema functions walk the
+    // decoded value recursively; use `unmarshall_with` to decode deeper values.
+    fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
+        let mut stack: Vec<Container> = Vec::new();
+        let mut offset = 0;
+        loop {
+            // 在 offset 处开始一个值: 标量直接解码，容器只读取头部并压栈
+            let mut value = match start_value(&buf[offset..])? {
+                Start::Value(value, n) => {
+                    offset += n;
+                    Some(value)
+                }
+                Start::Container(container, header) => {
+                    check_limit("max_depth", DEFAULT_MAX_DEPTH, stack.len() + 1)?;
+                    stack.push(container.at(offset));
+                    offset += header;
+                    None
+                }
+            };
+            // 把解码完成的值交给父容器，直到某个容器需要读取下一个子值
+            loop {
+                let Some(top) = stack.last_mut() else {
+                    return Ok((value.unwrap(), offset)); // 栈为空时 value 一定是根值
+                };
+                if let Some(value) = value.take() {
+                    top.push(value);
+                }
+                if top.next_child(buf, &mut offset)? {
+                    break;
+                }
+                value = Some(stack.pop().unwrap().finish()?);
             }
-            TypeMarker::EcmaArray => {
-                EcmaArrayType::unmarshall(buf).map(|v| (Amf0TypedValue::EcmaArray(v.0), v.1))
+        }
+    }
+}
+
+enum Start {
+    Value(Amf0TypedValue, usize),
+    // 容器以及头部(类型标记和个数)的长度
+    Container(Container, usize),
+}
+
+// 正在解码的容器。base 是容器在 buf 中的起始位置，错误中的长度都相对于它，
+// 和递归解码时传给每个容器的子切片一致
+enum Container {
+    StrictArray {
+        base: usize,
+        remaining: usize,
+        values: Vec<Amf0TypedValue>,
+    },
+    Properties {
+        base: usize,
+        // ECMA array 声明的个数，Object 为 None
+        length: Option<u32>,
+        // 读到的属性个数；重复的 key 合并后 properties.len() 会更小
+        count: usize,
+        properties: IndexMap<Utf8, Amf0TypedValue>,
+        key: Option<Utf8>,
+    },
+}
+
+impl Container {
+    fn at(mut self, offset: usize) -> Self {
+        match &mut self {
+            Container::StrictArray { base, .. } | Container::Properties { base, .. } => {
+                *base = offset
             }
-            TypeMarker::ObjectEnd => {
-                panic!("cannot happen")
+        }
+        self
+    }
+
+    fn push(&mut self, value: Amf0TypedValue) {
+        match self {
+            Container::StrictArray { values, .. } => values.push(value),
+            Container::Properties {
+                count,
+                properties,
+                key,
+                ..
+            } => {
+                *count += 1;
+                properties.insert(key.take().unwrap(), value);
             }
-            TypeMarker::StrictArray => {
-                StrictArrayType::unmarshall(buf).map(|v| (Amf0TypedValue::StrictArray(v.0), v.1))
+        }
+    }
+
+    // 还有子值需要读取时返回 true(属性的 key 已经读取)；读到结尾时返回 false
+    fn next_child(&mut self, buf: &[u8], offset: &mut usize) -> Result<bool, AmfError> {
+        match self {
+            Container::StrictArray { remaining, .. } => {
+                if *remaining == 0 {
+                    return Ok(false);
+                }
+                *remaining -= 1;
+                Ok(true)
             }
-            TypeMarker::Date => DateType::unmarshall(buf).map(|v| (Amf0TypedValue::Date(v.0), v.1)),
-            TypeMarker::LongString => {
-                LongStringType::unmarshall(buf).map(|v| (Amf0TypedValue::LongString(v.0), v.1))
+            Container::Properties { base, key, .. } => {
+                let buf = &buf[*base..];
+                let relative = *offset - *base;
+                // 找到了 object end 则结束
+                if buf.len() >= relative + 3 && buf[relative..relative + 3] == [0x00, 0x00, 0x09] {
+                    *offset += 3;
+                    return Ok(false);
+                }
+                // object end 之前 buffer 就结束了，说明数据不完整(而不是数据非法)
+                if buf.len() < relative + 3 {
+                    return Err(AmfError::BufferTooSmall {
+                        want: relative + 3,
+                        got: buf.len(),
+                    });
+                }
+                let (k, k_len) = Utf8::unmarshall(&buf[relative..])?;
+                *offset += k_len;
+                *key = Some(k);
+                Ok(true)
             }
-            TypeMarker::Unsupported => {
-                UnsupportedType::unmarshall(buf).map(|v| (Amf0TypedValue::Unsupported(v.0), v.1))
+        }
+    }
+
+    fn finish(self) -> Result<Amf0TypedValue, AmfError> {
+        match self {
+            Container::StrictArray { values, .. } => {
+                Ok(Amf0TypedValue::StrictArray(StrictArrayType::new(values)))
             }
-            TypeMarker::Recordset => {
-                RecordsetType::unmarshall(buf).map(|v| (Amf0TypedValue::Recordset(v.0), v.1))
+            Container::Properties {
+                length: None,
+                properties,
+                ..
+            } => Ok(Amf0TypedValue::Object(ObjectType::new(properties))),
+            Container::Properties {
+                length: Some(length),
+                count,
+                properties,
+                ..
+            } => {
+                if count != length as usize {
+                    return Err(AmfError::EcmaArrayCountMismatch {
+                        declared: length,
+                        actual: count,
+                    });
+                }
+                Ok(Amf0TypedValue::EcmaArray(EcmaArrayType::new(properties)))
             }
-            TypeMarker::XmlDocument => {
-                XmlDocumentType::unmarshall(buf).map(|v| (Amf0TypedValue::XmlDocument(v.0), v.1))
+        }
+    }
+}
+
+// 解码一个标量值，或者读取容器的头部
+fn start_value(buf: &[u8]) -> Result<Start, AmfError> {
+    if buf.is_empty() {
+        return Err(AmfError::BufferTooSmall { want: 1, got: 0 });
+    }
+    if buf.len() >= 3 && buf[0] == 0x00 && buf[1] == 0x00 && buf[2] == 0x09 {
+        return Ok(Start::Value(
+            Amf0TypedValue::ObjectEnd(ObjectEndType::default()),
+            3,
+        ));
+    }
+
+    let type_marker = TypeMarker::try_from(buf[0])?;
+    let (value, n) = match type_marker {
+        TypeMarker::Object | TypeMarker::EcmaArray => {
+            let lbw = if type_marker == TypeMarker::EcmaArray {
+                4
+            } else {
+                0
+            };
+            let required_size = 1 + lbw + 3; // 1 byte for type marker, LBW bytes(maybe 0) for optional properties length,  3 bytes for object end
+            if buf.len() < required_size {
+                return Err(AmfError::BufferTooSmall {
+                    want: required_size,
+                    got: buf.len(),
+                });
             }
-            TypeMarker::TypedObject => {
-                TypedObjectType::unmarshall(buf).map(|v| (Amf0TypedValue::TypedObject(v.0), v.1))
+            let length = (lbw == 4).then(|| u32::from_be_bytes(buf[1..5].try_into().unwrap()));
+            let capacity = properties_capacity(length.unwrap_or(0), buf.len() - required_size);
+            let container = Container::Properties {
+                base: 0,
+                length,
+                count: 0,
+                properties: IndexMap::with_capacity(capacity),
+                key: None,
+            };
+            return Ok(Start::Container(container, 1 + lbw));
+        }
+        TypeMarker::StrictArray => {
+            if buf.len() < 5 {
+                return Err(AmfError::BufferTooSmall {
+                    want: 5,
+                    got: buf.len(),
+                });
             }
+            let count = u32::from_be_bytes(buf[1..5].try_into().unwrap()) as usize; // 前边已经校验了 buf 的长度
+            let container = Container::StrictArray {
+                base: 0,
+                remaining: count,
+                values: Vec::with_capacity(count.min(MAX_PREALLOCATED_VALUES)),
+            };
+            return Ok(Start::Container(container, 5));
         }
-    }
+        TypeMarker::Number => {
+            NumberType::unmarshall(buf).map(|v| (Amf0TypedValue::Number(v.0), v.1))
+        }
+        TypeMarker::Boolean => {
+            BooleanType::unmarshall(buf).map(|v| (Amf0TypedValue::Boolean(v.0), v.1))
+        }
+        TypeMarker::String => {
+            StringType::unmarshall(buf).map(|v| (Amf0TypedValue::String(v.0), v.1))
+        }
+        TypeMarker::Null => NullType::unmarshall(buf).map(|v| (Amf0TypedValue::Null(v.0), v.1)),
+        TypeMarker::Undefined => {
+            UndefinedType::unmarshall(buf).map(|v| (Amf0TypedValue::Undefined(v.0), v.1))
+        }
+        // 合法的 object end(00 00 09)在上面已经处理了
+        TypeMarker::ObjectEnd => Err(AmfError::InvalidObjectEnd),
+        TypeMarker::LongString => {
+            LongStringType::unmarshall(buf).map(|v| (Amf0TypedValue::LongString(v.0), v.1))
+        }
+        // 这些类型没有实现，和 unmarshall_from 一样报告错误(解码它们的内容需要 DecodeOptions)
+        TypeMarker::MovieClip
+        | TypeMarker::Reference
+        | TypeMarker::Date
+        | TypeMarker::Unsupported
+        | TypeMarker::Recordset
+        | TypeMarker::XmlDocument
+        | TypeMarker::TypedObject => Err(AmfError::UnsupportedTypeMarker(buf[0])),
+    }?;
+    Ok(Start::Value(value, n))
 }
 
 impl TryFrom<&[u8]> for Amf0TypedValue {
@@ -172,80 +467,468 @@ impl TryFrom<Amf0TypedValue> for Vec<u8> {
     }
 }
 
-impl Display for Amf0TypedValue {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Amf0TypedValue::Number(v) => v.fmt(f),
-            Amf0TypedValue::Boolean(v) => v.fmt(f),
-            Amf0TypedValue::String(v) => v.fmt(f),
-            Amf0TypedValue::Object(v) => v.fmt(f),
-            Amf0TypedValue::MovieClip(v) => v.fmt(f),
-            Amf0TypedValue::Null(v) => v.fmt(f),
-            Amf0TypedValue::Undefined(v) => v.fmt(f),
-            Amf0TypedValue::Reference(v) => v.fmt(f),
-            Amf0TypedValue::EcmaArray(v) => v.fmt(f),
-            Amf0TypedValue::ObjectEnd(v) => v.fmt(f),
-            Amf0TypedValue::StrictArray(v) => v.fmt(f),
-            Amf0TypedValue::Date(v) => v.fmt(f),
-            Amf0TypedValue::LongString(v) => v.fmt(f),
-            Amf0TypedValue::Unsupported(v) => v.fmt(f),
-            Amf0TypedValue::Recordset(v) => v.fmt(f),
-            Amf0TypedValue::XmlDocument(v) => v.fmt(f),
-            Amf0TypedValue::TypedObject(v) => v.fmt(f),
-        }
-    }
-}
-
-#[derive(Debug, Clone, PartialEq)]
+// 以下 From 实现方便用户通过 .into() 构造值。字符串按 UTF-8 字节长度自动选择 String 或
+// LongString；超出 AMF 0 表示范围的字符串(4 GB 以上)或属性名(64 KB 以上)会导致 panic。
+
+impl From<f64> for Amf0TypedValue {
+    fn from(value: f64) -> Self {
+        Amf0TypedValue::Number(NumberType::new(value))
+    }
+}
+
+impl From<i32> for Amf0TypedValue {
+    fn from(value: i32) -> Self {
+        Self::from(value as f64)
+    }
+}
+
+impl From<u32> for Amf0TypedValue {
+    fn from(value: u32) -> Self {
+        Self::from(value as f64)
+    }
+}
+
+// 超过 2^53 的整数会丢失精度
+impl From<i64> for Amf0TypedValue {
+    fn from(value: i64) -> Self {
+        Self::from(value as f64)
+    }
+}
+
+impl From<bool> for Amf0TypedValue {
+    fn from(value: bool) -> Self {
+        Amf0TypedValue::Boolean(BooleanType::new(value))
+    }
+}
+
+impl Amf0TypedValue {
+    // Builds a string value without having to know the length cutoff: `StringType` for up to
+    // 65535 UTF-8 bytes, `LongStringType` above that. Only fails beyond the 4 GB LongString
+    // limit.
+    pub fn string(s: impl Into<String>) -> Result<Self, AmfError> {
+        let s = s.into();
+        if s.len() <= u16::MAX as usize {
+            Ok(Amf0TypedValue::String(StringType::new_from_string(s)?))
+        } else {
+            Ok(Amf0TypedValue::LongString(LongStringType::new_from_string(
+                s,
+            )?))
+        }
+    }
+
+    // A value kept as its type marker and the exact bytes that follow it, see `RawValue`. Lets
+    // callers produce wire types that have no variant of their own.
+    pub fn raw(marker: u8, bytes: Vec<u8>) -> Self {
+        Amf0TypedValue::Raw(RawValue::new(marker, bytes))
+    }
+
+    // Moves the value out, leaving Null in its place, like `Option::take`. Lets transformation
+    // code move subtrees out of a decoded value (`value["keyframes"]` through `get_mut`) instead
+    // of cloning them.
+    pub fn take(&mut self) -> Amf0TypedValue {
+        self.replace(Amf0TypedValue::Null(NullType))
+    }
+
+    // Puts `value` in place and returns the previous value, like `Option::replace`.
+    pub fn replace(&mut self, value: Amf0TypedValue) -> Amf0TypedValue {
+        std::mem::replace(self, value)
+    }
+
+    // Decodes a buffer that must hold exactly one value, e.g. a complete AMF payload whose
+    // length is known from the framing. Unlike `unmarshall`, leftover bytes are an error
+    // (`AmfError::TrailingBytes`) rather than silently ignored.
+    pub fn unmarshall_exact(buf: &[u8]) -> Result<Self, AmfError> {
+        let (value, consumed) = Self::unmarshall(buf)?;
+        if consumed != buf.len() {
+            return Err(AmfError::TrailingBytes {
+                consumed,
+                remaining: buf.len() - consumed,
+            });
+        }
+        Ok(value)
+    }
+}
+
+// 超过 LongString 的 4 GB 上限时失败，见 Amf0TypedValue::string
+impl TryFrom<String> for Amf0TypedValue {
+    type Error = AmfError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::string(value)
+    }
+}
+
+impl TryFrom<&str> for Amf0TypedValue {
+    type Error = AmfError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::string(value)
+    }
+}
+
+impl From<Vec<Amf0TypedValue>> for Amf0TypedValue {
+    fn from(value: Vec<Amf0TypedValue>) -> Self {
+        Amf0TypedValue::StrictArray(StrictArrayType::new(value))
+    }
+}
+
+impl From<ObjectType> for Amf0TypedValue {
+    fn from(value: ObjectType) -> Self {
+        Amf0TypedValue::Object(value)
+    }
+}
+
+impl From<EcmaArrayType> for Amf0TypedValue {
+    fn from(value: EcmaArrayType) -> Self {
+        Amf0TypedValue::EcmaArray(value)
+    }
+}
+
+// 所有的 key 都是数组下标(非负整数)时得到 EcmaArray，否则得到 Object。
+// 超过 65535 字节的 key 无法编码，返回 StringTooLong
+impl TryFrom<IndexMap<String, Amf0TypedValue>> for Amf0TypedValue {
+    type Error = AmfError;
+
+    fn try_from(value: IndexMap<String, Amf0TypedValue>) -> Result<Self, Self::Error> {
+        let is_ecma_array = !value.is_empty()
+            && value
+                .keys()
+                .all(|k| k.parse::<u32>().is_ok_and(|i| i.to_string() == *k));
+        let properties = value
+            .into_iter()
+            .map(|(k, v)| Ok((Utf8::new(k)?, v)))
+            .collect::<Result<IndexMap<_, _>, AmfError>>()?;
+        if is_ecma_array {
+            Ok(Amf0TypedValue::EcmaArray(EcmaArrayType::new(properties)))
+        } else {
+            Ok(Amf0TypedValue::Object(ObjectType::new(properties)))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct NestedType<const LBW: usize, const TM: u8> {
-    length: Option<u32>,
     properties: IndexMap<Utf8, Amf0TypedValue>,
+    // 仅在以 DuplicateKeyPolicy::KeepAll 解码时非空: 已经出现过的 key 的后续值，按出现顺序保存
+    duplicates: Vec<(Utf8, Amf0TypedValue)>,
+    // 以 DecodeOptions::allow_truncated(或 unmarshall_lossy)解码且输入在 object end 之前结束
+    truncated: bool,
     object_end: ObjectEndType,
 }
 
 impl<const LBW: usize, const TM: u8> NestedType<LBW, TM> {
     pub fn new(properties: IndexMap<Utf8, Amf0TypedValue>) -> Self {
-        let length = if LBW == 4 {
-            Some(properties.len() as u32)
-        } else {
-            None
-        };
         Self {
-            length,
             properties,
+            duplicates: Vec::new(),
+            truncated: false,
             object_end: ObjectEndType::default(),
         }
     }
+
+    pub(crate) fn with_duplicates(mut self, duplicates: Vec<(Utf8, Amf0TypedValue)>) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    pub(crate) fn with_truncated(mut self, truncated: bool) -> Self {
+        self.truncated = truncated;
+        self
+    }
+
+    // Whether the input ended before this value's object end marker, so the properties may be
+    // incomplete. Only ever set by `unmarshall_with` with `DecodeOptions::allow_truncated` and
+    // by `unmarshall_lossy`; marshalling writes a regular object end either way.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    // Repeated occurrences of keys that are already present, in document order. Only populated
+    // when decoding with `DuplicateKeyPolicy::KeepAll`; they are written back after the regular
+    // properties when marshalled.
+    pub fn duplicates(&self) -> &[(Utf8, Amf0TypedValue)] {
+        &self.duplicates
+    }
+
+    // Every value stored under `key`: the regular property first, then any duplicates.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a Amf0TypedValue> {
+        self.properties.get(key).into_iter().chain(
+            self.duplicates
+                .iter()
+                .filter(move |(k, _)| k.as_ref() == key)
+                .map(|(_, v)| v),
+        )
+    }
+
+    // ECMA array 的长度字段不单独保存，而是在编码时按属性个数(含重复的 key)计算，
+    // 这样无论通过哪种方式修改属性都不会与实际个数不一致
+    fn length(&self) -> Option<u32> {
+        (LBW == 4).then(|| (self.properties.len() + self.duplicates.len()) as u32)
+    }
+
+    // Sets `key` to `value`, returning the previous value. A new key is appended; an existing
+    // one keeps its position. Duplicates kept by `DuplicateKeyPolicy::KeepAll` are untouched.
+    // Fails only for keys longer than 65535 bytes.
+    pub fn insert(
+        &mut self,
+        key: &str,
+        value: impl Into<Amf0TypedValue>,
+    ) -> Result<Option<Amf0TypedValue>, AmfError> {
+        let value = value.into();
+        if let Some(slot) = self.properties.get_mut(key) {
+            return Ok(Some(std::mem::replace(slot, value)));
+        }
+        self.properties.insert(Utf8::new_from_str(key)?, value);
+        Ok(None)
+    }
+
+    // Removes `key` and any duplicates of it, keeping the order of the other properties.
+    pub fn remove(&mut self, key: &str) -> Option<Amf0TypedValue> {
+        self.duplicates.retain(|(k, _)| k.as_ref() != key);
+        self.properties.shift_remove(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Amf0TypedValue> {
+        self.properties.get_mut(key)
+    }
+
+    pub fn iter(&self) -> indexmap::map::Iter<'_, Utf8, Amf0TypedValue> {
+        self.properties.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> indexmap::map::IterMut<'_, Utf8, Amf0TypedValue> {
+        self.properties.iter_mut()
+    }
+
+    // IndexMap's entry for `key`, for updates that should look the key up only once:
+    // `obj.entry("duration")?.or_insert_with(|| 0.0.into())`. Fails only for keys longer than
+    // 65535 bytes.
+    pub fn entry(
+        &mut self,
+        key: &str,
+    ) -> Result<indexmap::map::Entry<'_, Utf8, Amf0TypedValue>, AmfError> {
+        Ok(self.properties.entry(Utf8::new_from_str(key)?))
+    }
+
+    pub fn clear(&mut self) {
+        self.properties.clear();
+        self.duplicates.clear();
+    }
+
+    // Orders the properties by key, e.g. to canonicalize a value before hashing or diffing it.
+    // Keys compare as strings, byte by byte.
+    pub fn sort_keys(&mut self) {
+        self.properties
+            .sort_by(|k1, _, k2, _| k1.as_ref().cmp(k2.as_ref()));
+    }
+
+    pub fn sort_by<F>(&mut self, cmp: F)
+    where
+        F: FnMut(&Utf8, &Amf0TypedValue, &Utf8, &Amf0TypedValue) -> std::cmp::Ordering,
+    {
+        self.properties.sort_by(cmp);
+    }
+
+    // Keeps only the properties (duplicates included) for which `keep` returns true, in order.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&Utf8, &mut Amf0TypedValue) -> bool,
+    {
+        self.properties.retain(&mut keep);
+        self.duplicates.retain_mut(|(k, v)| keep(k, v));
+    }
+
+    // Moves every property of `other` into `self`, leaving `other` empty. Values of keys both
+    // have are overwritten in place; new keys are appended in `other`'s order. Works across
+    // Object and ECMA array, e.g. to layer user overrides onto a decoded onMetaData.
+    pub fn append<const OLBW: usize, const OTM: u8>(&mut self, other: &mut NestedType<OLBW, OTM>) {
+        self.properties.append(&mut other.properties);
+        self.duplicates.append(&mut other.duplicates);
+    }
+
+    // 以下方法消费 self，避免逐个 clone 属性
+
+    pub fn into_inner(mut self) -> IndexMap<Utf8, Amf0TypedValue> {
+        std::mem::take(&mut self.properties)
+    }
+
+    pub fn into_keys(self) -> indexmap::map::IntoKeys<Utf8, Amf0TypedValue> {
+        self.into_inner().into_keys()
+    }
+
+    pub fn into_values(self) -> indexmap::map::IntoValues<Utf8, Amf0TypedValue> {
+        self.into_inner().into_values()
+    }
+}
+
+impl<const LBW: usize, const TM: u8> Drop for NestedType<LBW, TM> {
+    fn drop(&mut self) {
+        if self.properties.values().any(is_container) || !self.duplicates.is_empty() {
+            let properties = std::mem::take(&mut self.properties).into_values();
+            let duplicates = std::mem::take(&mut self.duplicates).into_iter();
+            drop_nested(properties.chain(duplicates.map(|(_, v)| v)));
+        }
+    }
+}
+
+fn is_container(value: &Amf0TypedValue) -> bool {
+    matches!(
+        value,
+        Amf0TypedValue::Object(_) | Amf0TypedValue::EcmaArray(_) | Amf0TypedValue::StrictArray(_)
+    )
+}
+
+// 默认的 drop 会逐层递归，和递归解码一样会被深层嵌套的值(比如 unmarshall 得到的十万层数组)
+// 撑爆调用栈。这里把子容器的内容移到堆上的栈里逐个释放，每个容器被释放时已经没有子容器了
+pub(crate) fn drop_nested(children: impl Iterator<Item = Amf0TypedValue>) {
+    let mut stack: Vec<Amf0TypedValue> = children.filter(is_container).collect();
+    while let Some(mut value) = stack.pop() {
+        match &mut value {
+            Amf0TypedValue::Object(v) => v.take_children(&mut stack),
+            Amf0TypedValue::EcmaArray(v) => v.take_children(&mut stack),
+            Amf0TypedValue::StrictArray(v) => {
+                stack.extend(v.take_values().into_iter().filter(is_container))
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<const LBW: usize, const TM: u8> NestedType<LBW, TM> {
+    fn take_children(&mut self, stack: &mut Vec<Amf0TypedValue>) {
+        let properties = std::mem::take(&mut self.properties).into_values();
+        let duplicates = std::mem::take(&mut self.duplicates).into_iter();
+        stack.extend(
+            properties
+                .chain(duplicates.map(|(_, v)| v))
+                .filter(is_container),
+        );
+    }
+
+    // 属性值(properties 之后是 duplicates)按顺序编号，供 clone_nested 逐个访问
+    fn value_at(&self, index: usize) -> Option<&Amf0TypedValue> {
+        match self.properties.get_index(index) {
+            Some((_, v)) => Some(v),
+            None => self
+                .duplicates
+                .get(index - self.properties.len())
+                .map(|(_, v)| v),
+        }
+    }
+
+    // 复制除属性值以外的部分，values 按 value_at 的顺序给出
+    fn with_values(&self, values: Vec<Amf0TypedValue>) -> Self {
+        let mut values = values.into_iter();
+        let properties = self
+            .properties
+            .keys()
+            .map(|k| (k.clone(), values.next().unwrap()))
+            .collect();
+        let duplicates = self
+            .duplicates
+            .iter()
+            .map(|(k, _)| (k.clone(), values.next().unwrap()))
+            .collect();
+        Self {
+            properties,
+            duplicates,
+            truncated: self.truncated,
+            object_end: self.object_end.clone(),
+        }
+    }
+
+    // 编码后不含属性值的长度(类型标记、ECMA 个数、key 和 object end)，属性值压入 stack
+    fn shallow_length<'a>(&'a self, stack: &mut Vec<&'a Amf0TypedValue>) -> usize {
+        let mut length = 1 + LBW + self.object_end.marshall_length();
+        for (k, v) in self
+            .properties
+            .iter()
+            .chain(self.duplicates.iter().map(|(k, v)| (k, v)))
+        {
+            length += k.marshall_length();
+            stack.push(v);
+        }
+        length
+    }
+
+    // 和 PartialEq 相同的比较，但属性值不在这里比较，而是成对压入 stack
+    fn shallow_eq<'a>(
+        &'a self,
+        other: &'a Self,
+        stack: &mut Vec<(&'a Amf0TypedValue, &'a Amf0TypedValue)>,
+    ) -> bool {
+        if self.properties.len() != other.properties.len()
+            || self.duplicates.len() != other.duplicates.len()
+        {
+            return false;
+        }
+        // 和 IndexMap 的比较一样，不要求顺序相同
+        for (k, v) in &self.properties {
+            match other.properties.get(k) {
+                Some(w) => stack.push((v, w)),
+                None => return false,
+            }
+        }
+        for ((k, v), (l, w)) in self.duplicates.iter().zip(&other.duplicates) {
+            if k != l {
+                return false;
+            }
+            stack.push((v, w));
+        }
+        true
+    }
+}
+
+impl Amf0TypedValue {
+    // 容器的第 index 个子值，见 NestedType::value_at
+    fn child(&self, index: usize) -> Option<&Amf0TypedValue> {
+        match self {
+            Amf0TypedValue::Object(v) => v.value_at(index),
+            Amf0TypedValue::EcmaArray(v) => v.value_at(index),
+            Amf0TypedValue::StrictArray(v) => v.get(index),
+            _ => None,
+        }
+    }
+}
+
+// 是否被截断只是解码时的附加信息，不参与比较
+impl<const LBW: usize, const TM: u8> PartialEq for NestedType<LBW, TM> {
+    fn eq(&self, other: &Self) -> bool {
+        self.properties == other.properties && self.duplicates == other.duplicates
+    }
 }
 
 impl<const LBW: usize, const TM: u8> Marshall for NestedType<LBW, TM> {
     fn marshall(&self) -> Result<Vec<u8>, AmfError> {
         let mut vec = Vec::with_capacity(self.marshall_length());
-        vec.push(TM);
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        buf.push(TM);
 
-        if let Some(length) = self.length {
-            let length_bytes = length.to_be_bytes();
-            vec.extend_from_slice(&length_bytes);
+        if let Some(length) = self.length() {
+            buf.extend_from_slice(&length.to_be_bytes());
         }
+        self.marshall_properties_to(buf)
+    }
+}
 
-        self.properties
+impl<const LBW: usize, const TM: u8> NestedType<LBW, TM> {
+    // Everything after the type marker and ECMA count: the properties (duplicates included)
+    // and the object end. Also used to write the body of a TypedObject.
+    pub(crate) fn marshall_properties_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        // 子节点直接写入同一个 buffer，不再为每个 key 和值分配临时的 Vec
+        for (k, v) in self
+            .properties
             .iter()
-            .try_for_each(|(k, v)| -> io::Result<()> {
-                let k_vec = k
-                    .marshall()
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                vec.extend_from_slice(&k_vec);
-                let v_vec = v
-                    .marshall()
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                vec.extend_from_slice(&v_vec);
-                Ok(())
-            })?;
-
-        let object_end_vec = self.object_end.marshall()?;
-        vec.extend_from_slice(&object_end_vec);
-
-        Ok(vec)
+            .chain(self.duplicates.iter().map(|(k, v)| (k, v)))
+        {
+            k.marshall_to(buf)?;
+            v.marshall_to(buf)?;
+        }
+        self.object_end.marshall_to(buf)
     }
 }
 
@@ -256,6 +939,7 @@ impl<const LBW: usize, const TM: u8> MarshallLength for NestedType<LBW, TM> {
         let properties_bytes_size: usize = self
             .properties
             .iter()
+            .chain(self.duplicates.iter().map(|(k, v)| (k, v)))
             .map(|(k, v)| k.marshall_length() + v.marshall_length())
             .sum();
         size += properties_bytes_size;
@@ -291,48 +975,77 @@ impl<const LBW: usize, const TM: u8> Unmarshall for NestedType<LBW, TM> {
             );
         }
 
-        let mut properties = IndexMap::new();
-        let mut offset = 1 + LBW;
-        while offset < buf.len() {
-            if offset <= buf.len() - 3 {
-                // 找到了 object end 则退出循环
-                if buf[offset] == 0x00 && buf[offset + 1] == 0x00 && buf[offset + 2] == 0x09 {
-                    break;
-                }
-            }
+        let capacity = properties_capacity(length, buf.len() - required_size);
+        let (properties, count, offset) = unmarshall_counted_properties(buf, 1 + LBW, capacity)?;
 
-            let (k, k_len) = Utf8::unmarshall(&buf[offset..])?;
-            offset += k_len;
-            let (v, v_len) = Amf0TypedValue::unmarshall(&buf[offset..])?;
-            offset += v_len;
-            properties.insert(k, v);
+        // 仅在 EcmaArray 情况下(也就是 LBW == 4 的情况下)校验长度
+        if LBW == 4 && count != length as usize {
+            return Err(AmfError::EcmaArrayCountMismatch {
+                declared: length,
+                actual: count,
+            });
         }
 
-        // 校验 object end 存在
-        if buf[buf.len() - 3..] != [0x00, 0x00, 0x09] {
-            return Err(AmfError::Custom(
-                "Invalid object, expected object end, got end of buffer".to_string(),
-            ));
-        }
+        Ok((Self::new(properties), offset))
+    }
+}
 
-        // 仅在 EcmaArray 情况下(也就是 LBW == 4 的情况下)校验长度
-        if LBW == 4 && properties.len() != length as usize {
-            return Err(AmfError::Custom(format!(
-                "Invalid properties length, want {}, got {}",
-                length,
-                properties.len()
-            )));
+// 每个属性至少占 3 个字节: 2 字节的 key 长度 + 1 字节的类型标记(Null/Undefined 没有值)
+const MIN_PROPERTY_BYTES: usize = 3;
+
+// 容器最多预分配这么多个元素/属性(每个元素本身就有上百个字节)。只按剩余 buffer 的长度限制
+// 是不够的: 嵌套的容器每一层都可以声明一个巨大的个数，所需内存会是输入长度的平方
+pub(crate) const MAX_PREALLOCATED_VALUES: usize = 1024;
+
+// ECMA array 的个数来自输入数据，不可信(有的编码器写 0，恶意数据可以写 0xFFFFFFFF)，
+// 只作为预分配的提示，并且不超过剩余的 buffer 最多能容纳的属性个数
+pub(crate) fn properties_capacity(declared: u32, remaining: usize) -> usize {
+    (declared as usize)
+        .min(remaining / MIN_PROPERTY_BYTES)
+        .min(MAX_PREALLOCATED_VALUES)
+}
+
+// 从 offset 开始读取属性直到 object end 标记(含)，返回属性以及 object end 之后的 offset。
+// TypedObject 与 Object 共用同样的属性编码，所以单独抽出来
+pub(crate) fn unmarshall_properties(
+    buf: &[u8],
+    offset: usize,
+    capacity: usize,
+) -> Result<(IndexMap<Utf8, Amf0TypedValue>, usize), AmfError> {
+    unmarshall_counted_properties(buf, offset, capacity)
+        .map(|(properties, _, offset)| (properties, offset))
+}
+
+// 同时返回读到的属性个数(重复的 key 也计入)，用来和 ECMA array 声明的个数比较
+fn unmarshall_counted_properties(
+    buf: &[u8],
+    mut offset: usize,
+    capacity: usize,
+) -> Result<(IndexMap<Utf8, Amf0TypedValue>, usize, usize), AmfError> {
+    let mut properties = IndexMap::with_capacity(capacity);
+    let mut count = 0;
+    loop {
+        // 找到了 object end 则退出循环
+        if buf.len() >= offset + 3 && buf[offset..offset + 3] == [0x00, 0x00, 0x09] {
+            offset += 3;
+            break;
+        }
+        // object end 之前 buffer 就结束了，说明数据不完整(而不是数据非法)
+        if buf.len() < offset + 3 {
+            return Err(AmfError::BufferTooSmall {
+                want: offset + 3,
+                got: buf.len(),
+            });
         }
 
-        let read_size = if offset == buf.len() {
-            offset
-        } else if offset == buf.len() - 3 {
-            offset + 3
-        } else {
-            buf.len()
-        };
-        Ok((Self::new(properties), read_size))
+        let (k, k_len) = Utf8::unmarshall(&buf[offset..])?;
+        offset += k_len;
+        let (v, v_len) = Amf0TypedValue::unmarshall(&buf[offset..])?;
+        offset += v_len;
+        count += 1;
+        properties.insert(k, v);
     }
+    Ok((properties, count, offset))
 }
 
 impl<const LBW: usize, const TM: u8> TryFrom<&[u8]> for NestedType<LBW, TM> {
@@ -373,6 +1086,12 @@ where
     }
 }
 
+impl<const LBW: usize, const TM: u8> From<NestedType<LBW, TM>> for IndexMap<Utf8, Amf0TypedValue> {
+    fn from(value: NestedType<LBW, TM>) -> Self {
+        value.into_inner()
+    }
+}
+
 impl<const LBW: usize, const TM: u8> AsRef<IndexMap<Utf8, Amf0TypedValue>> for NestedType<LBW, TM> {
     fn as_ref(&self) -> &IndexMap<Utf8, Amf0TypedValue> {
         &self.properties
@@ -387,6 +1106,13 @@ impl<const LBW: usize, const TM: u8> Deref for NestedType<LBW, TM> {
     }
 }
 
+// 通过 DerefMut 直接修改属性是安全的: ECMA array 的长度字段在编码时才计算
+impl<const LBW: usize, const TM: u8> DerefMut for NestedType<LBW, TM> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.properties
+    }
+}
+
 impl<const LBW: usize, const TM: u8> Borrow<IndexMap<Utf8, Amf0TypedValue>>
     for NestedType<LBW, TM>
 {
@@ -395,21 +1121,10 @@ impl<const LBW: usize, const TM: u8> Borrow<IndexMap<Utf8, Amf0TypedValue>>
     }
 }
 
+// 与 Amf0TypedValue 一样输出合法的 JSON(只包含常规属性)
 impl<const LBW: usize, const TM: u8> Display for NestedType<LBW, TM> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{{")?; // 写入开头的 "{"
-        // 使用 peeking iterator 来优雅地处理逗号
-        let mut iter = self.properties.iter().peekable();
-        while let Some((key, value)) = iter.next() {
-            // 写入 "key": value
-            // 注意 key 和 value 会自动使用它们自己的 Display 实现
-            write!(f, "\"{}\":{}", key, value)?;
-            // 如果这不是最后一个元素，就写入一个逗号和空格
-            if iter.peek().is_some() {
-                write!(f, ",")?;
-            }
-        }
-        write!(f, "}}") // 写入结尾的 "}"
+        write_json_object(f, self.properties.iter())
     }
 }
 
@@ -433,12 +1148,44 @@ where
     }
 }
 
+// 和 IndexMap::extend 一样，已有的 key 保持位置，值被覆盖
+impl<K, V, const LBW: usize, const TM: u8> Extend<(K, V)> for NestedType<LBW, TM>
+where
+    K: Into<Utf8>,
+    V: Into<Amf0TypedValue>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.properties
+            .extend(iter.into_iter().map(|(k, v)| (k.into(), v.into())));
+    }
+}
+
 impl<const LBW: usize, const TM: u8> IntoIterator for NestedType<LBW, TM> {
     type Item = (Utf8, Amf0TypedValue);
     type IntoIter = indexmap::map::IntoIter<Utf8, Amf0TypedValue>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.properties.into_iter()
+        self.into_inner().into_iter()
+    }
+}
+
+// 与 std 的 map 一致: `for (k, v) in &obj` 和 `for (k, v) in &mut obj` 不消耗对象。
+// 和 get 一样只遍历常规属性，不包括 duplicates()
+impl<'a, const LBW: usize, const TM: u8> IntoIterator for &'a NestedType<LBW, TM> {
+    type Item = (&'a Utf8, &'a Amf0TypedValue);
+    type IntoIter = indexmap::map::Iter<'a, Utf8, Amf0TypedValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, const LBW: usize, const TM: u8> IntoIterator for &'a mut NestedType<LBW, TM> {
+    type Item = (&'a Utf8, &'a mut Amf0TypedValue);
+    type IntoIter = indexmap::map::IterMut<'a, Utf8, Amf0TypedValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
     }
 }
 
@@ -456,9 +1203,39 @@ pub type ObjectType = NestedType<0, { TypeMarker::Object as u8 }>;
 // For the purposes of serialization this type is very similar to ananonymous Obiect.
 pub type EcmaArrayType = NestedType<4, { TypeMarker::EcmaArray as u8 }>;
 
+// Object 与 EcmaArray 的属性编码完全一样，只是类型标记以及 EcmaArray 额外的长度字段不同，
+// 所以可以无损地互相转换(属性直接移动，长度重新计算)
+
+impl ObjectType {
+    pub fn into_ecma_array(mut self) -> EcmaArrayType {
+        let duplicates = std::mem::take(&mut self.duplicates);
+        EcmaArrayType::new(self.into_inner()).with_duplicates(duplicates)
+    }
+}
+
+impl EcmaArrayType {
+    pub fn into_object(mut self) -> ObjectType {
+        let duplicates = std::mem::take(&mut self.duplicates);
+        ObjectType::new(self.into_inner()).with_duplicates(duplicates)
+    }
+}
+
+impl From<ObjectType> for EcmaArrayType {
+    fn from(value: ObjectType) -> Self {
+        value.into_ecma_array()
+    }
+}
+
+impl From<EcmaArrayType> for ObjectType {
+    fn from(value: EcmaArrayType) -> Self {
+        value.into_object()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::amf0;
     use indexmap::IndexMap;
 
     // Helper function to create a sample IndexMap for NestedType tests
@@ -593,6 +1370,112 @@ mod tests {
         assert_eq!(original, unmarshalled);
     }
 
+    // 声明 2 个属性，key "a" 出现两次：个数按线上的属性计算，而不是合并后的个数
+    #[test]
+    fn test_ecma_array_count_includes_duplicate_keys() {
+        let buf = [
+            0x08, 0x00, 0x00, 0x00, 0x02, // ECMA array, 2 个属性
+            0x00, 0x01, b'a', 0x05, // "a": null
+            0x00, 0x01, b'a', 0x06, // "a": undefined
+            0x00, 0x00, 0x09,
+        ];
+        let (value, n) = EcmaArrayType::unmarshall(&buf).unwrap();
+        assert_eq!(n, buf.len());
+        assert_eq!(value.len(), 1);
+        let (value, n) = Amf0TypedValue::unmarshall(&buf).unwrap();
+        assert_eq!(n, buf.len());
+        assert!(matches!(value, Amf0TypedValue::EcmaArray(ref a) if a.len() == 1));
+
+        // 合并后的个数不再被接受
+        let mut declared_one = buf;
+        declared_one[4] = 0x01;
+        assert!(matches!(
+            EcmaArrayType::unmarshall(&declared_one),
+            Err(AmfError::EcmaArrayCountMismatch {
+                declared: 1,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            Amf0TypedValue::unmarshall(&declared_one),
+            Err(AmfError::EcmaArrayCountMismatch {
+                declared: 1,
+                actual: 2
+            })
+        ));
+    }
+
+    // 二十万层 strict array、Object 和 ECMA array 交替嵌套，最内层是 null
+    fn deep_value() -> (Amf0TypedValue, Vec<u8>) {
+        let depth = 200_000;
+        let mut data = Vec::new();
+        for i in 0..depth {
+            match i % 3 {
+                0 => data.extend_from_slice(&[0x0a, 0x00, 0x00, 0x00, 0x01]),
+                1 => data.extend_from_slice(&[0x03, 0x00, 0x01, b'a']),
+                _ => data.extend_from_slice(&[0x08, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, b'b']),
+            }
+        }
+        data.push(0x05);
+        for i in (0..depth).rev() {
+            if i % 3 != 0 {
+                data.extend_from_slice(&[0x00, 0x00, 0x09]);
+            }
+        }
+        let (value, n) =
+            Amf0TypedValue::unmarshall_with(&data, &amf0::DecodeOptions::unlimited()).unwrap();
+        assert_eq!(n, data.len());
+        (value, data)
+    }
+
+    #[test]
+    fn test_deep_nesting_does_not_overflow_stack() {
+        let (value, data) = deep_value();
+        assert_eq!(value.marshall_length(), data.len());
+        assert_eq!(value.marshall().unwrap(), data);
+        assert_eq!(
+            value
+                .marshall_with(&amf0::EncodeOptions::default())
+                .unwrap(),
+            data
+        );
+
+        let cloned = value.clone();
+        assert!(cloned == value);
+        let (other, _) = deep_value();
+        assert!(other == value);
+
+        // 最内层不同
+        let mut changed = data.clone();
+        let null_offset = changed.iter().rposition(|&b| b == 0x05).unwrap();
+        changed[null_offset] = 0x06;
+        let (changed, _) =
+            Amf0TypedValue::unmarshall_with(&changed, &amf0::DecodeOptions::unlimited()).unwrap();
+        assert!(changed != value);
+    }
+
+    #[test]
+    fn test_nested_huge_counts_are_not_preallocated() {
+        // 每一层都声明了 0xFFFFFFFF 个元素，后面跟着 1 MB 的 null。按剩余 buffer 的长度预分配的话，
+        // 每一层都要分配上百 MB
+        let mut data = Vec::new();
+        for i in 0..60 {
+            if i % 2 == 0 {
+                data.extend_from_slice(&[0x0a, 0xff, 0xff, 0xff, 0xff]);
+            } else {
+                data.extend_from_slice(&[0x08, 0xff, 0xff, 0xff, 0xff, 0x00, 0x01, b'a']);
+            }
+        }
+        data.resize(data.len() + 1024 * 1024, 0x05);
+
+        let err = Amf0TypedValue::unmarshall(&data).unwrap_err();
+        assert!(matches!(err, AmfError::BufferTooSmall { .. }), "{:?}", err);
+        let err = StrictArrayType::unmarshall(&data).unwrap_err();
+        assert!(matches!(err, AmfError::BufferTooSmall { .. }), "{:?}", err);
+        let err = amf0::borrowed::Amf0ValueRef::unmarshall_borrowed(&data).unwrap_err();
+        assert!(matches!(err, AmfError::BufferTooSmall { .. }), "{:?}", err);
+    }
+
     #[test]
     fn test_nested_type_clone() {
         let original = ObjectType::new(sample_properties());
@@ -630,4 +1513,308 @@ mod tests {
         let result = ObjectType::unmarshall(&buf);
         assert!(matches!(result, Err(AmfError::BufferTooSmall { .. })));
     }
+
+    #[test]
+    fn test_nested_type_truncated_is_buffer_too_small() {
+        let marshalled = ObjectType::new(sample_properties()).marshall().unwrap();
+        for end in 1..marshalled.len() {
+            let result = ObjectType::unmarshall(&marshalled[..end]);
+            assert!(
+                matches!(result, Err(AmfError::BufferTooSmall { .. })),
+                "truncated at {}: {:?}",
+                end,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_unmarshall_exact() {
+        let object = Amf0TypedValue::Object(ObjectType::new(sample_properties()));
+        let mut buf = object.marshall().unwrap();
+        assert_eq!(Amf0TypedValue::unmarshall_exact(&buf).unwrap(), object);
+
+        let object_len = buf.len();
+        buf.extend_from_slice(&NumberType::new(1.0).marshall().unwrap());
+        let result = Amf0TypedValue::unmarshall_exact(&buf);
+        assert!(
+            matches!(
+                result,
+                Err(AmfError::TrailingBytes { consumed, remaining: 9 }) if consumed == object_len
+            ),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_nested_type_followed_by_other_values() {
+        let object = ObjectType::new(sample_properties());
+        let mut buf = object.marshall().unwrap();
+        let object_len = buf.len();
+        buf.extend_from_slice(&NumberType::new(1.0).marshall().unwrap());
+        buf.extend_from_slice(&object.marshall().unwrap());
+
+        let (first, n) = ObjectType::unmarshall(&buf).unwrap();
+        assert_eq!(first, object);
+        assert_eq!(n, object_len);
+        let (number, m) = Amf0TypedValue::unmarshall(&buf[n..]).unwrap();
+        assert_eq!(number, Amf0TypedValue::Number(NumberType::new(1.0)));
+        let (second, k) = Amf0TypedValue::unmarshall(&buf[n + m..]).unwrap();
+        assert_eq!(second, Amf0TypedValue::Object(object));
+        assert_eq!(n + m + k, buf.len());
+    }
+
+    #[test]
+    fn test_from_primitives() {
+        assert_eq!(
+            Amf0TypedValue::from(1.5),
+            Amf0TypedValue::Number(NumberType::new(1.5))
+        );
+        assert_eq!(
+            Amf0TypedValue::from(-3i32),
+            Amf0TypedValue::Number(NumberType::new(-3.0))
+        );
+        assert_eq!(
+            Amf0TypedValue::from(7u32),
+            Amf0TypedValue::Number(NumberType::new(7.0))
+        );
+        assert_eq!(
+            Amf0TypedValue::from(1i64 << 40),
+            Amf0TypedValue::Number(NumberType::new((1i64 << 40) as f64))
+        );
+        assert_eq!(
+            Amf0TypedValue::from(true),
+            Amf0TypedValue::Boolean(BooleanType::new(true))
+        );
+    }
+
+    #[test]
+    fn test_from_strings() {
+        assert_eq!(
+            Amf0TypedValue::try_from("abc").unwrap(),
+            Amf0TypedValue::String(StringType::new_from_str("abc").unwrap())
+        );
+        let value: Amf0TypedValue = "a".repeat(u16::MAX as usize).try_into().unwrap();
+        assert!(matches!(value, Amf0TypedValue::String(_)));
+        let value: Amf0TypedValue = "a".repeat(u16::MAX as usize + 1).try_into().unwrap();
+        assert!(matches!(value, Amf0TypedValue::LongString(_)));
+    }
+
+    #[test]
+    fn test_from_vec_and_map() {
+        let value: Amf0TypedValue = vec![1.0.into(), amf0!("x")].into();
+        assert_eq!(
+            value,
+            Amf0TypedValue::StrictArray(StrictArrayType::new(vec![
+                Amf0TypedValue::Number(NumberType::new(1.0)),
+                Amf0TypedValue::String(StringType::new_from_str("x").unwrap()),
+            ]))
+        );
+
+        let mut map = IndexMap::new();
+        map.insert("duration".to_string(), 12.5.into());
+        assert!(matches!(
+            Amf0TypedValue::try_from(map),
+            Ok(Amf0TypedValue::Object(ref v)) if v.len() == 1
+        ));
+
+        let mut map = IndexMap::new();
+        map.insert("0".to_string(), amf0!("a"));
+        map.insert("1".to_string(), amf0!("b"));
+        assert!(matches!(
+            Amf0TypedValue::try_from(map.clone()),
+            Ok(Amf0TypedValue::EcmaArray(ref v)) if v.len() == 2
+        ));
+
+        // "01" 不是规范的数组下标
+        map.insert("01".to_string(), amf0!("c"));
+        assert!(matches!(
+            Amf0TypedValue::try_from(map),
+            Ok(Amf0TypedValue::Object(_))
+        ));
+        assert!(matches!(
+            Amf0TypedValue::try_from(IndexMap::new()),
+            Ok(Amf0TypedValue::Object(_))
+        ));
+
+        // 超长的 key 无法编码，返回错误而不是 panic
+        let mut map = IndexMap::new();
+        map.insert("k".repeat(u16::MAX as usize + 1), amf0!(null));
+        assert!(matches!(
+            Amf0TypedValue::try_from(map),
+            Err(AmfError::StringTooLong { .. })
+        ));
+    }
+
+    #[test]
+    fn test_take_and_replace() {
+        let mut value = amf0!({"keyframes": {"times": [0.0, 2.0]}, "duration": 2.0});
+        let keyframes = value.as_object_mut().unwrap().get_mut("keyframes").unwrap();
+        let taken = keyframes.take();
+        assert_eq!(taken, amf0!({"times": [0.0, 2.0]}));
+        assert!(keyframes.is_null());
+        assert_eq!(keyframes.replace(amf0!("moved")), amf0!(null));
+        assert_eq!(value, amf0!({"keyframes": "moved", "duration": 2.0}));
+    }
+
+    #[test]
+    fn test_owned_extraction() {
+        let object = ObjectType::new(sample_properties());
+        assert_eq!(object.clone().into_inner(), sample_properties());
+        assert_eq!(
+            IndexMap::from(EcmaArrayType::new(sample_properties())),
+            sample_properties()
+        );
+
+        let keys: Vec<Utf8> = object.clone().into_keys().collect();
+        assert_eq!(
+            keys,
+            vec![
+                Utf8::new_from_str("key1").unwrap(),
+                Utf8::new_from_str("key2").unwrap()
+            ]
+        );
+        let values: Vec<Amf0TypedValue> = object.into_values().collect();
+        assert_eq!(
+            values,
+            sample_properties().into_values().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_object_ecma_array_conversion() {
+        let object = ObjectType::new(sample_properties());
+        let array = object.clone().into_ecma_array();
+        assert_eq!(array.length(), Some(2));
+        assert_eq!(*array, sample_properties());
+
+        let marshalled = array.marshall().unwrap();
+        assert_eq!(marshalled[0], TypeMarker::EcmaArray as u8);
+        assert_eq!(&marshalled[1..5], &[0, 0, 0, 2]);
+        assert_eq!(&marshalled[5..], &object.marshall().unwrap()[1..]);
+
+        let back: ObjectType = array.into();
+        assert_eq!(back, object);
+        assert_eq!(back.length(), None);
+        assert_eq!(EcmaArrayType::from(back.clone()).into_object(), back);
+    }
+
+    #[test]
+    fn test_mutation_keeps_ecma_length() {
+        let mut array = ObjectType::new(sample_properties()).into_ecma_array();
+        let first = array.keys().next().unwrap().to_string();
+        assert_eq!(array.insert("extra", 1.0).unwrap(), None);
+        assert_eq!(
+            array.insert("extra", 2.0).unwrap(),
+            Some(Amf0TypedValue::from(1.0))
+        );
+        assert!(array.insert(&"k".repeat(70000), 0.0).is_err());
+        *array.get_mut("extra").unwrap() = amf0!("x");
+        assert!(array.remove(&first).is_some());
+        assert!(array.remove("missing").is_none());
+        let last = array.len() - 1;
+        array.move_index(0, last); // 通过 DerefMut 调用 IndexMap 的方法
+
+        let data = array.marshall().unwrap();
+        assert_eq!(&data[1..5], &(array.len() as u32).to_be_bytes());
+        assert_eq!(EcmaArrayType::unmarshall(&data).unwrap().0, array);
+
+        array.clear();
+        assert_eq!(&array.marshall().unwrap()[1..], &[0, 0, 0, 0, 0, 0, 9]);
+    }
+
+    #[test]
+    fn test_extend_and_append() {
+        let mut metadata = EcmaArrayType::new(sample_properties());
+        let mut overrides = ObjectType::default();
+        overrides.insert("encoder", amf0!("obs")).unwrap();
+        let first = metadata.keys().next().unwrap().to_string();
+        overrides
+            .insert(&first, Amf0TypedValue::Null(NullType))
+            .unwrap();
+
+        metadata.append(&mut overrides);
+        assert!(overrides.is_empty());
+        assert_eq!(metadata.len(), 3);
+        assert!(metadata[0].is_null()); // 覆盖的 key 保持原来的位置
+        assert_eq!(metadata["encoder"], amf0!("obs"));
+
+        metadata.extend([(Utf8::new_from_str("width").unwrap(), 640.0)]);
+        assert_eq!(&metadata.marshall().unwrap()[1..5], &[0, 0, 0, 4]);
+    }
+
+    #[test]
+    fn test_sort_and_retain() {
+        let mut array: EcmaArrayType = crate::amf0!({"b": 2.0, "c": null, "a": 1.0})
+            .as_object()
+            .unwrap()
+            .clone()
+            .into_ecma_array();
+        array.sort_keys();
+        assert_eq!(
+            array.keys().map(|k| k.as_ref()).collect::<Vec<_>>(),
+            ["a", "b", "c"]
+        );
+        array.sort_by(|_, v1, _, v2| v1.is_null().cmp(&v2.is_null()).reverse());
+        assert_eq!(array.keys().next().unwrap().as_ref(), "c");
+
+        array.retain(|_, v| !v.is_null());
+        assert_eq!(array.len(), 2);
+        assert_eq!(&array.marshall().unwrap()[1..5], &[0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn test_borrowed_iteration() {
+        let mut object = ObjectType::new(sample_properties());
+        for (_, v) in &mut object {
+            *v = Amf0TypedValue::Null(NullType);
+        }
+        let mut count = 0;
+        for (k, v) in &object {
+            assert!(sample_properties().contains_key(k));
+            assert!(v.is_null());
+            count += 1;
+        }
+        assert_eq!(count, object.len());
+
+        let array = StrictArrayType::new(vec![1.0.into(), 2.0.into()]);
+        let sum: f64 = (&array).into_iter().filter_map(|v| v.as_number()).sum();
+        assert_eq!(sum, 3.0);
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut array = EcmaArrayType::default();
+        *array
+            .entry("duration")
+            .unwrap()
+            .or_insert_with(|| 0.0.into()) = 12.5.into();
+        array.entry("duration").unwrap().or_insert(1.0.into());
+        array
+            .entry("width")
+            .unwrap()
+            .and_modify(|v| *v = 0.0.into())
+            .or_insert(640.0.into());
+        assert_eq!(array["duration"], Amf0TypedValue::from(12.5));
+        assert_eq!(array["width"], Amf0TypedValue::from(640.0));
+        assert_eq!(&array.marshall().unwrap()[1..5], &[0, 0, 0, 2]);
+        assert!(array.entry(&"k".repeat(70000)).is_err());
+    }
+    #[test]
+    fn test_marshall_to_appends_in_place() {
+        let value = crate::amf0!({
+            "a": [1.0, "b", {"c": null}],
+            "d": {"e": true, "f": (Amf0TypedValue::LongString(LongStringType::new_from_str("g").unwrap()))},
+        });
+        let encoded = value.marshall().unwrap();
+        // 整棵树只分配一次: 容量正好等于编码长度
+        assert_eq!(encoded.len(), value.marshall_length());
+        assert_eq!(encoded.capacity(), encoded.len());
+
+        let mut buf = vec![0xaa];
+        value.marshall_to(&mut buf).unwrap();
+        assert_eq!(buf[0], 0xaa);
+        assert_eq!(&buf[1..], encoded.as_slice());
+    }
 }
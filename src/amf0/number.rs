@@ -1,7 +1,7 @@
 use crate::amf0::type_marker::TypeMarker;
 use crate::errors::AmfError;
 use crate::traits::{Marshall, MarshallLength, Unmarshall};
-use std::fmt::{Display, Formatter};
+use std::fmt::{Display, Formatter, Write};
 use std::ops::{Add, Deref};
 
 // An AMF 0 Number type is used to encode an ActionScript Number.
@@ -12,6 +12,32 @@ pub struct NumberType {
     value: f64,
 }
 
+// 2^53 - 1，f64 能精确表示的最大整数(即 JavaScript 的 Number.MAX_SAFE_INTEGER)
+pub const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+// How `NumberType::format` and `Amf0TypedValue::to_json_string_with` render a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberFormat {
+    // Rust's float formatting, the same as `Display`: `inf`, `-inf`, `NaN`, `-0`, and very
+    // large or small values written out in full (`1e21` becomes 22 digits).
+    #[default]
+    Rust,
+    // ECMAScript `Number.prototype.toString`, i.e. what `JSON.stringify` and flvmeta's JSON
+    // output write: `-0` becomes `0` and values at or above 1e21 or below 1e-6 use exponent form
+    // (`1e+21`, `1.5e-7`). Non-finite values are written as selected.
+    Ecma(NonFinite),
+}
+
+// What `NumberFormat::Ecma` writes for NaN and ±Infinity, which JSON cannot express.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFinite {
+    // `null`, as `JSON.stringify` does
+    #[default]
+    Null,
+    // The JSON strings `"NaN"`, `"Infinity"` and `"-Infinity"`
+    String,
+}
+
 impl NumberType {
     pub fn new(value: f64) -> Self {
         Self {
@@ -19,15 +45,46 @@ impl NumberType {
             value,
         }
     }
+
+    // Returns the value as an integer if it is integral and within ±(2^53 - 1), i.e. if it
+    // round-trips through f64 without loss. FLV `filesize` or RTMP transaction IDs are integers
+    // carried as Numbers.
+    pub fn as_i64_exact(&self) -> Option<i64> {
+        if self.value.fract() == 0.0 && self.value.abs() <= MAX_SAFE_INTEGER as f64 {
+            Some(self.value as i64)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_u32_exact(&self) -> Option<u32> {
+        self.as_i64_exact().and_then(|v| u32::try_from(v).ok())
+    }
+
+    // Renders the value in the given format; `Display` is `NumberFormat::Rust`.
+    pub fn format(&self, format: NumberFormat) -> String {
+        let mut out = String::new();
+        match format {
+            NumberFormat::Rust => write!(out, "{}", self.value),
+            NumberFormat::Ecma(non_finite) => write_ecma_number(&mut out, self.value, non_finite),
+        }
+        .unwrap(); // 写入 String 不会失败
+        out
+    }
 }
 
 impl Marshall for NumberType {
     fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
         debug_assert!(self.type_marker == TypeMarker::Number);
-        let mut buf = [0u8; 9];
-        buf[0] = self.type_marker as u8;
-        buf[1..9].copy_from_slice(&self.value.to_be_bytes());
-        Ok(buf.to_vec())
+        buf.push(self.type_marker as u8);
+        buf.extend_from_slice(&self.value.to_be_bytes());
+        Ok(())
     }
 }
 
@@ -89,6 +146,41 @@ impl From<f64> for NumberType {
     }
 }
 
+impl From<i32> for NumberType {
+    fn from(value: i32) -> Self {
+        Self::new(value as f64)
+    }
+}
+
+impl From<u32> for NumberType {
+    fn from(value: u32) -> Self {
+        Self::new(value as f64)
+    }
+}
+
+// 超出 ±(2^53 - 1) 的整数无法用 f64 精确表示，返回错误而不是悄悄丢失精度
+impl TryFrom<i64> for NumberType {
+    type Error = AmfError;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        if value.unsigned_abs() > MAX_SAFE_INTEGER as u64 {
+            return Err(AmfError::InexactInteger(value.into()));
+        }
+        Ok(Self::new(value as f64))
+    }
+}
+
+impl TryFrom<u64> for NumberType {
+    type Error = AmfError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        if value > MAX_SAFE_INTEGER as u64 {
+            return Err(AmfError::InexactInteger(value.into()));
+        }
+        Ok(Self::new(value as f64))
+    }
+}
+
 impl From<NumberType> for f64 {
     fn from(value: NumberType) -> Self {
         value.value
@@ -115,6 +207,58 @@ impl Display for NumberType {
     }
 }
 
+// ECMAScript Number::toString(x) 的算法: 先取最短的往返十进制数字，再按指数大小选择写法
+pub(crate) fn write_ecma_number<W: Write>(
+    out: &mut W,
+    n: f64,
+    non_finite: NonFinite,
+) -> std::fmt::Result {
+    if !n.is_finite() {
+        let name = if n.is_nan() {
+            "NaN"
+        } else if n > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        };
+        return match non_finite {
+            NonFinite::Null => out.write_str("null"),
+            NonFinite::String => write!(out, "\"{}\"", name),
+        };
+    }
+    if n == 0.0 {
+        return out.write_char('0'); // 包括 -0
+    }
+    if n < 0.0 {
+        out.write_char('-')?;
+    }
+    // {:e} 输出最短的往返表示，比如 "1.5e-7"、"1e21"
+    let sci = format!("{:e}", n.abs());
+    let (mantissa, exponent) = sci.split_once('e').unwrap();
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    // 小数点相对于第一个数字的位置
+    let point = exponent.parse::<i32>().unwrap() + 1;
+    if k <= point && point <= 21 {
+        out.write_str(&digits)?;
+        (k..point).try_for_each(|_| out.write_char('0'))
+    } else if 0 < point && point <= 21 {
+        let (int, frac) = digits.split_at(point as usize);
+        write!(out, "{}.{}", int, frac)
+    } else if -6 < point && point <= 0 {
+        out.write_str("0.")?;
+        (point..0).try_for_each(|_| out.write_char('0'))?;
+        out.write_str(&digits)
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.write_str(first)?;
+        if !rest.is_empty() {
+            write!(out, ".{}", rest)?;
+        }
+        write!(out, "e{:+}", point - 1)
+    }
+}
+
 impl Default for NumberType {
     fn default() -> Self {
         Self::new(0.0)
@@ -133,6 +277,7 @@ impl Add for NumberType {
 mod tests {
     use super::*;
     use crate::amf0::type_marker::TypeMarker;
+    use crate::errors::ErrorKind;
     use std::f64::{EPSILON, INFINITY, NAN, NEG_INFINITY};
 
     #[test]
@@ -142,6 +287,38 @@ mod tests {
         assert!((num.value - 3.14).abs() < EPSILON);
     }
 
+    #[test]
+    fn test_format_ecma() {
+        let ecma = |n: f64| NumberType::new(n).format(NumberFormat::Ecma(NonFinite::Null));
+        let cases = [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (1280.0, "1280"),
+            (-0.25, "-0.25"),
+            (29.97002997002997, "29.97002997002997"),
+            (1e21, "1e+21"),
+            (1.5e300, "1.5e+300"),
+            (123456789012345680000.0, "123456789012345680000"),
+            (0.000001, "0.000001"),
+            (1.5e-7, "1.5e-7"),
+            (-5e-324, "-5e-324"),
+        ];
+        for (n, expected) in cases {
+            assert_eq!(ecma(n), expected, "{:e}", n);
+        }
+        assert_eq!(ecma(f64::NAN), "null");
+        let strings = NumberFormat::Ecma(NonFinite::String);
+        assert_eq!(
+            NumberType::new(f64::NEG_INFINITY).format(strings),
+            "\"-Infinity\""
+        );
+        assert_eq!(NumberType::new(f64::NAN).format(NumberFormat::Rust), "NaN");
+        assert_eq!(
+            NumberType::new(1e21).format(NumberFormat::Rust),
+            "1000000000000000000000"
+        );
+    }
+
     #[test]
     fn test_default() {
         let num = NumberType::default();
@@ -331,4 +508,42 @@ mod tests {
         let num = NumberType::new(NAN);
         assert_eq!(format!("{}", num), "NaN");
     }
+
+    #[test]
+    fn test_from_integers() {
+        assert_eq!(*NumberType::from(-7i32), -7.0);
+        assert_eq!(*NumberType::from(u32::MAX), u32::MAX as f64);
+        assert_eq!(
+            *NumberType::try_from(MAX_SAFE_INTEGER).unwrap(),
+            9_007_199_254_740_991.0
+        );
+        assert_eq!(
+            *NumberType::try_from(-MAX_SAFE_INTEGER).unwrap(),
+            -9_007_199_254_740_991.0
+        );
+        assert!(matches!(
+            NumberType::try_from(MAX_SAFE_INTEGER + 1),
+            Err(AmfError::InexactInteger(9_007_199_254_740_992))
+        ));
+        assert!(NumberType::try_from(i64::MIN).is_err());
+        assert!(NumberType::try_from(MAX_SAFE_INTEGER as u64).is_ok());
+        assert_eq!(
+            NumberType::try_from(u64::MAX).unwrap_err().kind(),
+            ErrorKind::OutOfRange
+        );
+    }
+
+    #[test]
+    fn test_exact_getters() {
+        assert_eq!(NumberType::new(1024.0).as_i64_exact(), Some(1024));
+        assert_eq!(NumberType::new(-3.0).as_i64_exact(), Some(-3));
+        assert_eq!(NumberType::new(1.5).as_i64_exact(), None);
+        assert_eq!(NumberType::new(f64::NAN).as_i64_exact(), None);
+        assert_eq!(NumberType::new(f64::INFINITY).as_i64_exact(), None);
+        assert_eq!(NumberType::new(2f64.powi(53)).as_i64_exact(), None);
+
+        assert_eq!(NumberType::new(4294967295.0).as_u32_exact(), Some(u32::MAX));
+        assert_eq!(NumberType::new(4294967296.0).as_u32_exact(), None);
+        assert_eq!(NumberType::new(-1.0).as_u32_exact(), None);
+    }
 }
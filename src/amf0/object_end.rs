@@ -25,12 +25,17 @@ impl ObjectEndType {
 
 impl Marshall for ObjectEndType {
     fn marshall(&self) -> Result<Vec<u8>, AmfError> {
-        debug_assert!(self.type_marker == TypeMarker::ObjectEnd);
         let mut vec = Vec::with_capacity(self.marshall_length());
-        vec.extend_from_slice(&self.empty.marshall()?);
-        vec.push(self.type_marker as u8);
+        self.marshall_to(&mut vec)?;
         Ok(vec)
     }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        debug_assert!(self.type_marker == TypeMarker::ObjectEnd);
+        self.empty.marshall_to(buf)?;
+        buf.push(self.type_marker as u8);
+        Ok(())
+    }
 }
 
 impl MarshallLength for ObjectEndType {
@@ -47,6 +52,9 @@ impl Unmarshall for ObjectEndType {
                 got: buf.len(),
             });
         }
+        if buf[0..2] != [0x00, 0x00] {
+            return Err(AmfError::InvalidObjectEnd);
+        }
         let (empty, _) = Utf8::unmarshall(&buf[0..2])?;
         let type_marker = TypeMarker::try_from(buf[2])?;
         if type_marker != TypeMarker::ObjectEnd {
@@ -161,6 +169,13 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_unmarshall_non_empty_key() {
+        let data = [0x00, 0x01, 0x09];
+        let result = ObjectEndType::unmarshall(&data);
+        assert!(matches!(result, Err(AmfError::InvalidObjectEnd)));
+    }
+
     #[test]
     fn test_try_from_slice() {
         let data = [0x00, 0x00, 0x09];
@@ -0,0 +1,156 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+// A pool of reusable output buffers for encoding, so that a server writing thousands of small
+// command messages per second does not allocate (and free) a `Vec` for each of them. Clones
+// share the same buffers and the pool can be used from any thread. Check a buffer out with
+// `get` (or let `Amf0TypedValue::marshall_pooled` do it), write into it with `marshall_to`, and
+// it returns to the pool when dropped.
+#[derive(Debug, Clone)]
+pub struct BufferPool {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    max_buffers: usize,
+    max_capacity: usize,
+}
+
+impl BufferPool {
+    // Keeps at most `max_buffers` idle buffers. Buffers that grew beyond `max_capacity` bytes
+    // (after encoding an unusually large value) are freed instead of returned, so one big
+    // message does not pin its memory forever.
+    pub fn new(max_buffers: usize, max_capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                buffers: Mutex::new(Vec::with_capacity(max_buffers)),
+                max_buffers,
+                max_capacity,
+            }),
+        }
+    }
+
+    // An empty buffer, reusing an idle one if there is any.
+    pub fn get(&self) -> PooledBuffer {
+        let buf = self.lock().pop().unwrap_or_default();
+        PooledBuffer {
+            buf,
+            pool: self.clone(),
+        }
+    }
+
+    // The number of idle buffers in the pool.
+    pub fn available(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn put(&self, mut buf: Vec<u8>) {
+        if buf.capacity() == 0 || buf.capacity() > self.inner.max_capacity {
+            return;
+        }
+        buf.clear();
+        let mut buffers = self.lock();
+        if buffers.len() < self.inner.max_buffers {
+            buffers.push(buf);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        // 池中只有空闲的 buffer，持有锁的线程 panic 也不会留下不一致的状态
+        self.inner
+            .buffers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// 64 idle buffers of up to 64 KiB, enough for RTMP command and metadata messages.
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new(64, 64 * 1024)
+    }
+}
+
+// A buffer checked out of a `BufferPool`, dereferencing to `Vec<u8>`. It goes back to the pool
+// when dropped; `into_vec` keeps it instead.
+#[derive(Debug)]
+pub struct PooledBuffer {
+    buf: Vec<u8>,
+    pool: BufferPool,
+}
+
+impl PooledBuffer {
+    pub fn into_vec(mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buf
+    }
+}
+
+impl AsRef<[u8]> for PooledBuffer {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        self.pool.put(std::mem::take(&mut self.buf));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::traits::Marshall;
+
+    #[test]
+    fn test_buffers_are_reused() {
+        let pool = BufferPool::new(2, 1024);
+        let value = amf0!({"code": "NetStream.Play.Start", "level": "status"});
+        let encoded = value.marshall_pooled(&pool).unwrap();
+        assert_eq!(*encoded, value.marshall().unwrap());
+        let ptr = encoded.as_ptr();
+        drop(encoded);
+        assert_eq!(pool.available(), 1);
+
+        // 取回的是同一块已清空的内存
+        let buf = pool.get();
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_ptr(), ptr);
+        assert_eq!(pool.available(), 0);
+        let kept = buf.into_vec();
+        assert!(kept.capacity() > 0);
+        assert_eq!(pool.available(), 0);
+    }
+
+    #[test]
+    fn test_limits() {
+        let pool = BufferPool::new(1, 16);
+        let mut big = pool.get();
+        big.extend_from_slice(&[0; 17]);
+        drop(big);
+        assert_eq!(pool.available(), 0); // 超过 max_capacity 的 buffer 被释放
+
+        let (mut a, mut b) = (pool.get(), pool.get());
+        a.push(1);
+        b.push(2);
+        drop((a, b));
+        assert_eq!(pool.available(), 1);
+    }
+}
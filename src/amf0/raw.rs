@@ -0,0 +1,61 @@
+use crate::errors::AmfError;
+use crate::traits::{Marshall, MarshallLength};
+use std::fmt::{Display, Formatter};
+
+// A value this crate does not decode, kept as the type marker followed by the exact bytes that
+// came after it. Only produced by `Amf0TypedValue::unmarshall_with` when
+// `DecodeOptions::raw_unsupported` is set, so that a proxy can forward Date, Reference,
+// XmlDocument, TypedObject and unknown markers without understanding them. Encoding writes the
+// marker and the bytes back verbatim; nothing checks that they form a valid AMF 0 value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawValue {
+    pub marker: u8,
+    pub bytes: Vec<u8>,
+}
+
+impl RawValue {
+    pub fn new(marker: u8, bytes: Vec<u8>) -> Self {
+        Self { marker, bytes }
+    }
+}
+
+impl Marshall for RawValue {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        buf.push(self.marker);
+        buf.extend_from_slice(&self.bytes);
+        Ok(())
+    }
+}
+
+impl MarshallLength for RawValue {
+    fn marshall_length(&self) -> usize {
+        1 + self.bytes.len()
+    }
+}
+
+impl Display for RawValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<raw 0x{:02x}, {} bytes>", self.marker, self.bytes.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marshall_verbatim() {
+        let raw = RawValue::new(0x0b, vec![0x42, 0x77, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let encoded = raw.marshall().unwrap();
+        assert_eq!(encoded.len(), raw.marshall_length());
+        assert_eq!(encoded[0], 0x0b);
+        assert_eq!(&encoded[1..], raw.bytes.as_slice());
+        assert_eq!(raw.to_string(), "<raw 0x0b, 10 bytes>");
+    }
+}
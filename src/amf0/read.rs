@@ -0,0 +1,532 @@
+use crate::amf0::boolean::BooleanType;
+use crate::amf0::decode::{DecodeOptions, check_limit};
+use crate::amf0::marker::{NullType, UndefinedType};
+use crate::amf0::nested::{Amf0TypedValue, MAX_PREALLOCATED_VALUES, NestedType};
+use crate::amf0::number::NumberType;
+use crate::amf0::object_end::ObjectEndType;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::string::{LongStringType, StringType};
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::AmfUtf8;
+use crate::errors::AmfError;
+use indexmap::IndexMap;
+use std::io::{self, BufRead, Read};
+
+// 对于长度前缀，最多预分配这么多字节，剩下的随着实际读到的数据增长，
+// 避免一个伪造的长度前缀(比如 4 GB 的 LongString)导致一次性分配大量内存
+const MAX_PREALLOCATION: usize = 64 * 1024;
+
+impl Amf0TypedValue {
+    // Decodes one value straight from a `BufRead`, pulling bytes with `fill_buf`/`consume` as
+    // needed. Unlike `unmarshall`, the whole payload does not have to be in memory up front, so
+    // values larger than the reader's internal buffer (e.g. a multi-megabyte LongString) can be
+    // decoded. Accepts and rejects the same input as `unmarshall`, only truncated input fails
+    // with an `UnexpectedEof` I/O error. Returns the value and the number of bytes consumed from
+    // the reader. Sizes are not limited, nesting depth is limited to that of
+    // `DecodeOptions::default()`; use `unmarshall_from_with` for untrusted input.
+    pub fn unmarshall_from<R: BufRead>(reader: &mut R) -> Result<(Self, usize), AmfError> {
+        let options = DecodeOptions {
+            max_depth: DecodeOptions::default().max_depth,
+            ..DecodeOptions::unlimited()
+        };
+        Self::unmarshall_from_with(reader, &options)
+    }
+
+    // `unmarshall_from` enforcing the limits of `options` (`max_depth`, `max_string_bytes`,
+    // `max_properties` and `max_total_bytes`) before anything is read or allocated for the
+    // offending value. The other options are not applied. Containers are read recursively, so
+    // `max_depth` has to stay within what the call stack can hold; the default does.
+    pub fn unmarshall_from_with<R: BufRead>(
+        reader: &mut R,
+        options: &DecodeOptions,
+    ) -> Result<(Self, usize), AmfError> {
+        let mut stream = StreamReader {
+            reader,
+            options,
+            consumed: 0,
+            depth: 0,
+        };
+        let value = stream.read_value()?;
+        Ok((value, stream.consumed))
+    }
+
+    // Decodes one value from input split across several buffers, e.g. the chunks of an RTMP
+    // message body, without concatenating them first: `unmarshall_chunks(&[header, body])` or
+    // `unmarshall_chunks(rope.iter())`. Values may straddle any number of chunk boundaries;
+    // empty chunks are skipped. Returns the value and the number of bytes it occupies; later
+    // bytes are ignored, as with `unmarshall`. Use `ChunkReader` with `unmarshall_from` to read
+    // several values in a row.
+    pub fn unmarshall_chunks<I>(chunks: I) -> Result<(Self, usize), AmfError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        Self::unmarshall_from(&mut ChunkReader::new(chunks))
+    }
+}
+
+// A `BufRead` over a sequence of byte chunks (a rope), yielding them one after another without
+// copying. `fill_buf` returns what is left of the current chunk.
+pub struct ChunkReader<I: Iterator> {
+    chunks: I,
+    current: Option<I::Item>,
+    // 当前 chunk 中已经读取的字节数
+    position: usize,
+}
+
+impl<I> ChunkReader<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    pub fn new<C: IntoIterator<IntoIter = I>>(chunks: C) -> Self {
+        Self {
+            chunks: chunks.into_iter(),
+            current: None,
+            position: 0,
+        }
+    }
+}
+
+impl<I> BufRead for ChunkReader<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self
+            .current
+            .as_ref()
+            .is_none_or(|chunk| self.position >= chunk.as_ref().len())
+        {
+            match self.chunks.next() {
+                Some(chunk) => {
+                    self.current = Some(chunk);
+                    self.position = 0;
+                }
+                None => return Ok(&[]),
+            }
+        }
+        Ok(&self.current.as_ref().unwrap().as_ref()[self.position..]) // 循环保证了 current 有值
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt;
+    }
+}
+
+impl<I> Read for ChunkReader<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+struct StreamReader<'r, 'o, R> {
+    reader: &'r mut R,
+    options: &'o DecodeOptions,
+    consumed: usize,
+    depth: usize,
+}
+
+impl<R: BufRead> StreamReader<'_, '_, R> {
+    // 在读取之前检查总长度，超出限制的数据不会被读取
+    fn reserve(&self, n: usize) -> Result<(), AmfError> {
+        let end = self.consumed.saturating_add(n);
+        check_limit("max_total_bytes", self.options.max_total_bytes, end)
+    }
+
+    fn read_into(&mut self, out: &mut [u8]) -> Result<(), AmfError> {
+        self.reserve(out.len())?;
+        let mut filled = 0;
+        while filled < out.len() {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Err(unexpected_eof(out.len() - filled));
+            }
+            let n = available.len().min(out.len() - filled);
+            out[filled..filled + n].copy_from_slice(&available[..n]);
+            self.reader.consume(n);
+            self.consumed += n;
+            filled += n;
+        }
+        Ok(())
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], AmfError> {
+        let mut bytes = [0u8; N];
+        self.read_into(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, AmfError> {
+        self.read_array::<1>().map(|b| b[0])
+    }
+
+    fn read_utf8<const LBW: usize>(&mut self) -> Result<AmfUtf8<LBW>, AmfError> {
+        let length = if LBW == 2 {
+            u16::from_be_bytes(self.read_array()?) as usize
+        } else {
+            u32::from_be_bytes(self.read_array()?) as usize
+        };
+        check_limit("max_string_bytes", self.options.max_string_bytes, length)?;
+        self.reserve(length)?;
+
+        let mut bytes = Vec::with_capacity(length.min(MAX_PREALLOCATION));
+        while bytes.len() < length {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Err(unexpected_eof(length - bytes.len()));
+            }
+            let n = available.len().min(length - bytes.len());
+            bytes.extend_from_slice(&available[..n]);
+            self.reader.consume(n);
+            self.consumed += n;
+        }
+        let value = String::from_utf8(bytes).map_err(|e| AmfError::InvalidUtf8(e.utf8_error()))?;
+        AmfUtf8::new(value)
+    }
+
+    fn read_value(&mut self) -> Result<Amf0TypedValue, AmfError> {
+        let marker = self.read_u8()?;
+        self.read_value_with_marker(marker)
+    }
+
+    // 递归解码，嵌套深度受 max_depth 限制
+    fn read_value_with_marker(&mut self, marker: u8) -> Result<Amf0TypedValue, AmfError> {
+        let marker = TypeMarker::try_from(marker)?;
+        let container = matches!(
+            marker,
+            TypeMarker::Object | TypeMarker::EcmaArray | TypeMarker::StrictArray
+        );
+        if container {
+            self.depth += 1;
+            check_limit("max_depth", self.options.max_depth, self.depth)?;
+        }
+        let value = self.read_marked(marker);
+        if container {
+            self.depth -= 1;
+        }
+        value
+    }
+
+    fn read_marked(&mut self, marker: TypeMarker) -> Result<Amf0TypedValue, AmfError> {
+        match marker {
+            TypeMarker::Number => {
+                // 和 unmarshall 一样，00 00 09 解码为 ObjectEnd 而不是 Number
+                let mut bytes = [0u8; 8];
+                self.read_into(&mut bytes[..2])?;
+                if bytes[..2] == [0x00, TypeMarker::ObjectEnd as u8] {
+                    return Ok(Amf0TypedValue::ObjectEnd(ObjectEndType::default()));
+                }
+                self.read_into(&mut bytes[2..])?;
+                Ok(Amf0TypedValue::Number(NumberType::new(f64::from_be_bytes(
+                    bytes,
+                ))))
+            }
+            TypeMarker::Boolean => {
+                let value = self.read_u8()? != 0;
+                Ok(Amf0TypedValue::Boolean(BooleanType::new(value)))
+            }
+            TypeMarker::String => {
+                let inner = self.read_utf8::<2>()?;
+                Ok(Amf0TypedValue::String(StringType::new(inner)))
+            }
+            TypeMarker::LongString => {
+                let inner = self.read_utf8::<4>()?;
+                Ok(Amf0TypedValue::LongString(LongStringType::new(inner)))
+            }
+            TypeMarker::Null => Ok(Amf0TypedValue::Null(NullType)),
+            TypeMarker::Undefined => Ok(Amf0TypedValue::Undefined(UndefinedType)),
+            TypeMarker::Object => self
+                .read_nested::<0, { TypeMarker::Object as u8 }>()
+                .map(Amf0TypedValue::Object),
+            TypeMarker::EcmaArray => self
+                .read_nested::<4, { TypeMarker::EcmaArray as u8 }>()
+                .map(Amf0TypedValue::EcmaArray),
+            TypeMarker::StrictArray => {
+                let count = u32::from_be_bytes(self.read_array()?) as usize;
+                check_limit("max_properties", self.options.max_properties, count)?;
+                // 元素个数来自网络数据，不能直接用来预分配
+                let mut values = Vec::with_capacity(count.min(MAX_PREALLOCATED_VALUES));
+                for _ in 0..count {
+                    values.push(self.read_value()?);
+                }
+                Ok(Amf0TypedValue::StrictArray(StrictArrayType::new(values)))
+            }
+            TypeMarker::ObjectEnd => Err(AmfError::InvalidObjectEnd),
+            _ => Err(AmfError::UnsupportedTypeMarker(marker as u8)),
+        }
+    }
+
+    fn read_nested<const LBW: usize, const TM: u8>(
+        &mut self,
+    ) -> Result<NestedType<LBW, TM>, AmfError> {
+        let mut length = 0u32;
+        if LBW == 4 {
+            length = u32::from_be_bytes(self.read_array()?);
+        }
+
+        let mut properties = IndexMap::new();
+        // 读到的属性个数，重复的 key 也计入
+        let mut count = 0;
+        loop {
+            let key = self.read_utf8::<2>()?;
+            let marker = self.read_u8()?;
+            // 空 key 后紧跟 object end 标记，说明属性读取完毕
+            if key.is_empty() && marker == TypeMarker::ObjectEnd as u8 {
+                break;
+            }
+            count += 1;
+            check_limit("max_properties", self.options.max_properties, count)?;
+            let value = self.read_value_with_marker(marker)?;
+            properties.insert(key, value);
+        }
+
+        // 仅在 EcmaArray 情况下(也就是 LBW == 4 的情况下)校验长度
+        if LBW == 4 && count != length as usize {
+            return Err(AmfError::EcmaArrayCountMismatch {
+                declared: length,
+                actual: count,
+            });
+        }
+        Ok(NestedType::new(properties))
+    }
+}
+
+fn unexpected_eof(missing: usize) -> AmfError {
+    AmfError::Io(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!(
+            "EOF while decoding AMF 0 value, {} more bytes expected",
+            missing
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::amf0::nested::{EcmaArrayType, ObjectType};
+    use crate::amf0::utf8::Utf8;
+    use crate::traits::{Marshall, MarshallLength, Unmarshall};
+    use std::io::{BufReader, Cursor};
+
+    fn sample_values() -> Vec<Amf0TypedValue> {
+        let mut props = IndexMap::new();
+        props.insert(
+            Utf8::new_from_str("duration").unwrap(),
+            Amf0TypedValue::Number(NumberType::new(12.5)),
+        );
+        props.insert(
+            Utf8::new_from_str("stereo").unwrap(),
+            Amf0TypedValue::Boolean(BooleanType::new(true)),
+        );
+        props.insert(
+            Utf8::new_from_str("").unwrap(),
+            Amf0TypedValue::Null(NullType),
+        );
+        props.insert(
+            Utf8::new_from_str("nested").unwrap(),
+            Amf0TypedValue::Object(ObjectType::new(props.clone())),
+        );
+        vec![
+            Amf0TypedValue::String(StringType::new_from_str("onMetaData").unwrap()),
+            Amf0TypedValue::StrictArray(StrictArrayType::new(vec![
+                Amf0TypedValue::Number(NumberType::new(0.0)),
+                Amf0TypedValue::Number(NumberType::new(2.0)),
+            ])),
+            Amf0TypedValue::EcmaArray(EcmaArrayType::new(props)),
+            Amf0TypedValue::Undefined(UndefinedType),
+        ]
+    }
+
+    #[test]
+    fn test_unmarshall_chunks() {
+        let values = sample_values();
+        let data: Vec<u8> = values.iter().flat_map(|v| v.marshall().unwrap()).collect();
+        let first_len = values[0].marshall_length();
+
+        // 在每一个位置切开，值都可以跨越 chunk 的边界
+        for split in 0..=data.len() {
+            let (a, b) = data.split_at(split);
+            let (value, n) = Amf0TypedValue::unmarshall_chunks([a, &[], b]).unwrap();
+            assert_eq!((value, n), (values[0].clone(), first_len));
+        }
+
+        // 每个字节一个 chunk，连续读出所有的值
+        let bytes: Vec<Vec<u8>> = data.iter().map(|b| vec![*b]).collect();
+        let mut reader = ChunkReader::new(&bytes);
+        for expected in &values {
+            let (value, _) = Amf0TypedValue::unmarshall_from(&mut reader).unwrap();
+            assert_eq!(&value, expected);
+        }
+        assert!(reader.fill_buf().unwrap().is_empty());
+        assert!(Amf0TypedValue::unmarshall_chunks(data[..5].chunks(2)).is_err());
+    }
+
+    #[test]
+    fn test_unmarshall_from_matches_unmarshall() {
+        let values = sample_values();
+        let data: Vec<u8> = values.iter().flat_map(|v| v.marshall().unwrap()).collect();
+
+        // 用一个很小的内部缓冲区，确保每个值都跨越多次 fill_buf
+        let mut reader = BufReader::with_capacity(3, Cursor::new(data.clone()));
+        let mut offset = 0;
+        for expected in &values {
+            let (value, n) = Amf0TypedValue::unmarshall_from(&mut reader).unwrap();
+            let (sliced, m) = Amf0TypedValue::unmarshall(&data[offset..]).unwrap();
+            assert_eq!(&value, expected);
+            assert_eq!(value, sliced);
+            assert_eq!(n, m);
+            offset += n;
+        }
+        assert_eq!(offset, data.len());
+    }
+
+    #[test]
+    fn test_unmarshall_from_accepts_the_same_markers() {
+        // 每一个标记后面跟着同样的内容，两种解码方式的结果(包括错误)必须一致
+        let mut inputs: Vec<Vec<u8>> = (0..=0x12)
+            .map(|marker| [&[marker, 0x00, 0x01, b'a'][..], &[0x00, 0x00, 0x09]].concat())
+            .collect();
+        inputs.push(vec![0x00, 0x00, 0x09]); // 根值位置的 object end
+        inputs.push(vec![0x0a, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x09]);
+        for input in inputs {
+            let sliced = Amf0TypedValue::unmarshall(&input);
+            let streamed = Amf0TypedValue::unmarshall_from(&mut input.as_slice());
+            match (sliced, streamed) {
+                (Ok(a), Ok(b)) => assert_eq!(a, b, "{:02x?}", input),
+                // 数据不完整时的错误类型不同(BufferTooSmall 和 UnexpectedEof)，只比较 kind
+                (Err(a), Err(b)) => assert_eq!(a.kind(), b.kind(), "{:02x?}", input),
+                (a, b) => panic!("{:02x?}: {:?} != {:?}", input, a, b),
+            }
+        }
+    }
+
+    #[test]
+    fn test_deep_nesting_is_limited() {
+        // 二十万层嵌套的 strict array
+        let mut data = [0x0a, 0x00, 0x00, 0x00, 0x01].repeat(200_000);
+        data.push(0x05);
+        let is_max_depth = |e: AmfError| {
+            matches!(
+                e,
+                AmfError::LimitExceeded {
+                    limit: "max_depth",
+                    ..
+                }
+            )
+        };
+        assert!(is_max_depth(
+            Amf0TypedValue::unmarshall_from(&mut data.as_slice()).unwrap_err()
+        ));
+        assert!(is_max_depth(
+            Amf0TypedValue::unmarshall_chunks(data.chunks(7)).unwrap_err()
+        ));
+    }
+
+    #[test]
+    fn test_ecma_array_count_includes_duplicate_keys() {
+        let buf = [
+            0x08, 0x00, 0x00, 0x00, 0x02, // ECMA array, 2 个属性
+            0x00, 0x01, b'a', 0x05, // "a": null
+            0x00, 0x01, b'a', 0x06, // "a": undefined
+            0x00, 0x00, 0x09,
+        ];
+        let (value, n) = Amf0TypedValue::unmarshall_from(&mut buf.as_slice()).unwrap();
+        assert_eq!(n, buf.len());
+        assert!(matches!(value, Amf0TypedValue::EcmaArray(ref a) if a.len() == 1));
+    }
+
+    #[test]
+    fn test_unmarshall_from_with_limits() {
+        let data = amf0!({"a": [[1.0, 2.0]], "b": "text"}).marshall().unwrap();
+        let decode = |options: &DecodeOptions| {
+            Amf0TypedValue::unmarshall_from_with(&mut data.as_slice(), options)
+        };
+        assert!(decode(&DecodeOptions::default()).is_ok());
+        for (limit, options) in [
+            (
+                "max_depth",
+                DecodeOptions {
+                    max_depth: 2,
+                    ..Default::default()
+                },
+            ),
+            (
+                "max_properties",
+                DecodeOptions {
+                    max_properties: 1,
+                    ..Default::default()
+                },
+            ),
+            (
+                "max_string_bytes",
+                DecodeOptions {
+                    max_string_bytes: 3,
+                    ..Default::default()
+                },
+            ),
+            (
+                "max_total_bytes",
+                DecodeOptions {
+                    max_total_bytes: data.len() - 1,
+                    ..Default::default()
+                },
+            ),
+        ] {
+            assert!(
+                matches!(decode(&options), Err(AmfError::LimitExceeded { limit: l, .. }) if l == limit),
+                "{}",
+                limit
+            );
+        }
+    }
+
+    #[test]
+    fn test_unmarshall_from_large_long_string() {
+        let long = "amf".repeat(1024 * 1024);
+        let value = Amf0TypedValue::LongString(LongStringType::new_from_string(long).unwrap());
+        let data = value.marshall().unwrap();
+
+        let mut reader = BufReader::with_capacity(8 * 1024, Cursor::new(data.as_slice()));
+        let (decoded, n) = Amf0TypedValue::unmarshall_from(&mut reader).unwrap();
+        assert_eq!(decoded, value);
+        assert_eq!(n, data.len());
+    }
+
+    #[test]
+    fn test_unmarshall_from_truncated() {
+        let value = Amf0TypedValue::LongString(LongStringType::new_from_str("hello").unwrap());
+        let data = value.marshall().unwrap();
+        let mut reader = Cursor::new(&data[..data.len() - 1]);
+        let result = Amf0TypedValue::unmarshall_from(&mut reader);
+        assert!(
+            matches!(result, Err(AmfError::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn test_unmarshall_from_huge_length_prefix() {
+        // 长度前缀声称有 4 GB，但实际只有几个字节，不应该因为预分配而耗尽内存
+        let data = [TypeMarker::LongString as u8, 0xff, 0xff, 0xff, 0xff, b'a'];
+        let result = Amf0TypedValue::unmarshall_from(&mut Cursor::new(&data[..]));
+        assert!(matches!(result, Err(AmfError::Io(_))));
+    }
+
+    #[test]
+    fn test_unmarshall_from_invalid_utf8() {
+        let data = [TypeMarker::String as u8, 0x00, 0x02, 0xc3, 0x28];
+        let result = Amf0TypedValue::unmarshall_from(&mut Cursor::new(&data[..]));
+        assert!(matches!(result, Err(AmfError::InvalidUtf8(_))));
+    }
+}
@@ -0,0 +1,542 @@
+use crate::amf0::boolean::BooleanType;
+use crate::amf0::marker::NullType;
+use crate::amf0::nested::{Amf0TypedValue, EcmaArrayType, ObjectType};
+use crate::amf0::number::NumberType;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use crate::traits::Marshall;
+use indexmap::IndexMap;
+use serde::ser::{self, Impossible, Serialize};
+
+// Serializes any `T: Serialize` into AMF 0 bytes. Structs become anonymous Objects, maps become
+// ECMA arrays and sequences/tuples become strict arrays. See `to_value` for the full mapping.
+pub fn to_vec<T: ?Sized + Serialize>(value: &T) -> Result<Vec<u8>, AmfError> {
+    to_value(value)?.marshall()
+}
+
+// Serializes any `T: Serialize` into an `Amf0TypedValue` tree:
+//
+//	bool                           -> Boolean
+//	integers / floats              -> Number (integers beyond 2^53 lose precision)
+//	char / str                     -> String, or LongString above 65535 bytes
+//	bytes                          -> StrictArray of Numbers
+//	None / () / unit struct        -> Null
+//	Some(v) / newtype struct       -> v
+//	unit variant                   -> String (the variant name)
+//	newtype/tuple/struct variant   -> Object { variant: value }
+//	seq / tuple / tuple struct     -> StrictArray
+//	map                            -> EcmaArray (keys must be strings, chars or integers)
+//	struct                         -> Object
+pub fn to_value<T: ?Sized + Serialize>(value: &T) -> Result<Amf0TypedValue, AmfError> {
+    value.serialize(ValueSerializer)
+}
+
+fn variant_object(variant: &str, value: Amf0TypedValue) -> Result<Amf0TypedValue, AmfError> {
+    let mut properties = IndexMap::with_capacity(1);
+    properties.insert(Utf8::new_from_str(variant)?, value);
+    Ok(Amf0TypedValue::Object(ObjectType::new(properties)))
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Amf0TypedValue;
+    type Error = AmfError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(Amf0TypedValue::Boolean(BooleanType::new(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(Amf0TypedValue::Number(NumberType::new(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        Amf0TypedValue::string(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Amf0TypedValue::string(v.to_string())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(Amf0TypedValue::StrictArray(
+            v.iter()
+                .map(|b| Amf0TypedValue::Number(NumberType::new(*b as f64)))
+                .collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Amf0TypedValue::Null(NullType))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        variant_object(variant, value.serialize(self)?)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SeqSerializer {
+            variant: None,
+            values: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(SeqSerializer {
+            variant: Some(variant),
+            values: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(MapSerializer {
+            kind: MapKind::EcmaArray,
+            properties: IndexMap::with_capacity(len.unwrap_or(0)),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(MapSerializer {
+            kind: MapKind::Object,
+            properties: IndexMap::with_capacity(len),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(MapSerializer {
+            kind: MapKind::Variant(variant),
+            properties: IndexMap::with_capacity(len),
+            next_key: None,
+        })
+    }
+}
+
+struct SeqSerializer {
+    variant: Option<&'static str>,
+    values: Vec<Amf0TypedValue>,
+}
+
+impl SeqSerializer {
+    fn finish(self) -> Result<Amf0TypedValue, AmfError> {
+        let array = Amf0TypedValue::StrictArray(StrictArrayType::new(self.values));
+        match self.variant {
+            Some(variant) => variant_object(variant, array),
+            None => Ok(array),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Amf0TypedValue;
+    type Error = AmfError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), AmfError> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Amf0TypedValue, AmfError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Amf0TypedValue;
+    type Error = AmfError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), AmfError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Amf0TypedValue, AmfError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Amf0TypedValue;
+    type Error = AmfError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), AmfError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Amf0TypedValue, AmfError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Amf0TypedValue;
+    type Error = AmfError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), AmfError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Amf0TypedValue, AmfError> {
+        self.finish()
+    }
+}
+
+enum MapKind {
+    Object,
+    EcmaArray,
+    Variant(&'static str),
+}
+
+struct MapSerializer {
+    kind: MapKind,
+    properties: IndexMap<Utf8, Amf0TypedValue>,
+    next_key: Option<Utf8>,
+}
+
+impl MapSerializer {
+    fn finish(self) -> Result<Amf0TypedValue, AmfError> {
+        match self.kind {
+            MapKind::Object => Ok(Amf0TypedValue::Object(ObjectType::new(self.properties))),
+            MapKind::EcmaArray => Ok(Amf0TypedValue::EcmaArray(EcmaArrayType::new(
+                self.properties,
+            ))),
+            MapKind::Variant(variant) => variant_object(
+                variant,
+                Amf0TypedValue::Object(ObjectType::new(self.properties)),
+            ),
+        }
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Amf0TypedValue;
+    type Error = AmfError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), AmfError> {
+        self.next_key = Some(Utf8::new(key.serialize(KeySerializer)?)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), AmfError> {
+        let key = self.next_key.take().ok_or_else(|| {
+            AmfError::Custom("serialize_value called before serialize_key".to_string())
+        })?;
+        self.properties
+            .insert(key, value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Amf0TypedValue, AmfError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Amf0TypedValue;
+    type Error = AmfError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), AmfError> {
+        self.properties
+            .insert(Utf8::new_from_str(key)?, value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Amf0TypedValue, AmfError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = Amf0TypedValue;
+    type Error = AmfError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), AmfError> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Amf0TypedValue, AmfError> {
+        self.finish()
+    }
+}
+
+// AMF 0 的属性名只能是字符串，这里额外允许 char 和整数(转换为十进制字符串)
+struct KeySerializer;
+
+fn key_must_be_string() -> AmfError {
+    AmfError::Custom("AMF 0 property keys must be strings".to_string())
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = AmfError;
+    type SerializeSeq = Impossible<String, AmfError>;
+    type SerializeTuple = Impossible<String, AmfError>;
+    type SerializeTupleStruct = Impossible<String, AmfError>;
+    type SerializeTupleVariant = Impossible<String, AmfError>;
+    type SerializeMap = Impossible<String, AmfError>;
+    type SerializeStruct = Impossible<String, AmfError>;
+    type SerializeStructVariant = Impossible<String, AmfError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<String, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, AmfError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, AmfError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, AmfError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, AmfError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, AmfError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, AmfError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, AmfError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, AmfError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, AmfError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, AmfError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_none(self) -> Result<String, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<String, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_unit(self) -> Result<String, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, AmfError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, AmfError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, AmfError> {
+        Err(key_must_be_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, AmfError> {
+        Err(key_must_be_string())
+    }
+}
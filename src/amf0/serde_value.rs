@@ -0,0 +1,210 @@
+use crate::amf0::boolean::BooleanType;
+use crate::amf0::marker::NullType;
+use crate::amf0::nested::{Amf0TypedValue, ObjectType};
+use crate::amf0::number::NumberType;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::utf8::Utf8;
+use indexmap::IndexMap;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+// Amf0TypedValue 以自描述(self-describing)的方式实现 serde，类似于 serde_json::Value:
+//
+//	Number                -> f64
+//	Boolean               -> bool
+//	String / LongString   -> str
+//	Object / EcmaArray    -> map
+//	StrictArray           -> seq
+//	Null / Undefined      -> unit
+//
+// 反序列化时 map 总是得到 Object，字符串按 UTF-8 字节长度自动选择 String 或 LongString。
+impl Serialize for Amf0TypedValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Amf0TypedValue::Number(v) => serializer.serialize_f64(**v),
+            Amf0TypedValue::Boolean(v) => serializer.serialize_bool(**v),
+            Amf0TypedValue::String(v) => serializer.serialize_str(v),
+            Amf0TypedValue::LongString(v) => serializer.serialize_str(v),
+            Amf0TypedValue::Object(v) => {
+                let mut map = serializer.serialize_map(Some(v.len()))?;
+                for (key, value) in v.iter() {
+                    map.serialize_entry(key.as_ref() as &str, value)?;
+                }
+                map.end()
+            }
+            Amf0TypedValue::EcmaArray(v) => {
+                let mut map = serializer.serialize_map(Some(v.len()))?;
+                for (key, value) in v.iter() {
+                    map.serialize_entry(key.as_ref() as &str, value)?;
+                }
+                map.end()
+            }
+            Amf0TypedValue::StrictArray(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for value in v.iter() {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Amf0TypedValue::Null(_) | Amf0TypedValue::Undefined(_) => serializer.serialize_unit(),
+            other => Err(ser::Error::custom(format!(
+                "AMF 0 value {:?} cannot be serialized",
+                other
+            ))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Amf0TypedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Amf0TypedValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value representable in AMF 0")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Amf0TypedValue::Boolean(BooleanType::new(v)))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Amf0TypedValue::Number(NumberType::new(v as f64)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Amf0TypedValue::Number(NumberType::new(v as f64)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Amf0TypedValue::Number(NumberType::new(v)))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.visit_string(v.to_string())
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Amf0TypedValue::string(v).map_err(de::Error::custom)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Amf0TypedValue::Null(NullType))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.visit_unit()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Amf0TypedValue::StrictArray(StrictArrayType::new(values)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut properties = IndexMap::new();
+        while let Some((key, value)) = map.next_entry::<String, Amf0TypedValue>()? {
+            let key = Utf8::new(key).map_err(de::Error::custom)?;
+            properties.insert(key, value);
+        }
+        Ok(Amf0TypedValue::Object(ObjectType::new(properties)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0::marker::UndefinedType;
+    use crate::amf0::nested::EcmaArrayType;
+    use crate::amf0::string::StringType;
+    use crate::amf0::unsupported::UnsupportedType;
+
+    fn sample_object() -> Amf0TypedValue {
+        let mut props = IndexMap::new();
+        props.insert(
+            Utf8::new_from_str("duration").unwrap(),
+            Amf0TypedValue::Number(NumberType::new(12.5)),
+        );
+        props.insert(
+            Utf8::new_from_str("hasVideo").unwrap(),
+            Amf0TypedValue::Boolean(BooleanType::new(true)),
+        );
+        props.insert(
+            Utf8::new_from_str("encoder").unwrap(),
+            Amf0TypedValue::String(StringType::new_from_str("Lavf60").unwrap()),
+        );
+        props.insert(
+            Utf8::new_from_str("times").unwrap(),
+            Amf0TypedValue::StrictArray(StrictArrayType::new(vec![
+                Amf0TypedValue::Number(NumberType::new(0.0)),
+                Amf0TypedValue::Number(NumberType::new(2.0)),
+            ])),
+        );
+        props.insert(
+            Utf8::new_from_str("cover").unwrap(),
+            Amf0TypedValue::Null(NullType),
+        );
+        Amf0TypedValue::Object(ObjectType::new(props))
+    }
+
+    #[test]
+    fn test_serialize_to_json() {
+        let json = serde_json::to_string(&sample_object()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"duration":12.5,"hasVideo":true,"encoder":"Lavf60","times":[0.0,2.0],"cover":null}"#
+        );
+    }
+
+    #[test]
+    fn test_round_trip_through_json() {
+        let original = sample_object();
+        let json = serde_json::to_string(&original).unwrap();
+        let decoded: Amf0TypedValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, original);
+    }
+
+    #[test]
+    fn test_ecma_array_and_undefined() {
+        let mut props = IndexMap::new();
+        props.insert(
+            Utf8::new_from_str("a").unwrap(),
+            Amf0TypedValue::Undefined(UndefinedType),
+        );
+        let value = Amf0TypedValue::EcmaArray(EcmaArrayType::new(props));
+        assert_eq!(serde_json::to_string(&value).unwrap(), r#"{"a":null}"#);
+    }
+
+    #[test]
+    fn test_deserialize_integers_and_long_strings() {
+        let value: Amf0TypedValue = serde_json::from_str("-3").unwrap();
+        assert_eq!(value, Amf0TypedValue::Number(NumberType::new(-3.0)));
+
+        let long = "a".repeat(u16::MAX as usize + 1);
+        let value: Amf0TypedValue = serde_json::from_str(&format!("\"{}\"", long)).unwrap();
+        assert!(matches!(value, Amf0TypedValue::LongString(_)));
+        let value: Amf0TypedValue = serde_json::from_str("\"short\"").unwrap();
+        assert!(matches!(value, Amf0TypedValue::String(_)));
+    }
+
+    #[test]
+    fn test_serialize_unsupported_fails() {
+        let value = Amf0TypedValue::Unsupported(UnsupportedType {});
+        assert!(serde_json::to_string(&value).is_err());
+    }
+}
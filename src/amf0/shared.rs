@@ -0,0 +1,161 @@
+use crate::amf0::nested::Amf0TypedValue;
+use crate::errors::AmfError;
+use crate::traits::{Marshall, MarshallLength};
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock};
+
+// A reference-counted, immutable-by-default value for fan-out: cloning only bumps a counter, so
+// the same onMetaData can be handed to thousands of subscribers (and threads) without copying
+// its strings and maps. The encoding is computed once, on the first `marshall`/`encoded`, and
+// shared by all clones. `make_mut` gives copy-on-write access: the tree is copied only if other
+// clones still use it.
+#[derive(Debug, Clone)]
+pub struct SharedValue {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Clone)]
+struct Inner {
+    value: Amf0TypedValue,
+    // 第一次编码的结果，所有 clone 共用
+    encoded: OnceLock<Arc<[u8]>>,
+}
+
+impl SharedValue {
+    pub fn new(value: Amf0TypedValue) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                value,
+                encoded: OnceLock::new(),
+            }),
+        }
+    }
+
+    // The encoded value, computed on first use and shared by every clone afterwards.
+    pub fn encoded(&self) -> Result<Arc<[u8]>, AmfError> {
+        if let Some(bytes) = self.inner.encoded.get() {
+            return Ok(bytes.clone());
+        }
+        let bytes: Arc<[u8]> = self.inner.value.marshall()?.into();
+        // 并发时可能有多个线程同时编码，结果相同，保留先写入的那个
+        Ok(self.inner.encoded.get_or_init(|| bytes).clone())
+    }
+
+    // Mutable access to the value, copying it first if other clones share it. The cached
+    // encoding is discarded.
+    pub fn make_mut(&mut self) -> &mut Amf0TypedValue {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.encoded = OnceLock::new();
+        &mut inner.value
+    }
+
+    // The value itself, copied only if other clones share it.
+    pub fn into_inner(self) -> Amf0TypedValue {
+        Arc::unwrap_or_clone(self.inner).value
+    }
+
+    // Whether both point to the same shared value.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Deref for SharedValue {
+    type Target = Amf0TypedValue;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner.value
+    }
+}
+
+impl AsRef<Amf0TypedValue> for SharedValue {
+    fn as_ref(&self) -> &Amf0TypedValue {
+        &self.inner.value
+    }
+}
+
+impl PartialEq for SharedValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.inner.value == other.inner.value
+    }
+}
+
+impl Marshall for SharedValue {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        Ok(self.encoded()?.to_vec())
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        buf.extend_from_slice(&self.encoded()?);
+        Ok(())
+    }
+}
+
+impl MarshallLength for SharedValue {
+    fn marshall_length(&self) -> usize {
+        match self.inner.encoded.get() {
+            Some(bytes) => bytes.len(),
+            None => self.inner.value.marshall_length(),
+        }
+    }
+}
+
+impl Display for SharedValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.inner.value, f)
+    }
+}
+
+impl From<Amf0TypedValue> for SharedValue {
+    fn from(value: Amf0TypedValue) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<SharedValue> for Amf0TypedValue {
+    fn from(value: SharedValue) -> Self {
+        value.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+
+    #[test]
+    fn test_clones_share_value_and_encoding() {
+        let metadata = SharedValue::new(amf0!({"duration": 12.5, "encoder": "Lavf60"}));
+        let subscriber = metadata.clone();
+        assert!(subscriber.ptr_eq(&metadata));
+        assert_eq!(subscriber.as_number(), None);
+        assert_eq!(subscriber["encoder"], amf0!("Lavf60"));
+
+        let bytes = metadata.encoded().unwrap();
+        assert_eq!(
+            *bytes,
+            *amf0!({"duration": 12.5, "encoder": "Lavf60"})
+                .marshall()
+                .unwrap()
+        );
+        assert!(Arc::ptr_eq(&bytes, &subscriber.encoded().unwrap()));
+        assert_eq!(subscriber.marshall_length(), bytes.len());
+    }
+
+    #[test]
+    fn test_make_mut_copies_on_write() {
+        let original = SharedValue::new(amf0!({"duration": 12.5}));
+        let before = original.encoded().unwrap();
+        let mut edited = original.clone();
+        edited.make_mut().set_path("duration", 13.0).unwrap();
+        assert!(!edited.ptr_eq(&original));
+        assert_eq!(original["duration"], amf0!(12.5));
+        assert_eq!(original.marshall().unwrap(), *before);
+        assert_eq!(
+            edited.marshall().unwrap(),
+            amf0!({"duration": 13.0}).marshall().unwrap()
+        );
+        assert_eq!(Amf0TypedValue::from(edited), amf0!({"duration": 13.0}));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::amf0::nested::{Amf0TypedValue, NestedType};
+use crate::traits::MarshallLength;
+
+// Size and shape of a value tree, as returned by `Amf0TypedValue::stats`. The limits have the
+// same meaning as the fields of `DecodeOptions` with the same names, so the stats of typical
+// traffic can be used to pick them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValueStats {
+    pub numbers: usize,
+    pub booleans: usize,
+    pub strings: usize,
+    pub long_strings: usize,
+    pub objects: usize,
+    pub ecma_arrays: usize,
+    pub strict_arrays: usize,
+    pub nulls: usize,
+    pub undefineds: usize,
+    // Raw and unsupported values
+    pub others: usize,
+    // How many Objects, ECMA arrays and strict arrays are nested inside each other on the
+    // deepest path; 0 for a scalar.
+    pub max_depth: usize,
+    // The most properties (duplicates included) of one Object or ECMA array, or elements of one
+    // strict array.
+    pub max_properties: usize,
+    // The UTF-8 length of the longest String, LongString or property key.
+    pub max_string_bytes: usize,
+    // The UTF-8 length of all strings and property keys together.
+    pub string_bytes: usize,
+    // The length of the encoded value, `marshall_length`.
+    pub encoded_size: usize,
+}
+
+impl ValueStats {
+    // The number of values in the tree, containers included.
+    pub fn values(&self) -> usize {
+        self.numbers
+            + self.booleans
+            + self.strings
+            + self.long_strings
+            + self.objects
+            + self.ecma_arrays
+            + self.strict_arrays
+            + self.nulls
+            + self.undefineds
+            + self.others
+    }
+
+    fn add_string(&mut self, len: usize) {
+        self.string_bytes += len;
+        self.max_string_bytes = self.max_string_bytes.max(len);
+    }
+
+    fn add_properties<'a, const LBW: usize, const TM: u8>(
+        &mut self,
+        object: &'a NestedType<LBW, TM>,
+        depth: usize,
+        stack: &mut Vec<(&'a Amf0TypedValue, usize)>,
+    ) {
+        let entries = object
+            .iter()
+            .chain(object.duplicates().iter().map(|(k, v)| (k, v)));
+        let mut count = 0;
+        for (key, value) in entries {
+            self.add_string(key.len());
+            stack.push((value, depth));
+            count += 1;
+        }
+        self.max_properties = self.max_properties.max(count);
+    }
+}
+
+impl Amf0TypedValue {
+    // Counts the values of each type and measures nesting, strings and encoded size, e.g. for
+    // capacity planning, for tuning `DecodeOptions` limits, or to spot peers sending
+    // pathological payloads. Nesting is tracked on the heap, so deep trees cannot overflow the
+    // stack.
+    pub fn stats(&self) -> ValueStats {
+        let mut stats = ValueStats {
+            encoded_size: self.marshall_length(),
+            ..ValueStats::default()
+        };
+        // (值, 它所在的容器层数)
+        let mut stack = vec![(self, 0)];
+        while let Some((value, depth)) = stack.pop() {
+            match value {
+                Amf0TypedValue::Number(_) => stats.numbers += 1,
+                Amf0TypedValue::Boolean(_) => stats.booleans += 1,
+                Amf0TypedValue::String(v) => {
+                    stats.strings += 1;
+                    stats.add_string(v.as_ref().len());
+                }
+                Amf0TypedValue::LongString(v) => {
+                    stats.long_strings += 1;
+                    stats.add_string(v.as_ref().len());
+                }
+                Amf0TypedValue::Null(_) => stats.nulls += 1,
+                Amf0TypedValue::Undefined(_) => stats.undefineds += 1,
+                Amf0TypedValue::Object(v) => {
+                    stats.objects += 1;
+                    stats.max_depth = stats.max_depth.max(depth + 1);
+                    stats.add_properties(v, depth + 1, &mut stack);
+                }
+                Amf0TypedValue::EcmaArray(v) => {
+                    stats.ecma_arrays += 1;
+                    stats.max_depth = stats.max_depth.max(depth + 1);
+                    stats.add_properties(v, depth + 1, &mut stack);
+                }
+                Amf0TypedValue::StrictArray(v) => {
+                    stats.strict_arrays += 1;
+                    stats.max_depth = stats.max_depth.max(depth + 1);
+                    stats.max_properties = stats.max_properties.max(v.len());
+                    stack.extend(v.iter().map(|v| (v, depth + 1)));
+                }
+                _ => stats.others += 1,
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::amf0::decode::DecodeOptions;
+    use crate::traits::Marshall;
+
+    #[test]
+    fn test_stats() {
+        let value = amf0!({
+            "duration": 12.5,
+            "encoder": "Lavf60",
+            "keyframes": {"times": [0.0, 2.0, 4.0], "filepositions": []},
+            "cover": null,
+            "stereo": true,
+        });
+        let stats = value.stats();
+        assert_eq!(stats.numbers, 4);
+        assert_eq!((stats.strings, stats.booleans, stats.nulls), (1, 1, 1));
+        assert_eq!((stats.objects, stats.strict_arrays), (2, 2));
+        assert_eq!(stats.values(), 11);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.max_properties, 5);
+        assert_eq!(stats.max_string_bytes, "filepositions".len());
+        assert_eq!(stats.string_bytes, 8 + 7 + 6 + 9 + 5 + 13 + 5 + 6);
+        assert_eq!(stats.encoded_size, value.marshall_length());
+
+        // 与 DecodeOptions 的限制含义相同: 恰好等于统计值时可以解码
+        let options = DecodeOptions {
+            max_depth: stats.max_depth,
+            max_properties: stats.max_properties,
+            max_string_bytes: stats.max_string_bytes,
+            max_total_bytes: stats.encoded_size,
+            ..DecodeOptions::default()
+        };
+        let data = value.marshall().unwrap();
+        assert!(Amf0TypedValue::unmarshall_with(&data, &options).is_ok());
+        assert_eq!(amf0!(1.0).stats().max_depth, 0);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::amf0::nested::{Amf0TypedValue, MAX_PREALLOCATED_VALUES, drop_nested};
+use crate::amf0::to_json::write_json_array;
+use crate::amf0::type_marker::TypeMarker;
+use crate::errors::AmfError;
+use crate::traits::{Marshall, MarshallLength, Unmarshall};
+use std::borrow::Borrow;
+use std::fmt::{Display, Formatter};
+use std::ops::{Deref, DerefMut};
+
+//	A strict Array contains only ordinal indices; however, in AMF 0 the indices can be dense
+//	or sparse. Undefined entries in the sparse regions between indices are serialized as
+//	undefined.
+//	array-count = U32
+//	strict-array-type = array-count *(value-type)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StrictArrayType {
+    values: Vec<Amf0TypedValue>,
+}
+
+impl StrictArrayType {
+    pub fn new(values: Vec<Amf0TypedValue>) -> Self {
+        Self { values }
+    }
+
+    pub(crate) fn take_values(&mut self) -> Vec<Amf0TypedValue> {
+        std::mem::take(&mut self.values)
+    }
+}
+
+// 深层嵌套时避免递归 drop，见 nested::drop_nested
+impl Drop for StrictArrayType {
+    fn drop(&mut self) {
+        if self.values.iter().any(|v| {
+            matches!(
+                v,
+                Amf0TypedValue::Object(_)
+                    | Amf0TypedValue::EcmaArray(_)
+                    | Amf0TypedValue::StrictArray(_)
+            )
+        }) {
+            drop_nested(self.take_values().into_iter());
+        }
+    }
+}
+
+impl Marshall for StrictArrayType {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        if self.values.len() > u32::MAX as usize {
+            return Err(AmfError::TooLong {
+                what: "Strict array",
+                max: u32::MAX as usize,
+                got: self.values.len(),
+            });
+        }
+        buf.push(TypeMarker::StrictArray as u8);
+        buf.extend_from_slice(&(self.values.len() as u32).to_be_bytes());
+        for value in &self.values {
+            value.marshall_to(buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl MarshallLength for StrictArrayType {
+    fn marshall_length(&self) -> usize {
+        let values_bytes_size: usize = self.values.iter().map(|v| v.marshall_length()).sum();
+        1 + 4 + values_bytes_size // 1 byte for type marker, 4 bytes for array count
+    }
+}
+
+impl Unmarshall for StrictArrayType {
+    fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
+        if buf.len() < 5 {
+            return Err(AmfError::BufferTooSmall {
+                want: 5,
+                got: buf.len(),
+            });
+        }
+        if buf[0] != TypeMarker::StrictArray as u8 {
+            return Err(AmfError::TypeMarkerValueMismatch {
+                want: TypeMarker::StrictArray as u8,
+                got: buf[0],
+            });
+        }
+        let count = u32::from_be_bytes(buf[1..5].try_into().unwrap()) as usize; // 前边已经校验了 buf 的长度
+
+        let mut values = Vec::with_capacity(count.min(MAX_PREALLOCATED_VALUES));
+        let mut offset = 5;
+        for _ in 0..count {
+            let (v, v_len) = Amf0TypedValue::unmarshall(&buf[offset..])?;
+            offset += v_len;
+            values.push(v);
+        }
+        Ok((Self::new(values), offset))
+    }
+}
+
+// 实现 rust 惯用语("idiom") 方便用户使用
+
+impl TryFrom<&[u8]> for StrictArrayType {
+    type Error = AmfError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::unmarshall(value).map(|(v, _)| v)
+    }
+}
+
+impl TryFrom<Vec<u8>> for StrictArrayType {
+    type Error = AmfError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_slice())
+    }
+}
+
+impl TryFrom<StrictArrayType> for Vec<u8> {
+    type Error = AmfError;
+
+    fn try_from(value: StrictArrayType) -> Result<Self, Self::Error> {
+        value.marshall()
+    }
+}
+
+impl From<Vec<Amf0TypedValue>> for StrictArrayType {
+    fn from(values: Vec<Amf0TypedValue>) -> Self {
+        Self::new(values)
+    }
+}
+
+impl From<StrictArrayType> for Vec<Amf0TypedValue> {
+    fn from(mut value: StrictArrayType) -> Self {
+        value.take_values()
+    }
+}
+
+impl AsRef<Vec<Amf0TypedValue>> for StrictArrayType {
+    fn as_ref(&self) -> &Vec<Amf0TypedValue> {
+        &self.values
+    }
+}
+
+impl Deref for StrictArrayType {
+    type Target = Vec<Amf0TypedValue>;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_ref()
+    }
+}
+
+// 数组没有单独保存的长度字段，直接修改元素是安全的
+impl DerefMut for StrictArrayType {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.values
+    }
+}
+
+impl Borrow<Vec<Amf0TypedValue>> for StrictArrayType {
+    fn borrow(&self) -> &Vec<Amf0TypedValue> {
+        self.as_ref()
+    }
+}
+
+impl Display for StrictArrayType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write_json_array(f, self.values.iter())
+    }
+}
+
+impl<V: Into<Amf0TypedValue>> FromIterator<V> for StrictArrayType {
+    fn from_iter<I: IntoIterator<Item = V>>(iter: I) -> Self {
+        Self::new(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl IntoIterator for StrictArrayType {
+    type Item = Amf0TypedValue;
+    type IntoIter = std::vec::IntoIter<Amf0TypedValue>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.take_values().into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a StrictArrayType {
+    type Item = &'a Amf0TypedValue;
+    type IntoIter = std::slice::Iter<'a, Amf0TypedValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0::boolean::BooleanType;
+    use crate::amf0::marker::UndefinedType;
+    use crate::amf0::nested::ObjectType;
+    use crate::amf0::number::NumberType;
+    use crate::amf0::string::StringType;
+    use crate::amf0::utf8::Utf8;
+
+    fn sample_values() -> Vec<Amf0TypedValue> {
+        vec![
+            Amf0TypedValue::Number(NumberType::new(0.0)),
+            Amf0TypedValue::Number(NumberType::new(1.5)),
+            Amf0TypedValue::Boolean(BooleanType::new(true)),
+            Amf0TypedValue::String(StringType::new_from_str("x").unwrap()),
+            Amf0TypedValue::Undefined(UndefinedType),
+        ]
+    }
+
+    #[test]
+    fn test_marshall() {
+        let array = StrictArrayType::new(vec![
+            Amf0TypedValue::Number(NumberType::new(1.0)),
+            Amf0TypedValue::Boolean(BooleanType::new(false)),
+        ]);
+        let data = array.marshall().unwrap();
+        let mut expected = vec![TypeMarker::StrictArray as u8, 0x00, 0x00, 0x00, 0x02];
+        expected.extend_from_slice(&NumberType::new(1.0).marshall().unwrap());
+        expected.extend_from_slice(&[TypeMarker::Boolean as u8, 0x00]);
+        assert_eq!(data, expected);
+        assert_eq!(array.marshall_length(), data.len());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let array = StrictArrayType::new(sample_values());
+        let data = array.marshall().unwrap();
+        let (decoded, n) = StrictArrayType::unmarshall(&data).unwrap();
+        assert_eq!(decoded, array);
+        assert_eq!(n, data.len());
+    }
+
+    #[test]
+    fn test_round_trip_nested_in_value() {
+        let inner = Amf0TypedValue::StrictArray(StrictArrayType::new(sample_values()));
+        let object: ObjectType = vec![(Utf8::new_from_str("times").unwrap(), inner.clone())]
+            .into_iter()
+            .collect();
+        let original = Amf0TypedValue::StrictArray(StrictArrayType::new(vec![
+            inner,
+            Amf0TypedValue::Object(object),
+        ]));
+        let data = original.marshall().unwrap();
+        let (decoded, n) = Amf0TypedValue::unmarshall(&data).unwrap();
+        assert_eq!(decoded, original);
+        assert_eq!(n, data.len());
+    }
+
+    #[test]
+    fn test_empty() {
+        let array = StrictArrayType::default();
+        let data = array.marshall().unwrap();
+        assert_eq!(data, vec![TypeMarker::StrictArray as u8, 0, 0, 0, 0]);
+        let (decoded, n) = StrictArrayType::unmarshall(&data).unwrap();
+        assert!(decoded.is_empty());
+        assert_eq!(n, 5);
+    }
+
+    #[test]
+    fn test_unmarshall_buffer_too_small() {
+        let result = StrictArrayType::unmarshall(&[TypeMarker::StrictArray as u8, 0x00]);
+        assert!(matches!(
+            result,
+            Err(AmfError::BufferTooSmall { want: 5, got: 2 })
+        ));
+
+        // 声明了 2 个元素，实际只有 1 个
+        let mut data = vec![TypeMarker::StrictArray as u8, 0x00, 0x00, 0x00, 0x02];
+        data.extend_from_slice(&NumberType::new(1.0).marshall().unwrap());
+        let result = StrictArrayType::unmarshall(&data);
+        assert!(matches!(result, Err(AmfError::BufferTooSmall { .. })));
+    }
+
+    #[test]
+    fn test_unmarshall_huge_count() {
+        let data = [TypeMarker::StrictArray as u8, 0xff, 0xff, 0xff, 0xff];
+        let result = StrictArrayType::unmarshall(&data);
+        assert!(matches!(result, Err(AmfError::BufferTooSmall { .. })));
+    }
+
+    #[test]
+    fn test_unmarshall_invalid_marker() {
+        let data = [TypeMarker::Object as u8, 0x00, 0x00, 0x00, 0x00];
+        let result = StrictArrayType::unmarshall(&data);
+        assert!(matches!(
+            result,
+            Err(AmfError::TypeMarkerValueMismatch {
+                want: 0x0A,
+                got: 0x03
+            })
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        let array = StrictArrayType::new(vec![
+            Amf0TypedValue::Number(NumberType::new(1.0)),
+            Amf0TypedValue::String(StringType::new_from_str("a").unwrap()),
+        ]);
+        assert_eq!(format!("{}", array), "[1,\"a\"]");
+        assert_eq!(format!("{}", StrictArrayType::default()), "[]");
+    }
+
+    #[test]
+    fn test_deref_and_into_iter() {
+        let array: StrictArrayType = sample_values().into_iter().collect();
+        assert_eq!(array.len(), 5);
+        assert_eq!(array[1], Amf0TypedValue::Number(NumberType::new(1.5)));
+        let values: Vec<Amf0TypedValue> = array.into_iter().collect();
+        assert_eq!(values, sample_values());
+    }
+}
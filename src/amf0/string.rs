@@ -1,3 +1,4 @@
+use crate::amf0::to_json::write_json_string;
 use crate::amf0::type_marker::TypeMarker;
 use crate::amf0::utf8::AmfUtf8;
 use crate::errors::AmfError;
@@ -29,11 +30,14 @@ impl<const LBW: usize, const TM: u8> AmfUtf8ValuedType<LBW, TM> {
 impl<const LBW: usize, const TM: u8> Marshall for AmfUtf8ValuedType<LBW, TM> {
     fn marshall(&self) -> Result<Vec<u8>, AmfError> {
         let mut vec = Vec::with_capacity(self.marshall_length());
-        vec.push(TM);
-        let inner_vec = self.inner.marshall()?;
-        vec.extend_from_slice(inner_vec.as_slice());
+        self.marshall_to(&mut vec)?;
         Ok(vec)
     }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        buf.push(TM);
+        self.inner.marshall_to(buf)
+    }
 }
 
 impl<const LBW: usize, const TM: u8> MarshallLength for AmfUtf8ValuedType<LBW, TM> {
@@ -141,7 +145,7 @@ impl<const LBW: usize, const TM: u8> Borrow<AmfUtf8<LBW>> for AmfUtf8ValuedType<
 
 impl<const LBW: usize, const TM: u8> Display for AmfUtf8ValuedType<LBW, TM> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "\"{}\"", self.inner)
+        write_json_string(f, &self.inner)
     }
 }
 
@@ -0,0 +1,381 @@
+use crate::amf0::nested::Amf0TypedValue;
+use crate::amf0::number::{NonFinite, write_ecma_number};
+use crate::amf0::utf8::Utf8;
+use std::fmt::{Display, Formatter, Write};
+
+impl Amf0TypedValue {
+    // Renders the value as compact JSON, the same text as `Display`. Keys and strings are
+    // quoted and escaped, and values JSON cannot express are written the way `JSON.stringify`
+    // does: NaN/±Infinity, Undefined and the unsupported variants all become `null`. Numbers
+    // are formatted like ECMAScript (`NumberFormat::Ecma`), so integral values have no
+    // fractional part and the output matches JavaScript and flvmeta.
+    pub fn to_json_string(&self) -> String {
+        self.to_json_string_with(NonFinite::Null)
+    }
+
+    // Same as `to_json_string`, with NaN/±Infinity written as selected by `non_finite`, e.g. as
+    // the strings `"NaN"` and `"Infinity"` to keep them distinguishable from null.
+    pub fn to_json_string_with(&self, non_finite: NonFinite) -> String {
+        let mut writer = JsonWriter::new(None);
+        writer.non_finite = non_finite;
+        writer.write_value(self);
+        writer.out
+    }
+
+    // Same as `to_json_string`, but with two-space indentation and one entry per line. This is
+    // also what the alternate form of `Display` (`{:#}`) writes.
+    pub fn to_json_string_pretty(&self) -> String {
+        self.to_pretty_string(2)
+    }
+
+    // Multi-line rendering with `indent` spaces per nesting level, for readable dumps of large
+    // onMetaData objects in logs and CLIs.
+    pub fn to_pretty_string(&self, indent: usize) -> String {
+        let mut writer = JsonWriter::new(Some(indent));
+        writer.write_value(self);
+        writer.out
+    }
+}
+
+// Display 的实现也使用这里的转义和序列化，保证输出总是合法的 JSON
+pub(crate) fn write_json_string<W: Write>(out: &mut W, s: &str) -> std::fmt::Result {
+    out.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            '\u{08}' => out.write_str("\\b")?,
+            '\u{0c}' => out.write_str("\\f")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+pub(crate) fn write_json_object(
+    f: &mut Formatter<'_>,
+    properties: indexmap::map::Iter<'_, Utf8, Amf0TypedValue>,
+) -> std::fmt::Result {
+    let mut writer = JsonWriter::for_formatter(f);
+    writer.write_entries(Entries::Object(properties));
+    f.write_str(&writer.out)
+}
+
+pub(crate) fn write_json_array(
+    f: &mut Formatter<'_>,
+    values: std::slice::Iter<'_, Amf0TypedValue>,
+) -> std::fmt::Result {
+    let mut writer = JsonWriter::for_formatter(f);
+    writer.write_entries(Entries::Array(values));
+    f.write_str(&writer.out)
+}
+
+impl Display for Amf0TypedValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut writer = JsonWriter::for_formatter(f);
+        writer.write_value(self);
+        f.write_str(&writer.out)
+    }
+}
+
+// 容器中还没有输出的元素
+enum Entries<'a> {
+    Object(indexmap::map::Iter<'a, Utf8, Amf0TypedValue>),
+    Array(std::slice::Iter<'a, Amf0TypedValue>),
+}
+
+impl Entries<'_> {
+    fn brackets(&self) -> (char, char) {
+        match self {
+            Entries::Object(_) => ('{', '}'),
+            Entries::Array(_) => ('[', ']'),
+        }
+    }
+}
+
+struct JsonWriter {
+    out: String,
+    // 每层缩进的空格数，None 表示紧凑输出
+    indent: Option<usize>,
+    depth: usize,
+    non_finite: NonFinite,
+}
+
+impl JsonWriter {
+    fn new(indent: Option<usize>) -> Self {
+        Self {
+            out: String::new(),
+            indent,
+            depth: 0,
+            non_finite: NonFinite::Null,
+        }
+    }
+
+    // `{:#}` 输出两个空格缩进的多行格式
+    fn for_formatter(f: &Formatter<'_>) -> Self {
+        Self::new(f.alternate().then_some(2))
+    }
+
+    fn write_value(&mut self, value: &Amf0TypedValue) {
+        let entries = match value {
+            Amf0TypedValue::Number(v) => return self.write_number(**v),
+            Amf0TypedValue::Boolean(v) => {
+                return self.out.push_str(if **v { "true" } else { "false" });
+            }
+            Amf0TypedValue::String(v) => return self.write_string(v),
+            Amf0TypedValue::LongString(v) => return self.write_string(v),
+            Amf0TypedValue::Object(v) => Entries::Object(v.iter()),
+            Amf0TypedValue::EcmaArray(v) => Entries::Object(v.iter()),
+            Amf0TypedValue::StrictArray(v) => Entries::Array(v.iter()),
+            _ => return self.out.push_str("null"),
+        };
+        self.write_entries(entries)
+    }
+
+    // 嵌套的容器保存在堆上的栈里，深层嵌套的值不会撑爆调用栈
+    fn write_entries(&mut self, entries: Entries<'_>) {
+        // (还没有输出的元素, 是否已经输出过元素)
+        let mut stack = Vec::new();
+        self.open(entries, &mut stack);
+        while let Some((entries, started)) = stack.last_mut() {
+            let next = match entries {
+                Entries::Object(properties) => properties.next().map(|(k, v)| (Some(k), v)),
+                Entries::Array(values) => values.next().map(|v| (None, v)),
+            };
+            let Some((key, value)) = next else {
+                let (_, close) = entries.brackets();
+                stack.pop();
+                self.depth -= 1;
+                self.write_newline();
+                self.out.push(close);
+                continue;
+            };
+            if std::mem::replace(started, true) {
+                self.out.push(',');
+            }
+            self.write_newline();
+            if let Some(key) = key {
+                self.write_string(key);
+                self.out.push(':');
+                if self.indent.is_some() {
+                    self.out.push(' ');
+                }
+            }
+            match value {
+                Amf0TypedValue::Object(v) => self.open(Entries::Object(v.iter()), &mut stack),
+                Amf0TypedValue::EcmaArray(v) => self.open(Entries::Object(v.iter()), &mut stack),
+                Amf0TypedValue::StrictArray(v) => self.open(Entries::Array(v.iter()), &mut stack),
+                scalar => self.write_value(scalar),
+            }
+        }
+    }
+
+    // 空容器直接输出，否则输出开括号并压栈
+    fn open<'a>(&mut self, entries: Entries<'a>, stack: &mut Vec<(Entries<'a>, bool)>) {
+        let (open, close) = entries.brackets();
+        let empty = match &entries {
+            Entries::Object(properties) => properties.len() == 0,
+            Entries::Array(values) => values.len() == 0,
+        };
+        self.out.push(open);
+        if empty {
+            self.out.push(close);
+            return;
+        }
+        self.depth += 1;
+        stack.push((entries, false));
+    }
+
+    fn write_number(&mut self, n: f64) {
+        write_ecma_number(&mut self.out, n, self.non_finite).unwrap(); // 写入 String 不会失败
+    }
+
+    fn write_string(&mut self, s: &str) {
+        self.out.reserve(s.len() + 2);
+        write_json_string(&mut self.out, s).unwrap(); // 写入 String 不会失败
+    }
+
+    fn write_newline(&mut self) {
+        if let Some(indent) = self.indent {
+            self.out.push('\n');
+            self.out
+                .extend(std::iter::repeat_n(' ', indent * self.depth));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::amf0::boolean::BooleanType;
+    use crate::amf0::marker::{NullType, UndefinedType};
+    use crate::amf0::nested::{EcmaArrayType, ObjectType};
+    use crate::amf0::number::NumberType;
+    use crate::amf0::strict_array::StrictArrayType;
+    use crate::amf0::string::StringType;
+    use indexmap::IndexMap;
+
+    fn sample_value() -> Amf0TypedValue {
+        let mut inner = IndexMap::new();
+        inner.insert(
+            Utf8::new_from_str("ok").unwrap(),
+            Amf0TypedValue::Boolean(BooleanType::new(true)),
+        );
+        let mut props = IndexMap::new();
+        props.insert(
+            Utf8::new_from_str("duration").unwrap(),
+            Amf0TypedValue::Number(NumberType::new(12.5)),
+        );
+        props.insert(
+            Utf8::new_from_str("width").unwrap(),
+            Amf0TypedValue::Number(NumberType::new(1280.0)),
+        );
+        props.insert(
+            Utf8::new_from_str("title").unwrap(),
+            Amf0TypedValue::String(StringType::new_from_str("say \"hi\"\n").unwrap()),
+        );
+        props.insert(
+            Utf8::new_from_str("times").unwrap(),
+            Amf0TypedValue::StrictArray(StrictArrayType::new(vec![
+                Amf0TypedValue::Null(NullType),
+                Amf0TypedValue::Undefined(UndefinedType),
+            ])),
+        );
+        props.insert(
+            Utf8::new_from_str("inner").unwrap(),
+            Amf0TypedValue::Object(ObjectType::new(inner)),
+        );
+        Amf0TypedValue::EcmaArray(EcmaArrayType::new(props))
+    }
+
+    #[test]
+    fn test_to_json_string() {
+        assert_eq!(
+            sample_value().to_json_string(),
+            r#"{"duration":12.5,"width":1280,"title":"say \"hi\"\n","times":[null,null],"inner":{"ok":true}}"#
+        );
+    }
+
+    #[test]
+    fn test_display_is_json() {
+        let value = sample_value();
+        assert_eq!(value.to_string(), value.to_json_string());
+        let object = value.as_ecma_array().unwrap();
+        assert_eq!(object.to_string(), value.to_json_string());
+        assert_eq!(object["times"].to_string(), "[null,null]");
+
+        let key = Utf8::new_from_str("a\"b\\").unwrap();
+        let tricky: ObjectType = [(key, amf0!("\u{1}"))].into_iter().collect();
+        assert_eq!(tricky.to_string(), r#"{"a\"b\\":"\u0001"}"#);
+        assert_eq!(
+            StringType::new_from_str("\"").unwrap().to_string(),
+            r#""\"""#
+        );
+    }
+
+    #[test]
+    fn test_pretty_display() {
+        let value = sample_value();
+        assert_eq!(format!("{:#}", value), value.to_json_string_pretty());
+        assert_eq!(
+            format!("{:#}", value.as_ecma_array().unwrap()),
+            value.to_json_string_pretty()
+        );
+        assert_eq!(value.to_pretty_string(2), value.to_json_string_pretty());
+        assert_eq!(
+            value["times"].to_pretty_string(4),
+            "[\n    null,\n    null\n]"
+        );
+    }
+
+    #[test]
+    fn test_to_json_string_pretty() {
+        let expected = r#"{
+  "duration": 12.5,
+  "width": 1280,
+  "title": "say \"hi\"\n",
+  "times": [
+    null,
+    null
+  ],
+  "inner": {
+    "ok": true
+  }
+}"#;
+        assert_eq!(sample_value().to_json_string_pretty(), expected);
+    }
+
+    #[test]
+    fn test_non_finite_numbers() {
+        for n in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(
+                Amf0TypedValue::Number(NumberType::new(n)).to_json_string(),
+                "null"
+            );
+        }
+        assert_eq!(
+            Amf0TypedValue::Number(NumberType::new(-0.25)).to_json_string(),
+            "-0.25"
+        );
+        let value = amf0!([f64::NAN, f64::INFINITY, 1e21, -0.0]);
+        assert_eq!(
+            value.to_json_string_with(NonFinite::String),
+            r#"["NaN","Infinity",1e+21,0]"#
+        );
+    }
+
+    #[test]
+    fn test_escape_control_characters_and_keys() {
+        let mut props = IndexMap::new();
+        props.insert(
+            Utf8::new_from_str("a\\b").unwrap(),
+            Amf0TypedValue::String(StringType::new_from_str("\u{01}\t中").unwrap()),
+        );
+        let value = Amf0TypedValue::Object(ObjectType::new(props));
+        assert_eq!(value.to_json_string(), r#"{"a\\b":"\u0001\t中"}"#);
+    }
+
+    #[test]
+    fn test_deep_nesting_does_not_overflow_stack() {
+        // 二十万层 strict array 和 Object 交替嵌套
+        let depth = 200_000;
+        let mut data = Vec::new();
+        for i in 0..depth {
+            if i % 2 == 0 {
+                data.extend_from_slice(&[0x0a, 0x00, 0x00, 0x00, 0x01]);
+            } else {
+                data.extend_from_slice(&[0x03, 0x00, 0x01, b'a']);
+            }
+        }
+        data.push(0x05);
+        data.extend([0x00, 0x00, 0x09].repeat(depth / 2));
+        let (value, _) =
+            Amf0TypedValue::unmarshall_with(&data, &amf0::DecodeOptions::unlimited()).unwrap();
+
+        let json = value.to_string();
+        assert_eq!(
+            json.len(),
+            depth / 2 * ("[".len() + "{\"a\":".len() + "}]".len()) + 4
+        );
+        assert!(json.starts_with("[{\"a\":[{\"a\":"), "{}", &json[..20]);
+        assert_eq!(value.to_json_string(), json);
+        assert!(value.to_pretty_string(0).contains("null"));
+    }
+
+    #[test]
+    fn test_empty_containers() {
+        assert_eq!(
+            Amf0TypedValue::Object(ObjectType::default()).to_json_string_pretty(),
+            "{}"
+        );
+        assert_eq!(
+            Amf0TypedValue::StrictArray(StrictArrayType::default()).to_json_string_pretty(),
+            "[]"
+        );
+    }
+}
@@ -48,10 +48,7 @@ impl TryFrom<u8> for TypeMarker {
             0x0E => Ok(TypeMarker::Recordset),
             0x0F => Ok(TypeMarker::XmlDocument),
             0x10 => Ok(TypeMarker::TypedObject),
-            v => Err(AmfError::Custom(format!(
-                "Invalid type marker value: {:?}",
-                v
-            ))),
+            v => Err(AmfError::InvalidTypeMarker(v)),
         }
     }
 }
@@ -44,7 +44,6 @@ pub type RecordsetType = UnsupportedType;
 
 // 以下这些类型大概率在实际应用中用不到，所以暂时不实现
 pub type ReferenceType = UnsupportedType;
-pub type StrictArrayType = UnsupportedType;
 pub type DateType = UnsupportedType;
 pub type XmlDocumentType = UnsupportedType;
 pub type TypedObjectType = UnsupportedType;
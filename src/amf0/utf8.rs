@@ -1,44 +1,155 @@
-use crate::errors::AmfError;
+use crate::errors::{AmfError, checked_end};
 use crate::traits::{Marshall, MarshallLength, Unmarshall};
-use std::borrow::Borrow;
+use std::borrow::{Borrow, Cow};
 use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
+use std::str::Utf8Error;
+use std::sync::Arc;
+
+// 字符串内容的存储类型。开启 `compact_str` feature 后，不超过 24 字节的字符串(绝大多数
+// property key，如 "duration"、"width")内联存储，解码时不需要堆分配
+#[cfg(not(feature = "compact_str"))]
+type Storage = String;
+#[cfg(feature = "compact_str")]
+type Storage = compact_str::CompactString;
+
+#[cfg(not(feature = "compact_str"))]
+fn storage_from_string(s: String) -> Storage {
+    s
+}
+#[cfg(feature = "compact_str")]
+fn storage_from_string(s: String) -> Storage {
+    Storage::from(s)
+}
+
+#[cfg(not(feature = "compact_str"))]
+fn storage_into_string(s: Storage) -> String {
+    s
+}
+#[cfg(feature = "compact_str")]
+fn storage_into_string(s: Storage) -> String {
+    s.into_string()
+}
+
+#[derive(Clone)]
+enum Repr {
+    Owned(Storage),
+    // 由 KeyInterner 分配，同一次解码中相同的 key 共享这一份内存
+    Shared(Arc<str>),
+}
+
+impl Repr {
+    fn into_string(self) -> String {
+        match self {
+            Repr::Owned(s) => storage_into_string(s),
+            Repr::Shared(s) => s.to_string(),
+        }
+    }
+}
+
+impl Deref for Repr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Repr::Owned(s) => s,
+            Repr::Shared(s) => s,
+        }
+    }
+}
+
+impl PartialEq for Repr {
+    fn eq(&self, other: &Self) -> bool {
+        // 同一个 interner 分配的 key 只需要比较指针
+        if let (Repr::Shared(a), Repr::Shared(b)) = (self, other)
+            && Arc::ptr_eq(a, b)
+        {
+            return true;
+        }
+        **self == **other
+    }
+}
+
+impl Eq for Repr {}
+
+// 必须和 str 的 hash 一致，IndexMap 才能用 &str 查找
+impl Hash for Repr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl Debug for Repr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AmfUtf8<const LBW: usize> {
-    inner: String,
+    inner: Repr,
 }
 
 impl<const LBW: usize> AmfUtf8<LBW> {
     pub fn new(inner: String) -> Result<Self, AmfError> {
-        debug_assert!(LBW == 2 || LBW == 4);
-        let len = inner.len();
-        if (LBW == 2 && len > u16::MAX as usize) || (LBW == 4 && len > u32::MAX as usize) {
-            return Err(AmfError::StringTooLong { max: LBW, got: len });
-        }
+        Self::check_length(inner.len())?;
         Ok(Self {
-            inner: inner.to_string(),
+            inner: Repr::Owned(storage_from_string(inner)),
         })
     }
 
     pub fn new_from_str(inner: &str) -> Result<Self, AmfError> {
-        Self::new(inner.to_string())
+        Self::check_length(inner.len())?;
+        Ok(Self {
+            inner: Repr::Owned(Storage::from(inner)),
+        })
+    }
+
+    // Wraps an already shared allocation, as handed out by the key interner of
+    // `DecodeOptions::intern_keys`.
+    pub(crate) fn new_shared(inner: Arc<str>) -> Result<Self, AmfError> {
+        Self::check_length(inner.len())?;
+        Ok(Self {
+            inner: Repr::Shared(inner),
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn as_shared(&self) -> Option<&Arc<str>> {
+        match &self.inner {
+            Repr::Shared(s) => Some(s),
+            Repr::Owned(_) => None,
+        }
+    }
+
+    fn check_length(len: usize) -> Result<(), AmfError> {
+        debug_assert!(LBW == 2 || LBW == 4);
+        if (LBW == 2 && len > u16::MAX as usize) || (LBW == 4 && len > u32::MAX as usize) {
+            return Err(AmfError::StringTooLong { max: LBW, got: len });
+        }
+        Ok(())
     }
 }
 
 impl<const LBW: usize> Marshall for AmfUtf8<LBW> {
     fn marshall(&self) -> Result<Vec<u8>, AmfError> {
-        debug_assert!(LBW == 2 || LBW == 4);
         let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        debug_assert!(LBW == 2 || LBW == 4);
         if LBW == 2 {
-            vec.extend_from_slice((self.inner.len() as u16).to_be_bytes().as_slice())
+            buf.extend_from_slice((self.inner.len() as u16).to_be_bytes().as_slice())
         } else if LBW == 4 {
-            vec.extend_from_slice((self.inner.len() as u32).to_be_bytes().as_slice())
+            buf.extend_from_slice((self.inner.len() as u32).to_be_bytes().as_slice())
         } else {
             return Err(AmfError::Custom("Invalid length byte width".to_string()));
         }
-        vec.extend_from_slice(self.inner.as_bytes());
-        Ok(vec)
+        buf.extend_from_slice(self.inner.as_bytes());
+        Ok(())
     }
 }
 
@@ -51,44 +162,174 @@ impl<const LBW: usize> MarshallLength for AmfUtf8<LBW> {
 
 impl<const LBW: usize> Unmarshall for AmfUtf8<LBW> {
     fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
-        debug_assert!(LBW == 2 || LBW == 4);
-        let length;
-        if LBW == 2 {
-            if buf.len() < 2 {
-                return Err(AmfError::BufferTooSmall {
-                    want: 2,
-                    got: buf.len(),
-                });
-            }
-            length = u16::from_be_bytes(buf[0..2].try_into().unwrap()) as usize;
-        } else if LBW == 4 {
-            if buf.len() < 4 {
-                return Err(AmfError::BufferTooSmall {
-                    want: 4,
-                    got: buf.len(),
-                });
-            }
-            length = u32::from_be_bytes(buf[0..4].try_into().unwrap()) as usize;
-        } else {
-            return Err(AmfError::Custom("Invalid length byte width".to_string()));
-        }
+        let (value, end) = unmarshall_str::<LBW>(buf)?;
+        Ok((
+            Self {
+                inner: Repr::Owned(Storage::from(value)),
+            },
+            end,
+        ))
+    }
+}
+
+// UTF-8 validation used by every decoder in this crate. With the `simdutf8` feature the
+// common (valid) case goes through SIMD validation; an invalid string is validated again by
+// the standard library to get the `Utf8Error` details (`valid_up_to`), which callers use for
+// error reporting and for the `Utf8Fallback` modes.
+#[cfg(not(feature = "simdutf8"))]
+pub(crate) fn str_from_utf8(bytes: &[u8]) -> Result<&str, Utf8Error> {
+    std::str::from_utf8(bytes)
+}
+#[cfg(feature = "simdutf8")]
+pub(crate) fn str_from_utf8(bytes: &[u8]) -> Result<&str, Utf8Error> {
+    simdutf8::basic::from_utf8(bytes).or_else(|_| std::str::from_utf8(bytes))
+}
 
-        let start = LBW;
-        let end = start + length;
-        if buf.len() < end {
+// 解析长度前缀和 UTF-8 内容，返回指向 buf 内部的 &str
+pub(crate) fn unmarshall_str<const LBW: usize>(buf: &[u8]) -> Result<(&str, usize), AmfError> {
+    debug_assert!(LBW == 2 || LBW == 4);
+    let length;
+    if LBW == 2 {
+        if buf.len() < 2 {
+            return Err(AmfError::BufferTooSmall {
+                want: 2,
+                got: buf.len(),
+            });
+        }
+        length = u16::from_be_bytes(buf[0..2].try_into().unwrap()) as usize;
+    } else if LBW == 4 {
+        if buf.len() < 4 {
             return Err(AmfError::BufferTooSmall {
-                want: end,
+                want: 4,
                 got: buf.len(),
             });
         }
-        let value = std::str::from_utf8(&buf[start..end]).map_err(|e| AmfError::InvalidUtf8(e))?;
+        length = u32::from_be_bytes(buf[0..4].try_into().unwrap()) as usize;
+    } else {
+        return Err(AmfError::Custom("Invalid length byte width".to_string()));
+    }
+
+    let start = LBW;
+    let end = checked_end(start, length)?;
+    if buf.len() < end {
+        return Err(AmfError::BufferTooSmall {
+            want: end,
+            got: buf.len(),
+        });
+    }
+    let value = str_from_utf8(&buf[start..end]).map_err(AmfError::InvalidUtf8)?;
+    Ok((value, end))
+}
+
+// Same wire format as `AmfUtf8`, but the contents are a `Cow`: decoding with
+// `unmarshall_borrowed` points into the input buffer instead of copying it, which matters when
+// a large metadata block holds thousands of keys and strings. `into_owned` detaches the value
+// from the buffer for long-lived use.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AmfCowUtf8<'a, const LBW: usize> {
+    inner: Cow<'a, str>,
+}
+
+impl<'a, const LBW: usize> AmfCowUtf8<'a, LBW> {
+    pub fn new(inner: impl Into<Cow<'a, str>>) -> Result<Self, AmfError> {
+        debug_assert!(LBW == 2 || LBW == 4);
+        let inner = inner.into();
+        let len = inner.len();
+        if (LBW == 2 && len > u16::MAX as usize) || (LBW == 4 && len > u32::MAX as usize) {
+            return Err(AmfError::StringTooLong { max: LBW, got: len });
+        }
+        Ok(Self { inner })
+    }
+
+    // Decodes without copying: the result borrows from `buf`.
+    pub fn unmarshall_borrowed(buf: &'a [u8]) -> Result<(Self, usize), AmfError> {
+        let (value, end) = unmarshall_str::<LBW>(buf)?;
         Ok((
             Self {
-                inner: value.to_string(),
+                inner: Cow::Borrowed(value),
             },
             end,
         ))
     }
+
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.inner, Cow::Borrowed(_))
+    }
+
+    pub fn into_owned(self) -> AmfUtf8<LBW> {
+        AmfUtf8 {
+            inner: Repr::Owned(match self.inner {
+                Cow::Borrowed(s) => Storage::from(s),
+                Cow::Owned(s) => storage_from_string(s),
+            }),
+        }
+    }
+}
+
+impl<const LBW: usize> Marshall for AmfCowUtf8<'_, LBW> {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        if LBW == 2 {
+            buf.extend_from_slice(&(self.inner.len() as u16).to_be_bytes());
+        } else {
+            buf.extend_from_slice(&(self.inner.len() as u32).to_be_bytes());
+        }
+        buf.extend_from_slice(self.inner.as_bytes());
+        Ok(())
+    }
+}
+
+impl<const LBW: usize> MarshallLength for AmfCowUtf8<'_, LBW> {
+    fn marshall_length(&self) -> usize {
+        LBW + self.inner.len()
+    }
+}
+
+impl<const LBW: usize> From<AmfUtf8<LBW>> for AmfCowUtf8<'static, LBW> {
+    fn from(value: AmfUtf8<LBW>) -> Self {
+        Self {
+            inner: Cow::Owned(value.inner.into_string()),
+        }
+    }
+}
+
+impl<'a, const LBW: usize> From<&'a AmfUtf8<LBW>> for AmfCowUtf8<'a, LBW> {
+    fn from(value: &'a AmfUtf8<LBW>) -> Self {
+        Self {
+            inner: Cow::Borrowed(&value.inner),
+        }
+    }
+}
+
+impl<const LBW: usize> AsRef<str> for AmfCowUtf8<'_, LBW> {
+    fn as_ref(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl<const LBW: usize> Deref for AmfCowUtf8<'_, LBW> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<const LBW: usize> Borrow<str> for AmfCowUtf8<'_, LBW> {
+    fn borrow(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl<const LBW: usize> Display for AmfCowUtf8<'_, LBW> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.inner)
+    }
 }
 
 // 实现 rust 惯用语("idiom") 方便用户使用
@@ -129,7 +370,7 @@ impl<const LBW: usize> TryFrom<AmfUtf8<LBW>> for String {
     type Error = AmfError;
 
     fn try_from(value: AmfUtf8<LBW>) -> Result<Self, Self::Error> {
-        Ok(value.inner)
+        Ok(value.inner.into_string())
     }
 }
 
@@ -143,7 +384,7 @@ impl<const LBW: usize> TryFrom<&str> for AmfUtf8<LBW> {
 
 impl<const LBW: usize> AsRef<str> for AmfUtf8<LBW> {
     fn as_ref(&self) -> &str {
-        self.inner.as_ref()
+        &self.inner
     }
 }
 impl<const LBW: usize> Deref for AmfUtf8<LBW> {
@@ -161,7 +402,7 @@ impl<const LBW: usize> Borrow<str> for AmfUtf8<LBW> {
 
 impl<const LBW: usize> Display for AmfUtf8<LBW> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.inner)
+        write!(f, "{}", &*self.inner)
     }
 }
 
@@ -175,6 +416,8 @@ impl<const LBW: usize> Default for AmfUtf8<LBW> {
 
 pub type Utf8 = AmfUtf8<2>;
 pub type Utf8Long = AmfUtf8<4>;
+pub type CowUtf8<'a> = AmfCowUtf8<'a, 2>;
+pub type CowUtf8Long<'a> = AmfCowUtf8<'a, 4>;
 
 #[cfg(test)]
 mod tests {
@@ -182,12 +425,57 @@ mod tests {
     use crate::traits::{Marshall, MarshallLength, Unmarshall};
     use std::hash::{DefaultHasher, Hash, Hasher};
 
+    #[cfg(feature = "compact_str")]
+    #[test]
+    fn test_short_strings_are_inline() {
+        let (key, _) =
+            Utf8::unmarshall(&[0x00, 0x08, b'd', b'u', b'r', b'a', b't', b'i', b'o', b'n'])
+                .unwrap();
+        assert!(matches!(&key.inner, Repr::Owned(s) if !s.is_heap_allocated()));
+        assert_eq!(&*key, "duration");
+        let long = Utf8::new_from_str(&"k".repeat(64)).unwrap();
+        assert!(matches!(&long.inner, Repr::Owned(s) if s.is_heap_allocated()));
+        assert_eq!(String::try_from(long).unwrap(), "k".repeat(64));
+    }
+
+    #[test]
+    fn test_str_from_utf8_matches_std() {
+        // 足够长，覆盖 SIMD 的分块路径
+        let mut long = "数据".repeat(100).into_bytes();
+        assert_eq!(str_from_utf8(&long).unwrap(), "数据".repeat(100));
+        long[250] = 0xff;
+        let err = str_from_utf8(&long).unwrap_err();
+        assert_eq!(err, std::str::from_utf8(&long).unwrap_err());
+        assert_eq!(err.valid_up_to(), 249);
+        assert!(str_from_utf8(&[0xc3]).is_err());
+        assert_eq!(str_from_utf8(b"").unwrap(), "");
+    }
+
+    #[test]
+    fn test_cow_utf8_borrows_from_buffer() {
+        let data = [0x00, 0x03, b'a', b'b', b'c', 0xff];
+        let (s, n) = CowUtf8::unmarshall_borrowed(&data).unwrap();
+        assert_eq!(n, 5);
+        assert!(s.is_borrowed());
+        assert_eq!(&*s, "abc");
+        assert_eq!(s.marshall().unwrap(), &data[..5]);
+        assert_eq!(s.marshall_length(), 5);
+
+        let owned = s.into_owned();
+        assert_eq!(owned, Utf8::new_from_str("abc").unwrap());
+        let back = CowUtf8::from(owned);
+        assert!(!back.is_borrowed());
+
+        assert!(CowUtf8::unmarshall_borrowed(&data[..4]).is_err());
+        assert!(CowUtf8::new("a".repeat(u16::MAX as usize + 1)).is_err());
+    }
+
     // 测试有效字符串创建（LBW=2）
     #[test]
     fn new_valid_utf8_w2() {
         let s = "a".repeat(u16::MAX as usize);
         let amf_str = AmfUtf8::<2>::new_from_str(&s).unwrap();
-        assert_eq!(amf_str.inner, s);
+        assert_eq!(&*amf_str, s);
     }
 
     // 测试过长字符串创建（LBW=2）
@@ -205,7 +493,7 @@ mod tests {
     fn new_valid_utf8_w4() {
         let s = "a".repeat(1000); // 在u32范围内
         let amf_str = AmfUtf8::<4>::new_from_str(&s).unwrap();
-        assert_eq!(amf_str.inner, s);
+        assert_eq!(&*amf_str, s);
     }
 
     // 测试序列化（LBW=2）
@@ -232,7 +520,7 @@ mod tests {
     fn try_from_bytes_w2() {
         let data = [0x00, 0x05, b'h', b'e', b'l', b'l', b'o'];
         let (amf_str, consumed) = AmfUtf8::<2>::unmarshall(&data).unwrap();
-        assert_eq!(amf_str.inner, "hello");
+        assert_eq!(&*amf_str, "hello");
         assert_eq!(consumed, 7);
     }
 
@@ -241,7 +529,7 @@ mod tests {
     fn try_from_bytes_w4() {
         let data = [0x00, 0x00, 0x00, 0x05, b'w', b'o', b'r', b'l', b'd'];
         let (amf_str, consumed) = AmfUtf8::<4>::unmarshall(&data).unwrap();
-        assert_eq!(amf_str.inner, "world");
+        assert_eq!(&*amf_str, "world");
         assert_eq!(consumed, 9);
     }
 
@@ -260,7 +548,7 @@ mod tests {
     fn try_from_slice() {
         let data = [0x00, 0x03, b'f', b'o', b'o'];
         let amf_str: AmfUtf8<2> = data[..].try_into().unwrap();
-        assert_eq!(amf_str.inner, "foo");
+        assert_eq!(&*amf_str, "foo");
     }
 
     // 测试Deref和AsRef
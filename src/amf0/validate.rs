@@ -0,0 +1,196 @@
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::str_from_utf8;
+use crate::errors::{AmfError, checked_end};
+
+// Checks that `buf` starts with one structurally well-formed AMF 0 value (known markers,
+// lengths within the buffer, UTF-8 strings and keys, object ends, ECMA array counts) and returns
+// the number of bytes it occupies. Nothing is decoded: no strings, maps or values are built,
+// which makes this much cheaper than `unmarshall` for gateways that only forward payloads.
+// Follows the same rules as `unmarshall`; nesting is tracked on the heap, so deeply nested
+// input cannot overflow the stack.
+pub fn validate(buf: &[u8]) -> Result<usize, AmfError> {
+    Scanner { buf, offset: 0 }.scan()
+}
+
+const OBJECT_END: [u8; 3] = [0x00, 0x00, TypeMarker::ObjectEnd as u8];
+
+enum Frame {
+    // 剩余的元素个数
+    StrictArray(u32),
+    // ECMA array 声明的个数(Object 为 None)，以及已经读到的属性个数
+    Properties(Option<u32>, u32),
+}
+
+struct Scanner<'b> {
+    buf: &'b [u8],
+    offset: usize,
+}
+
+impl Scanner<'_> {
+    fn scan(&mut self) -> Result<usize, AmfError> {
+        let mut stack = Vec::new();
+        if let Some(frame) = self.value()? {
+            stack.push(frame);
+        }
+        while let Some(frame) = stack.last_mut() {
+            match frame {
+                Frame::StrictArray(0) => {
+                    stack.pop();
+                    continue;
+                }
+                Frame::StrictArray(remaining) => *remaining -= 1,
+                Frame::Properties(declared, count) => {
+                    if self.buf[self.offset..].starts_with(&OBJECT_END) {
+                        self.offset += 3;
+                        if let Some(declared) = *declared
+                            && declared != *count
+                        {
+                            return Err(AmfError::EcmaArrayCountMismatch {
+                                declared,
+                                actual: *count as usize,
+                            });
+                        }
+                        stack.pop();
+                        continue;
+                    }
+                    *count = count.saturating_add(1);
+                    let length = u16::from_be_bytes(self.read_array()?) as usize;
+                    self.utf8(length)?;
+                }
+            }
+            if let Some(frame) = self.value()? {
+                stack.push(frame);
+            }
+        }
+        Ok(self.offset)
+    }
+
+    // 跳过一个标量值；容器只读取头部，返回需要压栈的 frame
+    fn value(&mut self) -> Result<Option<Frame>, AmfError> {
+        let marker = self.take(1)?[0];
+        match TypeMarker::try_from(marker)? {
+            TypeMarker::Number => self.skip(8)?,
+            TypeMarker::Boolean => self.skip(1)?,
+            TypeMarker::String => {
+                let length = u16::from_be_bytes(self.read_array()?) as usize;
+                self.utf8(length)?;
+            }
+            TypeMarker::LongString => {
+                let length = u32::from_be_bytes(self.read_array()?) as usize;
+                self.utf8(length)?;
+            }
+            TypeMarker::Null | TypeMarker::Undefined => {}
+            TypeMarker::Object => return Ok(Some(Frame::Properties(None, 0))),
+            TypeMarker::EcmaArray => {
+                let declared = u32::from_be_bytes(self.read_array()?);
+                return Ok(Some(Frame::Properties(Some(declared), 0)));
+            }
+            TypeMarker::StrictArray => {
+                let count = u32::from_be_bytes(self.read_array()?);
+                return Ok(Some(Frame::StrictArray(count)));
+            }
+            TypeMarker::ObjectEnd => return Err(AmfError::InvalidObjectEnd),
+            _ => return Err(AmfError::UnsupportedTypeMarker(marker)),
+        }
+        Ok(None)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&[u8], AmfError> {
+        let end = checked_end(self.offset, n)?;
+        if self.buf.len() < end {
+            return Err(AmfError::BufferTooSmall {
+                want: end,
+                got: self.buf.len(),
+            });
+        }
+        let bytes = &self.buf[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, n: usize) -> Result<(), AmfError> {
+        self.take(n).map(|_| ())
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], AmfError> {
+        Ok(self.take(N)?.try_into().unwrap()) // take 保证了长度
+    }
+
+    fn utf8(&mut self, length: usize) -> Result<(), AmfError> {
+        str_from_utf8(self.take(length)?).map_err(AmfError::InvalidUtf8)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::amf0::nested::Amf0TypedValue;
+    use crate::traits::{Marshall, Unmarshall};
+
+    #[test]
+    fn test_validate_matches_unmarshall() {
+        let value = amf0!({
+            "duration": 12.5,
+            "encoder": "Lavf60",
+            "keyframes": {"times": [0.0, 2.0], "tag": undefined},
+            "empty": [],
+            "cover": null,
+        });
+        let ecma = Amf0TypedValue::EcmaArray(value.as_object().unwrap().clone().into_ecma_array());
+        for v in [value, ecma, amf0!(true), amf0!([[[]]])] {
+            let mut data = v.marshall().unwrap();
+            data.push(0xff); // 后面的数据不属于这个值
+            assert_eq!(validate(&data).unwrap(), data.len() - 1);
+
+            // 每一个截断的前缀都和 unmarshall 一样被拒绝
+            for end in 0..data.len() - 1 {
+                assert!(validate(&data[..end]).is_err(), "prefix of {} bytes", end);
+                assert!(Amf0TypedValue::unmarshall(&data[..end]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_errors() {
+        assert!(matches!(
+            validate(&[0x02, 0x00, 0x02, 0xc3, 0x28]),
+            Err(AmfError::InvalidUtf8(_))
+        ));
+        assert!(matches!(
+            validate(&[0x42]),
+            Err(AmfError::InvalidTypeMarker(0x42))
+        ));
+        assert!(matches!(
+            validate(&[TypeMarker::Date as u8]),
+            Err(AmfError::UnsupportedTypeMarker(0x0b))
+        ));
+        assert!(matches!(validate(&[0x09]), Err(AmfError::InvalidObjectEnd)));
+        // ECMA array 声明 2 个属性，实际只有 1 个
+        let data = [0x08, 0, 0, 0, 2, 0x00, 0x01, b'a', 0x05, 0x00, 0x00, 0x09];
+        assert!(matches!(
+            validate(&data),
+            Err(AmfError::EcmaArrayCountMismatch {
+                declared: 2,
+                actual: 1
+            })
+        ));
+        // 声明了巨大的元素个数也不会预先分配
+        assert!(matches!(
+            validate(&[0x0a, 0xff, 0xff, 0xff, 0xff, 0x05]),
+            Err(AmfError::BufferTooSmall { .. })
+        ));
+    }
+
+    #[test]
+    fn test_deep_nesting_does_not_overflow_stack() {
+        let depth = 1_000_000;
+        let mut data = Vec::with_capacity(depth * 5);
+        for _ in 0..depth {
+            data.extend_from_slice(&[0x0a, 0, 0, 0, 1]);
+        }
+        data.push(0x05);
+        assert_eq!(validate(&data).unwrap(), data.len());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::amf0::nested::Amf0TypedValue;
+use std::fmt::{Display, Formatter};
+
+// One step from a container to one of its children: a property key of an Object or ECMA
+// array, or an index into a strict array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+// The location of a value inside the tree being walked. `Display` renders it in the syntax
+// accepted by `Amf0TypedValue::get_path`, e.g. `keyframes.times[3]`; the root is "".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Path<'a> {
+    segments: Vec<PathSegment<'a>>,
+}
+
+impl<'a> Path<'a> {
+    pub fn segments(&self) -> &[PathSegment<'a>] {
+        &self.segments
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    // The key or index under which the current value is stored in its parent.
+    pub fn last(&self) -> Option<&PathSegment<'a>> {
+        self.segments.last()
+    }
+
+    pub(crate) fn push(&mut self, segment: PathSegment<'a>) {
+        self.segments.push(segment);
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.segments.pop();
+    }
+}
+
+impl Display for Path<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) if i == 0 => write!(f, "{}", key)?,
+                PathSegment::Key(key) => write!(f, ".{}", key)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Callbacks invoked by `Amf0TypedValue::walk`, in document order. Objects and ECMA arrays go
+// through `enter_object`/`leave_object`, strict arrays through `enter_array`/`leave_array`,
+// and every other value through `visit_scalar`. All methods default to doing nothing, so a
+// visitor only implements what it needs.
+pub trait Amf0Visitor {
+    fn enter_object(&mut self, _path: &Path<'_>, _value: &Amf0TypedValue) {}
+
+    fn leave_object(&mut self, _path: &Path<'_>, _value: &Amf0TypedValue) {}
+
+    fn enter_array(&mut self, _path: &Path<'_>, _value: &Amf0TypedValue) {}
+
+    fn leave_array(&mut self, _path: &Path<'_>, _value: &Amf0TypedValue) {}
+
+    fn visit_scalar(&mut self, _path: &Path<'_>, _value: &Amf0TypedValue) {}
+}
+
+impl Amf0TypedValue {
+    // Walks the whole tree depth-first, calling back into `visitor` with each value and its
+    // path from `self`.
+    pub fn walk<V: Amf0Visitor + ?Sized>(&self, visitor: &mut V) {
+        let mut path = Path::default();
+        walk_value(self, &mut path, visitor);
+    }
+}
+
+fn walk_value<'a, V: Amf0Visitor + ?Sized>(
+    value: &'a Amf0TypedValue,
+    path: &mut Path<'a>,
+    visitor: &mut V,
+) {
+    match value {
+        Amf0TypedValue::Object(properties) => {
+            visitor.enter_object(path, value);
+            walk_properties(properties.iter(), path, visitor);
+            visitor.leave_object(path, value);
+        }
+        Amf0TypedValue::EcmaArray(properties) => {
+            visitor.enter_object(path, value);
+            walk_properties(properties.iter(), path, visitor);
+            visitor.leave_object(path, value);
+        }
+        Amf0TypedValue::StrictArray(values) => {
+            visitor.enter_array(path, value);
+            for (i, v) in values.iter().enumerate() {
+                path.segments.push(PathSegment::Index(i));
+                walk_value(v, path, visitor);
+                path.segments.pop();
+            }
+            visitor.leave_array(path, value);
+        }
+        _ => visitor.visit_scalar(path, value),
+    }
+}
+
+fn walk_properties<'a, I, K, V>(properties: I, path: &mut Path<'a>, visitor: &mut V)
+where
+    I: Iterator<Item = (&'a K, &'a Amf0TypedValue)>,
+    K: AsRef<str> + 'a,
+    V: Amf0Visitor + ?Sized,
+{
+    for (k, v) in properties {
+        path.segments.push(PathSegment::Key(k.as_ref()));
+        walk_value(v, path, visitor);
+        path.segments.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl Amf0Visitor for Recorder {
+        fn enter_object(&mut self, path: &Path<'_>, _value: &Amf0TypedValue) {
+            self.events.push(format!("enter_object {}", path));
+        }
+
+        fn leave_object(&mut self, path: &Path<'_>, _value: &Amf0TypedValue) {
+            self.events.push(format!("leave_object {}", path));
+        }
+
+        fn enter_array(&mut self, path: &Path<'_>, _value: &Amf0TypedValue) {
+            self.events.push(format!("enter_array {}", path));
+        }
+
+        fn leave_array(&mut self, path: &Path<'_>, _value: &Amf0TypedValue) {
+            self.events.push(format!("leave_array {}", path));
+        }
+
+        fn visit_scalar(&mut self, path: &Path<'_>, value: &Amf0TypedValue) {
+            self.events.push(format!("scalar {} = {}", path, value));
+        }
+    }
+
+    #[test]
+    fn test_walk_order_and_paths() {
+        let value = amf0!({
+            "duration": 12.5,
+            "keyframes": {"times": [0.0, 2.0]},
+            "tags": [{"name": "a"}],
+        });
+        let mut recorder = Recorder::default();
+        value.walk(&mut recorder);
+        assert_eq!(
+            recorder.events,
+            vec![
+                "enter_object ",
+                "scalar duration = 12.5",
+                "enter_object keyframes",
+                "enter_array keyframes.times",
+                "scalar keyframes.times[0] = 0",
+                "scalar keyframes.times[1] = 2",
+                "leave_array keyframes.times",
+                "leave_object keyframes",
+                "enter_array tags",
+                "enter_object tags[0]",
+                "scalar tags[0].name = \"a\"",
+                "leave_object tags[0]",
+                "leave_array tags",
+                "leave_object ",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paths_resolve_with_get_path() {
+        // 访问者得到的路径可以直接用于 get_path
+        struct Check<'v> {
+            root: &'v Amf0TypedValue,
+            count: usize,
+        }
+        impl Amf0Visitor for Check<'_> {
+            fn visit_scalar(&mut self, path: &Path<'_>, value: &Amf0TypedValue) {
+                assert_eq!(self.root.get_path(&path.to_string()), Some(value));
+                self.count += 1;
+            }
+        }
+
+        let value = amf0!({"a": [[1.0, {"b": null}]], "c": true});
+        let mut check = Check {
+            root: &value,
+            count: 0,
+        };
+        value.walk(&mut check);
+        assert_eq!(check.count, 3);
+    }
+
+    #[test]
+    fn test_scalar_root() {
+        let mut recorder = Recorder::default();
+        amf0!("x").walk(&mut recorder);
+        assert_eq!(recorder.events, vec!["scalar  = \"x\""]);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::amf0::DecodeOptions;
+use crate::amf0::boolean::BooleanType;
+use crate::amf0::marker::{NullType, UndefinedType};
+use crate::amf0::nested::{Amf0TypedValue, ObjectType};
+use crate::amf0::number::NumberType;
+use crate::amf0::raw::RawValue;
+use crate::amf0::strict_array::StrictArrayType;
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use crate::traits::Marshall;
+use indexmap::IndexMap;
+use js_sys::{Array, Date, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+// JavaScript value -> Amf0TypedValue:
+//
+//	null / undefined -> Null / Undefined
+//	boolean          -> Boolean
+//	number           -> Number
+//	string           -> String, or LongString above 65535 bytes (UTF-8 length)
+//	Array            -> StrictArray
+//	Date             -> Date (kept as `Amf0TypedValue::Raw`, milliseconds since the epoch
+//	                    and a zero time zone, which is what Flash Player writes)
+//	other objects    -> Object, with their own enumerable string-keyed properties
+//
+// Symbols, BigInts and functions are rejected.
+impl TryFrom<&JsValue> for Amf0TypedValue {
+    type Error = AmfError;
+
+    fn try_from(value: &JsValue) -> Result<Self, Self::Error> {
+        if value.is_null() {
+            Ok(Amf0TypedValue::Null(NullType))
+        } else if value.is_undefined() {
+            Ok(Amf0TypedValue::Undefined(UndefinedType))
+        } else if let Some(v) = value.as_bool() {
+            Ok(Amf0TypedValue::Boolean(BooleanType::new(v)))
+        } else if let Some(v) = value.as_f64() {
+            Ok(Amf0TypedValue::Number(NumberType::new(v)))
+        } else if let Some(v) = value.as_string() {
+            Amf0TypedValue::string(v)
+        } else if Array::is_array(value) {
+            Ok(Amf0TypedValue::StrictArray(StrictArrayType::new(
+                Array::from(value)
+                    .iter()
+                    .map(|v| Amf0TypedValue::try_from(&v))
+                    .collect::<Result<_, _>>()?,
+            )))
+        } else if let Some(date) = value.dyn_ref::<Date>() {
+            Ok(Amf0TypedValue::Raw(date_to_raw(date.get_time())))
+        } else if let Some(object) = value.dyn_ref::<Object>().filter(|_| value.is_object()) {
+            let entries = Object::entries(object);
+            let mut properties = IndexMap::with_capacity(entries.length() as usize);
+            for entry in entries.iter() {
+                let entry = Array::from(&entry);
+                // Object.entries 的 key 总是字符串
+                let key = entry.get(0).as_string().unwrap_or_default();
+                properties.insert(Utf8::new(key)?, Amf0TypedValue::try_from(&entry.get(1))?);
+            }
+            Ok(Amf0TypedValue::Object(ObjectType::new(properties)))
+        } else {
+            Err(AmfError::TypeMismatch {
+                want: "AMF 0 value",
+                got: "JavaScript value",
+            })
+        }
+    }
+}
+
+impl TryFrom<JsValue> for Amf0TypedValue {
+    type Error = AmfError;
+
+    fn try_from(value: JsValue) -> Result<Self, Self::Error> {
+        Amf0TypedValue::try_from(&value)
+    }
+}
+
+// Amf0TypedValue -> JavaScript value, the reverse of the mapping above. Objects and ECMA
+// arrays both become plain objects (in property order), a `Raw` Date becomes a `Date`. The
+// other unsupported variants are rejected.
+impl TryFrom<&Amf0TypedValue> for JsValue {
+    type Error = AmfError;
+
+    fn try_from(value: &Amf0TypedValue) -> Result<Self, Self::Error> {
+        match value {
+            Amf0TypedValue::Null(_) => Ok(JsValue::NULL),
+            Amf0TypedValue::Undefined(_) => Ok(JsValue::UNDEFINED),
+            Amf0TypedValue::Boolean(v) => Ok(JsValue::from_bool(**v)),
+            Amf0TypedValue::Number(v) => Ok(JsValue::from_f64(**v)),
+            Amf0TypedValue::String(v) => Ok(JsValue::from_str(v)),
+            Amf0TypedValue::LongString(v) => Ok(JsValue::from_str(v)),
+            Amf0TypedValue::StrictArray(v) => {
+                let array = Array::new_with_length(v.len() as u32);
+                for (i, value) in v.iter().enumerate() {
+                    array.set(i as u32, JsValue::try_from(value)?);
+                }
+                Ok(array.into())
+            }
+            Amf0TypedValue::Object(v) => properties_to_js(v.iter()),
+            Amf0TypedValue::EcmaArray(v) => properties_to_js(v.iter()),
+            Amf0TypedValue::Raw(raw) => match raw_to_millis(raw) {
+                Some(millis) => Ok(Date::new(&JsValue::from_f64(millis)).into()),
+                None => Err(no_js_representation(value)),
+            },
+            other => Err(no_js_representation(other)),
+        }
+    }
+}
+
+fn no_js_representation(value: &Amf0TypedValue) -> AmfError {
+    AmfError::TypeMismatch {
+        want: "JavaScript value",
+        got: value.kind_name(),
+    }
+}
+
+impl TryFrom<Amf0TypedValue> for JsValue {
+    type Error = AmfError;
+
+    fn try_from(value: Amf0TypedValue) -> Result<Self, Self::Error> {
+        JsValue::try_from(&value)
+    }
+}
+
+// Decodes every AMF 0 value in `bytes` (e.g. the event name and data of an FLV script tag) into
+// a JavaScript array. Exported to JavaScript as `decodeAmf0`.
+#[wasm_bindgen(js_name = decodeAmf0)]
+pub fn decode_amf0(bytes: &[u8]) -> Result<Array, JsError> {
+    // Date 以 Raw 的形式解码，再转换为 JavaScript 的 Date
+    let options = DecodeOptions {
+        raw_unsupported: true,
+        ..DecodeOptions::default()
+    };
+    let values = Array::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let (value, n) = Amf0TypedValue::unmarshall_with(&bytes[offset..], &options)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        values.push(&JsValue::try_from(&value).map_err(|e| JsError::new(&e.to_string()))?);
+        offset += n;
+    }
+    Ok(values)
+}
+
+// Encodes one JavaScript value as AMF 0. Exported to JavaScript as `encodeAmf0`.
+#[wasm_bindgen(js_name = encodeAmf0)]
+pub fn encode_amf0(value: &JsValue) -> Result<Vec<u8>, JsError> {
+    Amf0TypedValue::try_from(value)
+        .and_then(|v| v.marshall())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+// AMF 0 Date: 8 字节的毫秒数(f64) + 2 字节的时区(保留，应为 0)
+fn date_to_raw(millis: f64) -> RawValue {
+    let mut bytes = millis.to_be_bytes().to_vec();
+    bytes.extend_from_slice(&[0, 0]);
+    RawValue::new(TypeMarker::Date as u8, bytes)
+}
+
+fn raw_to_millis(raw: &RawValue) -> Option<f64> {
+    if raw.marker != TypeMarker::Date as u8 || raw.bytes.len() != 10 {
+        return None;
+    }
+    Some(f64::from_be_bytes(raw.bytes[..8].try_into().unwrap()))
+}
+
+fn properties_to_js<'a, I>(properties: I) -> Result<JsValue, AmfError>
+where
+    I: IntoIterator<Item = (&'a Utf8, &'a Amf0TypedValue)>,
+{
+    let object = Object::new();
+    for (key, value) in properties {
+        // 在普通对象上设置属性不会失败
+        Reflect::set(&object, &JsValue::from_str(key), &JsValue::try_from(value)?)
+            .map_err(|e| AmfError::Custom(format!("Failed to set property {}: {:?}", key, e)))?;
+    }
+    Ok(object.into())
+}
+
+// JsValue 只能在 wasm32 上使用，这里只测试不依赖 JavaScript 的部分
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_matches_raw_decoding() {
+        let raw = date_to_raw(1_700_000_000_000.0);
+        let encoded = raw.marshall().unwrap();
+        let options = DecodeOptions {
+            raw_unsupported: true,
+            ..DecodeOptions::default()
+        };
+        let (decoded, n) = Amf0TypedValue::unmarshall_with(&encoded, &options).unwrap();
+        assert_eq!(n, 11);
+        let Amf0TypedValue::Raw(decoded) = decoded else {
+            panic!("expected raw value")
+        };
+        assert_eq!(raw_to_millis(&decoded), Some(1_700_000_000_000.0));
+        assert_eq!(raw_to_millis(&RawValue::new(0x07, vec![0, 1])), None);
+    }
+}
@@ -1,15 +1,256 @@
+use crate::schema::SchemaError;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 use std::io;
 
 #[derive(Debug)]
 pub enum AmfError {
-    BufferTooSmall { want: usize, got: usize },
-    StringTooLong { max: usize, got: usize },
+    BufferTooSmall {
+        want: usize,
+        got: usize,
+    },
+    StringTooLong {
+        max: usize,
+        got: usize,
+    },
     InvalidUtf8(std::str::Utf8Error),
-    TypeMarkerValueMismatch { want: u8, got: u8 },
+    TypeMarkerValueMismatch {
+        want: u8,
+        got: u8,
+    },
+    // A byte that is not an AMF 0 type marker at all.
+    InvalidTypeMarker(u8),
+    // A valid type marker for a type this decoder cannot decode (MovieClip, Reference, ...).
+    UnsupportedTypeMarker(u8),
+    // An object end marker outside of an object, or one not preceded by an empty key.
+    InvalidObjectEnd,
+    EcmaArrayCountMismatch {
+        declared: u32,
+        actual: usize,
+    },
+    DuplicateKey(String),
+    // A deviation from the specification rejected in `ParseMode::Strict`.
+    Deviation(AmfWarning),
+    LimitExceeded {
+        limit: &'static str,
+        max: usize,
+        got: usize,
+    },
+    TrailingBytes {
+        consumed: usize,
+        remaining: usize,
+    },
+    // `offset + length` does not fit in a usize. Only reachable on 32-bit targets, where a u32
+    // length read from the input can wrap the arithmetic and bypass the bounds checks.
+    LengthOverflow {
+        offset: usize,
+        length: usize,
+    },
+    // Nothing at the path of the enclosing `Context`: a missing property or array element.
+    MissingValue,
+    // A value of another type than required. Both are `Amf0TypedValue::kind_name`s, or the
+    // kind of value a conversion needs ("JSON value").
+    TypeMismatch {
+        want: &'static str,
+        got: &'static str,
+    },
+    // A TypedObject of another class than the one being decoded.
+    ClassNameMismatch {
+        want: Option<String>,
+        got: String,
+    },
+    // A `set_path` index more than one past the end of a strict array.
+    IndexOutOfBounds {
+        index: usize,
+        len: usize,
+    },
+    // A path that is not in `Amf0TypedValue::get_path` syntax.
+    InvalidPath(String),
+    // An integer outside ±(2^53 - 1), which a Number cannot hold exactly.
+    InexactInteger(i128),
+    // A Number that `target` cannot represent: one with a fraction or out of range for an
+    // integer type, or NaN/infinity for JSON.
+    NumberOutOfRange {
+        value: f64,
+        target: &'static str,
+    },
+    // A length that does not fit in the field it is encoded in, e.g. a strict array with more
+    // than u32::MAX elements.
+    TooLong {
+        what: &'static str,
+        max: usize,
+        got: usize,
+    },
+    // A header or framing field of a container format (FLV, .sol, RTMP, HTTP) with a value the
+    // format does not allow. `field` names it, e.g. "signature".
+    InvalidHeader {
+        format: &'static str,
+        field: &'static str,
+    },
+    // A format version this crate cannot read, e.g. the AMF version of a .sol file.
+    UnsupportedVersion {
+        format: &'static str,
+        version: u32,
+    },
+    // An RTMP message type id of another kind of message than the one being decoded.
+    UnexpectedMessageType(u8),
+    // A command or remoting message of another kind than the one being decoded, e.g. a `play`
+    // command where a response was expected. Holds the command name or target URI.
+    UnexpectedCommand(String),
+    // A remoting gateway that answered with a non-success HTTP status.
+    HttpStatus(u16),
+    // Every mismatch found by `Schema::validate`, in document order.
+    SchemaMismatch(Vec<SchemaError>),
     Custom(String),
     Io(io::Error),
+    // Where a failure happened: the path of the offending value in `Amf0TypedValue::get_path`
+    // syntax, e.g. `keyframes.times[2]`, and for decode errors the offset of the value (or
+    // property key) in the input.
+    Context {
+        offset: Option<usize>,
+        path: String,
+        source: Box<AmfError>,
+    },
+}
+
+// The failure class of an `AmfError`, for callers that need to react programmatically (e.g. wait
+// for more data on `Truncated`, drop the connection on anything else).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    // The input ended before the value did.
+    Truncated,
+    StringTooLong,
+    InvalidUtf8,
+    InvalidTypeMarker,
+    UnexpectedTypeMarker,
+    UnsupportedType,
+    InvalidObjectEnd,
+    CountMismatch,
+    DuplicateKey,
+    // A Boolean byte other than 0/1 or a LongString that fits in a String.
+    NonCanonical,
+    LimitExceeded,
+    TrailingBytes,
+    MissingValue,
+    TypeMismatch,
+    InvalidPath,
+    // A number or index outside the range of its target.
+    OutOfRange,
+    // A length too large for the field it is encoded in.
+    TooLong,
+    InvalidHeader,
+    // A message of another type or command than expected.
+    UnexpectedMessage,
+    SchemaMismatch,
+    Io,
+    Other,
+}
+
+impl AmfError {
+    // Classifies the error, looking through `Context`.
+    pub fn kind(&self) -> ErrorKind {
+        match self.without_context() {
+            AmfError::BufferTooSmall { .. } => ErrorKind::Truncated,
+            AmfError::StringTooLong { .. } => ErrorKind::StringTooLong,
+            AmfError::InvalidUtf8(_) => ErrorKind::InvalidUtf8,
+            AmfError::TypeMarkerValueMismatch { .. } => ErrorKind::UnexpectedTypeMarker,
+            AmfError::InvalidTypeMarker(_) => ErrorKind::InvalidTypeMarker,
+            AmfError::UnsupportedTypeMarker(_) => ErrorKind::UnsupportedType,
+            AmfError::InvalidObjectEnd => ErrorKind::InvalidObjectEnd,
+            AmfError::EcmaArrayCountMismatch { .. } => ErrorKind::CountMismatch,
+            AmfError::DuplicateKey(_) => ErrorKind::DuplicateKey,
+            AmfError::Deviation(AmfWarning::EcmaArrayCountMismatch { .. }) => {
+                ErrorKind::CountMismatch
+            }
+            AmfError::Deviation(_) => ErrorKind::NonCanonical,
+            AmfError::LimitExceeded { .. } | AmfError::LengthOverflow { .. } => {
+                ErrorKind::LimitExceeded
+            }
+            AmfError::TrailingBytes { .. } => ErrorKind::TrailingBytes,
+            AmfError::MissingValue => ErrorKind::MissingValue,
+            AmfError::TypeMismatch { .. } | AmfError::ClassNameMismatch { .. } => {
+                ErrorKind::TypeMismatch
+            }
+            AmfError::InvalidPath(_) => ErrorKind::InvalidPath,
+            AmfError::IndexOutOfBounds { .. }
+            | AmfError::InexactInteger(_)
+            | AmfError::NumberOutOfRange { .. } => ErrorKind::OutOfRange,
+            AmfError::TooLong { .. } => ErrorKind::TooLong,
+            AmfError::InvalidHeader { .. } => ErrorKind::InvalidHeader,
+            AmfError::UnsupportedVersion { .. } => ErrorKind::UnsupportedType,
+            AmfError::UnexpectedMessageType(_)
+            | AmfError::UnexpectedCommand(_)
+            | AmfError::HttpStatus(_) => ErrorKind::UnexpectedMessage,
+            AmfError::SchemaMismatch(_) => ErrorKind::SchemaMismatch,
+            // 流式解码时数据不完整表现为 UnexpectedEof
+            AmfError::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => ErrorKind::Truncated,
+            AmfError::Io(_) => ErrorKind::Io,
+            AmfError::Custom(_) | AmfError::Context { .. } => ErrorKind::Other,
+        }
+    }
+
+    // The error without any location context, for matching on what went wrong.
+    pub fn without_context(&self) -> &AmfError {
+        match self {
+            AmfError::Context { source, .. } => source.without_context(),
+            other => other,
+        }
+    }
+
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            AmfError::Context { offset, .. } => *offset,
+            _ => None,
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            AmfError::Context { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    // Locates the error at `path` inside a value, for errors found while reading or converting
+    // a decoded value rather than while decoding bytes.
+    pub(crate) fn at_path(self, path: &str) -> AmfError {
+        AmfError::Context {
+            offset: None,
+            path: path.to_string(),
+            source: Box::new(self),
+        }
+    }
+
+    // Locates the error at property `key`, in front of the path it already has: an error at
+    // `times[2]` inside property `keyframes` is at `keyframes.times[2]`.
+    pub(crate) fn in_property(self, key: &str) -> AmfError {
+        match self {
+            AmfError::Context {
+                offset,
+                path,
+                source,
+            } => {
+                let path = match path.chars().next() {
+                    None => key.to_string(),
+                    Some('[') => format!("{}{}", key, path),
+                    Some(_) => format!("{}.{}", key, path),
+                };
+                AmfError::Context {
+                    offset,
+                    path,
+                    source,
+                }
+            }
+            other => other.at_path(key),
+        }
+    }
+}
+
+// The end of a `length`-byte field starting at `offset`, where `length` comes from the input.
+pub(crate) fn checked_end(offset: usize, length: usize) -> Result<usize, AmfError> {
+    offset
+        .checked_add(length)
+        .ok_or(AmfError::LengthOverflow { offset, length })
 }
 
 impl Display for AmfError {
@@ -27,12 +268,179 @@ impl Display for AmfError {
             AmfError::TypeMarkerValueMismatch { want, got } => {
                 write!(f, "Type marker value mismatch: want {}, got {}", want, got)
             }
+            AmfError::InvalidTypeMarker(marker) => {
+                write!(f, "Invalid type marker value: {}", marker)
+            }
+            AmfError::UnsupportedTypeMarker(marker) => {
+                write!(f, "Unsupported type marker: {}", marker)
+            }
+            AmfError::InvalidObjectEnd => write!(f, "Invalid object end marker"),
+            AmfError::EcmaArrayCountMismatch { declared, actual } => write!(
+                f,
+                "Invalid properties length, want {}, got {}",
+                declared, actual
+            ),
+            AmfError::DuplicateKey(key) => write!(f, "Duplicate property key \"{}\"", key),
+            AmfError::Deviation(warning) => write!(f, "{}", warning),
+            AmfError::LimitExceeded { limit, max, got } => {
+                write!(
+                    f,
+                    "Decode limit {} exceeded: max {}, got {}",
+                    limit, max, got
+                )
+            }
+            AmfError::TrailingBytes {
+                consumed,
+                remaining,
+            } => write!(
+                f,
+                "Trailing bytes after AMF 0 value: consumed {}, remaining {}",
+                consumed, remaining
+            ),
+            AmfError::LengthOverflow { offset, length } => write!(
+                f,
+                "Length overflow: {} bytes at offset {} exceed the address space",
+                length, offset
+            ),
+            AmfError::MissingValue => write!(f, "Missing value"),
+            AmfError::TypeMismatch { want, got } => {
+                write!(f, "Type mismatch: want {}, got {}", want, got)
+            }
+            AmfError::ClassNameMismatch { want, got } => {
+                write!(f, "Unexpected class name: want {:?}, got {:?}", want, got)
+            }
+            AmfError::IndexOutOfBounds { index, len } => {
+                write!(f, "Index {} is out of bounds for length {}", index, len)
+            }
+            AmfError::InvalidPath(path) => write!(f, "Invalid path \"{}\"", path),
+            AmfError::InexactInteger(value) => write!(
+                f,
+                "Integer {} cannot be represented exactly as a Number",
+                value
+            ),
+            AmfError::NumberOutOfRange { value, target } => {
+                write!(f, "Number {} does not fit in {}", value, target)
+            }
+            AmfError::TooLong { what, max, got } => {
+                write!(f, "{} too long: max {}, got {}", what, max, got)
+            }
+            AmfError::InvalidHeader { format, field } => {
+                write!(f, "Invalid {} header: bad {}", format, field)
+            }
+            AmfError::UnsupportedVersion { format, version } => {
+                write!(f, "Unsupported {} version: {}", format, version)
+            }
+            AmfError::UnexpectedMessageType(message_type) => {
+                write!(f, "Unexpected message type: {}", message_type)
+            }
+            AmfError::UnexpectedCommand(name) => write!(f, "Unexpected command: {}", name),
+            AmfError::HttpStatus(status) => write!(f, "Unexpected HTTP status: {}", status),
+            AmfError::SchemaMismatch(errors) => {
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                Ok(())
+            }
             AmfError::Custom(msg) => {
                 write!(f, "{}", msg)
             }
             AmfError::Io(err) => {
                 write!(f, "{}", err)
             }
+            AmfError::Context {
+                offset: None,
+                path,
+                source,
+            } => write!(f, "{} (at path {})", source, path),
+            AmfError::Context {
+                offset: Some(offset),
+                path,
+                source,
+            } if path.is_empty() => write!(f, "{} (at offset {})", source, offset),
+            AmfError::Context {
+                offset: Some(offset),
+                path,
+                source,
+            } => write!(f, "{} (at offset {}, path {})", source, offset, path),
+        }
+    }
+}
+
+// A deviation from the AMF 0 specification that was tolerated while decoding in
+// `ParseMode::Lenient`. In `ParseMode::Strict` the same deviation is a hard error. `offset` is
+// the position of the offending value's type marker in the decoded buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmfWarning {
+    // A Boolean whose value byte is neither 0 nor 1.
+    NonCanonicalBoolean {
+        offset: usize,
+        byte: u8,
+    },
+    // An ECMA array whose declared count differs from the number of properties it holds.
+    EcmaArrayCountMismatch {
+        offset: usize,
+        declared: u32,
+        actual: usize,
+    },
+    // A LongString short enough to have been written as a String.
+    NonMinimalString {
+        offset: usize,
+        length: usize,
+    },
+    // A String, LongString or property key that was not valid UTF-8 and was decoded according
+    // to `DecodeOptions::invalid_utf8`.
+    NonUtf8String {
+        offset: usize,
+    },
+    // `unmarshall_lossy` only: a value (or property key) that could not be decoded and was
+    // replaced by `Undefined` (or dropped, for a key).
+    UndecodableValue {
+        offset: usize,
+        kind: ErrorKind,
+    },
+    // `unmarshall_lossy` only: a container that was closed before its end because the rest of
+    // the input could not be decoded.
+    Truncated {
+        offset: usize,
+    },
+}
+
+impl Display for AmfWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AmfWarning::NonCanonicalBoolean { offset, byte } => {
+                write!(
+                    f,
+                    "Non-canonical boolean byte {} at offset {}",
+                    byte, offset
+                )
+            }
+            AmfWarning::EcmaArrayCountMismatch {
+                offset,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "ECMA array count mismatch at offset {}: declared {}, got {}",
+                offset, declared, actual
+            ),
+            AmfWarning::NonUtf8String { offset } => {
+                write!(f, "String at offset {} is not valid UTF-8", offset)
+            }
+            AmfWarning::UndecodableValue { offset, kind } => {
+                write!(f, "Undecodable value at offset {}: {:?}", offset, kind)
+            }
+            AmfWarning::Truncated { offset } => {
+                write!(f, "Container at offset {} is truncated", offset)
+            }
+            AmfWarning::NonMinimalString { offset, length } => write!(
+                f,
+                "LongString of {} bytes at offset {} fits in a String",
+                length, offset
+            ),
         }
     }
 }
@@ -49,7 +457,23 @@ impl Error for AmfError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             AmfError::Io(err) => Some(err),
+            AmfError::Context { source, .. } => Some(source.as_ref()),
             _ => None,
         }
     }
 }
+
+// serde 的 Serializer/Deserializer 要求错误类型可以由任意消息构造
+#[cfg(feature = "serde")]
+impl serde::ser::Error for AmfError {
+    fn custom<T: Display>(msg: T) -> Self {
+        AmfError::Custom(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for AmfError {
+    fn custom<T: Display>(msg: T) -> Self {
+        AmfError::Custom(msg.to_string())
+    }
+}
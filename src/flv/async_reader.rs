@@ -0,0 +1,194 @@
+use crate::errors::AmfError;
+use crate::flv::tag::{FlvHeader, FlvTag, RawTag, RawTagHeader};
+use futures_core::Stream;
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, ReadBuf};
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+// The async counterpart of `FlvReader`, for FLV arriving over a socket or an HTTP upload body.
+// `new` reads and validates the file header; the reader is then a `Stream` of tags (or use
+// `next_tag`), ending at EOF on a tag boundary and stopping after the first error.
+//
+// Like `AsyncDecoder`, bytes are buffered until a whole tag is available, so `next_tag` is
+// cancellation-safe: dropping the future (e.g. in `tokio::select!`) loses no data.
+#[derive(Debug)]
+pub struct AsyncFlvReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    header: FlvHeader,
+    offset: u64,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncFlvReader<R> {
+    pub async fn new(reader: R) -> Result<Self, AmfError> {
+        let mut this = Self {
+            reader,
+            buf: Vec::with_capacity(READ_CHUNK_SIZE),
+            header: FlvHeader::new(false, false),
+            offset: 0,
+            done: false,
+        };
+        if !poll_fn(|cx| this.poll_fill(cx, FlvHeader::LENGTH)).await? {
+            return Err(unexpected_eof(&this.buf));
+        }
+        let (header, data_offset) =
+            FlvHeader::parse(this.buf[..FlvHeader::LENGTH].try_into().unwrap())?;
+        // 头部之后的扩展数据和 PreviousTagSize0 都丢弃
+        let start = data_offset as usize + 4;
+        if !poll_fn(|cx| this.poll_fill(cx, start)).await? {
+            return Err(unexpected_eof(&this.buf));
+        }
+        this.buf.drain(..start);
+        this.header = header;
+        this.offset = start as u64;
+        Ok(this)
+    }
+
+    pub fn header(&self) -> &FlvHeader {
+        &self.header
+    }
+
+    // The byte offset of the next tag from the start of the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // The next tag, or `None` at the end of the input.
+    pub async fn next_tag(&mut self) -> Result<Option<FlvTag>, AmfError> {
+        poll_fn(|cx| self.poll_tag(cx)).await
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    // Returns the reader together with any bytes already buffered but not yet decoded.
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        (self.reader, self.buf)
+    }
+
+    fn poll_tag(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<FlvTag>, AmfError>> {
+        if !ready!(self.poll_fill(cx, FlvTag::HEADER_LENGTH))? {
+            if self.buf.is_empty() {
+                return Poll::Ready(Ok(None));
+            }
+            return Poll::Ready(Err(unexpected_eof(&self.buf)));
+        }
+        let header = RawTagHeader(self.buf[..FlvTag::HEADER_LENGTH].try_into().unwrap());
+        let length = FlvTag::HEADER_LENGTH + header.data_size() + 4;
+        if !ready!(self.poll_fill(cx, length))? {
+            return Poll::Ready(Err(unexpected_eof(&self.buf)));
+        }
+        let data = self.buf[FlvTag::HEADER_LENGTH..length - 4].to_vec();
+        self.buf.drain(..length);
+        self.offset += length as u64;
+        Poll::Ready(RawTag { header, data }.decode().map(Some))
+    }
+
+    // Reads until at least `want` bytes are buffered; false if the reader hits EOF first.
+    fn poll_fill(&mut self, cx: &mut Context<'_>, want: usize) -> Poll<Result<bool, AmfError>> {
+        while self.buf.len() < want {
+            // 直接读到 buf 的尾部，读到多少保留多少，所以中途取消不会丢数据
+            let filled = self.buf.len();
+            self.buf
+                .resize(filled + READ_CHUNK_SIZE.max(want - filled), 0);
+            let mut read_buf = ReadBuf::new(&mut self.buf[filled..]);
+            let result = Pin::new(&mut self.reader).poll_read(cx, &mut read_buf);
+            let n = read_buf.filled().len();
+            self.buf.truncate(filled + n);
+            ready!(result)?;
+            if n == 0 {
+                return Poll::Ready(Ok(false));
+            }
+        }
+        Poll::Ready(Ok(true))
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for AsyncFlvReader<R> {
+    type Item = Result<FlvTag, AmfError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        let result = ready!(self.poll_tag(cx)).transpose();
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        Poll::Ready(result)
+    }
+}
+
+fn unexpected_eof(buf: &[u8]) -> AmfError {
+    AmfError::Io(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!("EOF with {} bytes of incomplete FLV tag", buf.len()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::errors::ErrorKind;
+    use crate::flv::tag::TagType;
+    use crate::flv::writer::FlvWriter;
+
+    fn sample() -> Vec<u8> {
+        let mut writer = FlvWriter::new(Vec::new(), FlvHeader::new(true, true)).unwrap();
+        writer.write_metadata(&amf0!({"duration": 1.0})).unwrap();
+        writer.write_raw(TagType::Video, 0, &[0x17, 0x00]).unwrap();
+        writer.write_raw(TagType::Audio, 23, &[0x2f]).unwrap();
+        writer.into_inner()
+    }
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn test_stream_tags() {
+        let data = sample();
+        // duplex 的缓冲区很小，数据会被拆成多次读取
+        let (mut tx, rx) = tokio::io::duplex(7);
+        let writer = tokio::spawn(async move {
+            tokio::io::AsyncWriteExt::write_all(&mut tx, &data)
+                .await
+                .unwrap();
+        });
+
+        let mut reader = AsyncFlvReader::new(rx).await.unwrap();
+        assert_eq!(*reader.header(), FlvHeader::new(true, true));
+        let mut tags = Vec::new();
+        while let Some(tag) = next(&mut reader).await {
+            tags.push(tag.unwrap());
+        }
+        writer.await.unwrap();
+        assert_eq!(tags.len(), 3);
+        assert!(tags[0].data.as_script().unwrap().is_on_metadata());
+        assert_eq!(tags[1].tag_type, TagType::Video);
+        assert_eq!(tags[2].timestamp, 23);
+        assert_eq!(reader.offset(), sample().len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_tag() {
+        let mut data = sample();
+        data.truncate(data.len() - 2);
+        let mut reader = AsyncFlvReader::new(data.as_slice()).await.unwrap();
+        assert!(reader.next_tag().await.unwrap().is_some());
+        assert!(reader.next_tag().await.unwrap().is_some());
+        let err = next(&mut reader).await.unwrap().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert!(next(&mut reader).await.is_none());
+
+        let err = AsyncFlvReader::new(&b"FLV\x01"[..]).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+    }
+}
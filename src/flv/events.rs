@@ -0,0 +1,207 @@
+use crate::amf0::convert::{field, key, object_properties, string};
+use crate::amf0::nested::{Amf0TypedValue, EcmaArrayType, ObjectType};
+use crate::amf0::string::StringType;
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use crate::flv::metadata::OnMetaData;
+use crate::flv::script_data::{ON_CUE_POINT, ON_METADATA, ON_TEXT_DATA, ScriptTagBody};
+use indexmap::IndexMap;
+
+// The arguments of an onCuePoint event: a named point on the timeline (`time` in seconds) of
+// type "event" or "navigation", with free-form `parameters`. Unknown properties are kept in
+// `extra`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CuePoint {
+    pub name: String,
+    pub time: f64,
+    pub cue_type: Option<String>,
+    pub parameters: IndexMap<Utf8, Amf0TypedValue>,
+    pub extra: IndexMap<Utf8, Amf0TypedValue>,
+}
+
+// The arguments of an onTextData event (timed text, e.g. subtitles).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextData {
+    pub text: String,
+    pub track_id: Option<f64>,
+    pub extra: IndexMap<Utf8, Amf0TypedValue>,
+}
+
+// A script data tag body by event name: the events this crate knows are typed, anything else
+// is left as it was decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptEvent {
+    MetaData(OnMetaData),
+    CuePoint(CuePoint),
+    TextData(TextData),
+    Other(ScriptTagBody),
+}
+
+impl TryFrom<Amf0TypedValue> for CuePoint {
+    type Error = AmfError;
+
+    // Accepts an ECMA array or an Object.
+    fn try_from(value: Amf0TypedValue) -> Result<Self, Self::Error> {
+        let mut properties = object_properties(value)?;
+        let parameters: Option<Amf0TypedValue> = field(&mut properties, "parameters")?;
+        Ok(Self {
+            name: field(&mut properties, "name")?,
+            time: field(&mut properties, "time")?,
+            cue_type: field(&mut properties, "type")?,
+            parameters: parameters
+                .map(object_properties)
+                .transpose()?
+                .unwrap_or_default(),
+            extra: properties,
+        })
+    }
+}
+
+impl TryFrom<Amf0TypedValue> for TextData {
+    type Error = AmfError;
+
+    // Accepts an ECMA array or an Object.
+    fn try_from(value: Amf0TypedValue) -> Result<Self, Self::Error> {
+        let mut properties = object_properties(value)?;
+        Ok(Self {
+            text: field(&mut properties, "text")?,
+            track_id: field(&mut properties, "trackid")?,
+            extra: properties,
+        })
+    }
+}
+
+impl From<CuePoint> for EcmaArrayType {
+    fn from(value: CuePoint) -> Self {
+        let mut properties = IndexMap::new();
+        properties.insert(key("name"), string(value.name));
+        properties.insert(key("time"), Amf0TypedValue::from(value.time));
+        if let Some(cue_type) = value.cue_type {
+            properties.insert(key("type"), string(cue_type));
+        }
+        properties.insert(
+            key("parameters"),
+            Amf0TypedValue::Object(ObjectType::new(value.parameters)),
+        );
+        properties.extend(value.extra);
+        EcmaArrayType::new(properties)
+    }
+}
+
+impl From<TextData> for EcmaArrayType {
+    fn from(value: TextData) -> Self {
+        let mut properties = IndexMap::new();
+        properties.insert(key("text"), string(value.text));
+        if let Some(track_id) = value.track_id {
+            properties.insert(key("trackid"), Amf0TypedValue::from(track_id));
+        }
+        properties.extend(value.extra);
+        EcmaArrayType::new(properties)
+    }
+}
+
+impl TryFrom<ScriptTagBody> for ScriptEvent {
+    type Error = AmfError;
+
+    fn try_from(value: ScriptTagBody) -> Result<Self, Self::Error> {
+        match &**value.name {
+            ON_METADATA => OnMetaData::try_from(value.value).map(ScriptEvent::MetaData),
+            ON_CUE_POINT => CuePoint::try_from(value.value).map(ScriptEvent::CuePoint),
+            ON_TEXT_DATA => TextData::try_from(value.value).map(ScriptEvent::TextData),
+            _ => Ok(ScriptEvent::Other(value)),
+        }
+    }
+}
+
+impl From<ScriptEvent> for ScriptTagBody {
+    fn from(value: ScriptEvent) -> Self {
+        let (name, arguments): (&str, EcmaArrayType) = match value {
+            ScriptEvent::MetaData(v) => (ON_METADATA, v.into()),
+            ScriptEvent::CuePoint(v) => (ON_CUE_POINT, v.into()),
+            ScriptEvent::TextData(v) => (ON_TEXT_DATA, v.into()),
+            ScriptEvent::Other(body) => return body,
+        };
+        ScriptTagBody::new(
+            StringType::new_from_str(name).unwrap(), // 事件名都很短
+            Amf0TypedValue::EcmaArray(arguments),
+        )
+    }
+}
+
+impl From<CuePoint> for ScriptTagBody {
+    fn from(value: CuePoint) -> Self {
+        ScriptEvent::CuePoint(value).into()
+    }
+}
+
+impl From<TextData> for ScriptTagBody {
+    fn from(value: TextData) -> Self {
+        ScriptEvent::TextData(value).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::traits::{Marshall, Unmarshall};
+
+    #[test]
+    fn test_cue_point_round_trip() {
+        let cue = CuePoint {
+            name: "ad-break".to_string(),
+            time: 12.5,
+            cue_type: Some("event".to_string()),
+            parameters: [(key("duration"), amf0!(30.0))].into_iter().collect(),
+            extra: IndexMap::new(),
+        };
+        let body = ScriptTagBody::from(cue.clone());
+        assert_eq!(&**body.name, "onCuePoint");
+        assert_eq!(
+            body.value.get_path("parameters.duration"),
+            Some(&amf0!(30.0))
+        );
+
+        let (decoded, _) = ScriptTagBody::unmarshall(&body.marshall().unwrap()).unwrap();
+        assert_eq!(
+            ScriptEvent::try_from(decoded).unwrap(),
+            ScriptEvent::CuePoint(cue)
+        );
+    }
+
+    #[test]
+    fn test_script_events() {
+        let text = ScriptTagBody::new(
+            StringType::new_from_str(ON_TEXT_DATA).unwrap(),
+            amf0!({"text": "Hello", "trackid": 1.0, "lang": "en"}),
+        );
+        let ScriptEvent::TextData(data) = ScriptEvent::try_from(text).unwrap() else {
+            panic!("expected onTextData");
+        };
+        assert_eq!(data.text, "Hello");
+        assert_eq!(data.track_id, Some(1.0));
+        assert_eq!(data.extra["lang"], amf0!("en"));
+
+        let metadata = ScriptTagBody::on_metadata(EcmaArrayType::default());
+        assert!(matches!(
+            ScriptEvent::try_from(metadata).unwrap(),
+            ScriptEvent::MetaData(_)
+        ));
+
+        let other = ScriptTagBody::new(
+            StringType::new_from_str("|RtmpSampleAccess").unwrap(),
+            amf0!(true),
+        );
+        assert_eq!(
+            ScriptTagBody::from(ScriptEvent::try_from(other.clone()).unwrap()),
+            other
+        );
+
+        // name 和 time 是必需的
+        let cue = ScriptTagBody::new(
+            StringType::new_from_str(ON_CUE_POINT).unwrap(),
+            amf0!({"name": "x"}),
+        );
+        assert!(ScriptEvent::try_from(cue).is_err());
+    }
+}
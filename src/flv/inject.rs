@@ -0,0 +1,84 @@
+use crate::amf0::string::StringType;
+use crate::errors::AmfError;
+use crate::flv::metadata::OnMetaData;
+use crate::flv::reader::FlvReader;
+use crate::flv::script_data::ON_METADATA;
+use crate::flv::tag::RawTag;
+use crate::flv::writer::FlvWriter;
+use crate::traits::Unmarshall;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+// Copies the FLV file in `input` to `output` with `metadata` as its onMetaData tag, like
+// `flvmeta --update` does: the new tag is written first, every existing onMetaData tag is
+// dropped and all other tags are copied byte for byte, with their PreviousTagSize rewritten.
+// `input` is read from its start, so it can be reused after a first pass over the file (e.g. to
+// build the keyframe index). Keyframe file positions in `metadata` must refer to `output`.
+pub fn inject_metadata<R, W>(mut input: R, output: W, metadata: &OnMetaData) -> Result<(), AmfError>
+where
+    R: Read + Seek,
+    W: Write,
+{
+    input.seek(SeekFrom::Start(0))?;
+    let mut reader = FlvReader::new(input)?;
+    let mut writer = FlvWriter::new(output, *reader.header())?;
+    writer.write_on_metadata(metadata)?;
+    while let Some(tag) = reader.next_raw_tag()? {
+        if !is_on_metadata(&tag) {
+            writer.write_tag_bytes(&tag.header, &tag.data)?;
+        }
+    }
+    writer.flush()
+}
+
+// 只解码名字；名字无法解码的 script tag 原样保留
+fn is_on_metadata(tag: &RawTag) -> bool {
+    tag.header.is_script()
+        && StringType::unmarshall(&tag.data).is_ok_and(|(name, _)| **name == *ON_METADATA)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::flv::script_data::ScriptTagBody;
+    use crate::flv::tag::{FlvHeader, TagType};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_inject_replaces_metadata() {
+        let mut writer = FlvWriter::new(Vec::new(), FlvHeader::new(true, true)).unwrap();
+        writer.write_raw(TagType::Video, 0, &[0x17, 0x00]).unwrap();
+        writer
+            .write_metadata(&amf0!({"duration": 0.0, "stale": true}))
+            .unwrap();
+        let cue = ScriptTagBody::new(
+            StringType::new_from_str("onCuePoint").unwrap(),
+            amf0!({"name": "ad"}),
+        );
+        writer.write_script(40, &cue).unwrap();
+        writer.write_raw(TagType::Audio, 40, &[0x2f]).unwrap();
+        let input = writer.into_inner();
+
+        let metadata = OnMetaData {
+            duration: Some(93.4),
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut cursor = Cursor::new(input);
+        cursor.seek(SeekFrom::End(0)).unwrap(); // 从头读取，与当前位置无关
+        inject_metadata(cursor, &mut output, &metadata).unwrap();
+
+        let tags = FlvReader::new(Cursor::new(&output))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(tags.len(), 4);
+        let body = tags[0].data.as_script().unwrap();
+        assert!(body.is_on_metadata());
+        assert_eq!(OnMetaData::try_from(body.value.clone()).unwrap(), metadata);
+        assert_eq!(tags[1].tag_type, TagType::Video);
+        assert_eq!(tags[2].data.as_script(), Some(&cue));
+        assert_eq!(tags[2].timestamp, 40);
+        assert_eq!(tags[3].data.as_bytes(), Some(&[0x2f][..]));
+    }
+}
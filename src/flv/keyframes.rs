@@ -0,0 +1,132 @@
+use crate::amf0::convert::ToAmf0;
+use crate::amf0::nested::Amf0TypedValue;
+use crate::errors::AmfError;
+use crate::flv::metadata::Keyframes;
+use crate::flv::reader::FlvReader;
+use crate::flv::tag::{FlvTag, TagData, TagType};
+use std::io::Read;
+
+// VideoTagHeader 的 FrameType(高 4 位，Enhanced RTMP 中是第 4-6 位)为 1 表示关键帧
+const KEY_FRAME: u8 = 1;
+
+// Collects the time and file position of every video keyframe while scanning an FLV file, for
+// the `keyframes` object of onMetaData that players use to seek in files recorded from live
+// streams (which have none). Positions are the offset of the tag header in the scanned file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KeyframeIndex {
+    keyframes: Keyframes,
+}
+
+impl KeyframeIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Scans every tag of `reader`, from its current position to the end of the input.
+    pub fn scan<R: Read>(reader: &mut FlvReader<R>) -> Result<Self, AmfError> {
+        let mut index = Self::new();
+        loop {
+            let position = reader.offset();
+            match reader.next_tag()? {
+                Some(tag) => index.add_tag(&tag, position),
+                None => return Ok(index),
+            }
+        }
+    }
+
+    // Records `tag` if it is a video keyframe; `position` is the offset of its tag header.
+    pub fn add_tag(&mut self, tag: &FlvTag, position: u64) {
+        if let (TagType::Video, TagData::Raw(data)) = (tag.tag_type, &tag.data)
+            && data.first().is_some_and(|b| (b >> 4) & 0x07 == KEY_FRAME)
+        {
+            self.push(tag.timestamp as f64 / 1000.0, position);
+        }
+    }
+
+    // Records a keyframe at `time` seconds.
+    pub fn push(&mut self, time: f64, position: u64) {
+        self.keyframes.times.push(time);
+        self.keyframes.filepositions.push(position as f64);
+    }
+
+    // Moves every position by `delta` bytes, e.g. by the size difference between the old and
+    // the new onMetaData tag when the index is written into a rewritten file.
+    pub fn shift(&mut self, delta: i64) {
+        for position in &mut self.keyframes.filepositions {
+            *position += delta as f64;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keyframes.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.times.is_empty()
+    }
+
+    pub fn keyframes(&self) -> &Keyframes {
+        &self.keyframes
+    }
+
+    pub fn into_keyframes(self) -> Keyframes {
+        self.keyframes
+    }
+}
+
+// 输出标准的 keyframes 对象：{filepositions: [...], times: [...]}
+impl ToAmf0 for KeyframeIndex {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        self.keyframes.to_amf0()
+    }
+}
+
+impl From<KeyframeIndex> for Keyframes {
+    fn from(value: KeyframeIndex) -> Self {
+        value.into_keyframes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::flv::tag::FlvHeader;
+    use crate::flv::writer::FlvWriter;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_scan_keyframes() {
+        let mut writer = FlvWriter::new(Vec::new(), FlvHeader::new(true, true)).unwrap();
+        writer.write_metadata(&amf0!({"duration": 4.0})).unwrap();
+        let first = writer.get_ref().len() as u64;
+        writer.write_raw(TagType::Video, 0, &[0x17, 0x01]).unwrap();
+        writer.write_raw(TagType::Audio, 0, &[0x2f, 0x01]).unwrap();
+        writer.write_raw(TagType::Video, 40, &[0x27, 0x01]).unwrap();
+        writer.write_raw(TagType::Video, 80, &[0x57, 0x01]).unwrap(); // video info frame
+        let second = writer.get_ref().len() as u64;
+        // Enhanced RTMP: IsExHeader | FrameType 1
+        writer
+            .write_raw(TagType::Video, 2000, &[0x90, b'h', b'v', b'c', b'1'])
+            .unwrap();
+        let data = writer.into_inner();
+
+        let mut reader = FlvReader::new(Cursor::new(data)).unwrap();
+        let mut index = KeyframeIndex::scan(&mut reader).unwrap();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.keyframes().times, vec![0.0, 2.0]);
+        assert_eq!(
+            index.keyframes().filepositions,
+            vec![first as f64, second as f64]
+        );
+
+        index.shift(-10);
+        assert_eq!(
+            index.to_amf0().unwrap(),
+            amf0!({
+                "filepositions": [first as f64 - 10.0, second as f64 - 10.0],
+                "times": [0.0, 2.0],
+            })
+        );
+    }
+}
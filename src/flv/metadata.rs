@@ -0,0 +1,197 @@
+use crate::amf0::convert::{
+    FromAmf0, ToAmf0, field, key, object_properties, string, take_property,
+};
+use crate::amf0::nested::{Amf0TypedValue, EcmaArrayType, ObjectType};
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use indexmap::IndexMap;
+
+// The onMetaData properties written by FFmpeg, flvmeta and yamdi, typed. Properties that are
+// absent (or null/undefined) are `None`; anything not listed here is kept in `extra`, in its
+// original order, so converting back to an ECMA array loses nothing.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OnMetaData {
+    pub duration: Option<f64>,
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    pub videodatarate: Option<f64>,
+    pub framerate: Option<f64>,
+    pub videocodecid: Option<f64>,
+    pub audiodatarate: Option<f64>,
+    pub audiosamplerate: Option<f64>,
+    pub audiosamplesize: Option<f64>,
+    pub stereo: Option<bool>,
+    pub audiocodecid: Option<f64>,
+    pub encoder: Option<String>,
+    pub filesize: Option<f64>,
+    pub keyframes: Option<Keyframes>,
+    pub extra: IndexMap<Utf8, Amf0TypedValue>,
+}
+
+// The `keyframes` object added by metadata injectors for seeking: the time (in seconds) and
+// byte offset of every keyframe tag.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Keyframes {
+    pub times: Vec<f64>,
+    pub filepositions: Vec<f64>,
+}
+
+impl ToAmf0 for Keyframes {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        let mut properties = IndexMap::new();
+        properties.insert(key("filepositions"), self.filepositions.to_amf0()?);
+        properties.insert(key("times"), self.times.to_amf0()?);
+        Ok(Amf0TypedValue::Object(ObjectType::new(properties)))
+    }
+}
+
+impl FromAmf0 for Keyframes {
+    fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+        let mut properties = object_properties(value)?;
+        Ok(Self {
+            times: take_property(&mut properties, "times")?,
+            filepositions: take_property(&mut properties, "filepositions")?,
+        })
+    }
+}
+
+impl TryFrom<Amf0TypedValue> for OnMetaData {
+    type Error = AmfError;
+
+    // Accepts an ECMA array or an Object.
+    fn try_from(value: Amf0TypedValue) -> Result<Self, Self::Error> {
+        let mut properties = object_properties(value)?;
+        // 先按固定的顺序取出已知的属性，剩下的保持原有顺序
+        Ok(Self {
+            duration: field(&mut properties, "duration")?,
+            width: field(&mut properties, "width")?,
+            height: field(&mut properties, "height")?,
+            videodatarate: field(&mut properties, "videodatarate")?,
+            framerate: field(&mut properties, "framerate")?,
+            videocodecid: field(&mut properties, "videocodecid")?,
+            audiodatarate: field(&mut properties, "audiodatarate")?,
+            audiosamplerate: field(&mut properties, "audiosamplerate")?,
+            audiosamplesize: field(&mut properties, "audiosamplesize")?,
+            stereo: field(&mut properties, "stereo")?,
+            audiocodecid: field(&mut properties, "audiocodecid")?,
+            encoder: field(&mut properties, "encoder")?,
+            filesize: field(&mut properties, "filesize")?,
+            keyframes: field(&mut properties, "keyframes")?,
+            extra: properties,
+        })
+    }
+}
+
+impl From<OnMetaData> for EcmaArrayType {
+    fn from(value: OnMetaData) -> Self {
+        let mut properties = IndexMap::new();
+        let mut put = |name: &str, v: Option<Amf0TypedValue>| {
+            if let Some(v) = v {
+                properties.insert(key(name), v);
+            }
+        };
+        put("duration", value.duration.map(Amf0TypedValue::from));
+        put("width", value.width.map(Amf0TypedValue::from));
+        put("height", value.height.map(Amf0TypedValue::from));
+        put(
+            "videodatarate",
+            value.videodatarate.map(Amf0TypedValue::from),
+        );
+        put("framerate", value.framerate.map(Amf0TypedValue::from));
+        put("videocodecid", value.videocodecid.map(Amf0TypedValue::from));
+        put(
+            "audiodatarate",
+            value.audiodatarate.map(Amf0TypedValue::from),
+        );
+        put(
+            "audiosamplerate",
+            value.audiosamplerate.map(Amf0TypedValue::from),
+        );
+        put(
+            "audiosamplesize",
+            value.audiosamplesize.map(Amf0TypedValue::from),
+        );
+        put("stereo", value.stereo.map(Amf0TypedValue::from));
+        put("audiocodecid", value.audiocodecid.map(Amf0TypedValue::from));
+        put("encoder", value.encoder.map(string));
+        put("filesize", value.filesize.map(Amf0TypedValue::from));
+        put(
+            "keyframes",
+            value
+                .keyframes
+                .map(|k| k.to_amf0().expect("keyframes always convert")),
+        );
+        properties.extend(value.extra);
+        EcmaArrayType::new(properties)
+    }
+}
+
+impl From<OnMetaData> for Amf0TypedValue {
+    fn from(value: OnMetaData) -> Self {
+        Amf0TypedValue::EcmaArray(value.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::errors::ErrorKind;
+
+    #[test]
+    fn test_from_ffmpeg_metadata() {
+        let value = amf0!({
+            "duration": 1.025,
+            "width": 320.0,
+            "height": 240.0,
+            "videocodecid": 2.0,
+            "stereo": false,
+            "title": "FFmpeg AMF0 TEST",
+            "encoder": "Lavf61.7.100",
+            "filesize": null,
+            "keyframes": {"filepositions": [13.0, 4096.0], "times": [0.0, 0.5]},
+        });
+        let metadata = OnMetaData::try_from(value).unwrap();
+        assert_eq!(metadata.duration, Some(1.025));
+        assert_eq!(metadata.width, Some(320.0));
+        assert_eq!(metadata.stereo, Some(false));
+        assert_eq!(metadata.encoder.as_deref(), Some("Lavf61.7.100"));
+        assert_eq!(metadata.filesize, None);
+        assert_eq!(metadata.framerate, None);
+        assert_eq!(metadata.keyframes.as_ref().unwrap().times, vec![0.0, 0.5]);
+        assert_eq!(metadata.extra.len(), 1);
+        assert_eq!(metadata.extra["title"], amf0!("FFmpeg AMF0 TEST"));
+
+        let ecma = EcmaArrayType::from(metadata.clone());
+        let keys: Vec<&str> = ecma.keys().map(|k| k.as_ref()).collect();
+        assert_eq!(
+            keys,
+            [
+                "duration",
+                "width",
+                "height",
+                "videocodecid",
+                "stereo",
+                "encoder",
+                "keyframes",
+                "title"
+            ]
+        );
+        assert_eq!(
+            OnMetaData::try_from(Amf0TypedValue::EcmaArray(ecma)).unwrap(),
+            metadata
+        );
+    }
+
+    #[test]
+    fn test_wrong_types() {
+        let err = OnMetaData::try_from(amf0!({"width": "320"})).unwrap_err();
+        assert_eq!(err.path(), Some("width"));
+        assert_eq!(err.kind(), ErrorKind::TypeMismatch);
+        assert!(OnMetaData::try_from(amf0!([1.0])).is_err());
+        assert_eq!(
+            OnMetaData::try_from(amf0!({})).unwrap(),
+            OnMetaData::default()
+        );
+    }
+}
@@ -0,0 +1,26 @@
+// FLV container support built on the AMF 0 codec: reading and writing tags, and the body of
+// script data tags (onMetaData and other script events).
+
+#[cfg(feature = "tokio")]
+pub mod async_reader;
+pub mod events;
+pub mod inject;
+pub mod keyframes;
+pub mod metadata;
+pub mod patch;
+pub mod reader;
+pub mod script_data;
+pub mod tag;
+pub mod writer;
+
+#[cfg(feature = "tokio")]
+pub use async_reader::AsyncFlvReader;
+pub use events::{CuePoint, ScriptEvent, TextData};
+pub use inject::inject_metadata;
+pub use keyframes::KeyframeIndex;
+pub use metadata::{Keyframes, OnMetaData};
+pub use patch::patch_metadata_numbers;
+pub use reader::FlvReader;
+pub use script_data::ScriptTagBody;
+pub use tag::{FlvHeader, FlvTag, TagData, TagType};
+pub use writer::FlvWriter;
@@ -0,0 +1,159 @@
+use crate::amf0::string::StringType;
+use crate::amf0::type_marker::TypeMarker;
+use crate::amf0::validate;
+use crate::errors::AmfError;
+use crate::flv::script_data::ON_METADATA;
+use crate::flv::tag::{FlvHeader, FlvTag, RawTagHeader};
+use crate::traits::Unmarshall;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+// Overwrites Number properties of the onMetaData tag in place, e.g. the duration and filesize
+// of a file recorded from a live stream. Numbers are 8 bytes whatever their value, so nothing
+// else in the file moves; audio and video tags are skipped without being read. Only top-level
+// properties that already exist as Numbers are changed (others in `values` are ignored); the
+// number of properties written is returned.
+pub fn patch_metadata_numbers<F>(mut file: F, values: &[(&str, f64)]) -> Result<usize, AmfError>
+where
+    F: Read + Write + Seek,
+{
+    let (start, data) = find_metadata(&mut file)?;
+    let offsets = number_offsets(&data, values)?;
+    for (offset, value) in &offsets {
+        file.seek(SeekFrom::Start(start + *offset as u64))?;
+        file.write_all(&value.to_be_bytes())?;
+    }
+    file.flush()?;
+    Ok(offsets.len())
+}
+
+// 返回 onMetaData tag 数据在文件中的偏移以及数据本身
+fn find_metadata<F: Read + Seek>(file: &mut F) -> Result<(u64, Vec<u8>), AmfError> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut bytes = [0u8; FlvHeader::LENGTH];
+    file.read_exact(&mut bytes)?;
+    let (_, data_offset) = FlvHeader::parse(&bytes)?;
+    let mut position = file.seek(SeekFrom::Start(data_offset + 4))?;
+    loop {
+        let mut header = [0u8; FlvTag::HEADER_LENGTH];
+        if file.read_exact(&mut header).is_err() {
+            return Err(AmfError::MissingValue.at_path(ON_METADATA));
+        }
+        let header = RawTagHeader(header);
+        position += FlvTag::HEADER_LENGTH as u64;
+        if header.is_script() {
+            let mut data = vec![0u8; header.data_size()];
+            file.read_exact(&mut data)?;
+            if StringType::unmarshall(&data).is_ok_and(|(name, _)| **name == *ON_METADATA) {
+                return Ok((position, data));
+            }
+            file.seek(SeekFrom::Current(4))?;
+        } else {
+            file.seek(SeekFrom::Current(header.data_size() as i64 + 4))?;
+        }
+        position += header.data_size() as u64 + 4;
+    }
+}
+
+// 找到每个需要修改的 Number 值(不含类型标记)在 tag 数据中的偏移
+fn number_offsets(data: &[u8], values: &[(&str, f64)]) -> Result<Vec<(usize, f64)>, AmfError> {
+    let (_, mut offset) = StringType::unmarshall(data)?;
+    offset += match data.get(offset).copied() {
+        Some(m) if m == TypeMarker::EcmaArray as u8 => 5, // 跳过元素个数
+        Some(m) if m == TypeMarker::Object as u8 => 1,
+        Some(m) => {
+            return Err(AmfError::TypeMarkerValueMismatch {
+                want: TypeMarker::EcmaArray as u8,
+                got: m,
+            });
+        }
+        None => {
+            return Err(AmfError::BufferTooSmall {
+                want: offset + 1,
+                got: data.len(),
+            });
+        }
+    };
+    let mut offsets = Vec::new();
+    // 没有 object end 的截断数据读到末尾为止
+    while let Some(length) = data.get(offset..offset + 2) {
+        let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+        let Some(key) = data.get(offset + 2..offset + 2 + length) else {
+            break;
+        };
+        offset += 2 + length;
+        if length == 0 && data.get(offset) == Some(&(TypeMarker::ObjectEnd as u8)) {
+            break;
+        }
+        if data.get(offset) == Some(&(TypeMarker::Number as u8))
+            && let Some((_, value)) = values.iter().find(|(k, _)| k.as_bytes() == key)
+        {
+            offsets.push((offset + 1, *value));
+        }
+        // flvmeta 写入的 metadatadate 是 Date，validate 不支持，长度固定为 1 + 8 + 2
+        offset += match data.get(offset) {
+            Some(&m) if m == TypeMarker::Date as u8 => 11,
+            _ => validate(&data[offset..])?,
+        };
+    }
+    Ok(offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::flv::reader::FlvReader;
+    use crate::flv::tag::TagType;
+    use crate::flv::writer::FlvWriter;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_patch_in_place() {
+        let mut writer = FlvWriter::new(Vec::new(), FlvHeader::new(false, true)).unwrap();
+        writer.write_raw(TagType::Video, 0, &[0x17, 0x00]).unwrap();
+        writer
+            .write_metadata(&amf0!({
+                "duration": 0.0,
+                "keyframes": {"filesize": 1.0},
+                "encoder": "Lavf61",
+                "filesize": 0.0,
+                "title": "duration",
+            }))
+            .unwrap();
+        let original = writer.into_inner();
+
+        let mut file = Cursor::new(original.clone());
+        let patched = patch_metadata_numbers(
+            &mut file,
+            &[
+                ("duration", 93.4),
+                ("filesize", 1048576.0),
+                ("encoder", 1.0),
+                ("width", 1.0),
+            ],
+        )
+        .unwrap();
+        assert_eq!(patched, 2);
+        let data = file.into_inner();
+        assert_eq!(data.len(), original.len());
+
+        let tags = FlvReader::new(Cursor::new(data))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let metadata = &tags[1].data.as_script().unwrap().value;
+        assert_eq!(metadata["duration"], amf0!(93.4));
+        assert_eq!(metadata["filesize"], amf0!(1048576.0));
+        // 嵌套的属性和非 Number 的值保持不变
+        assert_eq!(metadata.get_path("keyframes.filesize"), Some(&amf0!(1.0)));
+        assert_eq!(metadata["encoder"], amf0!("Lavf61"));
+    }
+
+    #[test]
+    fn test_no_metadata() {
+        let mut writer = FlvWriter::new(Vec::new(), FlvHeader::new(false, true)).unwrap();
+        writer.write_raw(TagType::Video, 0, &[0x17, 0x00]).unwrap();
+        let mut file = Cursor::new(writer.into_inner());
+        assert!(patch_metadata_numbers(&mut file, &[("duration", 1.0)]).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::errors::AmfError;
+use crate::flv::tag::{FlvHeader, FlvTag, RawTag, RawTagHeader};
+use std::io::{self, Read};
+
+// Reads the tags of an FLV file in order. `new` validates the file header; iterating yields one
+// `FlvTag` per tag, with script data tags decoded as AMF 0 and audio/video payloads as raw
+// bytes. Iteration ends cleanly at the end of the input and stops after the first error.
+// The reader is not buffered; wrap files in a `BufReader`.
+pub struct FlvReader<R> {
+    reader: R,
+    header: FlvHeader,
+    offset: u64,
+    done: bool,
+}
+
+impl<R: Read> FlvReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, AmfError> {
+        let mut bytes = [0u8; FlvHeader::LENGTH];
+        reader.read_exact(&mut bytes)?;
+        let (header, data_offset) = FlvHeader::parse(&bytes)?;
+        // 跳过头部之后的扩展数据和 PreviousTagSize0
+        let skip = data_offset - FlvHeader::LENGTH as u64 + 4;
+        let skipped = io::copy(&mut (&mut reader).take(skip), &mut io::sink())?;
+        if skipped < skip {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(Self {
+            reader,
+            header,
+            offset: data_offset + 4,
+            done: false,
+        })
+    }
+
+    pub fn header(&self) -> &FlvHeader {
+        &self.header
+    }
+
+    // The byte offset of the next tag from the start of the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // The next tag, or `None` at the end of the input.
+    pub fn next_tag(&mut self) -> Result<Option<FlvTag>, AmfError> {
+        self.next_raw_tag()?.map(RawTag::decode).transpose()
+    }
+
+    // The next tag without decoding anything, for copying tags through byte for byte.
+    pub(crate) fn next_raw_tag(&mut self) -> Result<Option<RawTag>, AmfError> {
+        let mut header = [0u8; FlvTag::HEADER_LENGTH];
+        if !read_exact_or_eof(&mut self.reader, &mut header)? {
+            return Ok(None);
+        }
+        let header = RawTagHeader(header);
+        let mut data = vec![0u8; header.data_size()];
+        self.reader.read_exact(&mut data)?;
+        // PreviousTagSize 只是冗余信息，不做校验
+        let mut previous_tag_size = [0u8; 4];
+        self.reader.read_exact(&mut previous_tag_size)?;
+        self.offset += (FlvTag::HEADER_LENGTH + data.len() + 4) as u64;
+        Ok(Some(RawTag { header, data }))
+    }
+}
+
+impl<R: Read> Iterator for FlvReader<R> {
+    type Item = Result<FlvTag, AmfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.next_tag().transpose();
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+
+// Fills `buf`, or returns false if the input ends before its first byte.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::errors::ErrorKind;
+    use crate::flv::script_data::ScriptTagBody;
+    use crate::flv::tag::TagType;
+    use crate::traits::Marshall;
+    use std::io::Cursor;
+
+    fn tag(tag_type: u8, timestamp: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![tag_type];
+        bytes.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+        bytes.extend_from_slice(&timestamp.to_be_bytes()[1..]);
+        bytes.push((timestamp >> 24) as u8);
+        bytes.extend_from_slice(&[0, 0, 0]);
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(&(11 + data.len() as u32).to_be_bytes());
+        bytes
+    }
+
+    fn file(tags: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00".to_vec();
+        tags.iter().for_each(|t| bytes.extend_from_slice(t));
+        bytes
+    }
+
+    #[test]
+    fn test_read_tags() {
+        let body = ScriptTagBody::on_metadata(
+            amf0!({"duration": 1.5})
+                .as_object()
+                .unwrap()
+                .clone()
+                .into_ecma_array(),
+        );
+        let data = file(&[
+            tag(18, 0, &body.marshall().unwrap()),
+            tag(9, 40, &[0x17, 0x00]),
+            tag(8, 0x0100_0002, &[0x2f]),
+        ]);
+
+        let mut reader = FlvReader::new(Cursor::new(data)).unwrap();
+        assert_eq!(*reader.header(), FlvHeader::new(true, true));
+        let tags = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags[0].tag_type, TagType::ScriptData);
+        assert_eq!(tags[0].data.as_script(), Some(&body));
+        assert_eq!(tags[1].tag_type, TagType::Video);
+        assert_eq!(tags[1].timestamp, 40);
+        assert_eq!(tags[1].data.as_bytes(), Some(&[0x17, 0x00][..]));
+        assert_eq!(tags[2].tag_type, TagType::Audio);
+        assert_eq!(tags[2].timestamp, 0x0100_0002);
+        assert!(reader.next().is_none());
+        assert_eq!(
+            reader.offset(),
+            reader.into_inner().into_inner().len() as u64
+        );
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let result = FlvReader::new(Cursor::new(b"FLX\x01\x05\x00\x00\x00\x09".to_vec()));
+        assert!(result.is_err_and(|e| e.kind() == ErrorKind::InvalidHeader));
+
+        // 截断的 tag 报错，并且之后迭代结束
+        let mut data = file(&[tag(9, 0, &[1, 2, 3])]);
+        data.truncate(data.len() - 5);
+        let mut reader = FlvReader::new(Cursor::new(data)).unwrap();
+        assert_eq!(
+            reader.next().unwrap().unwrap_err().kind(),
+            ErrorKind::Truncated
+        );
+        assert!(reader.next().is_none());
+    }
+}
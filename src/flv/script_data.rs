@@ -0,0 +1,128 @@
+use crate::amf0::nested::{Amf0TypedValue, EcmaArrayType};
+use crate::amf0::string::StringType;
+use crate::errors::AmfError;
+use crate::traits::{Marshall, MarshallLength, Unmarshall};
+use std::fmt::{Display, Formatter};
+
+// Names of the script events: stream metadata, cue points and timed text.
+pub const ON_METADATA: &str = "onMetaData";
+pub const ON_CUE_POINT: &str = "onCuePoint";
+pub const ON_TEXT_DATA: &str = "onTextData";
+
+//	The body of an FLV script data tag (tag type 18): the name of the event as an AMF 0 String,
+//	followed by a single AMF 0 value holding its arguments. For onMetaData the value is an ECMA
+//	array (FFmpeg, OBS) or, from some older encoders, an Object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptTagBody {
+    pub name: StringType,
+    pub value: Amf0TypedValue,
+}
+
+impl ScriptTagBody {
+    pub fn new(name: StringType, value: Amf0TypedValue) -> Self {
+        Self { name, value }
+    }
+
+    // An onMetaData body, with the metadata written as an ECMA array like FFmpeg does.
+    pub fn on_metadata(metadata: EcmaArrayType) -> Self {
+        Self::new(
+            StringType::new_from_str(ON_METADATA).unwrap(), // 长度是固定的
+            Amf0TypedValue::EcmaArray(metadata),
+        )
+    }
+
+    pub fn is_on_metadata(&self) -> bool {
+        **self.name == *ON_METADATA
+    }
+}
+
+impl Marshall for ScriptTagBody {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        self.name.marshall_to(buf)?;
+        self.value.marshall_to(buf)
+    }
+}
+
+impl MarshallLength for ScriptTagBody {
+    fn marshall_length(&self) -> usize {
+        self.name.marshall_length() + self.value.marshall_length()
+    }
+}
+
+// Anything after the value (some muxers pad the tag) is left unconsumed.
+impl Unmarshall for ScriptTagBody {
+    fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
+        let (name, name_len) = StringType::unmarshall(buf)?;
+        let (value, value_len) = Amf0TypedValue::unmarshall(&buf[name_len..])?;
+        Ok((Self::new(name, value), name_len + value_len))
+    }
+}
+
+// 实现 rust 惯用语("idiom") 方便用户使用
+
+impl TryFrom<&[u8]> for ScriptTagBody {
+    type Error = AmfError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::unmarshall(value).map(|(body, _)| body)
+    }
+}
+
+impl Display for ScriptTagBody {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::amf0::type_marker::TypeMarker;
+
+    #[test]
+    fn test_on_metadata_round_trip() {
+        let metadata = amf0!({"duration": 12.5, "width": 320.0, "encoder": "Lavf60"});
+        let body =
+            ScriptTagBody::on_metadata(metadata.as_object().unwrap().clone().into_ecma_array());
+        assert!(body.is_on_metadata());
+
+        let mut data = body.marshall().unwrap();
+        assert_eq!(data.len(), body.marshall_length());
+        assert_eq!(&data[..13], b"\x02\x00\x0aonMetaData");
+        assert_eq!(data[13], TypeMarker::EcmaArray as u8);
+
+        data.push(0x00); // 尾部的填充不属于 body
+        let (decoded, n) = ScriptTagBody::unmarshall(&data).unwrap();
+        assert_eq!(n, data.len() - 1);
+        assert_eq!(decoded, body);
+        assert_eq!(decoded.value.get_path("width"), Some(&amf0!(320.0)));
+    }
+
+    #[test]
+    fn test_name_must_be_a_string() {
+        let data = amf0!(1.0).marshall().unwrap();
+        assert!(matches!(
+            ScriptTagBody::unmarshall(&data),
+            Err(AmfError::TypeMarkerValueMismatch {
+                want: 0x02,
+                got: 0x00
+            })
+        ));
+        let body = ScriptTagBody::new(
+            StringType::new_from_str("onCuePoint").unwrap(),
+            amf0!({"name": "cue"}),
+        );
+        assert!(!body.is_on_metadata());
+        assert_eq!(
+            ScriptTagBody::try_from(body.marshall().unwrap().as_slice()).unwrap(),
+            body
+        );
+    }
+}
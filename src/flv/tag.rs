@@ -0,0 +1,192 @@
+use crate::errors::AmfError;
+use crate::flv::script_data::ScriptTagBody;
+use crate::traits::Unmarshall;
+
+// The 9-byte header at the start of every FLV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlvHeader {
+    pub version: u8,
+    pub has_audio: bool,
+    pub has_video: bool,
+}
+
+impl FlvHeader {
+    pub const SIGNATURE: [u8; 3] = *b"FLV";
+    // 头部固定 9 字节，DataOffset 字段也总是 9
+    pub const LENGTH: usize = 9;
+
+    pub fn new(has_audio: bool, has_video: bool) -> Self {
+        Self {
+            version: 1,
+            has_audio,
+            has_video,
+        }
+    }
+
+    // Parses the 9 header bytes, returning the header and its DataOffset field (the offset of
+    // PreviousTagSize0, normally 9).
+    pub(crate) fn parse(bytes: &[u8; Self::LENGTH]) -> Result<(Self, u64), AmfError> {
+        if bytes[..3] != Self::SIGNATURE {
+            return Err(AmfError::InvalidHeader {
+                format: "FLV",
+                field: "signature",
+            });
+        }
+        let header = Self {
+            version: bytes[3],
+            has_audio: bytes[4] & 0x04 != 0,
+            has_video: bytes[4] & 0x01 != 0,
+        };
+        let data_offset = u32::from_be_bytes(bytes[5..9].try_into().unwrap()) as u64;
+        if data_offset < Self::LENGTH as u64 {
+            return Err(AmfError::InvalidHeader {
+                format: "FLV",
+                field: "data offset",
+            });
+        }
+        Ok((header, data_offset))
+    }
+}
+
+// The TagType field of a tag header (its low 5 bits; the Filter bit of encrypted tags is
+// masked off).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagType {
+    Audio,
+    Video,
+    ScriptData,
+    Other(u8),
+}
+
+impl From<u8> for TagType {
+    fn from(value: u8) -> Self {
+        match value & 0x1f {
+            8 => TagType::Audio,
+            9 => TagType::Video,
+            18 => TagType::ScriptData,
+            other => TagType::Other(other),
+        }
+    }
+}
+
+impl From<TagType> for u8 {
+    fn from(value: TagType) -> Self {
+        match value {
+            TagType::Audio => 8,
+            TagType::Video => 9,
+            TagType::ScriptData => 18,
+            TagType::Other(other) => other,
+        }
+    }
+}
+
+// The payload of a tag: script data tags are decoded, everything else (audio, video, encrypted
+// or unknown tags) is kept as the raw tag body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagData {
+    Raw(Vec<u8>),
+    Script(ScriptTagBody),
+}
+
+impl TagData {
+    pub fn as_script(&self) -> Option<&ScriptTagBody> {
+        match self {
+            TagData::Script(body) => Some(body),
+            TagData::Raw(_) => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            TagData::Raw(bytes) => Some(bytes),
+            TagData::Script(_) => None,
+        }
+    }
+}
+
+// One FLV tag. `timestamp` is in milliseconds, with the TimestampExtended byte already folded
+// in as the upper 8 bits; `stream_id` is always 0 in conforming files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlvTag {
+    pub tag_type: TagType,
+    pub timestamp: u32,
+    pub stream_id: u32,
+    pub data: TagData,
+}
+
+impl FlvTag {
+    // 包含 TagType、DataSize、Timestamp、TimestampExtended 和 StreamID
+    pub const HEADER_LENGTH: usize = 11;
+}
+
+// The 11 header bytes of a tag as found in the file, including the Filter bit and StreamID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RawTagHeader(pub [u8; FlvTag::HEADER_LENGTH]);
+
+impl RawTagHeader {
+    pub fn new(tag_type: TagType, data_size: u32, timestamp: u32) -> Self {
+        let size = data_size.to_be_bytes();
+        let time = timestamp.to_be_bytes();
+        // TimestampExtended 放在低 24 位之后；StreamID 总是 0
+        Self([
+            tag_type.into(),
+            size[1],
+            size[2],
+            size[3],
+            time[1],
+            time[2],
+            time[3],
+            time[0],
+            0,
+            0,
+            0,
+        ])
+    }
+
+    pub fn tag_type(&self) -> TagType {
+        TagType::from(self.0[0])
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.0[0] & 0x20 != 0
+    }
+
+    // A script data tag that can be decoded.
+    pub fn is_script(&self) -> bool {
+        self.tag_type() == TagType::ScriptData && !self.is_encrypted()
+    }
+
+    pub fn data_size(&self) -> usize {
+        u32::from_be_bytes([0, self.0[1], self.0[2], self.0[3]]) as usize
+    }
+
+    pub fn timestamp(&self) -> u32 {
+        u32::from_be_bytes([self.0[7], self.0[4], self.0[5], self.0[6]])
+    }
+
+    pub fn stream_id(&self) -> u32 {
+        u32::from_be_bytes([0, self.0[8], self.0[9], self.0[10]])
+    }
+}
+
+pub(crate) struct RawTag {
+    pub header: RawTagHeader,
+    pub data: Vec<u8>,
+}
+
+impl RawTag {
+    pub fn decode(self) -> Result<FlvTag, AmfError> {
+        let RawTag { header, data } = self;
+        let data = if header.is_script() {
+            TagData::Script(ScriptTagBody::unmarshall(&data)?.0)
+        } else {
+            TagData::Raw(data)
+        };
+        Ok(FlvTag {
+            tag_type: header.tag_type(),
+            timestamp: header.timestamp(),
+            stream_id: header.stream_id(),
+            data,
+        })
+    }
+}
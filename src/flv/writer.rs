@@ -0,0 +1,162 @@
+use crate::amf0::nested::Amf0TypedValue;
+use crate::amf0::string::StringType;
+use crate::errors::AmfError;
+use crate::flv::metadata::OnMetaData;
+use crate::flv::script_data::{ON_METADATA, ScriptTagBody};
+use crate::flv::tag::{FlvHeader, FlvTag, RawTagHeader, TagData, TagType};
+use crate::traits::{Marshall, MarshallLength};
+use std::io::Write;
+
+// DataSize 字段只有 24 位
+const MAX_DATA_SIZE: usize = 0x00ff_ffff;
+
+// Writes an FLV file: `new` writes the file header, then every `write_*` call appends one tag
+// followed by its PreviousTagSize. Tags are written as given; ordering and timestamps are up to
+// the caller. The writer is not buffered; wrap files in a `BufWriter`.
+pub struct FlvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> FlvWriter<W> {
+    pub fn new(mut writer: W, header: FlvHeader) -> Result<Self, AmfError> {
+        let flags = (header.has_audio as u8) << 2 | header.has_video as u8;
+        let mut bytes = [0u8; FlvHeader::LENGTH + 4];
+        bytes[..3].copy_from_slice(&FlvHeader::SIGNATURE);
+        bytes[3] = header.version;
+        bytes[4] = flags;
+        bytes[5..9].copy_from_slice(&(FlvHeader::LENGTH as u32).to_be_bytes());
+        // 最后 4 字节是 PreviousTagSize0，总是 0
+        writer.write_all(&bytes)?;
+        Ok(Self { writer })
+    }
+
+    // An onMetaData script tag at timestamp 0. Objects are written as ECMA arrays, the way
+    // FFmpeg does.
+    pub fn write_metadata(&mut self, metadata: &Amf0TypedValue) -> Result<(), AmfError> {
+        let body = match metadata {
+            Amf0TypedValue::Object(object) => {
+                ScriptTagBody::on_metadata(object.clone().into_ecma_array())
+            }
+            other => ScriptTagBody::new(StringType::new_from_str(ON_METADATA)?, other.clone()),
+        };
+        self.write_script(0, &body)
+    }
+
+    pub fn write_on_metadata(&mut self, metadata: &OnMetaData) -> Result<(), AmfError> {
+        self.write_script(0, &ScriptTagBody::on_metadata(metadata.clone().into()))
+    }
+
+    pub fn write_script(&mut self, timestamp: u32, body: &ScriptTagBody) -> Result<(), AmfError> {
+        let mut data = Vec::with_capacity(body.marshall_length());
+        body.marshall_to(&mut data)?;
+        self.write_raw(TagType::ScriptData, timestamp, &data)
+    }
+
+    // Writes `tag` unchanged, e.g. when passing tags through from an `FlvReader`.
+    pub fn write_tag(&mut self, tag: &FlvTag) -> Result<(), AmfError> {
+        match &tag.data {
+            TagData::Script(body) => self.write_script(tag.timestamp, body),
+            TagData::Raw(data) => self.write_raw(tag.tag_type, tag.timestamp, data),
+        }
+    }
+
+    pub fn write_raw(
+        &mut self,
+        tag_type: TagType,
+        timestamp: u32,
+        data: &[u8],
+    ) -> Result<(), AmfError> {
+        if data.len() > MAX_DATA_SIZE {
+            return Err(AmfError::TooLong {
+                what: "FLV tag data",
+                max: MAX_DATA_SIZE,
+                got: data.len(),
+            });
+        }
+        let header = RawTagHeader::new(tag_type, data.len() as u32, timestamp);
+        self.write_tag_bytes(&header, data)
+    }
+
+    // Writes a tag header as is, keeping the Filter bit and StreamID of copied tags.
+    pub(crate) fn write_tag_bytes(
+        &mut self,
+        header: &RawTagHeader,
+        data: &[u8],
+    ) -> Result<(), AmfError> {
+        self.writer.write_all(&header.0)?;
+        self.writer.write_all(data)?;
+        let previous_tag_size = (FlvTag::HEADER_LENGTH + data.len()) as u32;
+        self.writer.write_all(&previous_tag_size.to_be_bytes())?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), AmfError> {
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::flv::reader::FlvReader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_write_then_read() {
+        let mut writer = FlvWriter::new(Vec::new(), FlvHeader::new(true, false)).unwrap();
+        writer
+            .write_metadata(&amf0!({"duration": 2.0, "stereo": true}))
+            .unwrap();
+        writer
+            .write_raw(TagType::Audio, 0x0123_4567, &[0x2f, 0xff])
+            .unwrap();
+        let data = writer.into_inner();
+        assert_eq!(&data[..13], b"FLV\x01\x04\x00\x00\x00\x09\x00\x00\x00\x00");
+        // 最后一个 PreviousTagSize = 11 + 2
+        assert_eq!(&data[data.len() - 4..], &[0, 0, 0, 13]);
+
+        let reader = FlvReader::new(Cursor::new(&data)).unwrap();
+        assert_eq!(*reader.header(), FlvHeader::new(true, false));
+        let tags = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        let body = tags[0].data.as_script().unwrap();
+        assert!(body.is_on_metadata());
+        assert!(matches!(body.value, Amf0TypedValue::EcmaArray(_)));
+        assert_eq!(body.value.get_path("duration"), Some(&amf0!(2.0)));
+        assert_eq!(tags[1].timestamp, 0x0123_4567);
+
+        // 原样转写得到相同的字节
+        let mut copy = FlvWriter::new(Vec::new(), FlvHeader::new(true, false)).unwrap();
+        tags.iter().for_each(|tag| copy.write_tag(tag).unwrap());
+        assert_eq!(copy.into_inner(), data);
+    }
+
+    #[test]
+    fn test_write_on_metadata() {
+        let metadata = OnMetaData {
+            duration: Some(3.0),
+            ..Default::default()
+        };
+        let mut writer = FlvWriter::new(Vec::new(), FlvHeader::new(false, true)).unwrap();
+        writer.write_on_metadata(&metadata).unwrap();
+        let mut reader = FlvReader::new(Cursor::new(writer.into_inner())).unwrap();
+        let tag = reader.next().unwrap().unwrap();
+        let body = tag.data.as_script().unwrap();
+        assert_eq!(OnMetaData::try_from(body.value.clone()).unwrap(), metadata);
+    }
+
+    #[test]
+    fn test_data_too_long() {
+        let mut writer = FlvWriter::new(Vec::new(), FlvHeader::new(false, true)).unwrap();
+        let data = vec![0u8; MAX_DATA_SIZE + 1];
+        assert!(writer.write_raw(TagType::Video, 0, &data).is_err());
+    }
+}
@@ -1,3 +1,15 @@
+// 让 #[derive(AmfObject)] 生成的 ::amf_rs 路径在本 crate 内部(比如测试中)也能解析
+extern crate self as amf_rs;
+
 pub mod amf0;
 pub mod errors;
+pub mod flv;
+pub mod packet;
+pub mod prelude;
+pub mod rtmp;
+pub mod schema;
+pub mod sol;
+pub mod testing;
 pub mod traits;
+
+pub use amf0::{DiffEntry, diff};
@@ -0,0 +1,329 @@
+use crate::amf0::nested::Amf0TypedValue;
+use crate::amf0::raw::RawValue;
+use crate::amf0::utf8::{Utf8, unmarshall_str};
+use crate::errors::{AmfError, checked_end};
+use crate::traits::{Marshall, MarshallLength, Unmarshall};
+
+// header-length 和 message-length 可以是 -1(U32 全 1)，表示长度未知
+const UNKNOWN_LENGTH: u32 = u32::MAX;
+
+// 切换到 AMF 3 的类型标记
+const AVMPLUS_MARKER: u8 = 0x11;
+
+//	header-type = header-name must-understand header-length value-type
+//	header-name = UTF-8
+//	must-understand = U8
+//	header-length = U32
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketHeader {
+    pub name: String,
+    pub must_understand: bool,
+    pub value: Amf0TypedValue,
+}
+
+impl PacketHeader {
+    pub fn new(name: impl Into<String>, must_understand: bool, value: Amf0TypedValue) -> Self {
+        Self {
+            name: name.into(),
+            must_understand,
+            value,
+        }
+    }
+}
+
+//	message-type = target-uri response-uri message-length value-type
+//	target-uri = UTF-8
+//	response-uri = UTF-8
+//	message-length = U32
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketMessage {
+    pub target_uri: String,
+    pub response_uri: String,
+    pub body: Amf0TypedValue,
+}
+
+impl PacketMessage {
+    // Whether the body switched to AMF 3 with the AVM+ marker; such a body is held as
+    // `Amf0TypedValue::Raw` with marker 0x11 and the undecoded AMF 3 bytes.
+    pub fn is_amf3(&self) -> bool {
+        matches!(&self.body, Amf0TypedValue::Raw(raw) if raw.marker == AVMPLUS_MARKER)
+    }
+
+    pub fn new(
+        target_uri: impl Into<String>,
+        response_uri: impl Into<String>,
+        body: Amf0TypedValue,
+    ) -> Self {
+        Self {
+            target_uri: target_uri.into(),
+            response_uri: response_uri.into(),
+            body,
+        }
+    }
+}
+
+//	amf-packet = version header-count *(header-type) message-count *(message-type)
+//	version = U16
+//	header-count = U16
+//	message-count = U16
+//
+//	Lengths are always written; when decoding, an unknown length (-1) is accepted and the
+//	value's own length is used instead.
+//
+//	In version 3 packets (BlazeDS, Flex) a header or body may start with the AVM+ marker and
+//	continue in AMF 3. AMF 3 is not decoded by this crate, so such a value is kept as
+//	`Amf0TypedValue::Raw` holding its exact bytes, which are written back verbatim. This needs
+//	the declared length, since the end of an AMF 3 value cannot be found without decoding it.
+//	Every header and body is read from its own slice, so AMF 3 reference tables never carry
+//	over from one body to the next.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AmfPacket {
+    pub version: u16,
+    pub headers: Vec<PacketHeader>,
+    pub messages: Vec<PacketMessage>,
+}
+
+impl AmfPacket {
+    pub const AMF0_VERSION: u16 = 0;
+    pub const AMF3_VERSION: u16 = 3;
+
+    pub fn new(headers: Vec<PacketHeader>, messages: Vec<PacketMessage>) -> Self {
+        Self {
+            version: 0,
+            headers,
+            messages,
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&PacketHeader> {
+        self.headers.iter().find(|h| h.name == name)
+    }
+}
+
+impl Marshall for AmfPacket {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        buf.extend_from_slice(&self.version.to_be_bytes());
+        buf.extend_from_slice(&count(self.headers.len(), "AMF packet headers")?.to_be_bytes());
+        for header in &self.headers {
+            Utf8::new_from_str(&header.name)?.marshall_to(buf)?;
+            buf.push(header.must_understand as u8);
+            marshall_with_length(&header.value, buf)?;
+        }
+        buf.extend_from_slice(&count(self.messages.len(), "AMF packet messages")?.to_be_bytes());
+        for message in &self.messages {
+            Utf8::new_from_str(&message.target_uri)?.marshall_to(buf)?;
+            Utf8::new_from_str(&message.response_uri)?.marshall_to(buf)?;
+            marshall_with_length(&message.body, buf)?;
+        }
+        Ok(())
+    }
+}
+
+fn count(n: usize, what: &'static str) -> Result<u16, AmfError> {
+    u16::try_from(n).map_err(|_| AmfError::TooLong {
+        what,
+        max: u16::MAX as usize,
+        got: n,
+    })
+}
+
+fn marshall_with_length(value: &Amf0TypedValue, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+    // 长度为 U32::MAX 的值会被当作长度未知，写成 -1 也是合法的
+    let length = u32::try_from(value.marshall_length()).unwrap_or(UNKNOWN_LENGTH);
+    buf.extend_from_slice(&length.to_be_bytes());
+    value.marshall_to(buf)
+}
+
+impl MarshallLength for AmfPacket {
+    fn marshall_length(&self) -> usize {
+        let headers: usize = self
+            .headers
+            .iter()
+            .map(|h| 2 + h.name.len() + 1 + 4 + h.value.marshall_length())
+            .sum();
+        let messages: usize = self
+            .messages
+            .iter()
+            .map(|m| {
+                2 + m.target_uri.len() + 2 + m.response_uri.len() + 4 + m.body.marshall_length()
+            })
+            .sum();
+        2 + 2 + headers + 2 + messages
+    }
+}
+
+struct Cursor<'b> {
+    buf: &'b [u8],
+    offset: usize,
+    avmplus: bool,
+}
+
+impl<'b> Cursor<'b> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], AmfError> {
+        let end = checked_end(self.offset, N)?;
+        if self.buf.len() < end {
+            return Err(AmfError::BufferTooSmall {
+                want: end,
+                got: self.buf.len(),
+            });
+        }
+        let bytes = self.buf[self.offset..end].try_into().unwrap();
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<&'b str, AmfError> {
+        let (s, n) = unmarshall_str::<2>(&self.buf[self.offset..])?;
+        self.offset += n;
+        Ok(s)
+    }
+
+    fn value(&mut self) -> Result<Amf0TypedValue, AmfError> {
+        let length = u32::from_be_bytes(self.take()?);
+        let rest = &self.buf[self.offset..];
+        let is_avmplus = self.avmplus && rest.first() == Some(&AVMPLUS_MARKER);
+        if length == UNKNOWN_LENGTH {
+            if is_avmplus {
+                // 没有 AMF 3 解码器就无法知道值在哪里结束
+                return Err(AmfError::UnsupportedTypeMarker(AVMPLUS_MARKER));
+            }
+            let (value, n) = Amf0TypedValue::unmarshall(rest)?;
+            self.offset += n;
+            return Ok(value);
+        }
+        let length = length as usize;
+        if rest.len() < length {
+            return Err(AmfError::BufferTooSmall {
+                want: self.offset + length,
+                got: self.buf.len(),
+            });
+        }
+        let value = if is_avmplus && length > 0 {
+            Amf0TypedValue::Raw(RawValue::new(AVMPLUS_MARKER, rest[1..length].to_vec()))
+        } else {
+            Amf0TypedValue::unmarshall(&rest[..length])?.0
+        };
+        self.offset += length;
+        Ok(value)
+    }
+}
+
+impl Unmarshall for AmfPacket {
+    fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
+        let mut cursor = Cursor {
+            buf,
+            offset: 0,
+            avmplus: false,
+        };
+        let version = u16::from_be_bytes(cursor.take()?);
+        cursor.avmplus = version == Self::AMF3_VERSION;
+        let header_count = u16::from_be_bytes(cursor.take()?);
+        let mut headers = Vec::with_capacity(header_count as usize);
+        for _ in 0..header_count {
+            let name = cursor.string()?.to_string();
+            let [must_understand] = cursor.take()?;
+            let value = cursor.value()?;
+            headers.push(PacketHeader::new(name, must_understand != 0, value));
+        }
+        let message_count = u16::from_be_bytes(cursor.take()?);
+        let mut messages = Vec::with_capacity(message_count as usize);
+        for _ in 0..message_count {
+            let target_uri = cursor.string()?.to_string();
+            let response_uri = cursor.string()?.to_string();
+            let body = cursor.value()?;
+            messages.push(PacketMessage::new(target_uri, response_uri, body));
+        }
+        let packet = Self {
+            version,
+            headers,
+            messages,
+        };
+        Ok((packet, cursor.offset))
+    }
+}
+
+impl TryFrom<&[u8]> for AmfPacket {
+    type Error = AmfError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::unmarshall(value).map(|(packet, _)| packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::testing::assert_roundtrip;
+
+    #[test]
+    fn test_request_bytes() {
+        let packet = AmfPacket::new(vec![], vec![PacketMessage::new("echo", "/1", amf0!([1.0]))]);
+        let data = packet.marshall().unwrap();
+        let mut expected =
+            b"\x00\x00\x00\x00\x00\x01\x00\x04echo\x00\x02/1\x00\x00\x00\x0e".to_vec();
+        expected.extend_from_slice(&amf0!([1.0]).marshall().unwrap());
+        assert_eq!(data, expected);
+        assert_roundtrip(&packet);
+    }
+
+    #[test]
+    fn test_headers_and_unknown_lengths() {
+        let packet = AmfPacket::new(
+            vec![PacketHeader::new(
+                "Credentials",
+                true,
+                amf0!({"userid": "u", "password": "p"}),
+            )],
+            vec![
+                PacketMessage::new("svc.login", "/1", amf0!(["u"])),
+                PacketMessage::new("/1/onResult", "null", amf0!(true)),
+            ],
+        );
+        assert_roundtrip(&packet);
+        assert!(packet.header("Credentials").unwrap().must_understand);
+
+        // 所有长度都写成 -1 的请求
+        let mut data = b"\x00\x00\x00\x01\x00\x01a\x00\xff\xff\xff\xff".to_vec();
+        data.extend_from_slice(&amf0!(null).marshall().unwrap());
+        data.extend_from_slice(b"\x00\x01\x00\x01t\x00\x01r\xff\xff\xff\xff");
+        data.extend_from_slice(&amf0!("x").marshall().unwrap());
+        let decoded = AmfPacket::try_from(data.as_slice()).unwrap();
+        assert_eq!(decoded.headers[0].name, "a");
+        assert!(!decoded.headers[0].must_understand);
+        assert_eq!(decoded.messages[0].body, amf0!("x"));
+
+        data.truncate(data.len() - 1);
+        assert!(AmfPacket::try_from(data.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_version_3_bodies() {
+        // 两个 AMF 3 body: 字符串 "hi" 和引用第 0 个字符串，各自使用独立的引用表
+        let mut data = b"\x00\x03\x00\x00\x00\x02".to_vec();
+        data.extend_from_slice(b"\x00\x01a\x00\x02/1\x00\x00\x00\x05\x11\x06\x05hi");
+        data.extend_from_slice(b"\x00\x01b\x00\x02/2\x00\x00\x00\x03\x11\x06\x00");
+        let packet = AmfPacket::try_from(data.as_slice()).unwrap();
+        assert_eq!(packet.version, AmfPacket::AMF3_VERSION);
+        assert!(packet.messages.iter().all(PacketMessage::is_amf3));
+        assert_eq!(
+            packet.messages[1].body,
+            Amf0TypedValue::Raw(RawValue::new(0x11, vec![0x06, 0x00]))
+        );
+        assert_eq!(packet.marshall().unwrap(), data);
+
+        // 版本 0 不识别 AVM+ 标记
+        data[1] = 0;
+        assert!(AmfPacket::try_from(data.as_slice()).is_err());
+        // 长度未知时无法确定 AMF 3 值的结尾
+        data[1] = 3;
+        data[16..20].copy_from_slice(&[0xff; 4]);
+        assert!(AmfPacket::try_from(data.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::errors::AmfError;
+use crate::packet::envelope::AmfPacket;
+use crate::traits::Marshall;
+use http::header::CONTENT_TYPE;
+use http::{HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Uri};
+
+// Remoting packets travel as the body of an HTTP POST and of its 200 response.
+pub const AMF_CONTENT_TYPE: &str = "application/x-amf";
+
+fn http_error(e: http::Error) -> AmfError {
+    AmfError::Custom(format!("Invalid HTTP message: {}", e))
+}
+
+// A POST of `packet` to `uri`.
+pub fn request(uri: Uri, packet: &AmfPacket) -> Result<Request<Vec<u8>>, AmfError> {
+    Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header(CONTENT_TYPE, HeaderValue::from_static(AMF_CONTENT_TYPE))
+        .body(packet.marshall()?)
+        .map_err(http_error)
+}
+
+// A 200 response carrying `packet`; faults are reported inside the packet, not by status code.
+pub fn response(packet: &AmfPacket) -> Result<Response<Vec<u8>>, AmfError> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, HeaderValue::from_static(AMF_CONTENT_TYPE))
+        .body(packet.marshall()?)
+        .map_err(http_error)
+}
+
+pub fn from_request<B: AsRef<[u8]>>(request: &Request<B>) -> Result<AmfPacket, AmfError> {
+    check_content_type(request.headers())?;
+    AmfPacket::try_from(request.body().as_ref())
+}
+
+pub fn from_response<B: AsRef<[u8]>>(response: &Response<B>) -> Result<AmfPacket, AmfError> {
+    if !response.status().is_success() {
+        return Err(AmfError::HttpStatus(response.status().as_u16()));
+    }
+    check_content_type(response.headers())?;
+    AmfPacket::try_from(response.body().as_ref())
+}
+
+// 忽略参数(如 `; charset=...`)和大小写
+fn check_content_type(headers: &HeaderMap) -> Result<(), AmfError> {
+    let value = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let media_type = value.split(';').next().unwrap_or_default().trim();
+    if media_type.eq_ignore_ascii_case(AMF_CONTENT_TYPE) {
+        Ok(())
+    } else {
+        Err(AmfError::InvalidHeader {
+            format: "HTTP",
+            field: "Content-Type",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::errors::ErrorKind;
+    use crate::packet::remoting::{RemotingRequest, RemotingResponse};
+
+    #[test]
+    fn test_call_roundtrip() {
+        let call = RemotingRequest::new("EchoService.echo", vec![amf0!("hi")]);
+        let packet = AmfPacket::from(call.clone());
+        let req = request(Uri::from_static("http://localhost/gateway"), &packet).unwrap();
+        assert_eq!(req.method(), Method::POST);
+        assert_eq!(req.headers()[CONTENT_TYPE], AMF_CONTENT_TYPE);
+        assert_eq!(from_request(&req).unwrap(), packet);
+
+        let reply = AmfPacket::new(
+            vec![],
+            vec![call.respond(RemotingResponse::result(amf0!("hi")))],
+        );
+        let res = response(&reply).unwrap();
+        assert_eq!(from_response(&res).unwrap(), reply);
+    }
+
+    #[test]
+    fn test_rejects_other_content() {
+        let packet = AmfPacket::default();
+        let mut res = response(&packet).unwrap();
+        res.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("Application/X-AMF; charset=binary"),
+        );
+        assert!(from_response(&res).is_ok());
+
+        res.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        assert_eq!(
+            from_response(&res).unwrap_err().kind(),
+            ErrorKind::InvalidHeader
+        );
+        res.headers_mut().remove(CONTENT_TYPE);
+        assert!(from_response(&res).is_err());
+
+        *res.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        assert!(matches!(
+            from_response(&res),
+            Err(AmfError::HttpStatus(500))
+        ));
+    }
+}
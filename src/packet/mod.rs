@@ -0,0 +1,10 @@
+// The AMF packet (envelope) used by Flash Remoting: HTTP request and response bodies of type
+// `application/x-amf` carrying headers and messages with AMF 0 values.
+
+pub mod envelope;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod remoting;
+
+pub use envelope::{AmfPacket, PacketHeader, PacketMessage};
+pub use remoting::{FaultObject, RemotingRequest, RemotingResponse};
@@ -0,0 +1,211 @@
+use crate::amf0::convert::{FromAmf0, field, key, object_properties, string};
+use crate::amf0::nested::{Amf0TypedValue, ObjectType};
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use crate::packet::envelope::{AmfPacket, PacketMessage};
+use indexmap::IndexMap;
+
+const ON_RESULT: &str = "/onResult";
+const ON_STATUS: &str = "/onStatus";
+
+// A call to a remote service method: the target (e.g. `"EchoService.echo"`), the response URI
+// the gateway answers to (`"/1"`, `"/2"`, ... per call in one packet) and the arguments, which
+// travel as a strict array.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemotingRequest {
+    pub target: String,
+    pub response_uri: String,
+    pub args: Vec<Amf0TypedValue>,
+}
+
+impl RemotingRequest {
+    pub fn new(target: impl Into<String>, args: Vec<Amf0TypedValue>) -> Self {
+        Self {
+            target: target.into(),
+            response_uri: "/1".to_string(),
+            args,
+        }
+    }
+
+    // The message that answers this request.
+    pub fn respond(&self, response: RemotingResponse) -> PacketMessage {
+        response.into_message(&self.response_uri)
+    }
+}
+
+impl From<RemotingRequest> for PacketMessage {
+    fn from(value: RemotingRequest) -> Self {
+        PacketMessage::new(value.target, value.response_uri, value.args.into())
+    }
+}
+
+impl From<RemotingRequest> for AmfPacket {
+    fn from(value: RemotingRequest) -> Self {
+        AmfPacket::new(vec![], vec![value.into()])
+    }
+}
+
+impl TryFrom<PacketMessage> for RemotingRequest {
+    type Error = AmfError;
+
+    // 有些客户端不用 strict array 包装单个参数
+    fn try_from(value: PacketMessage) -> Result<Self, Self::Error> {
+        let args = match value.body {
+            body @ Amf0TypedValue::StrictArray(_) => Vec::from_amf0(body)?,
+            Amf0TypedValue::Null(_) | Amf0TypedValue::Undefined(_) => vec![],
+            body => vec![body],
+        };
+        Ok(Self {
+            target: value.target_uri,
+            response_uri: value.response_uri,
+            args,
+        })
+    }
+}
+
+// The status object sent to `<response-uri>/onStatus` when a call fails, e.g.
+// `{level: "error", code: "Server.Processing", description: "...", details: "..."}`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FaultObject {
+    pub code: String,
+    pub description: String,
+    pub details: Option<String>,
+    pub extra: IndexMap<Utf8, Amf0TypedValue>,
+}
+
+impl FaultObject {
+    pub fn new(code: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            description: description.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl TryFrom<Amf0TypedValue> for FaultObject {
+    type Error = AmfError;
+
+    fn try_from(value: Amf0TypedValue) -> Result<Self, Self::Error> {
+        let mut properties = object_properties(value)?;
+        // level 总是 "error"，不保留
+        properties.shift_remove("level");
+        Ok(Self {
+            code: field(&mut properties, "code")?,
+            description: field::<Option<String>>(&mut properties, "description")?
+                .unwrap_or_default(),
+            details: field(&mut properties, "details")?,
+            extra: properties,
+        })
+    }
+}
+
+impl From<FaultObject> for ObjectType {
+    fn from(value: FaultObject) -> Self {
+        let mut properties = IndexMap::new();
+        properties.insert(key("level"), string("error"));
+        properties.insert(key("code"), string(value.code));
+        properties.insert(key("description"), string(value.description));
+        if let Some(details) = value.details {
+            properties.insert(key("details"), string(details));
+        }
+        properties.extend(value.extra);
+        ObjectType::new(properties)
+    }
+}
+
+// The outcome of a call. A gateway answers a request with `RemotingRequest::respond`; a client
+// reads the answer with `TryFrom<PacketMessage>` and matches it to the call by the message's
+// target URI (`<response-uri>/onResult` or `<response-uri>/onStatus`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemotingResponse {
+    Result(Amf0TypedValue),
+    Fault(FaultObject),
+}
+
+impl RemotingResponse {
+    pub fn result(value: impl Into<Amf0TypedValue>) -> Self {
+        RemotingResponse::Result(value.into())
+    }
+
+    pub fn fault(
+        code: impl Into<String>,
+        description: impl Into<String>,
+        details: impl Into<String>,
+    ) -> Self {
+        let mut fault = FaultObject::new(code, description);
+        fault.details = Some(details.into());
+        RemotingResponse::Fault(fault)
+    }
+
+    pub fn is_fault(&self) -> bool {
+        matches!(self, RemotingResponse::Fault(_))
+    }
+
+    // The reply message for the request with `response_uri`; its own response URI is "null".
+    pub fn into_message(self, response_uri: &str) -> PacketMessage {
+        let (suffix, body) = match self {
+            RemotingResponse::Result(value) => (ON_RESULT, value),
+            RemotingResponse::Fault(fault) => (ON_STATUS, Amf0TypedValue::Object(fault.into())),
+        };
+        PacketMessage::new(format!("{}{}", response_uri, suffix), "null", body)
+    }
+}
+
+impl TryFrom<PacketMessage> for RemotingResponse {
+    type Error = AmfError;
+
+    fn try_from(value: PacketMessage) -> Result<Self, Self::Error> {
+        if value.target_uri.ends_with(ON_RESULT) {
+            Ok(RemotingResponse::Result(value.body))
+        } else if value.target_uri.ends_with(ON_STATUS) {
+            FaultObject::try_from(value.body).map(RemotingResponse::Fault)
+        } else {
+            Err(AmfError::UnexpectedCommand(value.target_uri))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::traits::Marshall;
+
+    #[test]
+    fn test_request_response() {
+        let packet = AmfPacket::from(RemotingRequest::new(
+            "EchoService.echo",
+            vec![amf0!("hello"), amf0!(2.0)],
+        ));
+        let data = packet.marshall().unwrap();
+
+        // 网关这一侧
+        let mut decoded = AmfPacket::try_from(data.as_slice()).unwrap();
+        let request = RemotingRequest::try_from(decoded.messages.remove(0)).unwrap();
+        assert_eq!(request.target, "EchoService.echo");
+        assert_eq!(request.args, vec![amf0!("hello"), amf0!(2.0)]);
+        let reply = request.respond(RemotingResponse::result(request.args[0].clone()));
+        assert_eq!(reply.target_uri, "/1/onResult");
+        assert_eq!(reply.response_uri, "null");
+
+        assert_eq!(
+            RemotingResponse::try_from(reply).unwrap(),
+            RemotingResponse::Result(amf0!("hello"))
+        );
+    }
+
+    #[test]
+    fn test_fault() {
+        let response = RemotingResponse::fault("Server.Processing", "boom", "at line 3");
+        let message = response.clone().into_message("/2");
+        assert_eq!(message.target_uri, "/2/onStatus");
+        assert_eq!(
+            message.body,
+            amf0!({"level": "error", "code": "Server.Processing", "description": "boom", "details": "at line 3"})
+        );
+        let decoded = RemotingResponse::try_from(message).unwrap();
+        assert!(decoded.is_fault());
+        assert_eq!(decoded, response);
+    }
+}
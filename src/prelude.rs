@@ -0,0 +1,30 @@
+// The traits and types most code needs, so that `use amf_rs::prelude::*;` replaces the
+// per-module import block: the codec traits, `Amf0TypedValue` and the concrete AMF 0 types, the
+// `amf0!` macro, the Rust <-> AMF conversion traits and `AmfError`.
+pub use crate::amf0;
+pub use crate::amf0::boolean::BooleanType;
+pub use crate::amf0::convert::{FromAmf0, ToAmf0};
+pub use crate::amf0::marker::{NullType, UndefinedType};
+pub use crate::amf0::nested::{Amf0TypedValue, EcmaArrayType, ObjectType};
+pub use crate::amf0::number::NumberType;
+pub use crate::amf0::strict_array::StrictArrayType;
+pub use crate::amf0::string::{LongStringType, StringType};
+pub use crate::amf0::utf8::Utf8;
+pub use crate::errors::AmfError;
+pub use crate::traits::{Marshall, MarshallLength, Unmarshall};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prelude_is_enough_for_a_roundtrip() -> Result<(), AmfError> {
+        let value = amf0!({"duration": 12.5, "encoder": "Lavf60"});
+        let bytes = value.marshall()?;
+        assert_eq!(bytes.len(), value.marshall_length());
+        let (decoded, _) = Amf0TypedValue::unmarshall(&bytes)?;
+        assert_eq!(decoded, value);
+        assert_eq!(f64::from_amf0(2.5.to_amf0()?)?, 2.5);
+        Ok(())
+    }
+}
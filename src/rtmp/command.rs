@@ -0,0 +1,262 @@
+use crate::amf0::convert::string;
+use crate::amf0::marker::NullType;
+use crate::amf0::nested::{Amf0TypedValue, ObjectType};
+use crate::amf0::number::NumberType;
+use crate::amf0::string::StringType;
+use crate::errors::AmfError;
+use crate::traits::{Marshall, MarshallLength, Unmarshall};
+
+// RTMP message type IDs of command messages: AMF 0 commands, and "AMF 3" commands sent by
+// clients that connected with objectEncoding 3.
+pub const COMMAND_AMF0: u8 = 20;
+pub const COMMAND_AMF3: u8 = 17;
+
+// AVM+ 标记：之后是一个 AMF 3 值
+const AVMPLUS_MARKER: u8 = 0x11;
+
+//	The payload of an RTMP command message (message type 20): the command name, a transaction
+//	ID, the command object (an Object, or Null when the command has none) and any number of
+//	optional arguments, each an AMF 0 value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandMessage {
+    pub name: String,
+    pub transaction_id: f64,
+    pub command_object: Amf0TypedValue,
+    pub optional_args: Vec<Amf0TypedValue>,
+}
+
+impl CommandMessage {
+    pub fn new(
+        name: impl Into<String>,
+        transaction_id: f64,
+        command_object: Amf0TypedValue,
+        optional_args: Vec<Amf0TypedValue>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            transaction_id,
+            command_object,
+            optional_args,
+        }
+    }
+
+    // NetConnection.connect, always transaction 1. Takes an `ObjectType` or a `ConnectObject`.
+    pub fn connect(command_object: impl Into<ObjectType>) -> Self {
+        Self::new(
+            "connect",
+            1.0,
+            Amf0TypedValue::Object(command_object.into()),
+            vec![],
+        )
+    }
+
+    pub fn create_stream(transaction_id: f64) -> Self {
+        Self::new("createStream", transaction_id, null(), vec![])
+    }
+
+    // NetStream.publish; `publishing_type` is "live", "record" or "append".
+    pub fn publish(stream_name: &str, publishing_type: &str) -> Self {
+        Self::new(
+            "publish",
+            0.0,
+            null(),
+            vec![string(stream_name), string(publishing_type)],
+        )
+    }
+
+    // NetStream.play with the server's defaults for start, duration and reset.
+    pub fn play(stream_name: &str) -> Self {
+        Self::new("play", 0.0, null(), vec![string(stream_name)])
+    }
+
+    pub fn delete_stream(stream_id: f64) -> Self {
+        Self::new("deleteStream", 0.0, null(), vec![stream_id.into()])
+    }
+}
+
+impl CommandMessage {
+    // Decodes the payload of a command message of type `COMMAND_AMF0` or `COMMAND_AMF3`. An
+    // AMF 3 command is a 0x00 format byte followed by the same AMF 0 values; values switched to
+    // AMF 3 with the AVM+ marker are not supported.
+    pub fn from_payload(message_type: u8, payload: &[u8]) -> Result<Self, AmfError> {
+        let values = match message_type {
+            COMMAND_AMF0 => payload,
+            COMMAND_AMF3 => match payload.split_first() {
+                Some((0x00, values)) => values,
+                Some(_) => {
+                    return Err(AmfError::InvalidHeader {
+                        format: "AMF 3 command",
+                        field: "format byte",
+                    });
+                }
+                None => return Err(AmfError::BufferTooSmall { want: 1, got: 0 }),
+            },
+            other => return Err(AmfError::UnexpectedMessageType(other)),
+        };
+        Self::try_from(values).map_err(|e| match e.without_context() {
+            AmfError::InvalidTypeMarker(AVMPLUS_MARKER) => {
+                AmfError::UnsupportedTypeMarker(AVMPLUS_MARKER)
+            }
+            _ => e,
+        })
+    }
+
+    // Encodes the payload of a command message of type `COMMAND_AMF0` or `COMMAND_AMF3`.
+    pub fn to_payload(&self, message_type: u8) -> Result<Vec<u8>, AmfError> {
+        match message_type {
+            COMMAND_AMF0 => self.marshall(),
+            COMMAND_AMF3 => {
+                let mut vec = Vec::with_capacity(1 + self.marshall_length());
+                vec.push(0x00);
+                self.marshall_to(&mut vec)?;
+                Ok(vec)
+            }
+            other => Err(AmfError::UnexpectedMessageType(other)),
+        }
+    }
+}
+
+fn null() -> Amf0TypedValue {
+    Amf0TypedValue::Null(NullType)
+}
+
+impl Marshall for CommandMessage {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        StringType::new_from_str(&self.name)?.marshall_to(buf)?;
+        NumberType::new(self.transaction_id).marshall_to(buf)?;
+        self.command_object.marshall_to(buf)?;
+        for arg in &self.optional_args {
+            arg.marshall_to(buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl MarshallLength for CommandMessage {
+    fn marshall_length(&self) -> usize {
+        3 + self.name.len()
+            + 9
+            + self.command_object.marshall_length()
+            + self
+                .optional_args
+                .iter()
+                .map(|v| v.marshall_length())
+                .sum::<usize>()
+    }
+}
+
+// The message length comes from the RTMP chunk header, so the whole buffer is decoded: every
+// value after the command object is an optional argument.
+impl Unmarshall for CommandMessage {
+    fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
+        let (name, mut offset) = StringType::unmarshall(buf)?;
+        let (transaction_id, n) = NumberType::unmarshall(&buf[offset..])?;
+        offset += n;
+        let (command_object, n) = Amf0TypedValue::unmarshall(&buf[offset..])?;
+        offset += n;
+        let mut optional_args = Vec::new();
+        while offset < buf.len() {
+            let (arg, n) = Amf0TypedValue::unmarshall(&buf[offset..])?;
+            optional_args.push(arg);
+            offset += n;
+        }
+        let message = Self::new(
+            String::try_from(name)?,
+            *transaction_id,
+            command_object,
+            optional_args,
+        );
+        Ok((message, offset))
+    }
+}
+
+impl TryFrom<&[u8]> for CommandMessage {
+    type Error = AmfError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::unmarshall(value).map(|(message, _)| message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::testing::assert_roundtrip;
+
+    #[test]
+    fn test_connect_bytes() {
+        let object = amf0!({"app": "live"});
+        let message = CommandMessage::connect(object.as_object().unwrap().clone());
+        let data = message.marshall().unwrap();
+        let mut expected = b"\x02\x00\x07connect\x00\x3f\xf0\x00\x00\x00\x00\x00\x00".to_vec();
+        expected.extend_from_slice(&object.marshall().unwrap());
+        assert_eq!(data, expected);
+        assert_eq!(CommandMessage::try_from(data.as_slice()).unwrap(), message);
+    }
+
+    #[test]
+    fn test_builders_round_trip() {
+        for message in [
+            CommandMessage::create_stream(2.0),
+            CommandMessage::publish("stream", "live"),
+            CommandMessage::play("stream"),
+            CommandMessage::delete_stream(1.0),
+        ] {
+            assert_roundtrip(&message);
+        }
+        let publish = CommandMessage::publish("stream", "live");
+        assert!(publish.command_object.is_null());
+        assert_eq!(publish.optional_args, vec![amf0!("stream"), amf0!("live")]);
+    }
+
+    // 按 Flash Player(objectEncoding 3)发出的 type 17 connect 消息的格式构造
+    #[test]
+    fn test_amf3_command_framing() {
+        let mut payload = vec![0x00];
+        payload.extend_from_slice(b"\x02\x00\x07connect\x00\x3f\xf0\x00\x00\x00\x00\x00\x00");
+        payload.extend_from_slice(b"\x03\x00\x03app\x02\x00\x04live");
+        payload.extend_from_slice(b"\x00\x0eobjectEncoding\x00\x40\x08\x00\x00\x00\x00\x00\x00");
+        payload.extend_from_slice(b"\x00\x00\x09");
+        let message = CommandMessage::from_payload(COMMAND_AMF3, &payload).unwrap();
+        assert_eq!(message.name, "connect");
+        assert_eq!(message.command_object["objectEncoding"], amf0!(3.0));
+        assert_eq!(message.to_payload(COMMAND_AMF3).unwrap(), payload);
+        assert_eq!(
+            CommandMessage::from_payload(COMMAND_AMF0, &payload[1..]).unwrap(),
+            message
+        );
+
+        // 切换到 AMF 3 的值(AVM+ 标记)
+        let mut payload = vec![0x00];
+        payload.extend_from_slice(&CommandMessage::play("s").marshall().unwrap());
+        payload.extend_from_slice(&[0x11, 0x01]);
+        assert!(matches!(
+            CommandMessage::from_payload(COMMAND_AMF3, &payload),
+            Err(AmfError::UnsupportedTypeMarker(0x11))
+        ));
+        assert!(CommandMessage::from_payload(COMMAND_AMF3, &[]).is_err());
+        assert!(matches!(
+            CommandMessage::from_payload(18, &payload),
+            Err(AmfError::UnexpectedMessageType(18))
+        ));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        // 事务 ID 必须是 Number
+        let mut data = amf0!("connect").marshall().unwrap();
+        data.extend_from_slice(&amf0!("1").marshall().unwrap());
+        assert!(CommandMessage::try_from(data.as_slice()).is_err());
+        // 缺少 command object
+        let mut data = amf0!("connect").marshall().unwrap();
+        data.extend_from_slice(&amf0!(1.0).marshall().unwrap());
+        assert!(CommandMessage::try_from(data.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::amf0::convert::{FromAmf0, ToAmf0, field, key, object_properties, string};
+use crate::amf0::nested::{Amf0TypedValue, ObjectType};
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use indexmap::IndexMap;
+
+// The AMF version a client asks the server to use for its replies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ObjectEncoding {
+    #[default]
+    Amf0,
+    Amf3,
+}
+
+impl ToAmf0 for ObjectEncoding {
+    fn to_amf0(&self) -> Result<Amf0TypedValue, AmfError> {
+        let version = match self {
+            ObjectEncoding::Amf0 => 0.0,
+            ObjectEncoding::Amf3 => 3.0,
+        };
+        Ok(Amf0TypedValue::from(version))
+    }
+}
+
+impl FromAmf0 for ObjectEncoding {
+    fn from_amf0(value: Amf0TypedValue) -> Result<Self, AmfError> {
+        match f64::from_amf0(value)? {
+            0.0 => Ok(ObjectEncoding::Amf0),
+            3.0 => Ok(ObjectEncoding::Amf3),
+            other => Err(AmfError::NumberOutOfRange {
+                value: other,
+                target: "ObjectEncoding",
+            }),
+        }
+    }
+}
+
+// The command object of NetConnection.connect. Only `app` is required; properties that are
+// absent are `None`, and unknown ones are kept in `extra` so nothing is lost on the way back.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConnectObject {
+    pub app: String,
+    pub flash_ver: Option<String>,
+    pub swf_url: Option<String>,
+    pub tc_url: Option<String>,
+    pub fpad: Option<bool>,
+    pub capabilities: Option<f64>,
+    pub audio_codecs: Option<f64>,
+    pub video_codecs: Option<f64>,
+    pub video_function: Option<f64>,
+    pub page_url: Option<String>,
+    pub object_encoding: Option<ObjectEncoding>,
+    pub extra: IndexMap<Utf8, Amf0TypedValue>,
+}
+
+impl ConnectObject {
+    pub fn new(app: impl Into<String>) -> Self {
+        Self {
+            app: app.into(),
+            ..Default::default()
+        }
+    }
+
+    // The encoding the server should reply in; AMF 0 when the client did not say.
+    pub fn encoding(&self) -> ObjectEncoding {
+        self.object_encoding.unwrap_or_default()
+    }
+}
+
+impl TryFrom<Amf0TypedValue> for ConnectObject {
+    type Error = AmfError;
+
+    fn try_from(value: Amf0TypedValue) -> Result<Self, Self::Error> {
+        let mut properties = object_properties(value)?;
+        Ok(Self {
+            app: field(&mut properties, "app")?,
+            flash_ver: field(&mut properties, "flashVer")?,
+            swf_url: field(&mut properties, "swfUrl")?,
+            tc_url: field(&mut properties, "tcUrl")?,
+            fpad: field(&mut properties, "fpad")?,
+            capabilities: field(&mut properties, "capabilities")?,
+            audio_codecs: field(&mut properties, "audioCodecs")?,
+            video_codecs: field(&mut properties, "videoCodecs")?,
+            video_function: field(&mut properties, "videoFunction")?,
+            page_url: field(&mut properties, "pageUrl")?,
+            object_encoding: field(&mut properties, "objectEncoding")?,
+            extra: properties,
+        })
+    }
+}
+
+impl TryFrom<ObjectType> for ConnectObject {
+    type Error = AmfError;
+
+    fn try_from(value: ObjectType) -> Result<Self, Self::Error> {
+        Self::try_from(Amf0TypedValue::Object(value))
+    }
+}
+
+impl From<ConnectObject> for ObjectType {
+    fn from(value: ConnectObject) -> Self {
+        let mut properties = IndexMap::new();
+        let mut put = |name: &str, v: Option<Amf0TypedValue>| {
+            if let Some(v) = v {
+                properties.insert(key(name), v);
+            }
+        };
+        put("app", Some(string(value.app)));
+        put("flashVer", value.flash_ver.map(string));
+        put("swfUrl", value.swf_url.map(string));
+        put("tcUrl", value.tc_url.map(string));
+        put("fpad", value.fpad.map(Amf0TypedValue::from));
+        put("capabilities", value.capabilities.map(Amf0TypedValue::from));
+        put("audioCodecs", value.audio_codecs.map(Amf0TypedValue::from));
+        put("videoCodecs", value.video_codecs.map(Amf0TypedValue::from));
+        put(
+            "videoFunction",
+            value.video_function.map(Amf0TypedValue::from),
+        );
+        put("pageUrl", value.page_url.map(string));
+        put(
+            "objectEncoding",
+            value.object_encoding.map(|e| e.to_amf0().unwrap()), // 总是成功
+        );
+        properties.extend(value.extra);
+        ObjectType::new(properties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::errors::ErrorKind;
+    use crate::rtmp::command::CommandMessage;
+    use crate::traits::{Marshall, Unmarshall};
+
+    #[test]
+    fn test_flash_player_connect() {
+        let value = amf0!({
+            "app": "live",
+            "flashVer": "WIN 32,0,0,465",
+            "swfUrl": undefined,
+            "tcUrl": "rtmp://localhost/live",
+            "fpad": false,
+            "capabilities": 239.0,
+            "audioCodecs": 3575.0,
+            "videoCodecs": 252.0,
+            "videoFunction": 1.0,
+            "pageUrl": undefined,
+            "objectEncoding": 3.0,
+        });
+        let connect = ConnectObject::try_from(value).unwrap();
+        assert_eq!(connect.app, "live");
+        assert_eq!(connect.flash_ver.as_deref(), Some("WIN 32,0,0,465"));
+        assert_eq!(connect.swf_url, None);
+        assert_eq!(connect.audio_codecs, Some(3575.0));
+        assert_eq!(connect.encoding(), ObjectEncoding::Amf3);
+        assert!(connect.extra.is_empty());
+
+        let message = CommandMessage::connect(connect.clone());
+        let (decoded, _) = CommandMessage::unmarshall(&message.marshall().unwrap()).unwrap();
+        assert_eq!(
+            ConnectObject::try_from(decoded.command_object).unwrap(),
+            connect
+        );
+    }
+
+    #[test]
+    fn test_defaults_and_errors() {
+        let connect = ConnectObject::try_from(amf0!({"app": "vod", "custom": 1.0})).unwrap();
+        assert_eq!(connect.encoding(), ObjectEncoding::Amf0);
+        assert_eq!(connect.extra["custom"], amf0!(1.0));
+        assert_eq!(
+            Amf0TypedValue::Object(connect.into()),
+            amf0!({"app": "vod", "custom": 1.0})
+        );
+
+        assert!(ConnectObject::try_from(amf0!({"tcUrl": "rtmp://x"})).is_err());
+        let err = ConnectObject::try_from(amf0!({"app": "a", "objectEncoding": 1.0})).unwrap_err();
+        assert_eq!(err.path(), Some("objectEncoding"));
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::amf0::convert::string;
+use crate::amf0::string::StringType;
+use crate::errors::AmfError;
+use crate::flv::metadata::OnMetaData;
+use crate::flv::script_data::ScriptTagBody;
+use crate::traits::{Marshall, MarshallLength, Unmarshall};
+
+pub const SET_DATA_FRAME: &str = "@setDataFrame";
+
+// The payload of an RTMP AMF 0 data message (message type 18). A publisher sends its metadata
+// as `@setDataFrame`, `onMetaData`, ECMA array; the server stores it and sends it to players
+// without the `@setDataFrame` prefix, which is then the same body as an FLV script data tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataMessage {
+    pub set_data_frame: bool,
+    pub body: ScriptTagBody,
+}
+
+impl DataMessage {
+    pub fn new(set_data_frame: bool, body: ScriptTagBody) -> Self {
+        Self {
+            set_data_frame,
+            body,
+        }
+    }
+
+    // The message a publisher (OBS, FFmpeg) sends to set the stream metadata.
+    pub fn set_data_frame(metadata: OnMetaData) -> Self {
+        Self::new(true, ScriptTagBody::on_metadata(metadata.into()))
+    }
+
+    // The message a server sends to players.
+    pub fn on_metadata(metadata: OnMetaData) -> Self {
+        Self::new(false, ScriptTagBody::on_metadata(metadata.into()))
+    }
+
+    // Drops the `@setDataFrame` prefix, as a server does before relaying the metadata.
+    pub fn into_playback(self) -> Self {
+        Self::new(false, self.body)
+    }
+
+    // The typed metadata, if this is an onMetaData message.
+    pub fn metadata(&self) -> Option<Result<OnMetaData, AmfError>> {
+        self.body
+            .is_on_metadata()
+            .then(|| OnMetaData::try_from(self.body.value.clone()))
+    }
+}
+
+impl Marshall for DataMessage {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        if self.set_data_frame {
+            string(SET_DATA_FRAME).marshall_to(buf)?;
+        }
+        self.body.marshall_to(buf)
+    }
+}
+
+impl MarshallLength for DataMessage {
+    fn marshall_length(&self) -> usize {
+        let prefix = if self.set_data_frame {
+            3 + SET_DATA_FRAME.len()
+        } else {
+            0
+        };
+        prefix + self.body.marshall_length()
+    }
+}
+
+impl Unmarshall for DataMessage {
+    fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
+        let (name, n) = StringType::unmarshall(buf)?;
+        if **name == *SET_DATA_FRAME {
+            let (body, m) = ScriptTagBody::unmarshall(&buf[n..])?;
+            Ok((Self::new(true, body), n + m))
+        } else {
+            let (body, m) = ScriptTagBody::unmarshall(buf)?;
+            Ok((Self::new(false, body), m))
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for DataMessage {
+    type Error = AmfError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::unmarshall(value).map(|(message, _)| message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::testing::assert_roundtrip;
+
+    fn obs_metadata() -> OnMetaData {
+        OnMetaData {
+            width: Some(1920.0),
+            height: Some(1080.0),
+            framerate: Some(60.0),
+            encoder: Some("obs-output module (libobs version 30.0.0)".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_set_data_frame() {
+        let message = DataMessage::set_data_frame(obs_metadata());
+        let data = message.marshall().unwrap();
+        assert!(data.starts_with(b"\x02\x00\x0d@setDataFrame\x02\x00\x0aonMetaData\x08"));
+        assert_roundtrip(&message);
+
+        let decoded = DataMessage::try_from(data.as_slice()).unwrap();
+        assert!(decoded.set_data_frame);
+        assert_eq!(decoded.metadata().unwrap().unwrap(), obs_metadata());
+
+        // 转发给播放端时去掉前缀，和 FLV script tag 的内容相同
+        let playback = decoded.into_playback();
+        assert_eq!(playback, DataMessage::on_metadata(obs_metadata()));
+        assert_eq!(
+            playback.marshall().unwrap(),
+            playback.body.marshall().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_other_data_messages() {
+        let body = ScriptTagBody::new(
+            StringType::new_from_str("|RtmpSampleAccess").unwrap(),
+            amf0!(false),
+        );
+        let message = DataMessage::try_from(body.marshall().unwrap().as_slice()).unwrap();
+        assert!(!message.set_data_frame);
+        assert!(message.metadata().is_none());
+    }
+}
@@ -0,0 +1,14 @@
+// RTMP message payloads built on the AMF 0 codec. Only the AMF layer is covered: chunking,
+// handshakes and connection state are left to the RTMP implementation.
+
+pub mod command;
+pub mod connect;
+pub mod data;
+pub mod response;
+pub mod shared_object;
+
+pub use command::{COMMAND_AMF0, COMMAND_AMF3, CommandMessage};
+pub use connect::{ConnectObject, ObjectEncoding};
+pub use data::DataMessage;
+pub use response::{CommandResponse, ResponseKind, StatusInfo};
+pub use shared_object::{SharedObjectEvent, SharedObjectMessage};
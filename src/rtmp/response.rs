@@ -0,0 +1,232 @@
+use crate::amf0::convert::{field, key, object_properties, string};
+use crate::amf0::marker::NullType;
+use crate::amf0::nested::{Amf0TypedValue, ObjectType};
+use crate::amf0::utf8::Utf8;
+use crate::errors::AmfError;
+use crate::rtmp::command::CommandMessage;
+use crate::traits::{Marshall, MarshallLength, Unmarshall};
+use indexmap::IndexMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResponseKind {
+    Result,
+    Error,
+    Status,
+}
+
+impl ResponseKind {
+    pub fn command_name(&self) -> &'static str {
+        match self {
+            ResponseKind::Result => "_result",
+            ResponseKind::Error => "_error",
+            ResponseKind::Status => "onStatus",
+        }
+    }
+}
+
+// The info object of a status event or a connect reply, e.g.
+// `{level: "status", code: "NetStream.Play.Start", description: "..."}`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StatusInfo {
+    pub level: String,
+    pub code: String,
+    pub description: Option<String>,
+    pub extra: IndexMap<Utf8, Amf0TypedValue>,
+}
+
+impl StatusInfo {
+    pub fn new(level: impl Into<String>, code: impl Into<String>) -> Self {
+        Self {
+            level: level.into(),
+            code: code.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == "error"
+    }
+}
+
+impl TryFrom<Amf0TypedValue> for StatusInfo {
+    type Error = AmfError;
+
+    fn try_from(value: Amf0TypedValue) -> Result<Self, Self::Error> {
+        let mut properties = object_properties(value)?;
+        Ok(Self {
+            level: field(&mut properties, "level")?,
+            code: field(&mut properties, "code")?,
+            description: field(&mut properties, "description")?,
+            extra: properties,
+        })
+    }
+}
+
+impl From<StatusInfo> for ObjectType {
+    fn from(value: StatusInfo) -> Self {
+        let mut properties = IndexMap::new();
+        properties.insert(key("level"), string(value.level));
+        properties.insert(key("code"), string(value.code));
+        if let Some(description) = value.description {
+            properties.insert(key("description"), string(description));
+        }
+        properties.extend(value.extra);
+        ObjectType::new(properties)
+    }
+}
+
+// A reply to a command (`_result` / `_error`) or a status event (`onStatus`). `properties` is
+// the command object (server properties in a connect reply, usually Null otherwise). When the
+// first argument is an info object with a `code` it is decoded into `info`; the remaining
+// arguments, such as the stream ID in a createStream reply, are left in `args`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandResponse {
+    pub kind: ResponseKind,
+    pub transaction_id: f64,
+    pub properties: Amf0TypedValue,
+    pub info: Option<StatusInfo>,
+    pub args: Vec<Amf0TypedValue>,
+}
+
+impl CommandResponse {
+    // An onStatus event, always transaction 0.
+    pub fn on_status(info: StatusInfo) -> Self {
+        Self {
+            kind: ResponseKind::Status,
+            transaction_id: 0.0,
+            properties: Amf0TypedValue::Null(NullType),
+            info: Some(info),
+            args: vec![],
+        }
+    }
+
+    // The stream ID of a createStream reply.
+    pub fn stream_id(&self) -> Option<f64> {
+        self.args.first().and_then(Amf0TypedValue::as_number)
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.info.as_ref().map(|info| info.code.as_str())
+    }
+}
+
+impl TryFrom<CommandMessage> for CommandResponse {
+    type Error = AmfError;
+
+    fn try_from(value: CommandMessage) -> Result<Self, Self::Error> {
+        let kind = match value.name.as_str() {
+            "_result" => ResponseKind::Result,
+            "_error" => ResponseKind::Error,
+            "onStatus" => ResponseKind::Status,
+            other => return Err(AmfError::UnexpectedCommand(other.to_string())),
+        };
+        let mut args = value.optional_args;
+        let is_info = args.first().is_some_and(|arg| {
+            matches!(
+                arg,
+                Amf0TypedValue::Object(_) | Amf0TypedValue::EcmaArray(_)
+            ) && arg.get_path("code").is_some()
+        });
+        let info = if is_info {
+            Some(StatusInfo::try_from(args.remove(0))?)
+        } else {
+            None
+        };
+        Ok(Self {
+            kind,
+            transaction_id: value.transaction_id,
+            properties: value.command_object,
+            info,
+            args,
+        })
+    }
+}
+
+impl From<CommandResponse> for CommandMessage {
+    fn from(value: CommandResponse) -> Self {
+        let mut args = value.args;
+        if let Some(info) = value.info {
+            args.insert(0, Amf0TypedValue::Object(info.into()));
+        }
+        CommandMessage::new(
+            value.kind.command_name(),
+            value.transaction_id,
+            value.properties,
+            args,
+        )
+    }
+}
+
+impl Marshall for CommandResponse {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        CommandMessage::from(self.clone()).marshall()
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        CommandMessage::from(self.clone()).marshall_to(buf)
+    }
+}
+
+impl MarshallLength for CommandResponse {
+    fn marshall_length(&self) -> usize {
+        CommandMessage::from(self.clone()).marshall_length()
+    }
+}
+
+impl Unmarshall for CommandResponse {
+    fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
+        let (message, n) = CommandMessage::unmarshall(buf)?;
+        Ok((Self::try_from(message)?, n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+
+    #[test]
+    fn test_connect_result() {
+        let message = CommandMessage::new(
+            "_result",
+            1.0,
+            amf0!({"fmsVer": "FMS/3,0,1,123", "capabilities": 31.0}),
+            vec![amf0!({
+                "level": "status",
+                "code": "NetConnection.Connect.Success",
+                "description": "Connection succeeded.",
+                "objectEncoding": 0.0,
+            })],
+        );
+        let (response, _) = CommandResponse::unmarshall(&message.marshall().unwrap()).unwrap();
+        assert_eq!(response.kind, ResponseKind::Result);
+        assert_eq!(response.transaction_id, 1.0);
+        assert_eq!(response.properties["fmsVer"], amf0!("FMS/3,0,1,123"));
+        let info = response.info.as_ref().unwrap();
+        assert_eq!(info.code, "NetConnection.Connect.Success");
+        assert!(!info.is_error());
+        assert_eq!(info.extra["objectEncoding"], amf0!(0.0));
+        assert_eq!(CommandMessage::from(response), message);
+    }
+
+    #[test]
+    fn test_create_stream_result_and_status() {
+        let message = CommandMessage::new("_result", 2.0, amf0!(null), vec![amf0!(1.0)]);
+        let response = CommandResponse::try_from(message).unwrap();
+        assert_eq!(response.info, None);
+        assert_eq!(response.stream_id(), Some(1.0));
+
+        let mut info = StatusInfo::new("status", "NetStream.Play.Start");
+        info.description = Some("Started playing.".to_string());
+        let status = CommandResponse::on_status(info);
+        let (decoded, _) = CommandResponse::unmarshall(&status.marshall().unwrap()).unwrap();
+        assert_eq!(decoded, status);
+        assert_eq!(decoded.code(), Some("NetStream.Play.Start"));
+
+        let play = CommandMessage::play("stream");
+        assert!(matches!(
+            CommandResponse::try_from(play),
+            Err(AmfError::UnexpectedCommand(name)) if name == "play"
+        ));
+    }
+}
@@ -0,0 +1,354 @@
+use crate::amf0::nested::Amf0TypedValue;
+use crate::amf0::utf8::{Utf8, unmarshall_str};
+use crate::errors::{AmfError, checked_end};
+use crate::traits::{Marshall, MarshallLength, Unmarshall};
+use indexmap::IndexMap;
+
+// RTMP message type IDs of shared object messages. Only the AMF 0 flavour can be decoded: the
+// AMF 3 one has the same layout, but its values are AMF 3.
+pub const SHARED_OBJECT_AMF0: u8 = 19;
+pub const SHARED_OBJECT_AMF3: u8 = 16;
+
+// One event of a shared object message. Property names and strings are written as a U16 length
+// and UTF-8 bytes (no type marker), values as AMF 0.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SharedObjectEvent {
+    // Client: start using the shared object.
+    Use,
+    // Client: stop using it.
+    Release,
+    // Client: ask to change the given properties.
+    RequestChange(IndexMap<Utf8, Amf0TypedValue>),
+    // Server: the given properties changed.
+    Change(IndexMap<Utf8, Amf0TypedValue>),
+    // Server: the RequestChange of this property was accepted.
+    Success(String),
+    // Either side: a method name followed by its arguments, broadcast to every client.
+    SendMessage(Vec<Amf0TypedValue>),
+    // Server: an error or warning.
+    Status { code: String, level: String },
+    // Server: the client must drop its copy.
+    Clear,
+    // Server: a property was deleted.
+    Remove(String),
+    // Client: ask to delete a property.
+    RequestRemove(String),
+    // Server: the Use was accepted.
+    UseSuccess,
+    // An event type not listed above, kept as is.
+    Other { event_type: u8, data: Vec<u8> },
+}
+
+impl SharedObjectEvent {
+    pub fn event_type(&self) -> u8 {
+        match self {
+            SharedObjectEvent::Use => 1,
+            SharedObjectEvent::Release => 2,
+            SharedObjectEvent::RequestChange(_) => 3,
+            SharedObjectEvent::Change(_) => 4,
+            SharedObjectEvent::Success(_) => 5,
+            SharedObjectEvent::SendMessage(_) => 6,
+            SharedObjectEvent::Status { .. } => 7,
+            SharedObjectEvent::Clear => 8,
+            SharedObjectEvent::Remove(_) => 9,
+            SharedObjectEvent::RequestRemove(_) => 10,
+            SharedObjectEvent::UseSuccess => 11,
+            SharedObjectEvent::Other { event_type, .. } => *event_type,
+        }
+    }
+
+    fn data_length(&self) -> usize {
+        match self {
+            SharedObjectEvent::Use
+            | SharedObjectEvent::Release
+            | SharedObjectEvent::Clear
+            | SharedObjectEvent::UseSuccess => 0,
+            SharedObjectEvent::RequestChange(properties)
+            | SharedObjectEvent::Change(properties) => properties
+                .iter()
+                .map(|(k, v)| k.marshall_length() + v.marshall_length())
+                .sum(),
+            SharedObjectEvent::Success(name)
+            | SharedObjectEvent::Remove(name)
+            | SharedObjectEvent::RequestRemove(name) => 2 + name.len(),
+            SharedObjectEvent::SendMessage(values) => {
+                values.iter().map(|v| v.marshall_length()).sum()
+            }
+            SharedObjectEvent::Status { code, level } => 4 + code.len() + level.len(),
+            SharedObjectEvent::Other { data, .. } => data.len(),
+        }
+    }
+
+    fn marshall_data_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        match self {
+            SharedObjectEvent::Use
+            | SharedObjectEvent::Release
+            | SharedObjectEvent::Clear
+            | SharedObjectEvent::UseSuccess => {}
+            SharedObjectEvent::RequestChange(properties)
+            | SharedObjectEvent::Change(properties) => {
+                for (k, v) in properties {
+                    k.marshall_to(buf)?;
+                    v.marshall_to(buf)?;
+                }
+            }
+            SharedObjectEvent::Success(name)
+            | SharedObjectEvent::Remove(name)
+            | SharedObjectEvent::RequestRemove(name) => {
+                Utf8::new_from_str(name)?.marshall_to(buf)?
+            }
+            SharedObjectEvent::SendMessage(values) => {
+                for v in values {
+                    v.marshall_to(buf)?;
+                }
+            }
+            SharedObjectEvent::Status { code, level } => {
+                Utf8::new_from_str(code)?.marshall_to(buf)?;
+                Utf8::new_from_str(level)?.marshall_to(buf)?;
+            }
+            SharedObjectEvent::Other { data, .. } => buf.extend_from_slice(data),
+        }
+        Ok(())
+    }
+
+    fn unmarshall_data(event_type: u8, data: &[u8]) -> Result<Self, AmfError> {
+        let event = match event_type {
+            1 => SharedObjectEvent::Use,
+            2 => SharedObjectEvent::Release,
+            3 => SharedObjectEvent::RequestChange(unmarshall_properties(data)?),
+            4 => SharedObjectEvent::Change(unmarshall_properties(data)?),
+            5 => SharedObjectEvent::Success(unmarshall_str::<2>(data)?.0.to_string()),
+            6 => SharedObjectEvent::SendMessage(unmarshall_values(data)?),
+            7 => {
+                let (code, n) = unmarshall_str::<2>(data)?;
+                let (level, _) = unmarshall_str::<2>(&data[n..])?;
+                SharedObjectEvent::Status {
+                    code: code.to_string(),
+                    level: level.to_string(),
+                }
+            }
+            8 => SharedObjectEvent::Clear,
+            9 => SharedObjectEvent::Remove(unmarshall_str::<2>(data)?.0.to_string()),
+            10 => SharedObjectEvent::RequestRemove(unmarshall_str::<2>(data)?.0.to_string()),
+            11 => SharedObjectEvent::UseSuccess,
+            event_type => SharedObjectEvent::Other {
+                event_type,
+                data: data.to_vec(),
+            },
+        };
+        Ok(event)
+    }
+}
+
+fn unmarshall_properties(data: &[u8]) -> Result<IndexMap<Utf8, Amf0TypedValue>, AmfError> {
+    let mut properties = IndexMap::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let (k, n) = Utf8::unmarshall(&data[offset..])?;
+        offset += n;
+        let (v, n) = Amf0TypedValue::unmarshall(&data[offset..])?;
+        offset += n;
+        properties.insert(k, v);
+    }
+    Ok(properties)
+}
+
+fn unmarshall_values(data: &[u8]) -> Result<Vec<Amf0TypedValue>, AmfError> {
+    let mut values = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let (v, n) = Amf0TypedValue::unmarshall(&data[offset..])?;
+        offset += n;
+        values.push(v);
+    }
+    Ok(values)
+}
+
+//	The body of a shared object message (message types 16 and 19): the shared object name, its
+//	version, 8 bytes of flags (persistence), then a list of events, each an event type byte, a
+//	U32 data length and the event data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedObjectMessage {
+    pub name: String,
+    pub version: u32,
+    pub flags: [u8; 8],
+    pub events: Vec<SharedObjectEvent>,
+}
+
+impl SharedObjectMessage {
+    pub fn new(name: impl Into<String>, events: Vec<SharedObjectEvent>) -> Self {
+        Self {
+            name: name.into(),
+            version: 0,
+            flags: [0; 8],
+            events,
+        }
+    }
+}
+
+impl Marshall for SharedObjectMessage {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        Utf8::new_from_str(&self.name)?.marshall_to(buf)?;
+        buf.extend_from_slice(&self.version.to_be_bytes());
+        buf.extend_from_slice(&self.flags);
+        for event in &self.events {
+            let length = event.data_length();
+            if length > u32::MAX as usize {
+                return Err(AmfError::TooLong {
+                    what: "Shared object event",
+                    max: u32::MAX as usize,
+                    got: length,
+                });
+            }
+            buf.push(event.event_type());
+            buf.extend_from_slice(&(length as u32).to_be_bytes());
+            event.marshall_data_to(buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl MarshallLength for SharedObjectMessage {
+    fn marshall_length(&self) -> usize {
+        2 + self.name.len()
+            + 4
+            + 8
+            + self
+                .events
+                .iter()
+                .map(|e| 5 + e.data_length())
+                .sum::<usize>()
+    }
+}
+
+// Like a command message, the body runs to the end of the RTMP message, so the whole buffer
+// is decoded as events.
+impl Unmarshall for SharedObjectMessage {
+    fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
+        let (name, mut offset) = unmarshall_str::<2>(buf)?;
+        let header_end = checked_end(offset, 12)?;
+        if buf.len() < header_end {
+            return Err(AmfError::BufferTooSmall {
+                want: header_end,
+                got: buf.len(),
+            });
+        }
+        let version = u32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap());
+        let flags = buf[offset + 4..header_end].try_into().unwrap();
+        offset = header_end;
+
+        let mut events = Vec::new();
+        while offset < buf.len() {
+            let data_start = checked_end(offset, 5)?;
+            if buf.len() < data_start {
+                return Err(AmfError::BufferTooSmall {
+                    want: data_start,
+                    got: buf.len(),
+                });
+            }
+            let event_type = buf[offset];
+            let length = u32::from_be_bytes(buf[offset + 1..data_start].try_into().unwrap());
+            let data_end = checked_end(data_start, length as usize)?;
+            if buf.len() < data_end {
+                return Err(AmfError::BufferTooSmall {
+                    want: data_end,
+                    got: buf.len(),
+                });
+            }
+            events.push(SharedObjectEvent::unmarshall_data(
+                event_type,
+                &buf[data_start..data_end],
+            )?);
+            offset = data_end;
+        }
+        let message = Self {
+            name: name.to_string(),
+            version,
+            flags,
+            events,
+        };
+        Ok((message, offset))
+    }
+}
+
+impl TryFrom<&[u8]> for SharedObjectMessage {
+    type Error = AmfError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::unmarshall(value).map(|(message, _)| message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::testing::assert_roundtrip;
+
+    #[test]
+    fn test_use_bytes() {
+        let message = SharedObjectMessage::new("chat", vec![SharedObjectEvent::Use]);
+        let data = message.marshall().unwrap();
+        assert_eq!(
+            data,
+            b"\x00\x04chat\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x00\x00\x00\x00"
+        );
+        assert_eq!(
+            SharedObjectMessage::try_from(data.as_slice()).unwrap(),
+            message
+        );
+    }
+
+    #[test]
+    fn test_events_round_trip() {
+        let change: IndexMap<Utf8, Amf0TypedValue> = [
+            (Utf8::new_from_str("topic").unwrap(), amf0!("rust")),
+            (Utf8::new_from_str("users").unwrap(), amf0!(3.0)),
+        ]
+        .into_iter()
+        .collect();
+        let mut message = SharedObjectMessage::new(
+            "room",
+            vec![
+                SharedObjectEvent::UseSuccess,
+                SharedObjectEvent::Clear,
+                SharedObjectEvent::Change(change.clone()),
+                SharedObjectEvent::RequestChange(change),
+                SharedObjectEvent::Success("topic".to_string()),
+                SharedObjectEvent::SendMessage(vec![amf0!("say"), amf0!({"text": "hi"})]),
+                SharedObjectEvent::Status {
+                    code: "SharedObject.BadPersistence".to_string(),
+                    level: "error".to_string(),
+                },
+                SharedObjectEvent::Remove("users".to_string()),
+                SharedObjectEvent::RequestRemove("users".to_string()),
+                SharedObjectEvent::Release,
+                SharedObjectEvent::Other {
+                    event_type: 42,
+                    data: vec![1, 2, 3],
+                },
+            ],
+        );
+        message.version = 7;
+        message.flags[3] = 0x02;
+        assert_roundtrip(&message);
+    }
+
+    #[test]
+    fn test_truncated_event() {
+        let mut data = SharedObjectMessage::new("so", vec![SharedObjectEvent::Success("k".into())])
+            .marshall()
+            .unwrap();
+        data.pop();
+        assert!(matches!(
+            SharedObjectMessage::try_from(data.as_slice()),
+            Err(AmfError::BufferTooSmall { .. })
+        ));
+    }
+}
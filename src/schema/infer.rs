@@ -0,0 +1,342 @@
+use crate::amf0::nested::{Amf0TypedValue, NestedType};
+use crate::schema::{ObjectSchema, PropertySchema, Schema};
+use indexmap::IndexMap;
+use std::fmt::Write;
+
+impl Schema {
+    // The schema of a sample value, to bootstrap typed handling of an unknown payload: every
+    // property of the sample is required, numbers are unbounded, and the elements of a strict
+    // array are merged into one schema (properties missing from some elements become
+    // optional, differing types become `OneOf`). An empty array accepts any element. Validate
+    // more samples and relax the result where they disagree.
+    pub fn infer(value: &Amf0TypedValue) -> Schema {
+        match value {
+            Amf0TypedValue::Number(_) => Schema::number(),
+            Amf0TypedValue::Boolean(_) => Schema::Boolean,
+            Amf0TypedValue::String(_) | Amf0TypedValue::LongString(_) => Schema::String,
+            Amf0TypedValue::Null(_) => Schema::Null,
+            Amf0TypedValue::Undefined(_) => Schema::Undefined,
+            Amf0TypedValue::Object(v) => Schema::Object(infer_object(v)),
+            Amf0TypedValue::EcmaArray(v) => Schema::Object(infer_object(v)),
+            Amf0TypedValue::StrictArray(values) => {
+                let element = values.iter().map(Schema::infer).reduce(merge);
+                Schema::array_of(element.unwrap_or(Schema::Any))
+            }
+            _ => Schema::Any,
+        }
+    }
+
+    // Rust definitions for values of this schema, one struct per object schema (nested ones
+    // are named after their property key), ready for `#[derive(AmfObject)]`. Optional
+    // properties and `OneOf` with null/undefined become `Option`, anything without a Rust
+    // counterpart stays an `Amf0TypedValue`. Keys that are not snake_case identifiers get an
+    // `#[amf(rename = "...")]` attribute. A root that is not an object becomes a type alias.
+    pub fn to_rust_struct(&self, name: &str) -> String {
+        let mut generator = StructGenerator::default();
+        match self {
+            Schema::Object(object) => {
+                generator.push(pascal_case(name), object);
+            }
+            other => {
+                let ty = generator.type_of(other, name);
+                writeln!(generator.out, "pub type {} = {};", pascal_case(name), ty).unwrap();
+            }
+        }
+        while let Some((name, object)) = generator.pending.pop() {
+            generator.write_struct(&name, object);
+        }
+        generator.out
+    }
+}
+
+fn infer_object<const LBW: usize, const TM: u8>(object: &NestedType<LBW, TM>) -> ObjectSchema {
+    let properties = object
+        .iter()
+        .map(|(key, value)| {
+            let property = PropertySchema {
+                schema: Schema::infer(value),
+                required: true,
+            };
+            (key.to_string(), property)
+        })
+        .collect();
+    ObjectSchema {
+        properties,
+        deny_unknown: false,
+    }
+}
+
+// 合并两个 schema，结果同时接受两者接受的值
+fn merge(a: Schema, b: Schema) -> Schema {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (Schema::Any, _) | (_, Schema::Any) => Schema::Any,
+        (Schema::Array(a), Schema::Array(b)) => Schema::Array(Box::new(merge(*a, *b))),
+        (Schema::Object(a), Schema::Object(b)) => Schema::Object(merge_objects(a, b)),
+        (Schema::OneOf(schemas), other) | (other, Schema::OneOf(schemas)) => {
+            let mut schemas = schemas;
+            add_alternative(&mut schemas, other);
+            Schema::OneOf(schemas)
+        }
+        (a, b) => Schema::OneOf(vec![a, b]),
+    }
+}
+
+// 同类的 schema 合并到已有的分支里，否则新增一个分支
+fn add_alternative(schemas: &mut Vec<Schema>, schema: Schema) {
+    let alternatives = match schema {
+        Schema::OneOf(alternatives) => alternatives,
+        schema => vec![schema],
+    };
+    for schema in alternatives {
+        match schemas
+            .iter()
+            .position(|s| std::mem::discriminant(s) == std::mem::discriminant(&schema))
+        {
+            Some(i) => {
+                let existing = std::mem::replace(&mut schemas[i], Schema::Any);
+                schemas[i] = merge(existing, schema);
+            }
+            None => schemas.push(schema),
+        }
+    }
+}
+
+fn merge_objects(mut a: ObjectSchema, mut b: ObjectSchema) -> ObjectSchema {
+    let mut properties = IndexMap::with_capacity(a.properties.len());
+    for (key, property) in a.properties.drain(..) {
+        let merged = match b.properties.shift_remove(&key) {
+            Some(other) => PropertySchema {
+                schema: merge(property.schema, other.schema),
+                required: property.required && other.required,
+            },
+            None => PropertySchema {
+                required: false,
+                ..property
+            },
+        };
+        properties.insert(key, merged);
+    }
+    for (key, property) in b.properties {
+        properties.insert(
+            key,
+            PropertySchema {
+                required: false,
+                ..property
+            },
+        );
+    }
+    ObjectSchema {
+        properties,
+        deny_unknown: a.deny_unknown && b.deny_unknown,
+    }
+}
+
+#[derive(Default)]
+struct StructGenerator<'s> {
+    out: String,
+    // 还没有输出的结构体
+    pending: Vec<(String, &'s ObjectSchema)>,
+    names: Vec<String>,
+}
+
+impl<'s> StructGenerator<'s> {
+    // 登记一个结构体，重名时加上数字后缀
+    fn push(&mut self, name: String, object: &'s ObjectSchema) -> String {
+        let mut unique = name.clone();
+        let mut n = 2;
+        while self.names.contains(&unique) {
+            unique = format!("{}{}", name, n);
+            n += 1;
+        }
+        self.names.push(unique.clone());
+        self.pending.insert(0, (unique.clone(), object));
+        unique
+    }
+
+    fn write_struct(&mut self, name: &str, object: &'s ObjectSchema) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        let mut fields = String::new();
+        for (key, property) in &object.properties {
+            let field = field_name(key);
+            let mut ty = self.type_of(&property.schema, key);
+            if !property.required && !ty.starts_with("Option<") && ty != "Amf0TypedValue" {
+                ty = format!("Option<{}>", ty);
+            }
+            if field.trim_start_matches("r#") != key {
+                writeln!(fields, "    #[amf(rename = {:?})]", key).unwrap();
+            }
+            writeln!(fields, "    pub {}: {},", field, ty).unwrap();
+        }
+        writeln!(self.out, "#[derive(Debug, Clone, PartialEq, AmfObject)]").unwrap();
+        writeln!(self.out, "pub struct {} {{", name).unwrap();
+        self.out.push_str(&fields);
+        self.out.push_str("}\n");
+    }
+
+    fn type_of(&mut self, schema: &'s Schema, key: &str) -> String {
+        match schema {
+            Schema::Number { .. } => "f64".to_string(),
+            Schema::Boolean => "bool".to_string(),
+            Schema::String => "String".to_string(),
+            Schema::Array(element) => format!("Vec<{}>", self.type_of(element, key)),
+            Schema::Object(object) => self.push(pascal_case(key), object),
+            Schema::OneOf(schemas) => {
+                let mut present = schemas
+                    .iter()
+                    .filter(|s| !matches!(s, Schema::Null | Schema::Undefined));
+                match (present.next(), present.next()) {
+                    (Some(schema), None) => format!("Option<{}>", self.type_of(schema, key)),
+                    _ => "Amf0TypedValue".to_string(),
+                }
+            }
+            Schema::Any | Schema::Null | Schema::Undefined => "Amf0TypedValue".to_string(),
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+    "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while", "yield",
+];
+
+// "videoCodecId" -> "video_codec_id"，不能作为标识符的字符换成下划线
+fn field_name(key: &str) -> String {
+    let mut name = String::with_capacity(key.len() + 4);
+    let mut previous_lower = false;
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            if previous_lower {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+            previous_lower = false;
+        } else if c.is_ascii_alphanumeric() {
+            name.push(c);
+            previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        } else {
+            name.push('_');
+            previous_lower = false;
+        }
+    }
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) || name == "_" {
+        name.insert(0, '_');
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        // self/super/crate 不能作为原始标识符
+        if matches!(name.as_str(), "self" | "super" | "crate") {
+            name.push('_');
+        } else {
+            name.insert_str(0, "r#");
+        }
+    }
+    name
+}
+
+// "keyframes" -> "Keyframes"，"audio_codec-id" -> "AudioCodecId"
+fn pascal_case(key: &str) -> String {
+    let mut name = String::with_capacity(key.len());
+    let mut upper = true;
+    for c in key.chars() {
+        if c.is_ascii_alphanumeric() {
+            if upper {
+                name.push(c.to_ascii_uppercase());
+            } else {
+                name.push(c);
+            }
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, 'T');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+
+    #[test]
+    fn test_infer() {
+        let sample = amf0!({
+            "duration": 12.5,
+            "encoder": "Lavf60",
+            "keyframes": [{"time": 0.0, "offset": 13.0}, {"time": 2.0, "tag": null}],
+            "empty": [],
+        });
+        let schema = Schema::infer(&sample);
+        assert!(schema.matches(&sample));
+        assert!(!schema.matches(&amf0!({"duration": 1.0})));
+
+        let keyframe = Schema::object()
+            .required("time", Schema::number())
+            .optional("offset", Schema::number())
+            .optional("tag", Schema::Null);
+        let expected: Schema = Schema::object()
+            .required("duration", Schema::number())
+            .required("encoder", Schema::String)
+            .required("keyframes", Schema::array_of(keyframe.into()))
+            .required("empty", Schema::array_of(Schema::Any))
+            .into();
+        assert_eq!(schema, expected);
+
+        let mixed = Schema::infer(&amf0!([1.0, "a", 2.0, null]));
+        assert_eq!(
+            mixed,
+            Schema::array_of(Schema::one_of([
+                Schema::number(),
+                Schema::String,
+                Schema::Null
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_to_rust_struct() {
+        let sample = amf0!({
+            "app": "live",
+            "videoCodecs": 252.0,
+            "type": "nonprivate",
+            "fpad": false,
+            "info": {"2d": [1.0], "level": "status"},
+            "extra": null,
+        });
+        let schema = merge(
+            Schema::infer(&sample),
+            Schema::infer(&amf0!({"app": "vod", "videoCodecs": 0.0})),
+        );
+        assert_eq!(
+            schema.to_rust_struct("connect_object"),
+            r#"#[derive(Debug, Clone, PartialEq, AmfObject)]
+pub struct ConnectObject {
+    pub app: String,
+    #[amf(rename = "videoCodecs")]
+    pub video_codecs: f64,
+    pub r#type: Option<String>,
+    pub fpad: Option<bool>,
+    pub info: Option<Info>,
+    pub extra: Amf0TypedValue,
+}
+
+#[derive(Debug, Clone, PartialEq, AmfObject)]
+pub struct Info {
+    #[amf(rename = "2d")]
+    pub _2d: Vec<f64>,
+    pub level: String,
+}
+"#
+        );
+        assert_eq!(
+            Schema::array_of(Schema::String).to_rust_struct("names"),
+            "pub type Names = Vec<String>;\n"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use crate::amf0::nested::{Amf0TypedValue, NestedType};
+use crate::errors::AmfError;
+use indexmap::IndexMap;
+use std::fmt::{Display, Formatter};
+
+mod infer;
+
+// The expected shape of a decoded value, e.g. of an incoming `connect` command object or an
+// onMetaData:
+//
+//	Schema::object()
+//	    .required("app", Schema::String)
+//	    .required("tcUrl", Schema::String)
+//	    .optional("objectEncoding", Schema::number_between(Some(0.0), Some(3.0)))
+//	    .into()
+//
+// Objects and ECMA arrays both match `Object`, String and LongString both match `String`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    Any,
+    Number { min: Option<f64>, max: Option<f64> },
+    Boolean,
+    String,
+    Null,
+    Undefined,
+    // A strict array whose elements all match the schema.
+    Array(Box<Schema>),
+    Object(ObjectSchema),
+    // Matches if any of the schemas does, e.g. `one_of([Schema::number(), Schema::Null])`.
+    OneOf(Vec<Schema>),
+}
+
+// The properties of an Object or ECMA array. Properties that are not declared are allowed
+// unless `deny_unknown` is set.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectSchema {
+    pub properties: IndexMap<String, PropertySchema>,
+    pub deny_unknown: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertySchema {
+    pub schema: Schema,
+    pub required: bool,
+}
+
+// One mismatch between a value and a schema, located by a path in the syntax accepted by
+// `Amf0TypedValue::get_path` ("" is the root).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.path.as_str() {
+            "" => write!(f, "<root>: {}", self.message),
+            path => write!(f, "{}: {}", path, self.message),
+        }
+    }
+}
+
+impl Schema {
+    pub fn number() -> Self {
+        Schema::Number {
+            min: None,
+            max: None,
+        }
+    }
+
+    // A number within `min..=max`; `None` leaves that side open. NaN never matches a bound.
+    pub fn number_between(min: Option<f64>, max: Option<f64>) -> Self {
+        Schema::Number { min, max }
+    }
+
+    pub fn array_of(element: Schema) -> Self {
+        Schema::Array(Box::new(element))
+    }
+
+    pub fn object() -> ObjectSchema {
+        ObjectSchema::default()
+    }
+
+    pub fn one_of(schemas: impl IntoIterator<Item = Schema>) -> Self {
+        Schema::OneOf(schemas.into_iter().collect())
+    }
+
+    // Checks `value`, failing with every mismatch in one `AmfError::SchemaMismatch`, displayed
+    // as e.g. `app: missing; objectEncoding: 5 is above the maximum 3`.
+    pub fn validate(&self, value: &Amf0TypedValue) -> Result<(), AmfError> {
+        let errors = self.errors(value);
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(AmfError::SchemaMismatch(errors))
+    }
+
+    // Every mismatch between `value` and the schema, in document order.
+    pub fn errors(&self, value: &Amf0TypedValue) -> Vec<SchemaError> {
+        let mut errors = Vec::new();
+        self.check(value, "", &mut errors);
+        errors
+    }
+
+    pub fn matches(&self, value: &Amf0TypedValue) -> bool {
+        self.errors(value).is_empty()
+    }
+
+    // 递归的深度受 schema 的深度限制，与输入无关
+    fn check(&self, value: &Amf0TypedValue, path: &str, errors: &mut Vec<SchemaError>) {
+        let mut error = |message: String| {
+            errors.push(SchemaError {
+                path: path.to_string(),
+                message,
+            })
+        };
+        match (self, value) {
+            (Schema::Any, _)
+            | (Schema::Boolean, Amf0TypedValue::Boolean(_))
+            | (Schema::String, Amf0TypedValue::String(_) | Amf0TypedValue::LongString(_))
+            | (Schema::Null, Amf0TypedValue::Null(_))
+            | (Schema::Undefined, Amf0TypedValue::Undefined(_)) => {}
+            (Schema::Number { min, max }, Amf0TypedValue::Number(n)) => {
+                let n = **n;
+                if let Some(min) = min
+                    && (n < *min || n.is_nan())
+                {
+                    error(format!("{} is below the minimum {}", n, min));
+                } else if let Some(max) = max
+                    && (n > *max || n.is_nan())
+                {
+                    error(format!("{} is above the maximum {}", n, max));
+                }
+            }
+            (Schema::Array(element), Amf0TypedValue::StrictArray(values)) => {
+                for (i, v) in values.iter().enumerate() {
+                    element.check(v, &format!("{}[{}]", path, i), errors);
+                }
+            }
+            (Schema::Object(object), Amf0TypedValue::Object(v)) => object.check(v, path, errors),
+            (Schema::Object(object), Amf0TypedValue::EcmaArray(v)) => object.check(v, path, errors),
+            (Schema::OneOf(schemas), _) => {
+                if !schemas.iter().any(|s| s.matches(value)) {
+                    let names: Vec<_> = schemas.iter().map(Schema::kind_name).collect();
+                    error(format!(
+                        "expected one of {}, got {}",
+                        names.join(", "),
+                        value.kind_name()
+                    ));
+                }
+            }
+            _ => error(format!(
+                "expected {}, got {}",
+                self.kind_name(),
+                value.kind_name()
+            )),
+        }
+    }
+
+    fn kind_name(&self) -> &'static str {
+        match self {
+            Schema::Any => "any value",
+            Schema::Number { .. } => "number",
+            Schema::Boolean => "boolean",
+            Schema::String => "string",
+            Schema::Null => "null",
+            Schema::Undefined => "undefined",
+            Schema::Array(_) => "strict array",
+            Schema::Object(_) => "object",
+            Schema::OneOf(_) => "one of several types",
+        }
+    }
+}
+
+impl ObjectSchema {
+    pub fn required(mut self, key: impl Into<String>, schema: Schema) -> Self {
+        self.properties.insert(
+            key.into(),
+            PropertySchema {
+                schema,
+                required: true,
+            },
+        );
+        self
+    }
+
+    pub fn optional(mut self, key: impl Into<String>, schema: Schema) -> Self {
+        self.properties.insert(
+            key.into(),
+            PropertySchema {
+                schema,
+                required: false,
+            },
+        );
+        self
+    }
+
+    // Reject properties that are not declared.
+    pub fn deny_unknown(mut self) -> Self {
+        self.deny_unknown = true;
+        self
+    }
+
+    fn check<const LBW: usize, const TM: u8>(
+        &self,
+        object: &NestedType<LBW, TM>,
+        path: &str,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let child = |key: &str| match path {
+            "" => key.to_string(),
+            _ => format!("{}.{}", path, key),
+        };
+        for (key, property) in &self.properties {
+            match object.get(key.as_str()) {
+                Some(value) => property.schema.check(value, &child(key), errors),
+                None if property.required => errors.push(SchemaError {
+                    path: child(key),
+                    message: "missing".to_string(),
+                }),
+                None => {}
+            }
+        }
+        if self.deny_unknown {
+            for key in object.keys() {
+                if !self.properties.contains_key(key.as_ref()) {
+                    errors.push(SchemaError {
+                        path: child(key.as_ref()),
+                        message: "unknown property".to_string(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl From<ObjectSchema> for Schema {
+    fn from(value: ObjectSchema) -> Self {
+        Schema::Object(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::errors::ErrorKind;
+
+    fn connect_schema() -> Schema {
+        Schema::object()
+            .required("app", Schema::String)
+            .required("tcUrl", Schema::String)
+            .optional("fpad", Schema::Boolean)
+            .optional(
+                "objectEncoding",
+                Schema::number_between(Some(0.0), Some(3.0)),
+            )
+            .optional("codecs", Schema::array_of(Schema::String))
+            .optional(
+                "swfUrl",
+                Schema::one_of([Schema::String, Schema::Null, Schema::Undefined]),
+            )
+            .into()
+    }
+
+    #[test]
+    fn test_valid_value() {
+        let connect = amf0!({
+            "app": "live",
+            "tcUrl": "rtmp://localhost/live",
+            "objectEncoding": 0.0,
+            "swfUrl": undefined,
+            "flashVer": "FMLE/3.0",
+        });
+        assert!(connect_schema().validate(&connect).is_ok());
+        assert!(Schema::Any.matches(&amf0!([1.0, null])));
+    }
+
+    #[test]
+    fn test_errors_are_path_aware() {
+        let connect = amf0!({
+            "tcUrl": 1.0,
+            "objectEncoding": 5.0,
+            "codecs": ["aac", 7.0],
+            "swfUrl": true,
+        });
+        let errors: Vec<_> = connect_schema()
+            .errors(&connect)
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "app: missing",
+                "tcUrl: expected string, got number",
+                "objectEncoding: 5 is above the maximum 3",
+                "codecs[1]: expected string, got number",
+                "swfUrl: expected one of string, null, undefined, got boolean",
+            ]
+        );
+
+        let strict = Schema::from(Schema::object().optional("a", Schema::Any).deny_unknown());
+        let err = strict.validate(&amf0!({"a": 1.0, "b": 2.0})).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::SchemaMismatch);
+        assert_eq!(err.to_string(), "b: unknown property");
+        let err = strict.validate(&amf0!([])).unwrap_err();
+        assert_eq!(err.to_string(), "<root>: expected object, got strict array");
+    }
+}
@@ -0,0 +1,247 @@
+// Local Shared Objects: the `.sol` files in which Flash Player persists `SharedObject.data`.
+//
+//	sol-file = magic body-length signature pad name amf-version *(pair)
+//	magic = 0x00 0xBF
+//	body-length = U32           ; number of bytes after this field
+//	signature = "TCSO" 0x00 0x04 0x00 0x00 0x00 0x00
+//	name = UTF-8
+//	amf-version = U32           ; 0 for AMF 0
+//	pair = UTF-8 value-type 0x00
+//
+//	Only AMF 0 files are supported.
+
+use crate::amf0::nested::Amf0TypedValue;
+use crate::amf0::utf8::{Utf8, unmarshall_str};
+use crate::errors::AmfError;
+use crate::traits::{Marshall, MarshallLength, Unmarshall};
+use indexmap::IndexMap;
+use std::fs;
+use std::path::Path;
+
+const MAGIC: [u8; 2] = [0x00, 0xbf];
+const SIGNATURE: [u8; 10] = *b"TCSO\x00\x04\x00\x00\x00\x00";
+// magic + body-length
+const HEADER_LENGTH: usize = 6;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SolFile {
+    pub name: String,
+    pub values: IndexMap<Utf8, Amf0TypedValue>,
+}
+
+impl SolFile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            values: IndexMap::new(),
+        }
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AmfError> {
+        Self::try_from(fs::read(path)?.as_slice())
+    }
+
+    // Writes the whole file at once, replacing any existing one.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AmfError> {
+        fs::write(path, self.marshall()?)?;
+        Ok(())
+    }
+}
+
+impl Marshall for SolFile {
+    fn marshall(&self) -> Result<Vec<u8>, AmfError> {
+        let mut vec = Vec::with_capacity(self.marshall_length());
+        self.marshall_to(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        let body_length = u32::try_from(self.marshall_length() - HEADER_LENGTH).map_err(|_| {
+            AmfError::TooLong {
+                what: ".sol body",
+                max: u32::MAX as usize,
+                got: self.marshall_length() - HEADER_LENGTH,
+            }
+        })?;
+        buf.extend_from_slice(&MAGIC);
+        buf.extend_from_slice(&body_length.to_be_bytes());
+        buf.extend_from_slice(&SIGNATURE);
+        Utf8::new_from_str(&self.name)?.marshall_to(buf)?;
+        buf.extend_from_slice(&0u32.to_be_bytes());
+        for (key, value) in &self.values {
+            key.marshall_to(buf)?;
+            value.marshall_to(buf)?;
+            buf.push(0x00);
+        }
+        Ok(())
+    }
+}
+
+impl MarshallLength for SolFile {
+    fn marshall_length(&self) -> usize {
+        let values: usize = self
+            .values
+            .iter()
+            .map(|(k, v)| k.marshall_length() + v.marshall_length() + 1)
+            .sum();
+        HEADER_LENGTH + SIGNATURE.len() + 2 + self.name.len() + 4 + values
+    }
+}
+
+impl Unmarshall for SolFile {
+    fn unmarshall(buf: &[u8]) -> Result<(Self, usize), AmfError> {
+        let fixed = HEADER_LENGTH + SIGNATURE.len();
+        if buf.len() < fixed {
+            return Err(AmfError::BufferTooSmall {
+                want: fixed,
+                got: buf.len(),
+            });
+        }
+        if buf[..2] != MAGIC || buf[HEADER_LENGTH..fixed] != SIGNATURE {
+            return Err(AmfError::InvalidHeader {
+                format: ".sol",
+                field: "signature",
+            });
+        }
+        let body_length = u32::from_be_bytes(buf[2..HEADER_LENGTH].try_into().unwrap()) as usize;
+        let end = HEADER_LENGTH + body_length;
+        // body 至少要包含签名
+        if end < fixed {
+            return Err(AmfError::InvalidHeader {
+                format: ".sol",
+                field: "body length",
+            });
+        }
+        if buf.len() < end {
+            return Err(AmfError::BufferTooSmall {
+                want: end,
+                got: buf.len(),
+            });
+        }
+        let buf = &buf[..end];
+
+        let mut offset = fixed;
+        let (name, n) = unmarshall_str::<2>(&buf[offset..])?;
+        offset += n;
+        let version = buf
+            .get(offset..offset + 4)
+            .ok_or(AmfError::BufferTooSmall {
+                want: offset + 4,
+                got: end,
+            })?;
+        let version = u32::from_be_bytes(version.try_into().unwrap());
+        if version != 0 {
+            return Err(AmfError::UnsupportedVersion {
+                format: ".sol AMF",
+                version,
+            });
+        }
+        offset += 4;
+
+        let mut sol = SolFile::new(name);
+        while offset < end {
+            let (key, n) = Utf8::unmarshall(&buf[offset..])?;
+            offset += n;
+            let (value, n) = Amf0TypedValue::unmarshall(&buf[offset..])?;
+            offset += n;
+            // 每一对后面的填充字节
+            match buf.get(offset) {
+                Some(0x00) => offset += 1,
+                Some(_) => {
+                    return Err(AmfError::Context {
+                        offset: Some(offset),
+                        path: key.to_string(),
+                        source: Box::new(AmfError::InvalidHeader {
+                            format: ".sol",
+                            field: "padding",
+                        }),
+                    });
+                }
+                None => {
+                    return Err(AmfError::BufferTooSmall {
+                        want: offset + 1,
+                        got: end,
+                    });
+                }
+            }
+            sol.values.insert(key, value);
+        }
+        Ok((sol, end))
+    }
+}
+
+impl TryFrom<&[u8]> for SolFile {
+    type Error = AmfError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::unmarshall(value).map(|(sol, _)| sol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::errors::ErrorKind;
+    use crate::testing::assert_roundtrip;
+
+    #[test]
+    fn test_bytes() {
+        let mut sol = SolFile::new("game");
+        sol.values
+            .insert(Utf8::new_from_str("score").unwrap(), amf0!(42.0));
+        let data = sol.marshall().unwrap();
+        let mut expected = b"\x00\xbf\x00\x00\x00\x25TCSO\x00\x04\x00\x00\x00\x00\x00\x04game\x00\x00\x00\x00\x00\x05score".to_vec();
+        expected.extend_from_slice(&amf0!(42.0).marshall().unwrap());
+        expected.push(0x00);
+        assert_eq!(data, expected);
+        assert_roundtrip(&sol);
+
+        let mut bad = data.clone();
+        *bad.last_mut().unwrap() = 0x01;
+        let err = SolFile::try_from(bad.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidHeader);
+        assert_eq!(err.offset(), Some(bad.len() - 1));
+        bad[HEADER_LENGTH + SIGNATURE.len() + 2 + 4 + 3] = 3; // AMF 3
+        assert!(matches!(
+            SolFile::try_from(bad.as_slice()),
+            Err(AmfError::UnsupportedVersion { version: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_truncated_body_length() {
+        let data = SolFile::new("game").marshall().unwrap();
+        // 声明的 body 长度比签名还短
+        for body_length in 0..SIGNATURE.len() as u32 {
+            let mut bad = data.clone();
+            bad[2..HEADER_LENGTH].copy_from_slice(&body_length.to_be_bytes());
+            let err = SolFile::try_from(bad.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidHeader);
+        }
+        // body 在名字或版本中间结束
+        for body_length in SIGNATURE.len() as u32..(data.len() - HEADER_LENGTH) as u32 {
+            let mut bad = data.clone();
+            bad[2..HEADER_LENGTH].copy_from_slice(&body_length.to_be_bytes());
+            assert!(SolFile::try_from(bad.as_slice()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_save_and_open() {
+        let mut sol = SolFile::new("settings");
+        sol.values.insert(
+            Utf8::new_from_str("prefs").unwrap(),
+            amf0!({"volume": 0.5, "muted": false, "recent": ["a", "b"]}),
+        );
+        let path = std::env::temp_dir().join(format!("amf-rs-{}.sol", std::process::id()));
+        sol.save(&path).unwrap();
+        let mut reopened = SolFile::open(&path).unwrap();
+        assert_eq!(reopened, sol);
+
+        reopened.values.shift_remove("prefs");
+        reopened.save(&path).unwrap();
+        assert!(SolFile::open(&path).unwrap().values.is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
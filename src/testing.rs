@@ -0,0 +1,122 @@
+use crate::amf0::nested::Amf0TypedValue;
+use crate::traits::{Marshall, MarshallLength, Unmarshall};
+use std::fmt::Debug;
+
+// Assertion helpers for test suites that produce or consume AMF data. They panic with the
+// offset of the first byte that differs, which is far easier to act on than two long byte
+// vectors printed side by side.
+
+// Checks that `value` survives marshall -> unmarshall -> marshall: the decoder consumes exactly
+// the encoded bytes, re-encoding yields the same bytes, and `marshall_length` matches.
+#[track_caller]
+pub fn assert_roundtrip<T>(value: &T)
+where
+    T: Marshall + MarshallLength + Unmarshall + Debug,
+{
+    let encoded = value
+        .marshall()
+        .unwrap_or_else(|e| panic!("failed to marshall {:?}: {}", value, e));
+    assert_eq!(
+        value.marshall_length(),
+        encoded.len(),
+        "marshall_length of {:?} does not match the encoded length",
+        value
+    );
+    let (decoded, n) = T::unmarshall(&encoded)
+        .unwrap_or_else(|e| panic!("failed to unmarshall the encoding of {:?}: {}", value, e));
+    assert_eq!(
+        n,
+        encoded.len(),
+        "unmarshall consumed {} of {} bytes",
+        n,
+        encoded.len()
+    );
+    let reencoded = decoded
+        .marshall()
+        .unwrap_or_else(|e| panic!("failed to marshall decoded {:?}: {}", decoded, e));
+    assert_same_bytes(&encoded, &reencoded);
+}
+
+// Checks that `bytes` hold exactly one AMF 0 value which encodes back to the same bytes.
+#[track_caller]
+pub fn assert_bytes_roundtrip(bytes: &[u8]) {
+    let (value, n) = Amf0TypedValue::unmarshall(bytes)
+        .unwrap_or_else(|e| panic!("failed to unmarshall {} bytes: {}", bytes.len(), e));
+    assert_eq!(
+        n,
+        bytes.len(),
+        "unmarshall consumed {} of {} bytes",
+        n,
+        bytes.len()
+    );
+    let encoded = value
+        .marshall()
+        .unwrap_or_else(|e| panic!("failed to marshall decoded {:?}: {}", value, e));
+    assert_same_bytes(bytes, &encoded);
+}
+
+#[track_caller]
+fn assert_same_bytes(expected: &[u8], actual: &[u8]) {
+    if let Some(offset) = first_difference(expected, actual) {
+        panic!(
+            "round trip diverges at byte offset {}: expected {}, got {} (lengths {} and {})",
+            offset,
+            byte_at(expected, offset),
+            byte_at(actual, offset),
+            expected.len(),
+            actual.len()
+        );
+    }
+}
+
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    a.iter()
+        .zip(b)
+        .position(|(x, y)| x != y)
+        .or_else(|| (a.len() != b.len()).then(|| a.len().min(b.len())))
+}
+
+fn byte_at(bytes: &[u8], offset: usize) -> String {
+    match bytes.get(offset) {
+        Some(b) => format!("0x{:02x}", b),
+        None => "end of input".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amf0;
+    use crate::amf0::number::NumberType;
+    use std::panic::catch_unwind;
+
+    #[test]
+    fn test_assert_roundtrip() {
+        assert_roundtrip(&amf0!({"a": [1.0, "b", null], "c": {"d": true}}));
+        assert_roundtrip(&NumberType::new(f64::NAN));
+        assert_bytes_roundtrip(&amf0!([1.0, undefined]).marshall().unwrap());
+    }
+
+    #[test]
+    fn test_reports_first_diverging_offset() {
+        // Boolean 0x02 解码后重新编码为 0x01
+        let err = catch_unwind(|| assert_bytes_roundtrip(&[0x01, 0x02])).unwrap_err();
+        let message = err.downcast_ref::<String>().unwrap();
+        assert!(
+            message.contains("offset 1: expected 0x02, got 0x01"),
+            "{}",
+            message
+        );
+
+        let err = catch_unwind(|| assert_bytes_roundtrip(&[0x05, 0x05])).unwrap_err();
+        let message = err.downcast_ref::<String>().unwrap();
+        assert!(message.contains("consumed 1 of 2 bytes"), "{}", message);
+    }
+
+    #[test]
+    fn test_first_difference() {
+        assert_eq!(first_difference(b"abc", b"abc"), None);
+        assert_eq!(first_difference(b"abc", b"abd"), Some(2));
+        assert_eq!(first_difference(b"ab", b"abc"), Some(2));
+    }
+}
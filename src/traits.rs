@@ -2,8 +2,21 @@ use crate::errors::AmfError;
 
 pub trait Marshall {
     fn marshall(&self) -> Result<Vec<u8>, AmfError>;
+
+    // Appends the encoding to `buf`. Containers write their children through this, so that a
+    // whole tree is encoded into the single buffer allocated by the root's `marshall`. The
+    // default goes through `marshall`; the types of this crate write in place.
+    fn marshall_to(&self, buf: &mut Vec<u8>) -> Result<(), AmfError> {
+        buf.extend_from_slice(&self.marshall()?);
+        Ok(())
+    }
 }
 
+// The exact number of bytes `marshall` produces. For a tree this is one bottom-up pass over the
+// nodes; `marshall` calls it once on the root to size the buffer and `marshall_to` never asks
+// children for their length, so encoding stays linear in the size of the tree. Nothing is
+// cached: a cached size would go stale when a nested value is changed through a `&mut`
+// obtained from one of its ancestors.
 pub trait MarshallLength {
     fn marshall_length(&self) -> usize;
 }
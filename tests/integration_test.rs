@@ -1,23 +1,24 @@
 use std::{
     fs::File,
-    io::{self, BufReader, Read, Seek, SeekFrom},
+    io::{self, BufReader},
     process::Command,
 };
 
 mod test_setup {
     use super::*;
+    use amf_rs::flv::{FlvReader, ScriptTagBody, TagType};
     use once_cell::sync::OnceCell;
     use regex::Regex;
     use std::path::PathBuf;
     use std::{env, fs};
 
-    static SCRIPT_DATA: OnceCell<(Vec<u8>, String)> = OnceCell::new();
+    static SCRIPT_DATA: OnceCell<(ScriptTagBody, String)> = OnceCell::new();
 
-    pub fn setup() -> &'static (Vec<u8>, String) {
+    pub fn setup() -> &'static (ScriptTagBody, String) {
         SCRIPT_DATA.get_or_init(|| flv_metadata_generation().unwrap())
     }
 
-    fn flv_metadata_generation() -> io::Result<(Vec<u8>, String)> {
+    fn flv_metadata_generation() -> io::Result<(ScriptTagBody, String)> {
         // 检查必要命令是否存在
         assert!(command_exists("ffmpeg"), "ffmpeg not installed");
         assert!(command_exists("flvmeta"), "flvmeta not installed");
@@ -65,44 +66,20 @@ mod test_setup {
         );
 
         // 解析生成的 FLV 文件
-        let mut input_file = BufReader::new(File::open(output_path.as_path())?);
-        let mut header = [0u8; 9];
-        input_file.read_exact(&mut header)?;
-        assert_eq!(&header[0..3], b"FLV", "FLV file header verification failed");
-
-        // 跳过 FLV Header 和第一个 PreviousTagSize
-        input_file.seek(SeekFrom::Start(13))?;
-
-        let mut tag_data = vec![];
-        let mut found = false;
-        loop {
-            let mut tag_header = [0u8; 11];
-            if input_file.read_exact(&mut tag_header).is_err() {
-                break;
-            }
-
-            let tag_type = tag_header[0];
-            let data_size = u32::from_be_bytes([0, tag_header[1], tag_header[2], tag_header[3]]);
-            let timestamp =
-                u32::from_be_bytes([tag_header[7], tag_header[4], tag_header[5], tag_header[6]]);
-
-            if tag_type == 18 {
-                // 读取完整 Tag 数据
-                tag_data = vec![0u8; data_size as usize];
-                input_file.read_exact(&mut tag_data)?;
-
-                println!(
-                    "Successfully extracted ScriptData Tag（Timestamp：{}）",
-                    timestamp
-                );
-                found = true;
-                break;
-            } else {
-                input_file.seek(SeekFrom::Current(data_size as i64 + 4))?;
-            }
-        }
-
-        assert!(found, "ScriptData Tag not found");
+        let input_file = BufReader::new(File::open(output_path.as_path())?);
+        let mut reader = FlvReader::new(input_file).map_err(io::Error::other)?;
+        let tag = reader
+            .find_map(|tag| {
+                tag.map(|tag| (tag.tag_type == TagType::ScriptData).then_some(tag))
+                    .transpose()
+            })
+            .expect("ScriptData Tag not found")
+            .map_err(io::Error::other)?;
+        println!(
+            "Successfully extracted ScriptData Tag（Timestamp：{}）",
+            tag.timestamp
+        );
+        let body = tag.data.as_script().unwrap().clone();
 
         // 使用 flvmeta 提取 ScriptData 中数据
         let probe = Command::new("flvmeta")
@@ -119,7 +96,7 @@ mod test_setup {
             fs::remove_file(output_path.as_path())?;
         }
 
-        Ok((tag_data, json_data))
+        Ok((body, json_data))
     }
 
     fn check_ffmpeg_version(min_major: u32) -> bool {
@@ -160,24 +137,13 @@ mod test_setup {
 #[cfg(test)]
 mod tests {
     use crate::test_setup;
-    use amf_rs::amf0::nested::Amf0TypedValue;
-    use amf_rs::traits::Unmarshall;
 
     #[test]
     fn test_amf_rs() {
         let test_case = test_setup::setup();
-        let buf = test_case.0.as_slice();
-
-        let mut string_builder = String::new();
-        let mut offset = 0;
-        while offset < buf.len() {
-            let (v, n) = Amf0TypedValue::unmarshall(&buf[offset..]).unwrap();
-            let s = &format!("{}", v);
-            if s != "\"onMetaData\"" {
-                string_builder.push_str(s);
-            }
-            offset += n;
-        }
+        let body = &test_case.0;
+        assert!(body.is_on_metadata());
+        let string_builder = body.value.to_string();
 
         let expect = &test_case.1;
         let actual = &string_builder;